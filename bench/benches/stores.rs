@@ -0,0 +1,37 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use sea_orm::{ConnectOptions, Database};
+use tower_sessions_bench::StoreBench;
+use tower_sessions_core::session::Id;
+use tower_sessions_fs_store::FileStore;
+use tower_sessions_memory_store::MemoryStore;
+use tower_sessions_seaorm_store::SeaOrmStore;
+
+fn memory(c: &mut Criterion) {
+    StoreBench::new("memory").run(c, MemoryStore::default());
+}
+
+fn fs(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("tower-sessions-bench-{}", Id::default()));
+    StoreBench::new("fs").run(c, FileStore::new(&dir));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+fn sqlite(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("tower-sessions-bench-{}.db", Id::default()));
+    let bench = StoreBench::new("sqlite");
+    let store = bench.runtime().block_on(async {
+        let mut options = ConnectOptions::new(format!("sqlite://{}?mode=rwc", path.display()));
+        options.sqlx_logging(false);
+        let db = Database::connect(options)
+            .await
+            .expect("Failed to connect to SQLite");
+        let store = SeaOrmStore::new(db);
+        store.migrate().await.expect("Failed to migrate");
+        store
+    });
+    bench.run(c, store);
+    let _ = std::fs::remove_file(path);
+}
+
+criterion_group!(benches, memory, fs, sqlite);
+criterion_main!(benches);
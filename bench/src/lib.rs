@@ -0,0 +1,215 @@
+//! A benchmark harness for [`SessionStore`] implementations.
+//!
+//! [`StoreBench`] measures the latency and throughput of loading, saving and
+//! cycling sessions with [Criterion], at increasing levels of concurrency. The
+//! stores bundled with `tower-sessions` are compared by this crate's own
+//! benchmark:
+//!
+//! ```sh
+//! cargo bench -p tower-sessions-bench
+//! ```
+//!
+//! Each operation is reported as its own group, with one benchmark per store
+//! and level of concurrency. At a concurrency of one, the time per iteration
+//! is the latency of a single operation, while throughput is reported in
+//! operations per second.
+//!
+//! The levels of concurrency may be configured with the
+//! `TOWER_SESSIONS_BENCH_CONCURRENCY` environment variable, as a
+//! comma-separated list:
+//!
+//! ```sh
+//! TOWER_SESSIONS_BENCH_CONCURRENCY=1,64,1024 cargo bench -p tower-sessions-bench
+//! ```
+//!
+//! To measure a store against your own infrastructure before choosing a
+//! backend, run the harness from a benchmark of your own:
+//!
+//! ```rust,ignore
+//! use criterion::{criterion_group, criterion_main, Criterion};
+//! use my_store::MyStore;
+//! use tower_sessions_bench::StoreBench;
+//!
+//! fn my_store(c: &mut Criterion) {
+//!     let bench = StoreBench::new("my-store");
+//!     let store = bench
+//!         .runtime()
+//!         .block_on(MyStore::connect("postgres://..."))
+//!         .unwrap();
+//!     bench.run(c, store);
+//! }
+//!
+//! criterion_group!(benches, my_store);
+//! criterion_main!(benches);
+//! ```
+//!
+//! [Criterion]: https://docs.rs/criterion
+#![warn(
+    clippy::all,
+    nonstandard_style,
+    future_incompatible,
+    missing_debug_implementations
+)]
+#![deny(missing_docs)]
+#![forbid(unsafe_code)]
+
+use std::{future::Future, sync::Arc};
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use time::{Duration, OffsetDateTime};
+use tokio::{runtime::Runtime, sync::Mutex};
+use tower_sessions_core::{
+    session::{Id, Record},
+    SessionStore,
+};
+
+/// Environment variable listing the levels of concurrency to measure.
+pub const CONCURRENCY_VAR: &str = "TOWER_SESSIONS_BENCH_CONCURRENCY";
+
+const DEFAULT_CONCURRENCY: [usize; 3] = [1, 16, 128];
+
+/// Benchmarks a session store.
+///
+/// See the [crate documentation](crate) for an example.
+#[derive(Debug)]
+pub struct StoreBench {
+    name: String,
+    concurrency: Vec<usize>,
+    runtime: Runtime,
+}
+
+impl StoreBench {
+    /// Create a new `StoreBench`, reporting the store under the given name.
+    ///
+    /// The levels of concurrency are read from [`CONCURRENCY_VAR`], defaulting
+    /// to 1, 16 and 128.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`CONCURRENCY_VAR`] isn't a comma-separated list of positive
+    /// integers, or if the runtime can't be built.
+    pub fn new(name: impl Into<String>) -> Self {
+        let concurrency = match std::env::var(CONCURRENCY_VAR) {
+            Ok(levels) => levels
+                .split(',')
+                .map(|level| match level.trim().parse() {
+                    Ok(level) if level > 0 => level,
+                    _ => panic!("{CONCURRENCY_VAR} must list positive integers"),
+                })
+                .collect(),
+            Err(_) => DEFAULT_CONCURRENCY.to_vec(),
+        };
+
+        Self {
+            name: name.into(),
+            concurrency,
+            runtime: tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build runtime"),
+        }
+    }
+
+    /// Configures the levels of concurrency to measure, overriding
+    /// [`CONCURRENCY_VAR`].
+    pub fn with_concurrency(mut self, concurrency: impl IntoIterator<Item = usize>) -> Self {
+        self.concurrency = concurrency.into_iter().collect();
+        self
+    }
+
+    /// Returns the runtime the store is driven by, e.g. for connecting to the
+    /// store's backend.
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Measures loading, saving and cycling sessions of the store.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store fails.
+    pub fn run(&self, c: &mut Criterion, store: impl SessionStore) {
+        let store = Arc::new(store);
+
+        self.measure(c, "load", &store, |store, record| async move {
+            let record = record.lock().await;
+            store.load(&record.id).await.expect("Failed to load");
+        });
+
+        self.measure(c, "save", &store, |store, record| async move {
+            let record = record.lock().await;
+            store.save(&record).await.expect("Failed to save");
+        });
+
+        self.measure(c, "cycle", &store, |store, record| async move {
+            let mut record = record.lock().await;
+            let old_session_id = record.id;
+            record.id = Id::default();
+            store
+                .cycle_and_save(&old_session_id, &mut record)
+                .await
+                .expect("Failed to cycle");
+        });
+    }
+
+    // Runs `op` concurrently against a record of its own per task, at each level of
+    // concurrency.
+    fn measure<S, F, Fut>(&self, c: &mut Criterion, op: &str, store: &Arc<S>, f: F)
+    where
+        S: SessionStore,
+        F: Fn(Arc<S>, Arc<Mutex<Record>>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut group = c.benchmark_group(op);
+        for &concurrency in &self.concurrency {
+            let records = self.runtime.block_on(async {
+                let mut records = Vec::with_capacity(concurrency);
+                for _ in 0..concurrency {
+                    let mut record = record();
+                    store.create(&mut record).await.expect("Failed to create");
+                    records.push(Arc::new(Mutex::new(record)));
+                }
+                records
+            });
+
+            group.throughput(Throughput::Elements(concurrency as u64));
+            group.bench_with_input(
+                BenchmarkId::new(&self.name, concurrency),
+                &records,
+                |b, records| {
+                    b.to_async(&self.runtime).iter(|| async {
+                        let tasks = records
+                            .iter()
+                            .map(|record| tokio::spawn(f(store.clone(), record.clone())))
+                            .collect::<Vec<_>>();
+                        for task in tasks {
+                            task.await.expect("Operation panicked");
+                        }
+                    })
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+// A record with data typical of an authenticated session.
+fn record() -> Record {
+    let mut record = Record {
+        id: Id::default(),
+        data: Default::default(),
+        expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    };
+    record.data.insert("user_id".to_string(), 42.into());
+    record
+        .data
+        .insert("csrf_token".to_string(), Id::default().to_string().into());
+    record
+        .data
+        .insert("roles".to_string(), vec!["admin", "editor"].into());
+    record
+}
@@ -0,0 +1,49 @@
+use std::convert::Infallible;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use http::{Request, Response};
+use tower::{ServiceBuilder, ServiceExt};
+use tower_sessions::{MemoryStore, Session, SessionManagerLayer};
+
+type Body = String;
+
+async fn noop_handler(_: Request<Body>) -> Result<Response<Body>, Infallible> {
+    Ok(Response::new(Body::default()))
+}
+
+async fn insert_handler(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let session = req.extensions().get::<Session>().expect("Missing session");
+    session.insert("foo", 42).await.expect("Insert failed");
+    Ok(Response::new(Body::default()))
+}
+
+fn middleware(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("Failed to build runtime");
+
+    // Requests which neither present nor touch a session.
+    let svc = ServiceBuilder::new()
+        .layer(SessionManagerLayer::new(MemoryStore::default()))
+        .service_fn(noop_handler);
+    c.bench_function("untouched session", |b| {
+        b.to_async(&rt).iter(|| {
+            let req = Request::new(Body::default());
+            svc.clone().oneshot(req)
+        })
+    });
+
+    // Requests which create and save a new session.
+    let svc = ServiceBuilder::new()
+        .layer(SessionManagerLayer::new(MemoryStore::default()))
+        .service_fn(insert_handler);
+    c.bench_function("saved session", |b| {
+        b.to_async(&rt).iter(|| {
+            let req = Request::new(Body::default());
+            svc.clone().oneshot(req)
+        })
+    });
+}
+
+criterion_group!(benches, middleware);
+criterion_main!(benches);
@@ -0,0 +1,328 @@
+//! Store maintenance tooling for `tower-sessions`.
+//!
+//! Because stores are constructed and configured by the application, this
+//! crate provides the commands as a library rather than a prebuilt binary.
+//! Applications wire their store into a small binary of their own, which can
+//! then be used from ops runbooks:
+//!
+//! ```rust,no_run
+//! use clap::Parser;
+//! use tower_sessions_cli::{Admin, Cli};
+//! use tower_sessions_memory_store::MemoryStore;
+//!
+//! # tokio_test::block_on(async {
+//! let cli = Cli::parse();
+//! let session_store = MemoryStore::default();
+//! Admin::new(session_store)
+//!     .run(cli.command, &mut std::io::stdout())
+//!     .await
+//!     .unwrap();
+//! # });
+//! ```
+//!
+//! The following commands are available:
+//!
+//! - `purge`: delete expired sessions,
+//! - `count`: print the number of active sessions,
+//! - `delete <ID>`: delete a session,
+//! - `dump <ID>`: print a session record as JSON,
+//! - `migrate`: set up the store's schema.
+//!
+//! Sessions may also be identified by their cookie value by passing
+//! `--cookie`, in which case the value is decoded with the configured
+//! [`CookieValueCodec`].
+//!
+//! Commands other than `delete` and `dump` depend on capabilities beyond
+//! [`SessionStore`] and are provided by implementing [`AdminStore`].
+#![warn(
+    clippy::all,
+    nonstandard_style,
+    future_incompatible,
+    missing_debug_implementations
+)]
+#![deny(missing_docs)]
+#![forbid(unsafe_code)]
+
+use std::{io, sync::Arc};
+
+use async_trait::async_trait;
+use clap::{Args, Parser, Subcommand};
+use tower_sessions::{
+    service::{CookieValueCodec, PlainIdCodec},
+    session::Id,
+    session_store, SessionStore,
+};
+
+/// A specialized `Result` type for admin commands.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Admin command errors.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Maps `session_store::Error` errors.
+    #[error(transparent)]
+    Store(#[from] session_store::Error),
+
+    /// The store does not support the command.
+    #[error("The store does not support `{0}`")]
+    Unsupported(&'static str),
+
+    /// The session could not be identified from the given value.
+    #[error("Invalid session: {0}")]
+    InvalidSession(String),
+
+    /// No active session was found.
+    #[error("Session not found")]
+    NotFound,
+
+    /// Maps `serde_json` errors.
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+
+    /// Maps `std::io` errors.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Command line interface for store maintenance.
+#[derive(Debug, Parser)]
+#[command(about = "Maintenance commands for a tower-sessions store")]
+pub struct Cli {
+    /// The command to run.
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Maintenance commands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Delete expired sessions.
+    Purge,
+
+    /// Print the number of active sessions.
+    Count,
+
+    /// Delete a session.
+    Delete(Target),
+
+    /// Print a session record as JSON.
+    Dump(Target),
+
+    /// Set up the store's schema.
+    Migrate,
+}
+
+/// Identifies the session a command operates on.
+#[derive(Debug, Args)]
+pub struct Target {
+    /// The session ID, or the session cookie value with `--cookie`.
+    ///
+    /// Encoded IDs may begin with a hyphen, so such values are not taken to be
+    /// flags.
+    #[arg(allow_hyphen_values = true)]
+    pub value: String,
+
+    /// Decode the value as a session cookie value.
+    #[arg(long)]
+    pub cookie: bool,
+}
+
+/// Maintenance capabilities beyond [`SessionStore`].
+///
+/// All methods default to returning [`Error::Unsupported`], so stores only
+/// need to implement those they support.
+#[async_trait]
+pub trait AdminStore: SessionStore {
+    /// Deletes expired sessions.
+    async fn purge_expired(&self) -> Result<()> {
+        Err(Error::Unsupported("purge"))
+    }
+
+    /// Returns the number of active sessions.
+    async fn count(&self) -> Result<usize> {
+        Err(Error::Unsupported("count"))
+    }
+
+    /// Creates or updates the store's schema.
+    async fn migrate(&self) -> Result<()> {
+        Err(Error::Unsupported("migrate"))
+    }
+}
+
+#[cfg(feature = "memory-store")]
+#[async_trait]
+impl AdminStore for tower_sessions_memory_store::MemoryStore {
+    async fn purge_expired(&self) -> Result<()> {
+        use tower_sessions::session_store::ExpiredDeletion;
+
+        Ok(self.delete_expired().await?)
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(tower_sessions_memory_store::MemoryStore::count(self).await)
+    }
+
+    // There's no schema to set up.
+    async fn migrate(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs maintenance commands against a store.
+#[derive(Debug)]
+pub struct Admin<Store: AdminStore> {
+    store: Store,
+    cookie_value_codec: Arc<dyn CookieValueCodec>,
+}
+
+impl<Store: AdminStore> Admin<Store> {
+    /// Create a new [`Admin`] for the given store.
+    pub fn new(store: Store) -> Self {
+        Self {
+            store,
+            cookie_value_codec: Arc::new(PlainIdCodec),
+        }
+    }
+
+    /// Configures the codec used to decode cookie values given with
+    /// `--cookie`. This should match the codec the application's
+    /// `SessionManagerLayer` is configured with.
+    /// The default value is [`PlainIdCodec`].
+    pub fn with_cookie_value_codec(mut self, cookie_value_codec: impl CookieValueCodec) -> Self {
+        self.cookie_value_codec = Arc::new(cookie_value_codec);
+        self
+    }
+
+    /// Runs the command, writing its output to `out`.
+    pub async fn run(&self, command: Command, out: &mut impl io::Write) -> Result<()> {
+        match command {
+            Command::Purge => {
+                self.store.purge_expired().await?;
+                writeln!(out, "Purged expired sessions")?;
+            }
+
+            Command::Count => {
+                writeln!(out, "{}", self.store.count().await?)?;
+            }
+
+            Command::Delete(target) => {
+                let session_id = self.session_id(&target)?;
+                self.store.delete(&session_id).await?;
+                writeln!(out, "Deleted session {session_id}")?;
+            }
+
+            Command::Dump(target) => {
+                let session_id = self.session_id(&target)?;
+                let record = self.store.load(&session_id).await?.ok_or(Error::NotFound)?;
+                serde_json::to_writer_pretty(&mut *out, &record)?;
+                writeln!(out)?;
+            }
+
+            Command::Migrate => {
+                self.store.migrate().await?;
+                writeln!(out, "Migrated store")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn session_id(&self, target: &Target) -> Result<Id> {
+        let session_id = if target.cookie {
+            self.cookie_value_codec.decode(&target.value)
+        } else {
+            target.value.parse().map_err(Into::into)
+        };
+
+        session_id.map_err(|err| Error::InvalidSession(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+    use tower_sessions::session::Record;
+    use tower_sessions_memory_store::MemoryStore;
+
+    use super::*;
+
+    async fn run(admin: &Admin<MemoryStore>, args: &[&str]) -> Result<String> {
+        let cli =
+            Cli::try_parse_from(std::iter::once("tower-sessions").chain(args.iter().copied()))
+                .expect("Valid arguments");
+        let mut out = Vec::new();
+        admin.run(cli.command, &mut out).await?;
+        Ok(String::from_utf8(out).expect("UTF-8 output"))
+    }
+
+    async fn create(store: &MemoryStore, expiry_date: OffsetDateTime) -> Id {
+        let mut record = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        record.data.insert("foo".to_string(), serde_json::json!(42));
+        store.create(&mut record).await.unwrap();
+        record.id
+    }
+
+    #[tokio::test]
+    async fn test_count_and_purge() {
+        let store = MemoryStore::default();
+        let admin = Admin::new(store.clone());
+        create(&store, OffsetDateTime::now_utc() + Duration::minutes(30)).await;
+        create(&store, OffsetDateTime::now_utc() - Duration::minutes(30)).await;
+
+        assert_eq!(run(&admin, &["count"]).await.unwrap(), "1\n");
+        run(&admin, &["purge"]).await.unwrap();
+        assert_eq!(run(&admin, &["count"]).await.unwrap(), "1\n");
+    }
+
+    #[tokio::test]
+    async fn test_dump_hyphenated_id() {
+        let store = MemoryStore::default();
+        let admin = Admin::new(store.clone());
+        let record = Record {
+            id: "-AAAAAAAAAAAAAAAAAAAAA".parse().unwrap(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        store.save(&record).await.unwrap();
+
+        assert!(run(&admin, &["dump", "-AAAAAAAAAAAAAAAAAAAAA"])
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_dump_and_delete() {
+        let store = MemoryStore::default();
+        let admin = Admin::new(store.clone());
+        let session_id = create(&store, OffsetDateTime::now_utc() + Duration::minutes(30)).await;
+        let session_id = session_id.to_string();
+
+        let dump = run(&admin, &["dump", &session_id]).await.unwrap();
+        let dump: serde_json::Value = serde_json::from_str(&dump).unwrap();
+        assert_eq!(dump["data"]["foo"], 42);
+
+        run(&admin, &["delete", "--cookie", &session_id])
+            .await
+            .unwrap();
+        assert!(matches!(
+            run(&admin, &["dump", &session_id]).await,
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(
+            run(&admin, &["dump", "not-an-id"]).await,
+            Err(Error::InvalidSession(_))
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! An in-memory stand-in for the parts of etcd's KV and Lease services the
+//! store uses, for testing it without an etcd cluster.
+//!
+//! Leases don't expire, which the store doesn't rely on as it checks the
+//! expiry of records it loads.
+
+// Handlers fail with `tonic::Status`, as the services they stand in for.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    future::{ready, Ready},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    body::{empty_body, BoxBody},
+    codec::ProstCodec,
+    codegen::{http, Body, BoxFuture, Service, StdError},
+    metadata::GRPC_CONTENT_TYPE,
+    server::{Grpc, NamedService, UnaryService},
+    transport::{Endpoint, Server},
+    Code, Status,
+};
+
+use crate::{
+    proto::{self, compare, request_op::Request, response_op::Response},
+    EtcdStore,
+};
+
+#[derive(Clone, Default)]
+pub(crate) struct FakeEtcd {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    revision: i64,
+    kvs: BTreeMap<Vec<u8>, proto::KeyValue>,
+    // Time-to-lives of the granted leases by ID.
+    leases: HashMap<i64, i64>,
+    next_lease: i64,
+}
+
+impl FakeEtcd {
+    /// Serves the fake on a local port, returning a store connected to it.
+    pub(crate) async fn serve(self) -> EtcdStore {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(Kv(self.clone()))
+                .add_service(Lease(self))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        let endpoint = Endpoint::from_shared(format!("http://{addr}")).unwrap();
+        EtcdStore::connect_lazy(endpoint)
+    }
+
+    /// Returns the number of leases which haven't been revoked.
+    pub(crate) fn leases(&self) -> usize {
+        self.state.lock().unwrap().leases.len()
+    }
+
+    /// Returns the time-to-live of the lease the given key is attached to.
+    pub(crate) fn ttl(&self, key: &str) -> Option<i64> {
+        let state = self.state.lock().unwrap();
+        let kv = state.kvs.get(key.as_bytes())?;
+        state.leases.get(&kv.lease).copied()
+    }
+
+    fn call<B>(&self, req: http::Request<B>) -> BoxFuture<http::Response<BoxBody>, Infallible>
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        match req.uri().path() {
+            proto::RANGE => self.unary(req, State::range),
+            proto::PUT => self.unary(req, State::put),
+            proto::DELETE_RANGE => self.unary(req, State::delete_range),
+            proto::TXN => self.unary(req, State::txn),
+            proto::LEASE_GRANT => self.unary(req, State::lease_grant),
+            proto::LEASE_REVOKE => self.unary(req, State::lease_revoke),
+            _ => Box::pin(async {
+                let mut res = http::Response::new(empty_body());
+                let headers = res.headers_mut();
+                headers.insert(Status::GRPC_STATUS, (Code::Unimplemented as i32).into());
+                headers.insert(http::header::CONTENT_TYPE, GRPC_CONTENT_TYPE);
+                Ok(res)
+            }),
+        }
+    }
+
+    fn unary<B, Req, Res>(
+        &self,
+        req: http::Request<B>,
+        handler: Handler<Req, Res>,
+    ) -> BoxFuture<http::Response<BoxBody>, Infallible>
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+        Req: prost::Message + Default + Send + 'static,
+        Res: prost::Message + Send + 'static,
+    {
+        let method = Method {
+            state: self.state.clone(),
+            handler,
+        };
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::<Res, Req>::default());
+            Ok(grpc.unary(method, req).await)
+        })
+    }
+}
+
+type Handler<Req, Res> = fn(&mut State, Req) -> Result<Res, Status>;
+
+struct Method<Req, Res> {
+    state: Arc<Mutex<State>>,
+    handler: Handler<Req, Res>,
+}
+
+impl<Req, Res> UnaryService<Req> for Method<Req, Res> {
+    type Response = Res;
+    type Future = Ready<Result<tonic::Response<Res>, Status>>;
+
+    fn call(&mut self, req: tonic::Request<Req>) -> Self::Future {
+        let mut state = self.state.lock().unwrap();
+        ready((self.handler)(&mut state, req.into_inner()).map(tonic::Response::new))
+    }
+}
+
+impl State {
+    fn header(&self) -> Option<proto::ResponseHeader> {
+        Some(proto::ResponseHeader {
+            revision: self.revision,
+        })
+    }
+
+    fn range(&mut self, req: proto::RangeRequest) -> Result<proto::RangeResponse, Status> {
+        Ok(proto::RangeResponse {
+            header: self.header(),
+            kvs: self.kvs.get(&req.key).cloned().into_iter().collect(),
+        })
+    }
+
+    fn put(&mut self, req: proto::PutRequest) -> Result<proto::PutResponse, Status> {
+        if req.lease != 0 && !self.leases.contains_key(&req.lease) {
+            return Err(Status::not_found("etcdserver: requested lease not found"));
+        }
+
+        self.revision += 1;
+        let prev_kv = self.kvs.get(&req.key).cloned();
+        let kv = proto::KeyValue {
+            key: req.key.clone(),
+            create_revision: prev_kv
+                .as_ref()
+                .map_or(self.revision, |kv| kv.create_revision),
+            mod_revision: self.revision,
+            version: prev_kv.as_ref().map_or(1, |kv| kv.version + 1),
+            value: req.value,
+            lease: req.lease,
+        };
+        self.kvs.insert(req.key, kv);
+
+        Ok(proto::PutResponse {
+            header: self.header(),
+            prev_kv: prev_kv.filter(|_| req.prev_kv),
+        })
+    }
+
+    fn delete_range(
+        &mut self,
+        req: proto::DeleteRangeRequest,
+    ) -> Result<proto::DeleteRangeResponse, Status> {
+        let prev_kv = self.kvs.remove(&req.key);
+        if prev_kv.is_some() {
+            self.revision += 1;
+        }
+
+        Ok(proto::DeleteRangeResponse {
+            header: self.header(),
+            deleted: prev_kv.is_some().into(),
+            prev_kvs: prev_kv.filter(|_| req.prev_kv).into_iter().collect(),
+        })
+    }
+
+    fn txn(&mut self, req: proto::TxnRequest) -> Result<proto::TxnResponse, Status> {
+        let mut succeeded = true;
+        for cmp in &req.compare {
+            let Some(compare::TargetUnion::CreateRevision(revision)) = cmp.target_union else {
+                return Err(Status::unimplemented("unsupported comparison"));
+            };
+            if cmp.result != compare::EQUAL || cmp.target != compare::CREATE {
+                return Err(Status::unimplemented("unsupported comparison"));
+            }
+            let create_revision = self.kvs.get(&cmp.key).map_or(0, |kv| kv.create_revision);
+            succeeded &= create_revision == revision;
+        }
+
+        let ops = if succeeded { req.success } else { req.failure };
+        let responses = ops
+            .into_iter()
+            .map(|op| {
+                let response = match op.request {
+                    Some(Request::Range(req)) => Response::Range(self.range(req)?),
+                    Some(Request::Put(req)) => Response::Put(self.put(req)?),
+                    Some(Request::DeleteRange(req)) => {
+                        Response::DeleteRange(self.delete_range(req)?)
+                    }
+                    None => return Err(Status::invalid_argument("empty operation")),
+                };
+                Ok(proto::ResponseOp {
+                    response: Some(response),
+                })
+            })
+            .collect::<Result<_, Status>>()?;
+
+        Ok(proto::TxnResponse {
+            header: self.header(),
+            succeeded,
+            responses,
+        })
+    }
+
+    fn lease_grant(
+        &mut self,
+        req: proto::LeaseGrantRequest,
+    ) -> Result<proto::LeaseGrantResponse, Status> {
+        self.next_lease += 1;
+        self.leases.insert(self.next_lease, req.ttl);
+
+        Ok(proto::LeaseGrantResponse {
+            header: self.header(),
+            id: self.next_lease,
+            ttl: req.ttl,
+            error: String::new(),
+        })
+    }
+
+    fn lease_revoke(
+        &mut self,
+        req: proto::LeaseRevokeRequest,
+    ) -> Result<proto::LeaseRevokeResponse, Status> {
+        if self.leases.remove(&req.id).is_none() {
+            return Err(Status::not_found("etcdserver: requested lease not found"));
+        }
+        // Keys attached to a revoked lease are deleted along with it.
+        self.kvs.retain(|_, kv| kv.lease != req.id);
+
+        Ok(proto::LeaseRevokeResponse {
+            header: self.header(),
+        })
+    }
+}
+
+// Routes `etcdserverpb.KV` requests to the fake.
+#[derive(Clone)]
+struct Kv(FakeEtcd);
+
+impl NamedService for Kv {
+    const NAME: &'static str = "etcdserverpb.KV";
+}
+
+impl<B> Service<http::Request<B>> for Kv
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.0.call(req)
+    }
+}
+
+// Routes `etcdserverpb.Lease` requests to the fake.
+#[derive(Clone)]
+struct Lease(FakeEtcd);
+
+impl NamedService for Lease {
+    const NAME: &'static str = "etcdserverpb.Lease";
+}
+
+impl<B> Service<http::Request<B>> for Lease
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.0.call(req)
+    }
+}
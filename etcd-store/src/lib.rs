@@ -0,0 +1,418 @@
+//! A session store for [etcd](https://etcd.io) v3.
+//!
+//! [`EtcdStore`] talks to etcd's gRPC API with `tonic`, keeping each session
+//! under its own key attached to a lease which expires along with the session.
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tonic::{
+    client::Grpc,
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Endpoint},
+    Code, Status,
+};
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+
+use crate::proto::{compare, request_op::Request, response_op::Response};
+
+mod proto;
+
+#[cfg(test)]
+mod fake;
+
+const BACKEND: &str = "etcd";
+
+/// A session store backed by [etcd](https://etcd.io) v3.
+///
+/// Records are stored as JSON under their session ID, optionally prefixed.
+/// Each save attaches the key to a new lease whose time-to-live ends when the
+/// session expires, so etcd removes expired sessions itself and no cleanup
+/// task is required. The lease the key was previously attached to is revoked.
+///
+/// Creating and cycling sessions, as well as
+/// [`get_or_create`](SessionStore::get_or_create), are transactions which
+/// only write the new key if no key exists under its ID, compared by its
+/// create revision. Concurrent writers therefore never overwrite each other's
+/// new sessions, and cycling a session deletes the old key in the same
+/// transaction.
+///
+/// Requests are multiplexed over the HTTP/2 connections of the underlying
+/// [`Channel`], which can balance them over the members of a cluster via
+/// [`Channel::balance_list`]. Clusters with authentication enabled aren't
+/// supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use tonic::transport::Endpoint;
+/// use tower_sessions::{EtcdStore, SessionManagerLayer};
+///
+/// # tokio_test::block_on(async {
+/// let endpoint = Endpoint::from_static("http://etcd.internal:2379");
+/// let session_store = EtcdStore::connect_lazy(endpoint).with_prefix("sessions/");
+/// let session_layer = SessionManagerLayer::new(session_store);
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct EtcdStore {
+    grpc: Grpc<Channel>,
+    prefix: Cow<'static, str>,
+}
+
+impl EtcdStore {
+    /// Create a new `EtcdStore` sending requests over the given channel.
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            grpc: Grpc::new(channel),
+            prefix: Cow::Borrowed(""),
+        }
+    }
+
+    /// Create a new `EtcdStore` connecting to the given endpoint once the
+    /// first request is sent.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn connect_lazy(endpoint: Endpoint) -> Self {
+        Self::new(endpoint.connect_lazy())
+    }
+
+    /// Configures a prefix for the keys sessions are stored under, allowing
+    /// a cluster to be shared with other data.
+    /// The default value is `""`.
+    pub fn with_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, session_id: &Id) -> Vec<u8> {
+        format!("{}{}", self.prefix, session_id).into_bytes()
+    }
+
+    // Lease time-to-lives are whole seconds; etcd raises those below its
+    // minimum.
+    fn ttl(record: &Record, now: OffsetDateTime) -> i64 {
+        let remaining = record.expiry_date - now;
+        let ttl = remaining.whole_seconds() + i64::from(remaining.subsec_nanoseconds() > 0);
+        ttl.max(1)
+    }
+
+    async fn unary<Req, Res>(&self, path: &'static str, message: Req) -> session_store::Result<Res>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Res: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = self.grpc.clone();
+        grpc.ready().await.map_err(|err| {
+            session_store::Error::Unavailable(err.to_string()).with_backend(BACKEND)
+        })?;
+        let res = grpc
+            .unary(
+                tonic::Request::new(message),
+                PathAndQuery::from_static(path),
+                ProstCodec::default(),
+            )
+            .await
+            .map_err(status_error)?;
+        Ok(res.into_inner())
+    }
+
+    async fn grant(&self, record: &Record) -> session_store::Result<i64> {
+        let req = proto::LeaseGrantRequest {
+            ttl: Self::ttl(record, OffsetDateTime::now_utc()),
+        };
+        let res: proto::LeaseGrantResponse = self.unary(proto::LEASE_GRANT, req).await?;
+        if !res.error.is_empty() {
+            return Err(session_store::Error::Backend(res.error).with_backend(BACKEND));
+        }
+        Ok(res.id)
+    }
+
+    // Revokes a lease no key is attached to anymore. Leases expire on their own,
+    // so failing to revoke one only delays its removal.
+    async fn revoke(&self, lease: i64) {
+        if lease == 0 {
+            return;
+        }
+        let req = proto::LeaseRevokeRequest { id: lease };
+        let res: session_store::Result<proto::LeaseRevokeResponse> =
+            self.unary(proto::LEASE_REVOKE, req).await;
+        if let Err(err) = res {
+            tracing::debug!(err = %err, lease, "failed to revoke lease");
+        }
+    }
+
+    // Writes the record under its ID unless a key exists there, returning the
+    // response to `failure` otherwise.
+    async fn put_new(
+        &self,
+        record: &Record,
+        success: Vec<proto::RequestOp>,
+        failure: Vec<proto::RequestOp>,
+    ) -> session_store::Result<Result<proto::TxnResponse, proto::TxnResponse>> {
+        let key = self.key(&record.id);
+        let lease = self.grant(record).await?;
+        let put = proto::RequestOp {
+            request: Some(Request::Put(proto::PutRequest {
+                key: key.clone(),
+                value: encode(record)?,
+                lease,
+                prev_kv: false,
+            })),
+        };
+        let req = proto::TxnRequest {
+            compare: vec![proto::Compare {
+                result: compare::EQUAL,
+                target: compare::CREATE,
+                key,
+                target_union: Some(compare::TargetUnion::CreateRevision(0)),
+            }],
+            success: success.into_iter().chain([put]).collect(),
+            failure,
+        };
+        let res: proto::TxnResponse = self.unary(proto::TXN, req).await?;
+        if res.succeeded {
+            Ok(Ok(res))
+        } else {
+            self.revoke(lease).await;
+            Ok(Err(res))
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for EtcdStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        while self.put_new(record, Vec::new(), Vec::new()).await?.is_err() {
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let lease = self.grant(record).await?;
+        let req = proto::PutRequest {
+            key: self.key(&record.id),
+            value: encode(record)?,
+            lease,
+            prev_kv: true,
+        };
+        let res: proto::PutResponse = match self.unary(proto::PUT, req).await {
+            Ok(res) => res,
+            Err(err) => {
+                self.revoke(lease).await;
+                return Err(err);
+            }
+        };
+        if let Some(prev_kv) = res.prev_kv {
+            if prev_kv.lease != lease {
+                self.revoke(prev_kv.lease).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let req = proto::RangeRequest {
+            key: self.key(session_id),
+        };
+        let res: proto::RangeResponse = self.unary(proto::RANGE, req).await?;
+        res.kvs
+            .first()
+            .map(|kv| decode(&kv.value))
+            .transpose()
+            .map(|record| record.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        let req = proto::DeleteRangeRequest {
+            key: self.key(session_id),
+            prev_kv: true,
+        };
+        let res: proto::DeleteRangeResponse = self.unary(proto::DELETE_RANGE, req).await?;
+        for prev_kv in res.prev_kvs {
+            self.revoke(prev_kv.lease).await;
+        }
+        Ok(())
+    }
+
+    async fn get_or_create(&self, record: &Record) -> session_store::Result<Option<Record>> {
+        let range = proto::RequestOp {
+            request: Some(Request::Range(proto::RangeRequest {
+                key: self.key(&record.id),
+            })),
+        };
+        let Err(res) = self.put_new(record, Vec::new(), vec![range]).await? else {
+            return Ok(None);
+        };
+
+        let existing = res
+            .responses
+            .into_iter()
+            .find_map(|res| match res.response {
+                Some(Response::Range(range)) => range.kvs.into_iter().next(),
+                _ => None,
+            })
+            .map(|kv| decode(&kv.value))
+            .transpose()?;
+        match existing {
+            Some(existing) if existing.is_active() => Ok(Some(existing)),
+            // The key outlives the session by less than a second, until its
+            // lease expires.
+            _ => {
+                self.save(record).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn cycle_and_save(
+        &self,
+        old_session_id: &Id,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        let delete = || proto::RequestOp {
+            request: Some(Request::DeleteRange(proto::DeleteRangeRequest {
+                key: self.key(old_session_id),
+                prev_kv: true,
+            })),
+        };
+        let res = loop {
+            match self.put_new(record, vec![delete()], Vec::new()).await? {
+                Ok(res) => break res,
+                // Session ID collision mitigation.
+                Err(_) => record.id = Id::default(),
+            }
+        };
+
+        for res in res.responses {
+            if let Some(Response::DeleteRange(delete)) = res.response {
+                for prev_kv in delete.prev_kvs {
+                    self.revoke(prev_kv.lease).await;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn encode(record: &Record) -> session_store::Result<Vec<u8>> {
+    serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+}
+
+fn decode(value: &[u8]) -> session_store::Result<Record> {
+    serde_json::from_slice(value).map_err(|err| session_store::Error::Decode(err.to_string()))
+}
+
+fn status_error(status: Status) -> session_store::Error {
+    let message = status.message().to_string();
+    let err = match status.code() {
+        Code::DeadlineExceeded => session_store::Error::Timeout(message),
+        Code::Unavailable | Code::ResourceExhausted => session_store::Error::Unavailable(message),
+        _ => session_store::Error::Backend(message),
+    };
+    err.with_backend(BACKEND)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+    use tower_sessions_core::session_store::ErrorKind;
+
+    use super::*;
+    use crate::fake::FakeEtcd;
+
+    async fn store() -> EtcdStore {
+        FakeEtcd::default().serve().await.with_prefix("sessions/")
+    }
+
+    fn record(expiry_date: OffsetDateTime) -> Record {
+        Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    tower_sessions_test_kit::store_conformance_tests!(store().await);
+
+    #[tokio::test]
+    async fn test_leases() {
+        let etcd = FakeEtcd::default();
+        let store = etcd.clone().serve().await;
+
+        let mut record = record(OffsetDateTime::now_utc() + Duration::minutes(30));
+        store.create(&mut record).await.unwrap();
+        assert_eq!(etcd.leases(), 1);
+        let ttl = etcd.ttl(&record.id.to_string()).unwrap();
+        assert!((1799..=1800).contains(&ttl), "unexpected TTL {ttl}");
+
+        // Saving moves the key to a new lease, revoking the old one.
+        store.save(&record).await.unwrap();
+        assert_eq!(etcd.leases(), 1);
+
+        let old_session_id = record.id;
+        store
+            .cycle_and_save(&old_session_id, &mut record)
+            .await
+            .unwrap();
+        assert_ne!(record.id, old_session_id);
+        assert_eq!(store.load(&old_session_id).await.unwrap(), None);
+        assert_eq!(etcd.leases(), 1);
+
+        store.delete(&record.id).await.unwrap();
+        assert_eq!(etcd.leases(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_create_keeps_lease_count() {
+        let etcd = FakeEtcd::default();
+        let store = etcd.clone().serve().await;
+
+        let record = record(OffsetDateTime::now_utc() + Duration::minutes(30));
+        assert_eq!(store.get_or_create(&record).await.unwrap(), None);
+        assert_eq!(
+            store.get_or_create(&record).await.unwrap(),
+            Some(record.clone())
+        );
+        assert_eq!(etcd.leases(), 1);
+    }
+
+    #[test]
+    fn test_ttl() {
+        let now = OffsetDateTime::now_utc();
+
+        let record = record(now + Duration::seconds(90));
+        assert_eq!(EtcdStore::ttl(&record, now), 90);
+
+        let record = self::record(now + Duration::milliseconds(1500));
+        assert_eq!(EtcdStore::ttl(&record, now), 2);
+
+        // Expired records are still written, and aren't loaded.
+        let record = self::record(now - Duration::seconds(5));
+        assert_eq!(EtcdStore::ttl(&record, now), 1);
+    }
+
+    #[test]
+    fn test_status_error() {
+        let err = status_error(Status::deadline_exceeded("slow"));
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+
+        let err = status_error(Status::unavailable("etcdserver: leader changed"));
+        assert_eq!(err.kind(), ErrorKind::Unavailable);
+        assert!(err.is_retryable());
+
+        let err = status_error(Status::invalid_argument("etcdserver: key is not provided"));
+        assert_eq!(err.kind(), ErrorKind::Backend);
+        assert_eq!(err.to_string(), "etcd: etcdserver: key is not provided");
+    }
+}
@@ -0,0 +1,198 @@
+//! The subset of etcd's v3 API used by [`EtcdStore`](crate::EtcdStore), from
+//! `etcdserverpb/rpc.proto` and `mvccpb/kv.proto`.
+//!
+//! These are written by hand, matching what `prost-build` generates, so that
+//! building the crate doesn't require `protoc`. Fields the store doesn't use
+//! are left out; protobuf skips them when decoding.
+
+pub(crate) const RANGE: &str = "/etcdserverpb.KV/Range";
+pub(crate) const PUT: &str = "/etcdserverpb.KV/Put";
+pub(crate) const DELETE_RANGE: &str = "/etcdserverpb.KV/DeleteRange";
+pub(crate) const TXN: &str = "/etcdserverpb.KV/Txn";
+pub(crate) const LEASE_GRANT: &str = "/etcdserverpb.Lease/LeaseGrant";
+pub(crate) const LEASE_REVOKE: &str = "/etcdserverpb.Lease/LeaseRevoke";
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct ResponseHeader {
+    #[prost(int64, tag = "3")]
+    pub revision: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct KeyValue {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(int64, tag = "2")]
+    pub create_revision: i64,
+    #[prost(int64, tag = "3")]
+    pub mod_revision: i64,
+    #[prost(int64, tag = "4")]
+    pub version: i64,
+    #[prost(bytes = "vec", tag = "5")]
+    pub value: Vec<u8>,
+    #[prost(int64, tag = "6")]
+    pub lease: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RangeRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RangeResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+    #[prost(message, repeated, tag = "2")]
+    pub kvs: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct PutRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+    #[prost(int64, tag = "3")]
+    pub lease: i64,
+    #[prost(bool, tag = "4")]
+    pub prev_kv: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct PutResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+    #[prost(message, optional, tag = "2")]
+    pub prev_kv: Option<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DeleteRangeRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+    #[prost(bool, tag = "3")]
+    pub prev_kv: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DeleteRangeResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+    #[prost(int64, tag = "2")]
+    pub deleted: i64,
+    #[prost(message, repeated, tag = "3")]
+    pub prev_kvs: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct RequestOp {
+    #[prost(oneof = "request_op::Request", tags = "1, 2, 3")]
+    pub request: Option<request_op::Request>,
+}
+
+pub(crate) mod request_op {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub(crate) enum Request {
+        #[prost(message, tag = "1")]
+        Range(super::RangeRequest),
+        #[prost(message, tag = "2")]
+        Put(super::PutRequest),
+        #[prost(message, tag = "3")]
+        DeleteRange(super::DeleteRangeRequest),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct ResponseOp {
+    #[prost(oneof = "response_op::Response", tags = "1, 2, 3")]
+    pub response: Option<response_op::Response>,
+}
+
+pub(crate) mod response_op {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub(crate) enum Response {
+        #[prost(message, tag = "1")]
+        Range(super::RangeResponse),
+        #[prost(message, tag = "2")]
+        Put(super::PutResponse),
+        #[prost(message, tag = "3")]
+        DeleteRange(super::DeleteRangeResponse),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Compare {
+    // A `Compare.CompareResult`.
+    #[prost(int32, tag = "1")]
+    pub result: i32,
+    // A `Compare.CompareTarget`.
+    #[prost(int32, tag = "2")]
+    pub target: i32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub key: Vec<u8>,
+    #[prost(oneof = "compare::TargetUnion", tags = "5")]
+    pub target_union: Option<compare::TargetUnion>,
+}
+
+pub(crate) mod compare {
+    pub(crate) const EQUAL: i32 = 0;
+    pub(crate) const CREATE: i32 = 1;
+
+    #[derive(Clone, Copy, PartialEq, prost::Oneof)]
+    pub(crate) enum TargetUnion {
+        #[prost(int64, tag = "5")]
+        CreateRevision(i64),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct TxnRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub compare: Vec<Compare>,
+    #[prost(message, repeated, tag = "2")]
+    pub success: Vec<RequestOp>,
+    #[prost(message, repeated, tag = "3")]
+    pub failure: Vec<RequestOp>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct TxnResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+    #[prost(bool, tag = "2")]
+    pub succeeded: bool,
+    #[prost(message, repeated, tag = "3")]
+    pub responses: Vec<ResponseOp>,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct LeaseGrantRequest {
+    #[prost(int64, tag = "1")]
+    pub ttl: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct LeaseGrantResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+    #[prost(int64, tag = "2")]
+    pub id: i64,
+    #[prost(int64, tag = "3")]
+    pub ttl: i64,
+    #[prost(string, tag = "4")]
+    pub error: String,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct LeaseRevokeRequest {
+    #[prost(int64, tag = "1")]
+    pub id: i64,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct LeaseRevokeResponse {
+    #[prost(message, optional, tag = "1")]
+    pub header: Option<ResponseHeader>,
+}
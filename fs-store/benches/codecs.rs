@@ -0,0 +1,94 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::json;
+use time::{Duration, OffsetDateTime};
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store::{self, RecordCodec},
+};
+use tower_sessions_fs_store::{BincodeCodec, MsgpackCodec, PostcardCodec};
+
+#[derive(Debug)]
+struct JsonCodec;
+
+impl RecordCodec for JsonCodec {
+    fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+        serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+        serde_json::from_slice(bytes).map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+const CODECS: [(&str, &dyn RecordCodec); 4] = [
+    ("json", &JsonCodec),
+    ("msgpack", &MsgpackCodec),
+    ("bincode", &BincodeCodec),
+    ("postcard", &PostcardCodec),
+];
+
+fn record() -> Record {
+    Record {
+        id: Id::default(),
+        data: Default::default(),
+        expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    }
+}
+
+fn payloads() -> Vec<(&'static str, Record)> {
+    // An authenticated session holding a user ID and a CSRF token.
+    let mut small = record();
+    small.data.insert("user_id".to_string(), 42.into());
+    small
+        .data
+        .insert("csrf_token".to_string(), "a".repeat(43).into());
+
+    // A shopping cart of structured items.
+    let mut medium = small.clone();
+    let items = (0..32)
+        .map(|i| json!({ "sku": format!("sku-{i}"), "quantity": i % 5 + 1, "price": 9.99 }))
+        .collect::<Vec<_>>();
+    medium.data.insert("cart".to_string(), items.into());
+
+    // A record carrying a few hundred kilobytes of data.
+    let mut large = record();
+    for i in 0..256 {
+        large
+            .data
+            .insert(format!("key-{i}"), "x".repeat(1024).into());
+    }
+
+    vec![("small", small), ("medium", medium), ("large", large)]
+}
+
+fn codecs(c: &mut Criterion) {
+    for (payload, record) in payloads() {
+        let mut encode = c.benchmark_group(format!("encode {payload} record"));
+        for (name, codec) in CODECS {
+            let bytes = codec.encode(&record).expect("Failed to encode record");
+            println!("{name} {payload} record: {} bytes", bytes.len());
+            encode.throughput(Throughput::Bytes(bytes.len() as u64));
+            encode.bench_with_input(BenchmarkId::from_parameter(name), &record, |b, record| {
+                b.iter(|| codec.encode(record).expect("Failed to encode record"))
+            });
+        }
+        encode.finish();
+
+        let mut decode = c.benchmark_group(format!("decode {payload} record"));
+        for (name, codec) in CODECS {
+            let bytes = codec.encode(&record).expect("Failed to encode record");
+            decode.throughput(Throughput::Bytes(bytes.len() as u64));
+            decode.bench_with_input(BenchmarkId::from_parameter(name), &bytes, |b, bytes| {
+                b.iter(|| codec.decode(bytes).expect("Failed to decode record"))
+            });
+        }
+        decode.finish();
+    }
+}
+
+criterion_group!(benches, codecs);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use time::{Duration, OffsetDateTime};
+use tower_sessions_core::session::{Id, Record, RecordHeader};
+
+fn decode(c: &mut Criterion) {
+    // A record carrying a few hundred kilobytes of data.
+    let mut record = Record {
+        id: Id::default(),
+        data: Default::default(),
+        expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    };
+    for i in 0..256 {
+        record
+            .data
+            .insert(format!("key-{i}"), "x".repeat(1024).into());
+    }
+    let bytes = rmp_serde::to_vec(&record).expect("Failed to encode record");
+
+    c.bench_function("decode record", |b| {
+        b.iter(|| rmp_serde::from_slice::<Record>(&bytes).expect("Failed to decode record"))
+    });
+
+    c.bench_function("decode record header", |b| {
+        b.iter(|| {
+            rmp_serde::from_slice::<RecordHeader>(&bytes).expect("Failed to decode record header")
+        })
+    });
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
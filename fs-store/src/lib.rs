@@ -0,0 +1,1059 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    str,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tower_sessions_core::{
+    session::{Id, Record, RecordHeader},
+    session_store::{self, RecordCodec},
+    ExpiredDeletion, SessionStore, SessionStoreHealth, SessionStoreStats,
+};
+
+const EXTENSION: &str = "session";
+const TMP_EXTENSION: &str = "tmp";
+const SESSION_LOCK_EXTENSION: &str = "lock";
+const LOCK_FILE: &str = ".lock";
+
+/// A session store that persists each session as a file in a directory.
+///
+/// This suits small, self-hosted deployments which want sessions to survive
+/// restarts without running a database. Records are written to a temporary
+/// file which is then renamed over the session file, so readers never
+/// observe partially written records. Operations lock the directory, allowing
+/// several processes to share it.
+///
+/// Expired sessions are no longer loaded but remain on disk until removed via
+/// [`ExpiredDeletion::delete_expired`].
+///
+/// Records are encoded as MessagePack by default; see
+/// [`FileStore::new_with_codec`] for using another representation, such as
+/// `BincodeCodec` or `PostcardCodec` behind the `bincode` and `postcard`
+/// features. The `codecs` benchmark compares them on representative payloads.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::FileStore;
+///
+/// let session_store = FileStore::new("/var/lib/my-app/sessions").with_fsync(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    dir: Arc<PathBuf>,
+    fsync: bool,
+    codec: Arc<dyn RecordCodec>,
+}
+
+impl FileStore {
+    /// Create a new `FileStore` keeping its sessions in the given directory.
+    ///
+    /// The directory is created when first used. Records are encoded as
+    /// MessagePack within a [`CodecEnvelope`](session_store::CodecEnvelope)
+    /// tagged with [`MsgpackCodec::TAG`], and records written as plain
+    /// MessagePack remain readable.
+    #[cfg(feature = "msgpack")]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::new_with_codec(
+            dir,
+            session_store::CodecEnvelope::new(MsgpackCodec::TAG, MsgpackCodec)
+                .with_unversioned(MsgpackCodec),
+        )
+    }
+
+    /// Create a new `FileStore` keeping its sessions in the given directory,
+    /// encoded by the given codec.
+    ///
+    /// Records written with one codec can't be read with another. To change
+    /// the codec of an existing directory, use a
+    /// [`CodecEnvelope`](session_store::CodecEnvelope) which also decodes
+    /// records of the previous codec, and optionally rewrite them via
+    /// [`migrate_codec`](Self::migrate_codec).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{
+    ///     session::Record,
+    ///     session_store::{self, RecordCodec},
+    ///     FileStore,
+    /// };
+    ///
+    /// #[derive(Debug)]
+    /// struct JsonCodec;
+    ///
+    /// impl RecordCodec for JsonCodec {
+    ///     fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+    ///         serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+    ///     }
+    ///
+    ///     fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+    ///         serde_json::from_slice(bytes)
+    ///             .map_err(|err| session_store::Error::Decode(err.to_string()))
+    ///     }
+    /// }
+    ///
+    /// let session_store = FileStore::new_with_codec("/var/lib/my-app/sessions", JsonCodec);
+    /// ```
+    pub fn new_with_codec(dir: impl Into<PathBuf>, codec: impl RecordCodec) -> Self {
+        Self {
+            dir: Arc::new(dir.into()),
+            fsync: false,
+            codec: Arc::new(codec),
+        }
+    }
+
+    /// Configures whether writes are flushed to disk before completing.
+    /// The default value is `false`.
+    ///
+    /// Flushing ensures saved sessions survive a power loss, at the cost of
+    /// slower writes.
+    pub fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Rewrites the records which `from` decodes with `to`, returning the
+    /// number of records rewritten.
+    ///
+    /// Records are rewritten `batch_size` at a time, each batch holding the
+    /// directory's lock only while it's being rewritten, so the store remains
+    /// usable in the meantime. Records which `from` can't decode, e.g. those
+    /// already rewritten, are left as they are. The store itself should read
+    /// both codecs while migrating; see
+    /// [`CodecEnvelope`](session_store::CodecEnvelope).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use tower_sessions::{session_store::CodecEnvelope, FileStore};
+    /// use tower_sessions_fs_store::MsgpackCodec;
+    ///
+    /// # tokio_test::block_on(async {
+    /// // Wrap records written before envelopes were introduced.
+    /// let session_store = FileStore::new("/var/lib/my-app/sessions");
+    /// let rewritten = session_store
+    ///     .migrate_codec(
+    ///         MsgpackCodec,
+    ///         CodecEnvelope::new(MsgpackCodec::TAG, MsgpackCodec),
+    ///         100,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # })
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails when the directory can't be read or written, or `to` can't
+    /// encode a record.
+    pub async fn migrate_codec(
+        &self,
+        from: impl RecordCodec,
+        to: impl RecordCodec,
+        batch_size: usize,
+    ) -> session_store::Result<usize> {
+        let from: Arc<dyn RecordCodec> = Arc::new(from);
+        let to: Arc<dyn RecordCodec> = Arc::new(to);
+        let paths = self
+            .run(|store| {
+                let _lock = store.lock(false)?;
+                store.paths(EXTENSION)
+            })
+            .await?;
+
+        let mut rewritten = 0;
+        for batch in paths.chunks(batch_size.max(1)) {
+            let batch = batch.to_vec();
+            let (from, to) = (from.clone(), to.clone());
+            rewritten += self
+                .run(move |store| {
+                    let _lock = store.lock(true)?;
+                    let mut rewritten = 0;
+                    for path in batch {
+                        let Some(bytes) = store.read_bytes(&path)? else {
+                            continue;
+                        };
+                        if let Ok(record) = from.decode(&bytes) {
+                            store.write_bytes(&path, &to.encode(&record)?)?;
+                            rewritten += 1;
+                        }
+                    }
+                    Ok(rewritten)
+                })
+                .await?;
+        }
+        Ok(rewritten)
+    }
+
+    // Runs blocking filesystem work off the async runtime.
+    async fn run<T, F>(&self, f: F) -> session_store::Result<T>
+    where
+        F: FnOnce(&FileStore) -> session_store::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || f(&store))
+            .await
+            .map_err(|err| session_store::Error::Backend(err.to_string()))?
+    }
+
+    // Locks the directory, exclusively when modifying it. The lock is released
+    // when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> session_store::Result<File> {
+        fs::create_dir_all(self.dir.as_path()).map_err(backend)?;
+        let lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(LOCK_FILE))
+            .map_err(backend)?;
+        if exclusive {
+            lock_file.lock().map_err(backend)?;
+        } else {
+            lock_file.lock_shared().map_err(backend)?;
+        }
+        Ok(lock_file)
+    }
+
+    fn path(&self, session_id: &Id) -> PathBuf {
+        self.dir.join(format!("{session_id}.{EXTENSION}"))
+    }
+
+    fn read(&self, path: &Path) -> session_store::Result<Option<Record>> {
+        self.read_bytes(path)?
+            .map(|bytes| self.codec.decode(&bytes))
+            .transpose()
+    }
+
+    // Avoids decoding the data of large records when only their expiry is
+    // needed.
+    fn read_header(&self, path: &Path) -> session_store::Result<Option<RecordHeader>> {
+        self.read_bytes(path)?
+            .map(|bytes| self.codec.decode_header(&bytes))
+            .transpose()
+    }
+
+    fn read_bytes(&self, path: &Path) -> session_store::Result<Option<Vec<u8>>> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(backend(err)),
+        }
+    }
+
+    // Callers must hold the exclusive lock.
+    fn write(&self, record: &Record) -> session_store::Result<()> {
+        let bytes = self.codec.encode(record)?;
+        self.write_bytes(&self.path(&record.id), &bytes)
+    }
+
+    // Callers must hold the exclusive lock.
+    fn write_bytes(&self, path: &Path, bytes: &[u8]) -> session_store::Result<()> {
+        let tmp_path = path.with_extension(TMP_EXTENSION);
+        let mut tmp_file = File::create(&tmp_path).map_err(backend)?;
+        tmp_file.write_all(bytes).map_err(backend)?;
+        if self.fsync {
+            tmp_file.sync_all().map_err(backend)?;
+        }
+        fs::rename(&tmp_path, path).map_err(backend)?;
+        if self.fsync {
+            sync_dir(&self.dir).map_err(backend)?;
+        }
+
+        Ok(())
+    }
+
+    // Callers must hold the exclusive lock.
+    fn create_locked(&self, record: &mut Record) -> session_store::Result<()> {
+        while self.path(&record.id).try_exists().map_err(backend)? {
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+        self.write(record)
+    }
+
+    // Callers must hold the exclusive lock.
+    fn remove(&self, path: &Path) -> session_store::Result<()> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(backend(err)),
+        }
+    }
+
+    // Keys are hex encoded so that any key makes a valid file name.
+    fn session_lock_path(&self, session_id: &Id, key: &str) -> PathBuf {
+        let key = key
+            .bytes()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.dir
+            .join(format!("{session_id}.{key}.{SESSION_LOCK_EXTENSION}"))
+    }
+
+    // Returns the token of the holder of a session lock and when it expires.
+    // Unreadable locks, left behind by interrupted writes, count as expired.
+    fn read_session_lock(
+        &self,
+        path: &Path,
+    ) -> session_store::Result<Option<(u128, OffsetDateTime)>> {
+        let Some(bytes) = self.read_bytes(path)? else {
+            return Ok(None);
+        };
+        let lock = str::from_utf8(&bytes).ok().and_then(|lock| {
+            let (token, expires_at) = lock.split_once(' ')?;
+            let token = u128::from_str_radix(token, 16).ok()?;
+            let expires_at =
+                OffsetDateTime::from_unix_timestamp_nanos(expires_at.parse().ok()?).ok()?;
+            Some((token, expires_at))
+        });
+        Ok(Some(lock.unwrap_or((0, OffsetDateTime::UNIX_EPOCH))))
+    }
+
+    fn paths(&self, extension: &str) -> session_store::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(self.dir.as_path()).map_err(backend)? {
+            let path = entry.map_err(backend)?.path();
+            if path.extension().is_some_and(|ext| ext == extension) {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let mut created = record.clone();
+        *record = self
+            .run(move |store| {
+                let _lock = store.lock(true)?;
+                store.create_locked(&mut created)?;
+                Ok(created)
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let record = record.clone();
+        self.run(move |store| {
+            let _lock = store.lock(true)?;
+            store.write(&record)
+        })
+        .await
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let session_id = *session_id;
+        let record = self
+            .run(move |store| {
+                let _lock = store.lock(false)?;
+                store.read(&store.path(&session_id))
+            })
+            .await?;
+        Ok(record.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        let session_id = *session_id;
+        self.run(move |store| {
+            let _lock = store.lock(true)?;
+            store.remove(&store.path(&session_id))
+        })
+        .await
+    }
+
+    async fn cycle_and_save(
+        &self,
+        old_session_id: &Id,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        let old_session_id = *old_session_id;
+        let mut created = record.clone();
+        *record = self
+            .run(move |store| {
+                let _lock = store.lock(true)?;
+                store.remove(&store.path(&old_session_id))?;
+                store.create_locked(&mut created)?;
+                Ok(created)
+            })
+            .await?;
+        Ok(())
+    }
+
+    async fn increment(
+        &self,
+        session_id: &Id,
+        key: &str,
+        by: i64,
+    ) -> session_store::Result<Option<i64>> {
+        let session_id = *session_id;
+        let key = key.to_string();
+        self.run(move |store| {
+            let _lock = store.lock(true)?;
+            let Some(mut record) = store
+                .read(&store.path(&session_id))?
+                .filter(Record::is_active)
+            else {
+                return Ok(None);
+            };
+
+            let current = match record.data.get(&key) {
+                Some(value) => value.as_i64().ok_or_else(|| {
+                    session_store::Error::Decode(format!("Value of `{key}` is not an integer"))
+                })?,
+                None => 0,
+            };
+            let value = current.saturating_add(by);
+            record.data.insert(key, value.into());
+            store.write(&record)?;
+            Ok(Some(value))
+        })
+        .await
+    }
+
+    async fn list_recent(&self, limit: usize) -> session_store::Result<Vec<Record>> {
+        self.run(move |store| {
+            let _lock = store.lock(false)?;
+            let mut headers = Vec::new();
+            for path in store.paths(EXTENSION)? {
+                if let Some(header) = store.read_header(&path)? {
+                    headers.push(header);
+                }
+            }
+            headers.retain(RecordHeader::is_active);
+            headers.sort_by_key(|header| std::cmp::Reverse(header.expiry_date));
+
+            // Only the returned records, and internal ones skipped along the way, are
+            // decoded in full.
+            let mut records = Vec::with_capacity(limit.min(headers.len()));
+            for header in headers {
+                if records.len() == limit {
+                    break;
+                }
+                if let Some(record) = store.read(&store.path(&header.id))? {
+                    if !record.is_internal() {
+                        records.push(record);
+                    }
+                }
+            }
+            Ok(records)
+        })
+        .await
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> session_store::Result<bool> {
+        let path = self.session_lock_path(session_id, key);
+        self.run(move |store| {
+            let _lock = store.lock(true)?;
+            let now = OffsetDateTime::now_utc();
+            if store
+                .read_session_lock(&path)?
+                .is_some_and(|(_, expires_at)| expires_at > now)
+            {
+                return Ok(false);
+            }
+
+            let expires_at = (now + ttl).unix_timestamp_nanos();
+            fs::write(&path, format!("{token:x} {expires_at}")).map_err(backend)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> session_store::Result<()> {
+        let path = self.session_lock_path(session_id, key);
+        self.run(move |store| {
+            let _lock = store.lock(true)?;
+            if store
+                .read_session_lock(&path)?
+                .is_some_and(|(holder, _)| holder == token)
+            {
+                store.remove(&path)?;
+            }
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl SessionStoreHealth for FileStore {
+    async fn ping(&self) -> session_store::Result<()> {
+        self.run(|store| store.lock(false).map(drop)).await
+    }
+}
+
+#[async_trait]
+impl SessionStoreStats for FileStore {
+    async fn active_count(&self) -> session_store::Result<u64> {
+        self.run(|store| {
+            let _lock = store.lock(false)?;
+            let mut count = 0;
+            for path in store.paths(EXTENSION)? {
+                let header = store.read_header(&path)?;
+                if header.is_some_and(|header| header.is_active()) {
+                    count += 1;
+                }
+            }
+            Ok(count)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for FileStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        self.run(|store| {
+            let _lock = store.lock(true)?;
+            for path in store.paths(EXTENSION)? {
+                let header = store.read_header(&path)?;
+                if !header.is_some_and(|header| header.is_active()) {
+                    store.remove(&path)?;
+                }
+            }
+
+            // Temporary files are only left behind by interrupted writes.
+            for path in store.paths(TMP_EXTENSION)? {
+                store.remove(&path)?;
+            }
+
+            let now = OffsetDateTime::now_utc();
+            for path in store.paths(SESSION_LOCK_EXTENSION)? {
+                let lock = store.read_session_lock(&path)?;
+                if lock.is_none_or(|(_, expires_at)| expires_at <= now) {
+                    store.remove(&path)?;
+                }
+            }
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// The default [`RecordCodec`] of [`FileStore`], encoding records as
+/// MessagePack.
+///
+/// Headers are decoded without decoding the records' data.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgpackCodec;
+
+#[cfg(feature = "msgpack")]
+impl MsgpackCodec {
+    /// The tag of this codec within a [`CodecEnvelope`](session_store::CodecEnvelope).
+    pub const TAG: u8 = 1;
+}
+
+#[cfg(feature = "msgpack")]
+impl RecordCodec for MsgpackCodec {
+    fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+        rmp_serde::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+        rmp_serde::from_slice(bytes).map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+
+    fn decode_header(&self, bytes: &[u8]) -> session_store::Result<RecordHeader> {
+        rmp_serde::from_slice(bytes).map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+/// A [`RecordCodec`] encoding records with `bincode`.
+///
+/// `bincode` isn't self-describing, so the records' data is embedded as JSON.
+/// Headers are decoded without decoding the records' data.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl BincodeCodec {
+    /// The tag of this codec within a [`CodecEnvelope`](session_store::CodecEnvelope).
+    pub const TAG: u8 = 2;
+}
+
+#[cfg(feature = "bincode")]
+impl RecordCodec for BincodeCodec {
+    fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+        bincode::serde::encode_to_vec(
+            compact::RecordRef::try_from(record)?,
+            bincode::config::standard(),
+        )
+        .map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+        bincode::serde::decode_from_slice::<compact::Record, _>(bytes, bincode::config::standard())
+            .map_err(|err| session_store::Error::Decode(err.to_string()))?
+            .0
+            .try_into()
+    }
+
+    fn decode_header(&self, bytes: &[u8]) -> session_store::Result<RecordHeader> {
+        bincode::serde::decode_from_slice::<compact::Header, _>(bytes, bincode::config::standard())
+            .map(|(header, _)| header.into())
+            .map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+/// A [`RecordCodec`] encoding records with `postcard`.
+///
+/// `postcard` isn't self-describing, so the records' data is embedded as JSON.
+/// Headers are decoded without decoding the records' data.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostcardCodec;
+
+#[cfg(feature = "postcard")]
+impl PostcardCodec {
+    /// The tag of this codec within a [`CodecEnvelope`](session_store::CodecEnvelope).
+    pub const TAG: u8 = 3;
+}
+
+#[cfg(feature = "postcard")]
+impl RecordCodec for PostcardCodec {
+    fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+        postcard::to_allocvec(&compact::RecordRef::try_from(record)?)
+            .map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+        postcard::from_bytes::<compact::Record>(bytes)
+            .map_err(|err| session_store::Error::Decode(err.to_string()))?
+            .try_into()
+    }
+
+    fn decode_header(&self, bytes: &[u8]) -> session_store::Result<RecordHeader> {
+        postcard::take_from_bytes::<compact::Header>(bytes)
+            .map(|(header, _)| header.into())
+            .map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+/// The layout shared by codecs for formats which aren't self-describing.
+///
+/// The header fields come first so headers can be decoded from a prefix of the
+/// encoded record.
+#[cfg(any(feature = "bincode", feature = "postcard"))]
+mod compact {
+    use serde::{Deserialize, Serialize};
+    use time::OffsetDateTime;
+    use tower_sessions_core::{
+        session::{self, Id, RecordHeader},
+        session_store,
+    };
+
+    #[derive(Serialize)]
+    pub(crate) struct RecordRef<'a> {
+        id: Id,
+        expiry_date: OffsetDateTime,
+        absolute_expiry: Option<OffsetDateTime>,
+        client_fingerprint: &'a Option<String>,
+        version: u8,
+        data: String,
+        validator_hash: &'a Option<String>,
+    }
+
+    impl<'a> TryFrom<&'a session::Record> for RecordRef<'a> {
+        type Error = session_store::Error;
+
+        fn try_from(record: &'a session::Record) -> session_store::Result<Self> {
+            Ok(Self {
+                id: record.id,
+                expiry_date: record.expiry_date,
+                absolute_expiry: record.absolute_expiry,
+                client_fingerprint: &record.client_fingerprint,
+                version: record.version,
+                data: serde_json::to_string(&record.data)
+                    .map_err(|err| session_store::Error::Encode(err.to_string()))?,
+                validator_hash: &record.validator_hash,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub(crate) struct Record {
+        id: Id,
+        expiry_date: OffsetDateTime,
+        absolute_expiry: Option<OffsetDateTime>,
+        client_fingerprint: Option<String>,
+        version: u8,
+        data: String,
+        validator_hash: Option<String>,
+    }
+
+    impl TryFrom<Record> for session::Record {
+        type Error = session_store::Error;
+
+        fn try_from(record: Record) -> session_store::Result<Self> {
+            Ok(Self {
+                id: record.id,
+                data: serde_json::from_str(&record.data)
+                    .map_err(|err| session_store::Error::Decode(err.to_string()))?,
+                expiry_date: record.expiry_date,
+                client_fingerprint: record.client_fingerprint,
+                version: record.version,
+                absolute_expiry: record.absolute_expiry,
+                validator_hash: record.validator_hash,
+            })
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub(crate) struct Header {
+        id: Id,
+        expiry_date: OffsetDateTime,
+        absolute_expiry: Option<OffsetDateTime>,
+    }
+
+    impl From<Header> for RecordHeader {
+        fn from(header: Header) -> Self {
+            RecordHeader::new(header.id, header.expiry_date, header.absolute_expiry)
+        }
+    }
+}
+
+fn backend(err: io::Error) -> session_store::Error {
+    session_store::Error::Backend(err.to_string())
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Duration, OffsetDateTime};
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("tower-sessions-{}", Id::default())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn record(expiry: Duration) -> Record {
+        Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + expiry,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_load_delete() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0).with_fsync(true);
+
+        let mut record = record(Duration::minutes(30));
+        record.data.insert("foo".to_string(), 42.into());
+        store.create(&mut record).await.unwrap();
+
+        // Sessions persist across store instances.
+        let reopened = FileStore::new(&dir.0);
+        assert_eq!(
+            reopened.load(&record.id).await.unwrap(),
+            Some(record.clone())
+        );
+
+        record.data.insert("bar".to_string(), 43.into());
+        store.save(&record).await.unwrap();
+        assert_eq!(
+            reopened.load(&record.id).await.unwrap(),
+            Some(record.clone())
+        );
+
+        store.delete(&record.id).await.unwrap();
+        assert_eq!(reopened.load(&record.id).await.unwrap(), None);
+        store.delete(&record.id).await.unwrap();
+    }
+
+    #[derive(Debug)]
+    struct JsonCodec;
+
+    impl RecordCodec for JsonCodec {
+        fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+            serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+        }
+
+        fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+            serde_json::from_slice(bytes)
+                .map_err(|err| session_store::Error::Decode(err.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_codec() {
+        let dir = TempDir::new();
+        let store = FileStore::new_with_codec(&dir.0, JsonCodec);
+
+        let mut active = record(Duration::minutes(30));
+        active.data.insert("foo".to_string(), 42.into());
+        store.create(&mut active).await.unwrap();
+        let mut expired = record(Duration::minutes(-30));
+        store.create(&mut expired).await.unwrap();
+
+        let bytes = fs::read(store.path(&active.id)).unwrap();
+        assert_eq!(serde_json::from_slice::<Record>(&bytes).unwrap(), active);
+        assert_eq!(store.load(&active.id).await.unwrap(), Some(active.clone()));
+        assert_eq!(store.list_recent(10).await.unwrap(), vec![active.clone()]);
+
+        store.delete_expired().await.unwrap();
+        assert_eq!(
+            store.paths(EXTENSION).unwrap(),
+            vec![store.path(&active.id)]
+        );
+
+        // Records can't be read with another codec.
+        assert!(FileStore::new(&dir.0).load(&active.id).await.is_err());
+    }
+
+    #[cfg(any(feature = "bincode", feature = "postcard"))]
+    async fn assert_compact_codec(codec: impl RecordCodec + Copy) {
+        let mut record = record(Duration::minutes(30));
+        record.data.insert("foo".to_string(), 42.into());
+        record.data.insert(
+            "bar".to_string(),
+            serde_json::json!({ "nested": [1, "two", null, 3.5] }),
+        );
+        record.client_fingerprint = Some("fingerprint".to_string());
+        record.version = 3;
+        record.absolute_expiry = Some(OffsetDateTime::now_utc() + Duration::hours(1));
+        record.validator_hash = Some("hash".to_string());
+
+        let bytes = codec.encode(&record).unwrap();
+        assert_eq!(codec.decode(&bytes).unwrap(), record);
+        let header = codec.decode_header(&bytes).unwrap();
+        assert_eq!(header.id, record.id);
+        assert_eq!(header.expiry_date, record.expiry_date);
+        assert_eq!(header.absolute_expiry, record.absolute_expiry);
+        assert!(codec.decode(&bytes[..bytes.len() - 1]).is_err());
+
+        let dir = TempDir::new();
+        let store = FileStore::new_with_codec(&dir.0, codec);
+        store.create(&mut record).await.unwrap();
+        let mut expired = self::record(Duration::minutes(-30));
+        store.create(&mut expired).await.unwrap();
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record.clone()));
+
+        store.delete_expired().await.unwrap();
+        assert_eq!(
+            store.paths(EXTENSION).unwrap(),
+            vec![store.path(&record.id)]
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn test_migrate_codec() {
+        let dir = TempDir::new();
+        let legacy_store = FileStore::new_with_codec(&dir.0, MsgpackCodec);
+        let mut legacy = record(Duration::minutes(30));
+        legacy_store.create(&mut legacy).await.unwrap();
+
+        // Records written before envelopes remain readable.
+        let store = FileStore::new(&dir.0);
+        assert_eq!(store.load(&legacy.id).await.unwrap(), Some(legacy.clone()));
+        let mut current = record(Duration::minutes(30));
+        store.create(&mut current).await.unwrap();
+        assert_eq!(
+            fs::read(store.path(&current.id)).unwrap()[0],
+            MsgpackCodec::TAG
+        );
+
+        let envelope = session_store::CodecEnvelope::new(MsgpackCodec::TAG, MsgpackCodec);
+        let rewritten = store.migrate_codec(MsgpackCodec, envelope.clone(), 1).await;
+        assert_eq!(rewritten.unwrap(), 1);
+        assert_eq!(
+            fs::read(store.path(&legacy.id)).unwrap()[0],
+            MsgpackCodec::TAG
+        );
+        assert_eq!(
+            store
+                .migrate_codec(MsgpackCodec, envelope, 1)
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Once migrated, the unversioned codec is no longer needed.
+        let store = FileStore::new_with_codec(
+            &dir.0,
+            session_store::CodecEnvelope::new(MsgpackCodec::TAG, MsgpackCodec),
+        );
+        assert_eq!(store.load(&legacy.id).await.unwrap(), Some(legacy));
+        assert_eq!(store.load(&current.id).await.unwrap(), Some(current));
+    }
+
+    #[cfg(feature = "bincode")]
+    #[tokio::test]
+    async fn test_bincode_codec() {
+        assert_compact_codec(BincodeCodec).await;
+    }
+
+    #[cfg(feature = "postcard")]
+    #[tokio::test]
+    async fn test_postcard_codec() {
+        assert_compact_codec(PostcardCodec).await;
+    }
+
+    #[tokio::test]
+    async fn test_create_id_collision() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+
+        let mut first = record(Duration::minutes(30));
+        store.create(&mut first).await.unwrap();
+
+        let mut second = record(Duration::minutes(30));
+        second.id = first.id;
+        store.create(&mut second).await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(store.load(&first.id).await.unwrap().is_some());
+        assert!(store.load(&second.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_cycle_and_save() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+
+        let mut record = record(Duration::minutes(30));
+        store.create(&mut record).await.unwrap();
+        let old_session_id = record.id;
+
+        record.id = Id::default();
+        store
+            .cycle_and_save(&old_session_id, &mut record)
+            .await
+            .unwrap();
+        assert_eq!(store.load(&old_session_id).await.unwrap(), None);
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record));
+    }
+
+    #[tokio::test]
+    async fn test_increment() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+
+        assert_eq!(store.increment(&Id::default(), "n", 1).await.unwrap(), None);
+
+        let mut record = record(Duration::minutes(30));
+        record.data.insert("s".to_string(), "x".into());
+        store.create(&mut record).await.unwrap();
+        assert_eq!(store.increment(&record.id, "n", 2).await.unwrap(), Some(2));
+        assert_eq!(store.increment(&record.id, "n", 3).await.unwrap(), Some(5));
+        assert!(store.increment(&record.id, "s", 1).await.is_err());
+
+        let loaded = store.load(&record.id).await.unwrap().unwrap();
+        assert_eq!(loaded.data.get("n"), Some(&5.into()));
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+        let session_id = Id::default();
+        let ttl = Duration::minutes(1);
+
+        assert!(store.try_lock(&session_id, "a", 1, ttl).await.unwrap());
+        assert!(!FileStore::new(&dir.0)
+            .try_lock(&session_id, "a", 2, ttl)
+            .await
+            .unwrap());
+        assert!(store.try_lock(&session_id, "a/b", 2, ttl).await.unwrap());
+
+        // Only the holder releases the lock.
+        store.unlock(&session_id, "a", 2).await.unwrap();
+        assert!(!store.try_lock(&session_id, "a", 2, ttl).await.unwrap());
+        store.unlock(&session_id, "a", 1).await.unwrap();
+        assert!(store.try_lock(&session_id, "a", 2, ttl).await.unwrap());
+
+        // Expired and unreadable locks are taken over and deleted.
+        assert!(store
+            .try_lock(&session_id, "c", 1, Duration::ZERO)
+            .await
+            .unwrap());
+        assert!(store.try_lock(&session_id, "c", 2, ttl).await.unwrap());
+        fs::write(store.session_lock_path(&session_id, "c"), "garbage").unwrap();
+        assert!(store.try_lock(&session_id, "c", 3, ttl).await.unwrap());
+        assert!(store
+            .try_lock(&session_id, "d", 1, Duration::ZERO)
+            .await
+            .unwrap());
+        store.delete_expired().await.unwrap();
+        assert_eq!(store.paths(SESSION_LOCK_EXTENSION).unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_conformance() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+
+        tower_sessions_test_kit::expired_not_loaded(store.clone()).await;
+        tower_sessions_test_kit::absolute_expired_not_loaded(store.clone()).await;
+        tower_sessions_test_kit::expired_not_listed(store.clone()).await;
+        tower_sessions_test_kit::internal_not_listed(store.clone()).await;
+        tower_sessions_test_kit::expired_not_incremented(store).await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let dir = TempDir::new();
+        let store = FileStore::new(&dir.0);
+
+        let mut active = record(Duration::minutes(30));
+        store.create(&mut active).await.unwrap();
+        let mut expired = record(Duration::minutes(-30));
+        store.create(&mut expired).await.unwrap();
+        fs::write(dir.0.join("interrupted.tmp"), b"").unwrap();
+
+        assert_eq!(store.load(&expired.id).await.unwrap(), None);
+        assert_eq!(store.list_recent(10).await.unwrap(), vec![active.clone()]);
+        assert_eq!(store.active_count().await.unwrap(), 1);
+
+        store.delete_expired().await.unwrap();
+        assert_eq!(
+            store.paths(EXTENSION).unwrap(),
+            vec![store.path(&active.id)]
+        );
+        assert!(store.paths(TMP_EXTENSION).unwrap().is_empty());
+        assert!(store.ping().await.is_ok());
+    }
+}
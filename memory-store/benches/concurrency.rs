@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use time::{Duration, OffsetDateTime};
+use tower_sessions_core::{
+    session::{Id, Record},
+    SessionStore,
+};
+use tower_sessions_memory_store::MemoryStore;
+
+const REQUESTS: usize = 10_000;
+
+fn record() -> Record {
+    let mut record = Record {
+        id: Id::default(),
+        data: Default::default(),
+        expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    };
+    record.data.insert("user_id".to_string(), 42.into());
+    record
+}
+
+fn concurrency(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .build()
+        .expect("Failed to build runtime");
+
+    let mut group = c.benchmark_group(format!("{REQUESTS} parallel requests"));
+    group.throughput(Throughput::Elements(REQUESTS as u64));
+
+    // A single shard matches a store behind one global lock.
+    for shards in [1, 32, 128] {
+        let store = MemoryStore::new_with_shards(shards);
+        let records = rt.block_on(async {
+            let mut records = Vec::with_capacity(REQUESTS);
+            for _ in 0..REQUESTS {
+                let mut record = record();
+                store.create(&mut record).await.expect("Failed to create");
+                records.push(record);
+            }
+            records
+        });
+
+        // Each request loads and then saves its own session.
+        group.bench_with_input(
+            BenchmarkId::new("shards", shards),
+            &records,
+            |b, records| {
+                b.to_async(&rt).iter(|| async {
+                    let tasks = records
+                        .iter()
+                        .cloned()
+                        .map(|record| {
+                            let store = store.clone();
+                            tokio::spawn(async move {
+                                store.load(&record.id).await.expect("Failed to load");
+                                store.save(&record).await.expect("Failed to save");
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    for task in tasks {
+                        task.await.expect("Request panicked");
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, concurrency);
+criterion_main!(benches);
@@ -1,69 +1,439 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use time::OffsetDateTime;
 use tokio::sync::Mutex;
 use tower_sessions_core::{
     session::{Id, Record},
-    session_store, SessionStore,
+    session_store, ExpiredDeletion, SessionStore, SessionStoreHealth, SessionStoreStats,
 };
 
 /// A session store that lives only in memory.
 ///
 /// This is useful for testing but not recommended for real applications.
 ///
+/// Sessions are spread over a number of shards by ID, each behind its own
+/// lock, so that operations on different sessions rarely contend. The
+/// `concurrency` benchmark compares shard counts under parallel load.
+///
 /// # Examples
 ///
 /// ```rust
 /// use tower_sessions::MemoryStore;
 /// MemoryStore::default();
 /// ```
-#[derive(Clone, Debug, Default)]
-pub struct MemoryStore(Arc<Mutex<HashMap<Id, Record>>>);
+#[derive(Clone, Debug)]
+pub struct MemoryStore {
+    shards: Arc<[Shard]>,
+    locks: Arc<Mutex<HashMap<LockKey, (u128, OffsetDateTime)>>>,
+    namespace: Cow<'static, str>,
+}
+
+type Shard = Mutex<HashMap<Key, Record>>;
+
+type Key = (Cow<'static, str>, Id);
+
+type LockKey = (Key, String);
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new_with_shards(Self::DEFAULT_SHARDS)
+    }
+}
+
+impl MemoryStore {
+    const DEFAULT_SHARDS: usize = 32;
+
+    /// Create a new `MemoryStore` spreading sessions over the given number
+    /// of shards.
+    ///
+    /// More shards reduce contention between concurrent requests at the cost
+    /// of slower iteration, e.g. by [`count`](MemoryStore::count). The
+    /// default store uses 32 shards; a single shard serializes all
+    /// operations.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `shards` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::MemoryStore;
+    ///
+    /// let store = MemoryStore::new_with_shards(64);
+    /// ```
+    pub fn new_with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "a memory store needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| Shard::default()).collect(),
+            locks: Default::default(),
+            namespace: Cow::Borrowed(""),
+        }
+    }
+
+    /// Returns a store sharing the same sessions but keeping its own under the
+    /// given namespace.
+    ///
+    /// This allows multiple applications to safely share a single store. The
+    /// default namespace is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::{session::Record, MemoryStore, SessionStore};
+    ///
+    /// let store = MemoryStore::default();
+    /// let app_store = store.with_namespace("app");
+    /// let admin_store = store.with_namespace("admin");
+    ///
+    /// let mut record = Record {
+    ///     id: Default::default(),
+    ///     data: Default::default(),
+    ///     expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+    ///     client_fingerprint: None,
+    ///     version: 0,
+    ///     absolute_expiry: None,
+    ///     validator_hash: None,
+    /// };
+    /// app_store.create(&mut record).await.unwrap();
+    ///
+    /// assert!(app_store.load(&record.id).await.unwrap().is_some());
+    /// assert!(admin_store.load(&record.id).await.unwrap().is_none());
+    /// # });
+    /// ```
+    pub fn with_namespace<N: Into<Cow<'static, str>>>(&self, namespace: N) -> Self {
+        Self {
+            shards: self.shards.clone(),
+            locks: self.locks.clone(),
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Returns the number of active sessions in the store's namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = MemoryStore::default();
+    /// let session = Session::new(None, Arc::new(store.clone()), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// assert_eq!(store.count().await, 1);
+    /// # });
+    /// ```
+    pub async fn count(&self) -> usize {
+        let mut count = 0;
+        for shard in self.shards.iter() {
+            count += shard
+                .lock()
+                .await
+                .iter()
+                .filter(|((namespace, _), record)| {
+                    *namespace == self.namespace && record.is_active() && !record.is_internal()
+                })
+                .count();
+        }
+        count
+    }
+
+    /// Serializes the active sessions in the store's namespace.
+    ///
+    /// This is intended for development servers which want to persist
+    /// sessions across restarts, e.g. by writing the snapshot to a local file
+    /// and passing it to [`restore`](MemoryStore::restore) on startup.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = MemoryStore::default();
+    /// let session = Session::new(None, Arc::new(store.clone()), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let snapshot = store.snapshot().await.unwrap();
+    ///
+    /// let restored_store = MemoryStore::default();
+    /// restored_store.restore(&snapshot).await.unwrap();
+    /// assert_eq!(restored_store.count().await, 1);
+    /// # });
+    /// ```
+    pub async fn snapshot(&self) -> session_store::Result<Vec<u8>> {
+        let records = self.active_records().await;
+        rmp_serde::to_vec(&records).map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    /// Loads sessions from a snapshot produced by
+    /// [`snapshot`](MemoryStore::snapshot) into the store's namespace.
+    ///
+    /// Existing sessions with the same ID are replaced and expired sessions
+    /// are skipped.
+    pub async fn restore(&self, snapshot: &[u8]) -> session_store::Result<()> {
+        let records: Vec<Record> = rmp_serde::from_slice(snapshot)
+            .map_err(|err| session_store::Error::Decode(err.to_string()))?;
+        for record in records.into_iter().filter(Record::is_active) {
+            self.shard(&record.id)
+                .lock()
+                .await
+                .insert(self.key(&record.id), record);
+        }
+        Ok(())
+    }
+
+    fn key(&self, session_id: &Id) -> Key {
+        (self.namespace.clone(), *session_id)
+    }
+
+    // IDs are random, so their low bits spread sessions evenly.
+    fn shard_index(&self, session_id: &Id) -> usize {
+        (session_id.0 as u64 % self.shards.len() as u64) as usize
+    }
+
+    fn shard(&self, session_id: &Id) -> &Shard {
+        &self.shards[self.shard_index(session_id)]
+    }
+
+    // Shards are locked one at a time, so this isn't a consistent snapshot of the whole store.
+    async fn active_records(&self) -> Vec<Record> {
+        let mut records = Vec::new();
+        for shard in self.shards.iter() {
+            records.extend(
+                shard
+                    .lock()
+                    .await
+                    .iter()
+                    .filter(|((namespace, _), record)| {
+                        *namespace == self.namespace && record.is_active()
+                    })
+                    .map(|(_, record)| record.clone()),
+            );
+        }
+        records
+    }
+}
 
 #[async_trait]
 impl SessionStore for MemoryStore {
     async fn create(&self, record: &mut Record) -> session_store::Result<()> {
-        let mut store_guard = self.0.lock().await;
-        while store_guard.contains_key(&record.id) {
+        loop {
+            let mut shard_guard = self.shard(&record.id).lock().await;
+            if let Entry::Vacant(entry) = shard_guard.entry(self.key(&record.id)) {
+                entry.insert(record.clone());
+                return Ok(());
+            }
             // Session ID collision mitigation.
             record.id = Id::default();
         }
-        store_guard.insert(record.id, record.clone());
-        Ok(())
     }
 
     async fn save(&self, record: &Record) -> session_store::Result<()> {
-        self.0.lock().await.insert(record.id, record.clone());
+        self.shard(&record.id)
+            .lock()
+            .await
+            .insert(self.key(&record.id), record.clone());
         Ok(())
     }
 
     async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
         Ok(self
-            .0
+            .shard(session_id)
             .lock()
             .await
-            .get(session_id)
-            .filter(|Record { expiry_date, .. }| is_active(*expiry_date))
+            .get(&self.key(session_id))
+            .filter(|record| record.is_active())
             .cloned())
     }
 
     async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
-        self.0.lock().await.remove(session_id);
+        self.shard(session_id)
+            .lock()
+            .await
+            .remove(&self.key(session_id));
+        Ok(())
+    }
+
+    async fn get_or_create(&self, record: &Record) -> session_store::Result<Option<Record>> {
+        let mut shard_guard = self.shard(&record.id).lock().await;
+        match shard_guard.entry(self.key(&record.id)) {
+            Entry::Occupied(entry) if entry.get().is_active() => Ok(Some(entry.get().clone())),
+            Entry::Occupied(mut entry) => {
+                entry.insert(record.clone());
+                Ok(None)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(record.clone());
+                Ok(None)
+            }
+        }
+    }
+
+    async fn cycle_and_save(
+        &self,
+        old_session_id: &Id,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        loop {
+            let old_index = self.shard_index(old_session_id);
+            let new_index = self.shard_index(&record.id);
+            if old_index == new_index {
+                let mut shard_guard = self.shards[old_index].lock().await;
+                shard_guard.remove(&self.key(old_session_id));
+                if let Entry::Vacant(entry) = shard_guard.entry(self.key(&record.id)) {
+                    entry.insert(record.clone());
+                    return Ok(());
+                }
+            } else {
+                // Locking in index order rules out deadlocks with concurrent cycles.
+                let (mut old_guard, mut new_guard) = if old_index < new_index {
+                    let old_guard = self.shards[old_index].lock().await;
+                    (old_guard, self.shards[new_index].lock().await)
+                } else {
+                    let new_guard = self.shards[new_index].lock().await;
+                    (self.shards[old_index].lock().await, new_guard)
+                };
+                if let Entry::Vacant(entry) = new_guard.entry(self.key(&record.id)) {
+                    old_guard.remove(&self.key(old_session_id));
+                    entry.insert(record.clone());
+                    return Ok(());
+                }
+            }
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+    }
+
+    async fn increment(
+        &self,
+        session_id: &Id,
+        key: &str,
+        by: i64,
+    ) -> session_store::Result<Option<i64>> {
+        let mut shard_guard = self.shard(session_id).lock().await;
+        let Some(record) = shard_guard
+            .get_mut(&self.key(session_id))
+            .filter(|record| record.is_active())
+        else {
+            return Ok(None);
+        };
+
+        let current = match record.data.get(key) {
+            Some(value) => value.as_i64().ok_or_else(|| {
+                session_store::Error::Decode(format!("Value of `{key}` is not an integer"))
+            })?,
+            None => 0,
+        };
+        let value = current.saturating_add(by);
+        record.data.insert(key.to_string(), value.into());
+        Ok(Some(value))
+    }
+
+    async fn list_recent(&self, limit: usize) -> session_store::Result<Vec<Record>> {
+        let mut records = self.active_records().await;
+        records.retain(|record| !record.is_internal());
+        records.sort_by_key(|record| std::cmp::Reverse(record.expiry_date));
+        records.truncate(limit);
+        Ok(records)
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> session_store::Result<bool> {
+        let now = OffsetDateTime::now_utc();
+        let mut locks = self.locks.lock().await;
+        let lock_key = (self.key(session_id), key.to_string());
+        if locks
+            .get(&lock_key)
+            .is_some_and(|&(_, expires_at)| expires_at > now)
+        {
+            return Ok(false);
+        }
+        locks.insert(lock_key, (token, now + ttl));
+        Ok(true)
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> session_store::Result<()> {
+        let mut locks = self.locks.lock().await;
+        let lock_key = (self.key(session_id), key.to_string());
+        if locks
+            .get(&lock_key)
+            .is_some_and(|&(holder, _)| holder == token)
+        {
+            locks.remove(&lock_key);
+        }
         Ok(())
     }
 }
 
-fn is_active(expiry_date: OffsetDateTime) -> bool {
-    expiry_date > OffsetDateTime::now_utc()
+#[async_trait]
+impl SessionStoreHealth for MemoryStore {
+    async fn ping(&self) -> session_store::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStoreStats for MemoryStore {
+    async fn active_count(&self) -> session_store::Result<u64> {
+        Ok(self.count().await as u64)
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for MemoryStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        for shard in self.shards.iter() {
+            shard.lock().await.retain(|(namespace, _), record| {
+                *namespace != self.namespace || record.is_active()
+            });
+        }
+        let now = OffsetDateTime::now_utc();
+        self.locks
+            .lock()
+            .await
+            .retain(|((namespace, _), _), &mut (_, expires_at)| {
+                *namespace != self.namespace || expires_at > now
+            });
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use time::Duration;
+    use time::{Duration, OffsetDateTime};
+    use tower_sessions_test_kit::test_harness::TestApp;
 
     use super::*;
 
+    // The number of stored records, including expired ones.
+    async fn stored(store: &MemoryStore) -> usize {
+        let mut len = 0;
+        for shard in store.shards.iter() {
+            len += shard.lock().await.len();
+        }
+        len
+    }
+
     #[tokio::test]
     async fn test_create() {
         let store = MemoryStore::default();
@@ -71,6 +441,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         assert!(store.create(&mut record).await.is_ok());
     }
@@ -82,6 +456,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         assert!(store.save(&record).await.is_ok());
     }
@@ -93,6 +471,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         store.create(&mut record).await.unwrap();
         let loaded_record = store.load(&record.id).await.unwrap();
@@ -106,12 +488,225 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         store.create(&mut record).await.unwrap();
         assert!(store.delete(&record.id).await.is_ok());
         assert_eq!(None, store.load(&record.id).await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_namespace() {
+        let store = MemoryStore::default();
+        let other_store = store.with_namespace("other");
+        let mut record = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        store.create(&mut record).await.unwrap();
+        assert_eq!(None, other_store.load(&record.id).await.unwrap());
+
+        other_store.delete(&record.id).await.unwrap();
+        assert_eq!(Some(record.clone()), store.load(&record.id).await.unwrap());
+
+        let mut other_record = record.clone();
+        other_store.create(&mut other_record).await.unwrap();
+        assert_eq!(record.id, other_record.id); // No collision across
+                                                // namespaces.
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let store = MemoryStore::default();
+        let mut active = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        let mut expired = Record {
+            expiry_date: OffsetDateTime::now_utc() - Duration::minutes(30),
+            ..active.clone()
+        };
+        store.create(&mut active).await.unwrap();
+        store.create(&mut expired).await.unwrap();
+        assert_eq!(store.count().await, 1);
+
+        store.delete_expired().await.unwrap();
+        assert_eq!(stored(&store).await, 1);
+        assert!(store.load(&active.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_recent() {
+        let store = MemoryStore::default();
+        let other_store = store.with_namespace("other");
+        let now = OffsetDateTime::now_utc();
+        let mut ids = Vec::new();
+        for (minutes, store) in [
+            (10, &store),
+            (30, &store),
+            (20, &store),
+            (-10, &store),
+            (60, &other_store),
+        ] {
+            let mut record = Record {
+                id: Default::default(),
+                data: Default::default(),
+                expiry_date: now + Duration::minutes(minutes),
+                client_fingerprint: None,
+                version: 0,
+                absolute_expiry: None,
+                validator_hash: None,
+            };
+            store.create(&mut record).await.unwrap();
+            ids.push(record.id);
+        }
+
+        // Records which aren't sessions are neither listed nor counted.
+        let mut internal = store.load(&ids[0]).await.unwrap().unwrap();
+        internal.id = Default::default();
+        internal.expiry_date = now + Duration::minutes(90);
+        internal
+            .data
+            .insert(Record::INTERNAL_KEY.to_string(), true.into());
+        store.create(&mut internal).await.unwrap();
+
+        let recent = store.list_recent(2).await.unwrap();
+        let recent_ids = recent.iter().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(recent_ids, vec![ids[1], ids[2]]);
+        assert_eq!(store.list_recent(10).await.unwrap().len(), 3);
+        assert_eq!(store.active_count().await.unwrap(), 3);
+        assert_eq!(other_store.active_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_increment() {
+        let store = MemoryStore::default();
+        let mut record = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        record.data.insert("name".to_string(), "foo".into());
+        store.create(&mut record).await.unwrap();
+
+        for _ in 0..10 {
+            store.increment(&record.id, "count", 1).await.unwrap();
+        }
+        assert_eq!(
+            store.increment(&record.id, "count", -4).await.unwrap(),
+            Some(6)
+        );
+        assert!(store.increment(&record.id, "name", 1).await.is_err());
+        assert_eq!(
+            store.increment(&Id::default(), "count", 1).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock() {
+        let store = MemoryStore::default();
+        let other_store = store.with_namespace("other");
+        let session_id = Id::default();
+        let ttl = Duration::minutes(1);
+
+        assert!(store.try_lock(&session_id, "a", 1, ttl).await.unwrap());
+        assert!(!store.try_lock(&session_id, "a", 2, ttl).await.unwrap());
+        assert!(store.try_lock(&session_id, "b", 2, ttl).await.unwrap());
+        assert!(other_store
+            .try_lock(&session_id, "a", 2, ttl)
+            .await
+            .unwrap());
+
+        // Only the holder releases the lock.
+        store.unlock(&session_id, "a", 2).await.unwrap();
+        assert!(!store.try_lock(&session_id, "a", 2, ttl).await.unwrap());
+        store.unlock(&session_id, "a", 1).await.unwrap();
+        assert!(store.try_lock(&session_id, "a", 2, ttl).await.unwrap());
+
+        // Expired locks are taken over and deleted.
+        assert!(store
+            .try_lock(&session_id, "c", 1, Duration::ZERO)
+            .await
+            .unwrap());
+        assert!(store.try_lock(&session_id, "c", 2, ttl).await.unwrap());
+        store.unlock(&session_id, "c", 2).await.unwrap();
+        assert!(store
+            .try_lock(&session_id, "d", 1, Duration::ZERO)
+            .await
+            .unwrap());
+        store.delete_expired().await.unwrap();
+        assert_eq!(store.locks.lock().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let store = MemoryStore::default();
+        let app = TestApp::new(store.clone());
+
+        let res = app.request("/insert").await;
+        let cookie = res.session_cookie().unwrap();
+        assert_eq!(store.count().await, 1);
+
+        let res = app.request_with_cookie("/get", &cookie).await;
+        assert_eq!(res.body(), "42");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore() {
+        let store = MemoryStore::default();
+        let mut active = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        let mut expired = Record {
+            expiry_date: OffsetDateTime::now_utc() - Duration::minutes(30),
+            ..active.clone()
+        };
+        let mut other = active.clone();
+        store.create(&mut active).await.unwrap();
+        store.create(&mut expired).await.unwrap();
+        store
+            .with_namespace("other")
+            .create(&mut other)
+            .await
+            .unwrap();
+
+        let snapshot = store.snapshot().await.unwrap();
+
+        let restored_store = MemoryStore::default();
+        restored_store.restore(&snapshot).await.unwrap();
+        assert_eq!(stored(&restored_store).await, 1);
+        assert_eq!(
+            Some(active.clone()),
+            restored_store.load(&active.id).await.unwrap()
+        );
+
+        assert!(restored_store.restore(b"not a snapshot").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_create_id_collision() {
         let store = MemoryStore::default();
@@ -120,15 +715,77 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         let mut record2 = Record {
             id: Default::default(),
             data: Default::default(),
             expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         store.create(&mut record1).await.unwrap();
         record2.id = record1.id; // Set the same ID for record2
         store.create(&mut record2).await.unwrap();
         assert_ne!(record1.id, record2.id); // IDs should be different
     }
+
+    #[tokio::test]
+    async fn test_cycle_and_save_across_shards() {
+        let store = MemoryStore::new_with_shards(2);
+        let mut record = Record {
+            id: Id(2),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        store.create(&mut record).await.unwrap();
+        let mut other = record.clone();
+        other.id = Id(3);
+        store.create(&mut other).await.unwrap();
+
+        // Cycling into an ID taken in another shard picks a fresh one.
+        let old_id = record.id;
+        record.id = other.id;
+        store.cycle_and_save(&old_id, &mut record).await.unwrap();
+        assert_ne!(record.id, other.id);
+        assert_eq!(store.load(&old_id).await.unwrap(), None);
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record.clone()));
+        assert_eq!(store.load(&other.id).await.unwrap(), Some(other));
+        assert_eq!(store.count().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tombstones_not_counted() {
+        let store = session_store::TombstoneStore::new(MemoryStore::default(), Duration::days(1));
+        let mut record = Record {
+            id: Default::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        store.create(&mut record).await.unwrap();
+        assert_eq!(store.active_count().await.unwrap(), 1);
+
+        store.delete(&record.id).await.unwrap();
+        assert!(store.is_tombstoned(&record.id).await.unwrap());
+        assert_eq!(store.active_count().await.unwrap(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one shard")]
+    fn test_no_shards() {
+        MemoryStore::new_with_shards(0);
+    }
 }
@@ -0,0 +1,261 @@
+//! A session store delegating to a remote session service over gRPC.
+//!
+//! [`RemoteSessionStore`] lets several services share sessions kept by a
+//! single session service, speaking the protocol described in
+//! `proto/session_store.proto`. [`RemoteSessionService`] serves that protocol
+//! over any [`SessionStore`], e.g. to build such a service with `tonic`.
+use async_trait::async_trait;
+use tonic::{
+    client::Grpc,
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, Endpoint},
+    Code, Status,
+};
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+
+mod proto;
+mod server;
+
+pub use server::RemoteSessionService;
+
+const BACKEND: &str = "remote";
+
+/// A session store backed by a remote session service, reached via gRPC.
+///
+/// Sessions are created, saved, loaded, deleted and cycled by the service;
+/// other operations, such as atomic increments or listing sessions, use the
+/// defaults of [`SessionStore`].
+///
+/// Requests are multiplexed over the HTTP/2 connections of the underlying
+/// [`Channel`], which can balance them over several instances of the service
+/// via [`Channel::balance_list`]. Deadlines are configured on the
+/// [`Endpoint`], see [`Endpoint::timeout`].
+///
+/// Failed requests aren't retried. Requests which time out or find the service
+/// unavailable fail with [`session_store::Error::Timeout`] and
+/// [`session_store::Error::Unavailable`] respectively, so that they're
+/// retried when wrapping the store in `RetryStore`, which requires the
+/// `resilience` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use tonic::transport::Endpoint;
+/// use tower_sessions::{RemoteSessionStore, SessionManagerLayer};
+///
+/// # tokio_test::block_on(async {
+/// let endpoint = Endpoint::from_static("http://sessions.internal:50051");
+/// let session_store = RemoteSessionStore::connect_lazy(endpoint);
+/// let session_layer = SessionManagerLayer::new(session_store);
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct RemoteSessionStore {
+    grpc: Grpc<Channel>,
+}
+
+impl RemoteSessionStore {
+    /// Create a new `RemoteSessionStore` sending requests over the given
+    /// channel.
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            grpc: Grpc::new(channel),
+        }
+    }
+
+    /// Create a new `RemoteSessionStore` connecting to the given endpoint once
+    /// the first request is sent.
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn connect_lazy(endpoint: Endpoint) -> Self {
+        Self::new(endpoint.connect_lazy())
+    }
+
+    async fn unary<Req, Res>(&self, path: &'static str, message: Req) -> session_store::Result<Res>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Res: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = self.grpc.clone();
+        grpc.ready().await.map_err(|err| {
+            session_store::Error::Unavailable(err.to_string()).with_backend(BACKEND)
+        })?;
+        let res = grpc
+            .unary(
+                tonic::Request::new(message),
+                PathAndQuery::from_static(path),
+                ProstCodec::default(),
+            )
+            .await
+            .map_err(status_error)?;
+        Ok(res.into_inner())
+    }
+}
+
+#[async_trait]
+impl SessionStore for RemoteSessionStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let req = proto::CreateRequest {
+            record: encode_record(record)?,
+        };
+        let res: proto::CreateResponse = self.unary(proto::CREATE, req).await?;
+        record.id = decode_id(&res.id)?;
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let req = proto::SaveRequest {
+            record: encode_record(record)?,
+        };
+        let _: proto::SaveResponse = self.unary(proto::SAVE, req).await?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let req = proto::LoadRequest {
+            id: session_id.to_string(),
+        };
+        let res: proto::LoadResponse = self.unary(proto::LOAD, req).await?;
+        res.record
+            .map(|record| decode_record(&record))
+            .transpose()
+            .map(|record| record.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        let req = proto::DeleteRequest {
+            id: session_id.to_string(),
+        };
+        let _: proto::DeleteResponse = self.unary(proto::DELETE, req).await?;
+        Ok(())
+    }
+
+    async fn cycle_and_save(
+        &self,
+        old_session_id: &Id,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        let req = proto::CycleRequest {
+            old_id: old_session_id.to_string(),
+            record: encode_record(record)?,
+        };
+        let res: proto::CycleResponse = self.unary(proto::CYCLE, req).await?;
+        record.id = decode_id(&res.id)?;
+        Ok(())
+    }
+}
+
+fn encode_record(record: &Record) -> session_store::Result<Vec<u8>> {
+    serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+}
+
+fn decode_record(bytes: &[u8]) -> session_store::Result<Record> {
+    serde_json::from_slice(bytes).map_err(|err| session_store::Error::Decode(err.to_string()))
+}
+
+fn decode_id(id: &str) -> session_store::Result<Id> {
+    id.parse()
+        .map_err(|err| session_store::Error::Decode(format!("invalid session ID: {err}")))
+}
+
+fn status_error(status: Status) -> session_store::Error {
+    let message = status.message().to_string();
+    let err = match status.code() {
+        Code::DeadlineExceeded => session_store::Error::Timeout(message),
+        Code::Unavailable | Code::ResourceExhausted => session_store::Error::Unavailable(message),
+        _ => session_store::Error::Backend(message),
+    };
+    err.with_backend(BACKEND)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use time::{Duration, OffsetDateTime};
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::Server;
+    use tower_sessions::MemoryStore;
+    use tower_sessions_core::session_store::ErrorKind;
+
+    use super::*;
+
+    async fn serve(store: impl SessionStore) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(RemoteSessionService::new(store))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        addr
+    }
+
+    async fn store() -> RemoteSessionStore {
+        let addr = serve(MemoryStore::default()).await;
+        let endpoint = Endpoint::from_shared(format!("http://{addr}")).unwrap();
+        RemoteSessionStore::connect_lazy(endpoint)
+    }
+
+    fn record(expiry_date: OffsetDateTime) -> Record {
+        Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    tower_sessions_test_kit::store_conformance_tests!(store().await);
+
+    #[tokio::test]
+    async fn test_record_roundtrip() {
+        let store = store().await;
+
+        let mut record = record(OffsetDateTime::now_utc() + Duration::minutes(30));
+        record.data.insert("foo".to_string(), 42.into());
+        record.client_fingerprint = Some("fingerprint".to_string());
+        record.version = 3;
+        store.create(&mut record).await.unwrap();
+
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record));
+    }
+
+    #[tokio::test]
+    async fn test_unavailable() {
+        // Nothing listens on the port once the listener is dropped.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let endpoint = Endpoint::from_shared(format!("http://{addr}")).unwrap();
+        let store = RemoteSessionStore::connect_lazy(endpoint);
+
+        let err = store.load(&Id::default()).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unavailable);
+        assert!(err.is_retryable());
+        assert_eq!(err.backend(), Some("remote"));
+    }
+
+    #[test]
+    fn test_status_error() {
+        let err = status_error(Status::deadline_exceeded("slow"));
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+
+        let err = status_error(Status::unavailable("down"));
+        assert_eq!(err.kind(), ErrorKind::Unavailable);
+
+        let err = status_error(Status::internal("broken"));
+        assert_eq!(err.kind(), ErrorKind::Backend);
+        assert!(!err.is_retryable());
+        assert_eq!(err.to_string(), "remote: broken");
+    }
+}
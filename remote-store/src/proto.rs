@@ -0,0 +1,69 @@
+//! Messages of the `tower_sessions.v1.SessionStore` service, see
+//! `proto/session_store.proto`.
+//!
+//! These are written by hand, matching what `prost-build` generates, so that
+//! building the crate doesn't require `protoc`.
+
+pub(crate) const SERVICE_NAME: &str = "tower_sessions.v1.SessionStore";
+
+pub(crate) const CREATE: &str = "/tower_sessions.v1.SessionStore/Create";
+pub(crate) const SAVE: &str = "/tower_sessions.v1.SessionStore/Save";
+pub(crate) const LOAD: &str = "/tower_sessions.v1.SessionStore/Load";
+pub(crate) const DELETE: &str = "/tower_sessions.v1.SessionStore/Delete";
+pub(crate) const CYCLE: &str = "/tower_sessions.v1.SessionStore/Cycle";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct CreateRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub record: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct CreateResponse {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct SaveRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub record: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct SaveResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct LoadRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct LoadResponse {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub record: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DeleteRequest {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
+
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub(crate) struct DeleteResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct CycleRequest {
+    #[prost(string, tag = "1")]
+    pub old_id: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub record: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct CycleResponse {
+    #[prost(string, tag = "1")]
+    pub id: String,
+}
@@ -0,0 +1,222 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tonic::{
+    body::{empty_body, BoxBody},
+    codec::ProstCodec,
+    codegen::{http, Body, BoxFuture, Service, StdError},
+    metadata::GRPC_CONTENT_TYPE,
+    server::{Grpc, NamedService, UnaryService},
+    Code, Status,
+};
+use tower_sessions_core::{
+    session_store::{self, ErrorKind},
+    SessionStore,
+};
+
+use crate::{decode_id, decode_record, proto};
+
+/// A gRPC service exposing a [`SessionStore`] to [`RemoteSessionStore`]
+/// clients.
+///
+/// Store errors which time out or find the store unavailable are reported with
+/// the `DEADLINE_EXCEEDED` and `UNAVAILABLE` status codes, which clients may
+/// retry, and other store errors with `INTERNAL`.
+///
+/// [`RemoteSessionStore`]: crate::RemoteSessionStore
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use tonic::transport::Server;
+/// use tower_sessions::{MemoryStore, RemoteSessionService};
+///
+/// # tokio_test::block_on(async {
+/// Server::builder()
+///     .add_service(RemoteSessionService::new(MemoryStore::default()))
+///     .serve("0.0.0.0:50051".parse().unwrap())
+///     .await
+///     .unwrap();
+/// # });
+/// ```
+pub struct RemoteSessionService<Store> {
+    store: Arc<Store>,
+}
+
+impl<Store: SessionStore> RemoteSessionService<Store> {
+    /// Create a new `RemoteSessionService` serving the given store.
+    pub fn new(store: Store) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+
+    fn unary<B, Req, Res>(
+        &self,
+        req: http::Request<B>,
+        handler: Handler<Store, Req, Res>,
+    ) -> ResponseFuture
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+        Req: prost::Message + Default + Send + 'static,
+        Res: prost::Message + Send + 'static,
+    {
+        let method = Method {
+            store: self.store.clone(),
+            handler,
+        };
+        Box::pin(async move {
+            let mut grpc = Grpc::new(ProstCodec::<Res, Req>::default());
+            Ok(grpc.unary(method, req).await)
+        })
+    }
+}
+
+type ResponseFuture = BoxFuture<http::Response<BoxBody>, Infallible>;
+
+impl<Store, B> Service<http::Request<B>> for RemoteSessionService<Store>
+where
+    Store: SessionStore,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = ResponseFuture;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        match req.uri().path() {
+            proto::CREATE => self.unary(req, |store, req| Box::pin(create(store, req))),
+            proto::SAVE => self.unary(req, |store, req| Box::pin(save(store, req))),
+            proto::LOAD => self.unary(req, |store, req| Box::pin(load(store, req))),
+            proto::DELETE => self.unary(req, |store, req| Box::pin(delete(store, req))),
+            proto::CYCLE => self.unary(req, |store, req| Box::pin(cycle(store, req))),
+            _ => Box::pin(async {
+                let mut res = http::Response::new(empty_body());
+                let headers = res.headers_mut();
+                headers.insert(Status::GRPC_STATUS, (Code::Unimplemented as i32).into());
+                headers.insert(http::header::CONTENT_TYPE, GRPC_CONTENT_TYPE);
+                Ok(res)
+            }),
+        }
+    }
+}
+
+impl<Store> Clone for RemoteSessionService<Store> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<Store: fmt::Debug> fmt::Debug for RemoteSessionService<Store> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSessionService")
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+impl<Store> NamedService for RemoteSessionService<Store> {
+    const NAME: &'static str = proto::SERVICE_NAME;
+}
+
+type Handler<Store, Req, Res> = fn(Arc<Store>, Req) -> BoxFuture<Res, Status>;
+
+// Adapts a handler of one of the service's methods to `tonic`.
+struct Method<Store, Req, Res> {
+    store: Arc<Store>,
+    handler: Handler<Store, Req, Res>,
+}
+
+impl<Store, Req, Res> UnaryService<Req> for Method<Store, Req, Res>
+where
+    Res: 'static,
+{
+    type Response = Res;
+    type Future = BoxFuture<tonic::Response<Res>, Status>;
+
+    fn call(&mut self, req: tonic::Request<Req>) -> Self::Future {
+        let res = (self.handler)(self.store.clone(), req.into_inner());
+        Box::pin(async move { res.await.map(tonic::Response::new) })
+    }
+}
+
+async fn create<Store: SessionStore>(
+    store: Arc<Store>,
+    req: proto::CreateRequest,
+) -> Result<proto::CreateResponse, Status> {
+    let mut record = decode_record(&req.record).map_err(invalid_argument)?;
+    store.create(&mut record).await.map_err(store_status)?;
+    Ok(proto::CreateResponse {
+        id: record.id.to_string(),
+    })
+}
+
+async fn save<Store: SessionStore>(
+    store: Arc<Store>,
+    req: proto::SaveRequest,
+) -> Result<proto::SaveResponse, Status> {
+    let record = decode_record(&req.record).map_err(invalid_argument)?;
+    store.save(&record).await.map_err(store_status)?;
+    Ok(proto::SaveResponse {})
+}
+
+async fn load<Store: SessionStore>(
+    store: Arc<Store>,
+    req: proto::LoadRequest,
+) -> Result<proto::LoadResponse, Status> {
+    let session_id = decode_id(&req.id).map_err(invalid_argument)?;
+    let record = store.load(&session_id).await.map_err(store_status)?;
+    let record = record
+        .map(|record| serde_json::to_vec(&record))
+        .transpose()
+        .map_err(|err| Status::internal(err.to_string()))?;
+    Ok(proto::LoadResponse { record })
+}
+
+async fn delete<Store: SessionStore>(
+    store: Arc<Store>,
+    req: proto::DeleteRequest,
+) -> Result<proto::DeleteResponse, Status> {
+    let session_id = decode_id(&req.id).map_err(invalid_argument)?;
+    store.delete(&session_id).await.map_err(store_status)?;
+    Ok(proto::DeleteResponse {})
+}
+
+async fn cycle<Store: SessionStore>(
+    store: Arc<Store>,
+    req: proto::CycleRequest,
+) -> Result<proto::CycleResponse, Status> {
+    let old_session_id = decode_id(&req.old_id).map_err(invalid_argument)?;
+    let mut record = decode_record(&req.record).map_err(invalid_argument)?;
+    store
+        .cycle_and_save(&old_session_id, &mut record)
+        .await
+        .map_err(store_status)?;
+    Ok(proto::CycleResponse {
+        id: record.id.to_string(),
+    })
+}
+
+fn invalid_argument(err: session_store::Error) -> Status {
+    Status::invalid_argument(err.to_string())
+}
+
+fn store_status(err: session_store::Error) -> Status {
+    match err.kind() {
+        ErrorKind::Timeout => Status::deadline_exceeded(err.to_string()),
+        ErrorKind::Unavailable => Status::unavailable(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
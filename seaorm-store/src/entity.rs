@@ -0,0 +1,23 @@
+//! The [SeaORM](https://www.sea-ql.org/SeaORM/) entity for the sessions table.
+use sea_orm::entity::prelude::*;
+
+/// A session as stored in the `tower_sessions` table.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "tower_sessions")]
+pub struct Model {
+    /// The session ID.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+
+    /// The session record, encoded by the store's codec.
+    pub data: Vec<u8>,
+
+    /// The session's expiry date, in seconds since the Unix epoch.
+    pub expiry_date: i64,
+}
+
+/// Sessions have no relations.
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,391 @@
+//! A session store backed by [SeaORM](https://www.sea-ql.org/SeaORM/).
+//!
+//! [`SeaOrmStore`] works with any database SeaORM supports, keeping sessions
+//! in a table described by the [`entity`] module. The table is created by the
+//! [`Migration`], which may be run alongside an application's own migrations
+//! or via [`SeaOrmStore::migrate`].
+//!
+//! This crate doesn't enable any SeaORM database driver or runtime; enable
+//! them on the application's own `sea-orm` dependency.
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait, TryInsertResult, Unchanged,
+};
+use sea_orm_migration::{MigrationTrait, SchemaManager};
+use time::OffsetDateTime;
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store::{self, CodecEnvelope, RecordCodec},
+    ExpiredDeletion, SessionStore, SessionStoreHealth, SessionStoreStats,
+};
+
+use crate::entity::{ActiveModel, Column, Entity};
+
+pub mod entity;
+mod migration;
+
+pub use migration::Migration;
+
+/// A session store backed by a SeaORM [`DatabaseConnection`].
+///
+/// Records are encoded as MessagePack alongside their expiry date, within a
+/// [`CodecEnvelope`] tagged with [`SeaOrmStore::MSGPACK_TAG`]; see
+/// [`SeaOrmStore::with_codec`] for using another representation. New
+/// sessions are inserted atomically and cycling a session's ID happens within
+/// a transaction, so rotation can't lose or duplicate sessions. Expired
+/// sessions are no longer loaded but remain in the table until removed via
+/// [`ExpiredDeletion::delete_expired`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use sea_orm::Database;
+/// use tower_sessions::{SeaOrmStore, SessionManagerLayer};
+///
+/// # tokio_test::block_on(async {
+/// let db = Database::connect("sqlite::memory:").await.unwrap();
+/// let session_store = SeaOrmStore::new(db);
+/// session_store.migrate().await.unwrap();
+/// let session_layer = SessionManagerLayer::new(session_store);
+/// # })
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeaOrmStore {
+    db: DatabaseConnection,
+    codec: Arc<dyn RecordCodec>,
+}
+
+impl SeaOrmStore {
+    /// The tag of the default MessagePack encoding within a [`CodecEnvelope`].
+    ///
+    /// Records written as plain MessagePack, before envelopes were
+    /// introduced, remain readable by default.
+    pub const MSGPACK_TAG: u8 = 1;
+
+    /// Create a new `SeaOrmStore` using the given database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            codec: Arc::new(
+                CodecEnvelope::new(Self::MSGPACK_TAG, MsgpackCodec).with_unversioned(MsgpackCodec),
+            ),
+        }
+    }
+
+    /// Configures the codec records are encoded with.
+    /// The default value encodes records as MessagePack within a
+    /// [`CodecEnvelope`].
+    ///
+    /// Records written with one codec can't be read with another. To change
+    /// the codec of an existing table, use a [`CodecEnvelope`] which also
+    /// decodes records of the previous codec, and optionally rewrite them via
+    /// [`migrate_codec`](Self::migrate_codec).
+    pub fn with_codec(mut self, codec: impl RecordCodec) -> Self {
+        self.codec = Arc::new(codec);
+        self
+    }
+
+    /// Rewrites the records which `from` decodes with `to`, returning the
+    /// number of records rewritten.
+    ///
+    /// Rows are rewritten `batch_size` at a time, each batch within a
+    /// transaction of its own. Records which `from` can't decode, e.g. those
+    /// already rewritten, are left as they are. The store itself should read
+    /// both codecs while migrating; see [`CodecEnvelope`].
+    ///
+    /// # Errors
+    ///
+    /// Fails when the table can't be read or written, or `to` can't encode a
+    /// record.
+    pub async fn migrate_codec(
+        &self,
+        from: impl RecordCodec,
+        to: impl RecordCodec,
+        batch_size: usize,
+    ) -> session_store::Result<usize> {
+        let mut pages = Entity::find()
+            .order_by_asc(Column::Id)
+            .paginate(&self.db, batch_size.max(1) as u64);
+
+        let mut rewritten = 0;
+        while let Some(models) = pages.fetch_and_next().await.map_err(backend_error)? {
+            let txn = self.db.begin().await.map_err(backend_error)?;
+            for model in models {
+                let Ok(record) = from.decode(&model.data) else {
+                    continue;
+                };
+                let model = ActiveModel {
+                    id: Unchanged(model.id),
+                    data: Set(to.encode(&record)?),
+                    expiry_date: Unchanged(model.expiry_date),
+                };
+                Entity::update(model)
+                    .exec(&txn)
+                    .await
+                    .map_err(backend_error)?;
+                rewritten += 1;
+            }
+            txn.commit().await.map_err(backend_error)?;
+        }
+        Ok(rewritten)
+    }
+
+    /// Create the sessions table if it doesn't exist.
+    ///
+    /// This applies [`Migration`] without recording it, for applications
+    /// which don't manage their schema with SeaORM migrations.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the schema can't be created.
+    pub async fn migrate(&self) -> Result<(), DbErr> {
+        Migration.up(&SchemaManager::new(&self.db)).await
+    }
+
+    async fn insert(
+        &self,
+        conn: &impl ConnectionTrait,
+        record: &Record,
+        upsert: bool,
+    ) -> session_store::Result<bool> {
+        let model = ActiveModel {
+            id: Set(record.id.to_string()),
+            data: Set(self.codec.encode(record)?),
+            expiry_date: Set(record.expiry_date.unix_timestamp()),
+        };
+
+        if upsert {
+            Entity::insert(model)
+                .on_conflict(
+                    OnConflict::column(Column::Id)
+                        .update_columns([Column::Data, Column::ExpiryDate])
+                        .to_owned(),
+                )
+                .exec_without_returning(conn)
+                .await
+                .map_err(backend_error)?;
+            return Ok(true);
+        }
+
+        let result = Entity::insert(model)
+            .on_conflict(OnConflict::column(Column::Id).do_nothing().to_owned())
+            .do_nothing()
+            .exec_without_returning(conn)
+            .await
+            .map_err(backend_error)?;
+        Ok(matches!(result, TryInsertResult::Inserted(rows) if rows > 0))
+    }
+
+    async fn create_with(
+        &self,
+        conn: &impl ConnectionTrait,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        while !self.insert(conn, record, false).await? {
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+        Ok(())
+    }
+}
+
+// Encodes records as MessagePack, as the store did before codecs were configurable.
+#[derive(Debug)]
+struct MsgpackCodec;
+
+impl RecordCodec for MsgpackCodec {
+    fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+        rmp_serde::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+        rmp_serde::from_slice(bytes).map_err(|err| session_store::Error::Decode(err.to_string()))
+    }
+}
+
+fn backend_error(err: DbErr) -> session_store::Error {
+    session_store::Error::Backend(err.to_string()).with_backend("sea-orm")
+}
+
+fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+#[async_trait]
+impl SessionStore for SeaOrmStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        self.create_with(&self.db, record).await
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        self.insert(&self.db, record, true).await.map(|_| ())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let model = Entity::find_by_id(session_id.to_string())
+            .filter(Column::ExpiryDate.gt(now()))
+            .one(&self.db)
+            .await
+            .map_err(backend_error)?;
+        model
+            .map(|model| self.codec.decode(&model.data))
+            .transpose()
+            .map(|record| record.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        Entity::delete_by_id(session_id.to_string())
+            .exec(&self.db)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn cycle_and_save(
+        &self,
+        old_session_id: &Id,
+        record: &mut Record,
+    ) -> session_store::Result<()> {
+        let txn = self.db.begin().await.map_err(backend_error)?;
+        Entity::delete_by_id(old_session_id.to_string())
+            .exec(&txn)
+            .await
+            .map_err(backend_error)?;
+        self.create_with(&txn, record).await?;
+        txn.commit().await.map_err(backend_error)
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for SeaOrmStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        Entity::delete_many()
+            .filter(Column::ExpiryDate.lte(now()))
+            .exec(&self.db)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStoreHealth for SeaOrmStore {
+    async fn ping(&self) -> session_store::Result<()> {
+        self.db.ping().await.map_err(backend_error)
+    }
+}
+
+#[async_trait]
+impl SessionStoreStats for SeaOrmStore {
+    async fn active_count(&self) -> session_store::Result<u64> {
+        Entity::find()
+            .filter(Column::ExpiryDate.gt(now()))
+            .count(&self.db)
+            .await
+            .map_err(backend_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ConnectOptions, Database};
+    use time::Duration;
+
+    use super::*;
+
+    async fn store() -> SeaOrmStore {
+        // Each connection to an in-memory database sees its own database.
+        let mut options = ConnectOptions::new("sqlite::memory:");
+        options.max_connections(1).sqlx_logging(false);
+        let store = SeaOrmStore::new(Database::connect(options).await.unwrap());
+        store.migrate().await.unwrap();
+        store
+    }
+
+    fn record(expiry_date: OffsetDateTime) -> Record {
+        Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    tower_sessions_test_kit::store_conformance_tests!(store().await);
+
+    #[tokio::test]
+    async fn test_migrate_is_idempotent() {
+        let store = store().await;
+        store.migrate().await.unwrap();
+        store.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let store = store().await;
+        let now = OffsetDateTime::now_utc();
+
+        let mut active = record(now + Duration::hours(1));
+        store.create(&mut active).await.unwrap();
+        let mut expired = record(now - Duration::hours(1));
+        store.create(&mut expired).await.unwrap();
+        assert_eq!(store.active_count().await.unwrap(), 1);
+
+        store.delete_expired().await.unwrap();
+        assert_eq!(Entity::find().count(&store.db).await.unwrap(), 1);
+        assert_eq!(store.load(&active.id).await.unwrap(), Some(active));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_codec() {
+        let store = store().await;
+        let legacy_store = store.clone().with_codec(MsgpackCodec);
+        let mut legacy = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        legacy_store.create(&mut legacy).await.unwrap();
+
+        // Records written before envelopes remain readable.
+        assert_eq!(store.load(&legacy.id).await.unwrap(), Some(legacy.clone()));
+        let mut current = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        store.create(&mut current).await.unwrap();
+
+        let envelope = CodecEnvelope::new(SeaOrmStore::MSGPACK_TAG, MsgpackCodec);
+        let rewritten = store.migrate_codec(MsgpackCodec, envelope.clone(), 1).await;
+        assert_eq!(rewritten.unwrap(), 1);
+        assert_eq!(
+            store
+                .migrate_codec(MsgpackCodec, envelope, 1)
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Once migrated, the unversioned codec is no longer needed.
+        let store = store.with_codec(CodecEnvelope::new(SeaOrmStore::MSGPACK_TAG, MsgpackCodec));
+        assert_eq!(store.load(&legacy.id).await.unwrap(), Some(legacy));
+        assert_eq!(store.load(&current.id).await.unwrap(), Some(current));
+    }
+
+    #[tokio::test]
+    async fn test_cycle_and_save_replaces_session() {
+        let store = store().await;
+        let mut record = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        store.create(&mut record).await.unwrap();
+
+        let old_session_id = record.id;
+        record.id = Id::default();
+        store
+            .cycle_and_save(&old_session_id, &mut record)
+            .await
+            .unwrap();
+
+        assert_eq!(store.load(&old_session_id).await.unwrap(), None);
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record));
+        assert_eq!(Entity::find().count(&store.db).await.unwrap(), 1);
+    }
+}
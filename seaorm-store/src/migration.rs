@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, sea_orm::DbErr};
+
+use crate::entity::{Column, Entity};
+
+/// Creates the sessions table.
+///
+/// Applications managing their schema with SeaORM migrations can include
+/// this migration in their own `MigratorTrait` implementation. Otherwise
+/// [`SeaOrmStore::migrate`](crate::SeaOrmStore::migrate) applies it directly.
+///
+/// # Examples
+///
+/// ```rust
+/// use sea_orm_migration::prelude::*;
+///
+/// pub struct Migrator;
+///
+/// impl MigratorTrait for Migrator {
+///     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+///         vec![Box::new(tower_sessions_seaorm_store::Migration)]
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20250101_000001_create_tower_sessions_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Entity)
+                    .if_not_exists()
+                    .col(ColumnDef::new(Column::Id).string().not_null().primary_key())
+                    .col(ColumnDef::new(Column::Data).blob().not_null())
+                    .col(ColumnDef::new(Column::ExpiryDate).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Allows expired sessions to be deleted without scanning the table.
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("tower_sessions_expiry_date_idx")
+                    .table(Entity)
+                    .col(Column::ExpiryDate)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Entity).if_exists().to_owned())
+            .await
+    }
+}
@@ -121,6 +121,41 @@
 //! Note that by default or when using browser session expiration, sessions are
 //! considered expired after two weeks.
 //!
+//! # Other frameworks
+//!
+//! The `actix-web` and `poem` features implement those frameworks' respective
+//! `FromRequest` traits for [`Session`]. As with `axum`, the session is taken
+//! from the request extensions, so [`SessionManagerLayer`] must be installed
+//! via the framework's `tower` compatibility shim.
+//!
+//! With the `grpc` feature, gRPC services such as those built with `tonic`
+//! may share sessions with HTTP clients: the session ID is then carried in
+//! request and response metadata rather than a cookie. See
+//! `SessionManagerLayer::with_metadata_key`. Within `tonic` services, the
+//! [`Session`] is available from the request extensions.
+//!
+//! Plain `hyper` and `tower` services need no extractor feature:
+//! [`RequestSessionExt`] retrieves the session from a request, its parts or
+//! its extensions.
+//!
+//! ```rust
+//! use std::convert::Infallible;
+//!
+//! use http::{Request, Response};
+//! use tower::ServiceBuilder;
+//! use tower_sessions::{MemoryStore, RequestSessionExt, SessionManagerLayer};
+//!
+//! async fn handler(req: Request<String>) -> Result<Response<String>, Infallible> {
+//!     let session = req.session().expect("session layer is installed");
+//!     session.insert("seen", true).await.unwrap();
+//!     Ok(Response::new(String::new()))
+//! }
+//!
+//! let svc = ServiceBuilder::new()
+//!     .layer(SessionManagerLayer::new(MemoryStore::default()))
+//!     .service_fn(handler);
+//! ```
+//!
 //! # Extractor pattern
 //!
 //! When using `axum`, the [`Session`] will already function as an extractor.
@@ -265,6 +300,24 @@
 //! the same session, but would be segmented by their own name-space, avoiding
 //! the mixing of domains unnecessarily.[^data-domains]
 //!
+//! [`Session::namespace`] provides such a name-space directly, prefixing the
+//! keys of its view of the session so that libraries sharing a session with
+//! the application don't collide with its keys. Together with
+//! [`Session::entry`], which gives a typed handle to a single key, buckets
+//! can be kept without hand-rolled key constants:
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! # use std::sync::Arc;
+//! # use tower_sessions::{MemoryStore, Session};
+//! # let session = Session::new(None, Arc::new(MemoryStore::default()), None);
+//! let pageviews = session.namespace("guest").entry::<usize>("pageviews");
+//! pageviews.get_or_insert_with(|| 0).await.unwrap();
+//! pageviews.update(|pageviews| *pageviews += 1).await.unwrap();
+//! # assert_eq!(session.get::<usize>("guest.pageviews").await.unwrap(), Some(1));
+//! # });
+//! ```
+//!
 //! # Layered caching
 //!
 //! In some cases, the canonical store for a session may benefit from a cache.
@@ -437,16 +490,47 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 pub use tower_cookies::cookie;
-pub use tower_sessions_core::{session, session_store};
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub use tower_sessions_core::testing;
+pub use tower_sessions_core::{extract, flash, session, session_keys, session_store};
 #[doc(inline)]
 pub use tower_sessions_core::{
+    extract::RequestSessionExt,
     session::{Expiry, Session},
-    session_store::{CachingSessionStore, ExpiredDeletion, SessionStore},
+    session_store::{
+        CachingSessionStore, ExpiredDeletion, SessionStore, SessionStoreHealth, SessionStoreStats,
+        ShardedSessionStore,
+    },
 };
+#[cfg(feature = "etcd-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "etcd-store")))]
+#[doc(inline)]
+pub use tower_sessions_etcd_store::EtcdStore;
+#[cfg(feature = "fs-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fs-store")))]
+#[doc(inline)]
+pub use tower_sessions_fs_store::FileStore;
 #[cfg(feature = "memory-store")]
 #[cfg_attr(docsrs, doc(cfg(feature = "memory-store")))]
 #[doc(inline)]
 pub use tower_sessions_memory_store::MemoryStore;
+#[cfg(feature = "remote-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-store")))]
+#[doc(inline)]
+pub use tower_sessions_remote_store::{RemoteSessionService, RemoteSessionStore};
+#[cfg(feature = "seaorm-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "seaorm-store")))]
+#[doc(inline)]
+pub use tower_sessions_seaorm_store::SeaOrmStore;
+#[cfg(feature = "workers-d1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "workers-d1")))]
+#[doc(inline)]
+pub use tower_sessions_workers_store::WorkersD1Store;
+#[cfg(feature = "workers-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "workers-store")))]
+#[doc(inline)]
+pub use tower_sessions_workers_store::WorkersKvStore;
 
 pub use crate::service::{SessionManager, SessionManagerLayer};
 
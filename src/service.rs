@@ -1,29 +1,42 @@
 //! A middleware that provides [`Session`] as a request extension.
 use std::{
     borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Write as _},
     future::Future,
-    pin::Pin,
-    sync::Arc,
-    task::{Context, Poll},
+    net::IpAddr,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
 };
 
-use http::{Request, Response};
-use time::OffsetDateTime;
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, Request, Response, Uri};
+use pin_project_lite::pin_project;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use time::{Duration, OffsetDateTime};
 #[cfg(any(feature = "signed", feature = "private"))]
 use tower_cookies::Key;
 use tower_cookies::{cookie::SameSite, Cookie, CookieManager, Cookies};
 use tower_layer::Layer;
 use tower_service::Service;
-use tracing::Instrument;
+use tracing::Instrument as _;
 
 use crate::{
-    session::{self, Expiry},
+    session::{
+        self, BindingPolicy, ClientMetadata, Expiry, ExpiryFn, RecordSizes, RecordValidator,
+        SecurityEvent, SecurityEventKind, SecurityEvents, SessionDataMigrator, SessionOptions,
+        TombstonePolicy, ValidationError,
+    },
     Session, SessionStore,
 };
 
 #[doc(hidden)]
 pub trait CookieController: Clone + Send + 'static {
-    fn get(&self, cookies: &Cookies, name: &str) -> Option<Cookie<'static>>;
+    fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>>;
     fn add(&self, cookies: &Cookies, cookie: Cookie<'static>);
     fn remove(&self, cookies: &Cookies, cookie: Cookie<'static>);
 }
@@ -33,8 +46,8 @@ pub trait CookieController: Clone + Send + 'static {
 pub struct PlaintextCookie;
 
 impl CookieController for PlaintextCookie {
-    fn get(&self, cookies: &Cookies, name: &str) -> Option<Cookie<'static>> {
-        cookies.get(name).map(Cookie::into_owned)
+    fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        Some(cookie)
     }
 
     fn add(&self, cookies: &Cookies, cookie: Cookie<'static>) {
@@ -55,8 +68,11 @@ pub struct SignedCookie {
 
 #[cfg(feature = "signed")]
 impl CookieController for SignedCookie {
-    fn get(&self, cookies: &Cookies, name: &str) -> Option<Cookie<'static>> {
-        cookies.signed(&self.key).get(name).map(Cookie::into_owned)
+    fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = tower_cookies::cookie::CookieJar::new();
+        jar.add_original(cookie);
+        jar.signed(&self.key).get(&name)
     }
 
     fn add(&self, cookies: &Cookies, cookie: Cookie<'static>) {
@@ -77,8 +93,11 @@ pub struct PrivateCookie {
 
 #[cfg(feature = "private")]
 impl CookieController for PrivateCookie {
-    fn get(&self, cookies: &Cookies, name: &str) -> Option<Cookie<'static>> {
-        cookies.private(&self.key).get(name).map(Cookie::into_owned)
+    fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = tower_cookies::cookie::CookieJar::new();
+        jar.add_original(cookie);
+        jar.private(&self.key).get(&name)
     }
 
     fn add(&self, cookies: &Cookies, cookie: Cookie<'static>) {
@@ -90,548 +109,4647 @@ impl CookieController for PrivateCookie {
     }
 }
 
+#[doc(hidden)]
+#[cfg(all(feature = "signed", feature = "private"))]
 #[derive(Debug, Clone)]
-struct SessionConfig<'a> {
-    name: Cow<'a, str>,
-    http_only: bool,
-    same_site: SameSite,
-    expiry: Option<Expiry>,
-    secure: bool,
-    path: Cow<'a, str>,
-    domain: Option<Cow<'a, str>>,
-    always_save: bool,
+pub struct SignedPrivateCookie {
+    signing_key: Key,
+    encryption_key: Key,
 }
 
-impl<'a> SessionConfig<'a> {
-    fn build_cookie(self, session_id: session::Id, expiry: Option<Expiry>) -> Cookie<'a> {
-        let mut cookie_builder = Cookie::build((self.name, session_id.to_string()))
-            .http_only(self.http_only)
-            .same_site(self.same_site)
-            .secure(self.secure)
-            .path(self.path);
-
-        cookie_builder = match expiry {
-            Some(Expiry::OnInactivity(duration)) => cookie_builder.max_age(duration),
-            Some(Expiry::AtDateTime(datetime)) => {
-                cookie_builder.max_age(datetime - OffsetDateTime::now_utc())
-            }
-            Some(Expiry::OnSessionEnd) | None => cookie_builder,
-        };
+// The cookie's value is signed with the signing key and the signed value is
+// then encrypted with the encryption key, each exactly as `SignedCookies` and
+// `PrivateCookies` would do it, so either layer can be read by plain
+// `tower-cookies` jars.
+#[cfg(all(feature = "signed", feature = "private"))]
+impl CookieController for SignedPrivateCookie {
+    fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_string();
+        let mut jar = tower_cookies::cookie::CookieJar::new();
+        jar.add_original(cookie);
+        let signed = jar.private(&self.encryption_key).get(&name)?;
+        let mut jar = tower_cookies::cookie::CookieJar::new();
+        jar.add_original(signed);
+        jar.signed(&self.signing_key).get(&name)
+    }
 
-        if let Some(domain) = self.domain {
-            cookie_builder = cookie_builder.domain(domain);
+    fn add(&self, cookies: &Cookies, cookie: Cookie<'static>) {
+        let name = cookie.name().to_string();
+        let mut jar = tower_cookies::cookie::CookieJar::new();
+        jar.signed_mut(&self.signing_key).add(cookie);
+        if let Some(signed) = jar.get(&name) {
+            cookies.private(&self.encryption_key).add(signed.clone());
         }
+    }
 
-        cookie_builder.build()
+    fn remove(&self, cookies: &Cookies, cookie: Cookie<'static>) {
+        cookies.private(&self.encryption_key).remove(cookie)
     }
 }
 
-impl Default for SessionConfig<'_> {
-    fn default() -> Self {
-        Self {
-            name: "id".into(), /* See: https://cheatsheetseries.owasp.org/cheatsheets/Session_Management_Cheat_Sheet.html#session-id-name-fingerprinting */
-            http_only: true,
-            same_site: SameSite::Strict,
-            expiry: None, // TODO: Is `Max-Age: "Session"` the right default?
-            secure: true,
-            path: "/".into(),
-            domain: None,
-            always_save: false,
-        }
+/// Errors encountered by the middleware itself, rather than the inner
+/// service.
+///
+/// When one of these occurs the middleware responds with `500 Internal Server
+/// Error` and attaches the error to the response's extensions. This allows
+/// applications to log the cause and render a proper error page, for
+/// instance via `axum::middleware::map_response`:
+///
+/// ```rust
+/// use axum::{
+///     http::StatusCode,
+///     response::{IntoResponse, Response},
+/// };
+/// use tower_sessions::service::SessionLayerError;
+///
+/// async fn render_session_error(res: Response) -> Response {
+///     match res.extensions().get::<SessionLayerError>() {
+///         Some(err) => (
+///             StatusCode::INTERNAL_SERVER_ERROR,
+///             format!("Session error: {err}"),
+///         )
+///             .into_response(),
+///         None => res,
+///     }
+/// }
+/// ```
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum SessionLayerError {
+    /// The cookies request extension was not found.
+    ///
+    /// This occurs when a layer created via
+    /// [`SessionManagerLayer::with_external_cookie_manager`] isn't wrapped by
+    /// a `CookieManagerLayer`.
+    #[error("Missing cookies request extension, is a `CookieManagerLayer` installed?")]
+    MissingCookies,
+
+    /// Saving the session to the store failed.
+    #[error("Failed to save session: {0}")]
+    Save(#[source] Arc<session::Error>),
+
+    /// The session had no ID after saving.
+    #[error("Missing session id")]
+    MissingId,
+
+    /// Redeeming a session transfer token failed, see
+    /// [`SessionManagerLayer::with_transfer_token`].
+    #[error("Failed to redeem session transfer token: {0}")]
+    Transfer(#[source] Arc<session::Error>),
+}
+
+impl SessionLayerError {
+    fn into_response<ResBody: Default>(self) -> Response<ResBody> {
+        tracing::error!(err = %self, "session layer error");
+
+        let mut res = Response::default();
+        *res.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+        res.extensions_mut().insert(self);
+        res
     }
 }
 
-/// A middleware that provides [`Session`] as a request extension.
-#[derive(Debug, Clone)]
-pub struct SessionManager<S, Store: SessionStore, C: CookieController = PlaintextCookie> {
-    inner: S,
-    session_store: Arc<Store>,
-    session_config: SessionConfig<'static>,
-    cookie_controller: C,
+/// A pending change of the session cookie, exposed as a response extension
+/// in place of `Set-Cookie` headers.
+///
+/// See [`SessionManagerLayer::with_external_cookie_writer`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SessionCookieUpdate {
+    /// The session was saved and its cookie should be set.
+    Set {
+        /// The ID of the session.
+        session_id: session::Id,
+
+        /// When the cookie should expire, or `None` if it should only last for
+        /// the browser session.
+        expires_at: Option<OffsetDateTime>,
+
+        /// The cookie the middleware would have set.
+        cookie: Cookie<'static>,
+    },
+
+    /// The session was emptied and its cookie should be removed.
+    Remove {
+        /// The cookie to remove, identified by its name, path and domain.
+        cookie: Cookie<'static>,
+    },
 }
 
-impl<S, Store: SessionStore> SessionManager<S, Store> {
-    /// Create a new [`SessionManager`].
-    pub fn new(inner: S, session_store: Store) -> Self {
-        Self {
-            inner,
-            session_store: Arc::new(session_store),
-            session_config: Default::default(),
-            cookie_controller: PlaintextCookie,
+/// A directive of the `Clear-Site-Data` header sent when the session cookie
+/// is removed.
+///
+/// See [`SessionManagerLayer::with_clear_site_data`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ClearSiteData {
+    /// Clears the HTTP cache of the site.
+    Cache,
+
+    /// Clears all cookies of the site, including those of its subdomains.
+    Cookies,
+
+    /// Clears DOM storage of the site, such as `localStorage` and
+    /// `IndexedDB`, and unregisters its service workers.
+    Storage,
+
+    /// Reloads all browsing contexts of the site.
+    ExecutionContexts,
+
+    /// Clears all of the above, as well as any data types added in the
+    /// future.
+    All,
+}
+
+impl ClearSiteData {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Cache => "\"cache\"",
+            Self::Cookies => "\"cookies\"",
+            Self::Storage => "\"storage\"",
+            Self::ExecutionContexts => "\"executionContexts\"",
+            Self::All => "\"*\"",
         }
     }
 }
 
-impl<ReqBody, ResBody, S, Store: SessionStore, C: CookieController> Service<Request<ReqBody>>
-    for SessionManager<S, Store, C>
-where
-    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
-    S::Future: Send,
-    ReqBody: Send + 'static,
-    ResBody: Default + Send,
-{
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+/// Invalid layer configuration, as reported by
+/// [`SessionManagerLayer::try_build`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The cookie name is empty.
+    #[error("Cookie name must not be empty")]
+    EmptyName,
+
+    /// The cookie name contains characters not allowed in cookie names.
+    #[error("Cookie name `{0}` contains invalid characters")]
+    InvalidName(String),
+
+    /// `SameSite=None` was configured without `Secure`, which browsers
+    /// reject.
+    #[error("`SameSite=None` requires the `Secure` attribute")]
+    SameSiteNoneWithoutSecure,
+
+    /// The cookie domain starts with a dot, which is ignored by browsers and
+    /// suggests a misunderstanding of how domain matching works.
+    #[error("Cookie domain `{0}` must not start with a dot")]
+    DomainLeadingDot(String),
+
+    /// The cookie path doesn't start with a slash.
+    #[error("Cookie path `{0}` must start with `/`")]
+    InvalidPath(String),
+
+    /// The companion cookie has the same name as the session cookie.
+    #[error("Companion cookie name `{0}` conflicts with the session cookie")]
+    CompanionNameConflict(String),
+
+    /// The cookie name has a `__Secure-` or `__Host-` prefix whose
+    /// requirements aren't met, so browsers reject the cookie.
+    ///
+    /// Both prefixes require the `Secure` attribute. `__Host-` additionally
+    /// requires the path `/` and no domain.
+    #[error("Cookie name `{0}` has a prefix whose requirements aren't met")]
+    PrefixRequirements(String),
+}
 
-    #[inline]
-    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
+/// The IP address of the client making a request.
+///
+/// Client binding on IP requires this to be present as a request extension.
+/// Because only the application knows whether the peer address or a proxy
+/// header should be trusted, it's left to the application to provide it, e.g.
+/// from axum's `ConnectInfo` via a small middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Configuration for binding sessions to the client that created them.
+///
+/// When enabled, a fingerprint of the selected client attributes is recorded
+/// with the session when it's created. Subsequent requests presenting the
+/// session are checked against that fingerprint and the configured
+/// [`BindingPolicy`] is applied on mismatch.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{service::ClientBinding, session::BindingPolicy};
+///
+/// let client_binding = ClientBinding::new(BindingPolicy::Reject)
+///     .with_ip(true)
+///     .with_user_agent(true);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientBinding {
+    ip: bool,
+    user_agent: bool,
+    policy: BindingPolicy,
+}
+
+impl ClientBinding {
+    /// Create a new `ClientBinding` with the given policy, binding to the
+    /// user agent only.
+    pub fn new(policy: BindingPolicy) -> Self {
+        Self {
+            ip: false,
+            user_agent: true,
+            policy,
+        }
     }
 
-    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
-        let span = tracing::info_span!("call");
+    /// Configures whether the client IP, as provided by the [`ClientIp`]
+    /// request extension, is part of the binding.
+    pub fn with_ip(mut self, ip: bool) -> Self {
+        self.ip = ip;
+        self
+    }
 
-        let session_store = self.session_store.clone();
-        let session_config = self.session_config.clone();
-        let cookie_controller = self.cookie_controller.clone();
+    /// Configures whether the `User-Agent` header is part of the binding.
+    pub fn with_user_agent(mut self, user_agent: bool) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
 
-        // Because the inner service can panic until ready, we need to ensure we only
-        // use the ready service.
-        //
-        // See: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
-        let clone = self.inner.clone();
-        let mut inner = std::mem::replace(&mut self.inner, clone);
+    fn fingerprint<B>(&self, req: &Request<B>) -> String {
+        let mut hasher = Sha256::new();
 
-        Box::pin(
-            async move {
-                let Some(cookies) = req.extensions().get::<_>().cloned() else {
-                    // In practice this should never happen because we wrap `CookieManager`
-                    // directly.
-                    tracing::error!("missing cookies request extension");
-                    return Ok(Response::default());
-                };
-
-                let session_cookie = cookie_controller.get(&cookies, &session_config.name);
-                let session_id = session_cookie.as_ref().and_then(|cookie| {
-                    cookie
-                        .value()
-                        .parse::<session::Id>()
-                        .map_err(|err| {
-                            tracing::warn!(
-                                err = %err,
-                                "possibly suspicious activity: malformed session id"
-                            )
-                        })
-                        .ok()
-                });
-
-                let session = Session::new(session_id, session_store, session_config.expiry);
-
-                req.extensions_mut().insert(session.clone());
-
-                let res = inner.call(req).await?;
-
-                let modified = session.is_modified();
-                let empty = session.is_empty().await;
-
-                tracing::trace!(
-                    modified = modified,
-                    empty = empty,
-                    always_save = session_config.always_save,
-                    "session response state",
-                );
+        if self.ip {
+            let ip = req.extensions().get::<ClientIp>().map(|ClientIp(ip)| ip);
+            if ip.is_none() {
+                tracing::warn!("client binding on ip requires the `ClientIp` request extension");
+            }
+            hasher.update(b"ip:");
+            hasher.update(ip.map(ToString::to_string).unwrap_or_default());
+            hasher.update(b"\n");
+        }
 
-                match session_cookie {
-                    Some(mut cookie) if empty => {
-                        tracing::debug!("removing session cookie");
+        if self.user_agent {
+            hasher.update(b"ua:");
+            if let Some(user_agent) = req.headers().get(http::header::USER_AGENT) {
+                hasher.update(user_agent.as_bytes());
+            }
+            hasher.update(b"\n");
+        }
 
-                        // Path and domain must be manually set to ensure a proper removal cookie is
-                        // constructed.
-                        //
-                        // See: https://docs.rs/cookie/latest/cookie/struct.CookieJar.html#method.remove
-                        cookie.set_path(session_config.path);
-                        if let Some(domain) = session_config.domain {
-                            cookie.set_domain(domain);
-                        }
+        hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+}
 
-                        cookie_controller.remove(&cookies, cookie);
-                    }
+type CompanionValueFn = Arc<dyn Fn(&session::Id) -> String + Send + Sync>;
+
+/// A secondary cookie sent alongside the session cookie, carrying a value
+/// derived from the session ID.
+///
+/// This is intended for the double-submit pattern: client-side code reads the
+/// companion cookie and echoes its value back, e.g. in a header, which the
+/// application then compares against the value derived from the session. The
+/// companion cookie is set whenever the session cookie is, so it's rotated
+/// whenever the session ID cycles, and removed along with the session
+/// cookie.
+///
+/// Attributes not configured here are inherited from the session cookie,
+/// except that the companion cookie is not `HttpOnly` by default. It's always
+/// sent in plaintext, even when the session cookie is signed or private, so
+/// that client-side code can read it. Its value should therefore not reveal
+/// the session ID, e.g. by using a keyed hash of it.
+///
+/// # Examples
+///
+/// ```rust
+/// use sha2::{Digest, Sha256};
+/// use tower_sessions::{service::CompanionCookie, session::Id};
+///
+/// const SECRET: &[u8] = b"an application secret";
+///
+/// let companion_cookie = CompanionCookie::new("csrf-token", |session_id: &Id| {
+///     let mut hasher = Sha256::new();
+///     hasher.update(SECRET);
+///     hasher.update(session_id.to_string());
+///     format!("{:x}", hasher.finalize())
+/// });
+/// ```
+#[derive(Clone)]
+pub struct CompanionCookie {
+    name: Cow<'static, str>,
+    value_fn: CompanionValueFn,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    path: Option<Cow<'static, str>>,
+    domain: Option<Cow<'static, str>>,
+}
 
-                    _ if (modified || session_config.always_save)
-                        && !empty
-                        && !res.status().is_server_error() =>
-                    {
-                        tracing::debug!("saving session");
-                        if let Err(err) = session.save().await {
-                            tracing::error!(err = %err, "failed to save session");
+impl CompanionCookie {
+    /// Create a new `CompanionCookie` with the given name, whose value is
+    /// computed from the session ID by `value_fn`.
+    pub fn new<N, F>(name: N, value_fn: F) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+        F: Fn(&session::Id) -> String + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            value_fn: Arc::new(value_fn),
+            http_only: false,
+            same_site: None,
+            path: None,
+            domain: None,
+        }
+    }
 
-                            let mut res = Response::default();
-                            *res.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
-                            return Ok(res);
-                        }
+    /// Configures the `"HttpOnly"` attribute of the cookie.
+    /// The default value is `false`.
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
 
-                        let Some(session_id) = session.id() else {
-                            tracing::error!("missing session id");
+    /// Configures the `"SameSite"` attribute of the cookie.
+    /// The default value is that of the session cookie.
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
 
-                            let mut res = Response::default();
-                            *res.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
-                            return Ok(res);
-                        };
+    /// Configures the `"Path"` attribute of the cookie.
+    /// The default value is that of the session cookie.
+    pub fn with_path<P: Into<Cow<'static, str>>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
 
-                        let expiry = session.expiry();
-                        let session_cookie = session_config.build_cookie(session_id, expiry);
+    /// Configures the `"Domain"` attribute of the cookie.
+    /// The default value is that of the session cookie.
+    pub fn with_domain<D: Into<Cow<'static, str>>>(mut self, domain: D) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
 
-                        tracing::debug!("adding session cookie");
-                        cookie_controller.add(&cookies, session_cookie);
-                    }
+    fn build(&self, session_id: &session::Id, session_cookie: &Cookie<'static>) -> Cookie<'static> {
+        let mut cookie = session_cookie.clone();
+        cookie.set_name(self.name.clone());
+        cookie.set_value((self.value_fn)(session_id));
+        cookie.set_http_only(self.http_only);
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+        if let Some(ref path) = self.path {
+            cookie.set_path(path.clone());
+        }
+        if let Some(ref domain) = self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
 
-                    _ => (),
-                };
+    fn removal(
+        &self,
+        path: Cow<'static, str>,
+        domain: Option<Cow<'static, str>>,
+    ) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.name.clone(), "");
+        cookie.set_path(self.path.clone().unwrap_or(path));
+        if let Some(domain) = self.domain.clone().or(domain) {
+            cookie.set_domain(domain);
+        }
+        cookie
+    }
+}
 
-                Ok(res)
-            }
-            .instrument(span),
-        )
+impl fmt::Debug for CompanionCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompanionCookie")
+            .field("name", &self.name)
+            .field("http_only", &self.http_only)
+            .field("same_site", &self.same_site)
+            .field("path", &self.path)
+            .field("domain", &self.domain)
+            .finish_non_exhaustive()
     }
 }
 
-/// A layer for providing [`Session`] as a request extension.
-#[derive(Debug, Clone)]
-pub struct SessionManagerLayer<Store: SessionStore, C: CookieController = PlaintextCookie> {
-    session_store: Arc<Store>,
-    session_config: SessionConfig<'static>,
-    cookie_controller: C,
+type SaveErrorFn = dyn Fn(&session::Error) + Send + Sync;
+
+/// Configuration for saving sessions in the background, after the response
+/// has been returned.
+///
+/// Saving otherwise happens before the response is returned, adding the
+/// store's latency to every request which modifies the session. Deferring it
+/// trades that latency for weaker guarantees:
+///
+/// - Save failures can't be reported to the client. By default, a failed save
+///   is logged and not retried, i.e. saves happen at most once. See
+///   [`with_retries`](DeferredSave::with_retries),
+///   [`with_backoff`](DeferredSave::with_backoff) and
+///   [`with_error_handler`](DeferredSave::with_error_handler).
+/// - A request following shortly after may load the session before the save
+///   has completed, observing its previous state.
+///
+/// Sessions without an ID in the store, such as new sessions or those whose
+/// ID has been cycled, are always saved before responding, as their ID is
+/// only final once the store has accepted them. Saving in the background
+/// requires a Tokio runtime; without one, sessions are saved before
+/// responding. Use a [`SessionLayerHandle`] to wait for pending saves when
+/// shutting down.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{service::DeferredSave, MemoryStore, SessionManagerLayer};
+///
+/// let deferred_save = DeferredSave::new()
+///     .with_retries(2)
+///     .with_error_handler(|err| eprintln!("failed to save session: {err}"));
+///
+/// let session_store = MemoryStore::default();
+/// let session_service = SessionManagerLayer::new(session_store).with_deferred_save(deferred_save);
+/// ```
+#[derive(Clone)]
+pub struct DeferredSave {
+    retries: u32,
+    base_delay: std::time::Duration,
+    max_delay: std::time::Duration,
+    on_error: Option<Arc<SaveErrorFn>>,
 }
 
-impl<Store: SessionStore, C: CookieController> SessionManagerLayer<Store, C> {
-    /// Configures the name of the cookie used for the session.
-    /// The default value is `"id"`.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_name("my.sid");
-    /// ```
-    pub fn with_name<N: Into<Cow<'static, str>>>(mut self, name: N) -> Self {
-        self.session_config.name = name.into();
-        self
+impl Default for DeferredSave {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            base_delay: std::time::Duration::from_millis(50),
+            max_delay: std::time::Duration::from_secs(1),
+            on_error: None,
+        }
     }
+}
 
-    /// Configures the `"HttpOnly"` attribute of the cookie used for the
-    /// session.
-    ///
-    /// # ⚠️ **Warning: Cross-site scripting risk**
-    ///
-    /// Applications should generally **not** override the default value of
-    /// `true`. If you do, you are exposing your application to increased risk
-    /// of cookie theft via techniques like cross-site scripting.
-    ///
-    /// # Examples
+impl DeferredSave {
+    /// Create a new `DeferredSave` attempting each save once.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures how many times a failed save is retried.
     ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    /// Only saves failing with a store error which may succeed when retried,
+    /// see [`is_retryable`](crate::session_store::Error::is_retryable), are
+    /// retried, each after a backoff, see
+    /// [`with_backoff`](DeferredSave::with_backoff).
     ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_http_only(true);
-    /// ```
-    pub fn with_http_only(mut self, http_only: bool) -> Self {
-        self.session_config.http_only = http_only;
+    /// The default value is `0`.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
         self
     }
 
-    /// Configures the `"SameSite"` attribute of the cookie used for the
-    /// session.
-    /// The default value is [`SameSite::Strict`].
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{cookie::SameSite, MemoryStore, SessionManagerLayer};
+    /// Configures the delay before the first retry, which doubles for each
+    /// subsequent retry up to `max_delay`.
     ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_same_site(SameSite::Lax);
-    /// ```
-    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
-        self.session_config.same_site = same_site;
+    /// The default values are 50 milliseconds and 1 second.
+    pub fn with_backoff(
+        mut self,
+        base_delay: std::time::Duration,
+        max_delay: std::time::Duration,
+    ) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay.max(base_delay);
         self
     }
 
-    /// Configures the `"Max-Age"` attribute of the cookie used for the session.
+    /// Configures a function called with the error of a save which failed
+    /// after all attempts.
     /// The default value is `None`.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use time::Duration;
-    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_expiry = Expiry::OnInactivity(Duration::hours(1));
-    /// let session_service = SessionManagerLayer::new(session_store).with_expiry(session_expiry);
-    /// ```
-    pub fn with_expiry(mut self, expiry: Expiry) -> Self {
-        self.session_config.expiry = Some(expiry);
+    pub fn with_error_handler<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(&session::Error) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(on_error));
         self
     }
 
-    /// Configures the `"Secure"` attribute of the cookie used for the session.
-    /// The default value is `true`.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_secure(true);
-    /// ```
-    pub fn with_secure(mut self, secure: bool) -> Self {
-        self.session_config.secure = secure;
-        self
+    async fn save(self, session: Session) {
+        // The request's deadline has no bearing on saves after responding.
+        #[cfg(feature = "resilience")]
+        session.clear_deadline();
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let err = match session.save().await {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+            let is_retryable = matches!(err, session::Error::Store(ref err) if err.is_retryable());
+            if is_retryable && attempt <= self.retries {
+                let delay = self.delay(attempt);
+                tracing::warn!(err = %err, attempt, ?delay, "deferred session save failed; retrying");
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            tracing::error!(err = %err, "deferred session save failed");
+            if let Some(ref on_error) = self.on_error {
+                on_error(&err);
+            }
+            return;
+        }
     }
 
-    /// Configures the `"Path"` attribute of the cookie used for the session.
-    /// The default value is `"/"`.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_path("/some/path");
-    /// ```
-    pub fn with_path<P: Into<Cow<'static, str>>>(mut self, path: P) -> Self {
-        self.session_config.path = path.into();
-        self
+    fn delay(&self, attempt: u32) -> std::time::Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay)
     }
+}
 
-    /// Configures the `"Domain"` attribute of the cookie used for the session.
-    /// The default value is `None`.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_domain("localhost");
-    /// ```
-    pub fn with_domain<D: Into<Cow<'static, str>>>(mut self, domain: D) -> Self {
-        self.session_config.domain = Some(domain.into());
-        self
+impl fmt::Debug for DeferredSave {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeferredSave")
+            .field("retries", &self.retries)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .finish_non_exhaustive()
     }
+}
 
-    /// Configures whether unmodified session should be saved on read or not.
-    /// When the value is `true`, the session will be saved even if it was not
-    /// changed.
+/// A handle for shutting down a [`SessionManagerLayer`]'s background work.
+///
+/// Sessions saved in the background with
+/// [`with_deferred_save`](SessionManagerLayer::with_deferred_save) are lost if
+/// the process exits before their saves complete. Calling
+/// [`shutdown`](SessionLayerHandle::shutdown) once the server has stopped
+/// accepting requests waits for those saves; requests still in flight save
+/// their sessions before responding from then on.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use axum::{routing::get, Router};
+/// use tower_sessions::{service::DeferredSave, MemoryStore, SessionManagerLayer};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let session_store = MemoryStore::default();
+/// let session_layer =
+///     SessionManagerLayer::new(session_store).with_deferred_save(DeferredSave::new());
+/// let handle = session_layer.handle();
+///
+/// let app = Router::new()
+///     .route("/", get(|| async { "Hello, World!" }))
+///     .layer(session_layer);
+///
+/// let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+/// axum::serve(listener, app)
+///     .with_graceful_shutdown(async {
+///         tokio::signal::ctrl_c().await.ok();
+///     })
+///     .await?;
+///
+/// // Flush sessions still being saved before exiting.
+/// handle.shutdown().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SessionLayerHandle {
+    inner: Arc<HandleInner>,
+}
+
+#[derive(Debug, Default)]
+struct HandleInner {
+    shutdown: AtomicBool,
+    pending: AtomicUsize,
+    idle: tokio::sync::Notify,
+}
+
+impl SessionLayerHandle {
+    /// Stop deferring saves and wait for pending ones to complete.
+    ///
+    /// Saves are retried as configured by [`DeferredSave`] before this
+    /// returns. Calling this more than once is harmless.
+    pub async fn shutdown(&self) {
+        self.inner.shutdown.store(true, Ordering::SeqCst);
+        loop {
+            let mut idle = pin!(self.inner.idle.notified());
+            // Register for the wakeup before checking, so it can't be missed.
+            idle.as_mut().enable();
+            if self.inner.pending.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Whether [`shutdown`](SessionLayerHandle::shutdown) has been called.
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// The number of saves currently pending in the background.
+    pub fn pending(&self) -> usize {
+        self.inner.pending.load(Ordering::SeqCst)
+    }
+
+    // Registers a background save, unless shutting down.
+    fn track(&self) -> Option<PendingSave> {
+        // Counting before checking the flag ensures `shutdown` either sees
+        // this save or this save sees the flag.
+        self.inner.pending.fetch_add(1, Ordering::SeqCst);
+        let pending = PendingSave(self.inner.clone());
+        (!self.is_shutdown()).then_some(pending)
+    }
+}
+
+struct PendingSave(Arc<HandleInner>);
+
+impl Drop for PendingSave {
+    fn drop(&mut self) {
+        if self.0.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+type PathFn = dyn Fn(&Uri, &HeaderMap) -> Option<String> + Send + Sync;
+
+// Resolves the cookie path per request.
+#[derive(Clone)]
+struct CookiePathFn(Arc<PathFn>);
+
+impl CookiePathFn {
+    fn resolve(&self, uri: &Uri, headers: &HeaderMap) -> Option<String> {
+        let path = (self.0)(uri, headers)?;
+        if !is_valid_path(&path) {
+            tracing::warn!(path = %path, "ignoring invalid cookie path");
+            return None;
+        }
+        Some(path)
+    }
+}
+
+impl fmt::Debug for CookiePathFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CookiePathFn").finish_non_exhaustive()
+    }
+}
+
+// Derives the ID of new sessions from a token the client sends in a header.
+#[derive(Clone)]
+struct CreationToken {
+    header: HeaderName,
+    key: Arc<[u8]>,
+}
+
+impl CreationToken {
+    // Tokens shorter than this are too easily guessed.
+    const MIN_LEN: usize = 16;
+    const MAX_LEN: usize = 256;
+
+    fn creation_id(&self, headers: &HeaderMap) -> Option<session::Id> {
+        let token = headers.get(&self.header)?.to_str().ok()?;
+        if !(Self::MIN_LEN..=Self::MAX_LEN).contains(&token.len()) {
+            tracing::warn!("ignoring session creation token of invalid length");
+            return None;
+        }
+        Some(session::Id::from_token(token, &self.key))
+    }
+}
+
+impl fmt::Debug for CreationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreationToken")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+// Redeems transfer tokens passed in a query parameter, see `Session::redeem_transfer_token`.
+#[derive(Clone)]
+struct TransferToken {
+    param: Cow<'static, str>,
+    key: Arc<[u8]>,
+}
+
+impl TransferToken {
+    // Transfer tokens are URL-safe, so the parameter is matched without decoding.
+    fn token<'r>(&self, uri: &'r http::Uri) -> Option<&'r str> {
+        uri.query()?.split('&').find_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            (name == self.param).then_some(value)
+        })
+    }
+}
+
+impl fmt::Debug for TransferToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransferToken")
+            .field("param", &self.param)
+            .finish_non_exhaustive()
+    }
+}
+
+fn is_valid_path(path: &str) -> bool {
+    path.starts_with('/') && !path.chars().any(|c| c == ';' || c.is_ascii_control())
+}
+
+// Whether a cookie with the given path is sent for the request path, per RFC 6265 5.1.4.
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+// Whether a cookie with the given domain is accepted from the host, per RFC 6265 5.1.3.
+fn domain_matches(domain: &str, host: &str) -> bool {
+    // Strip the port, leaving IPv6 literals intact.
+    let host = match host.rsplit_once(':') {
+        Some((name, _)) if !name.contains(':') || name.ends_with(']') => name,
+        _ => host,
+    };
+    let host = host.trim_end_matches('.');
+    if host.eq_ignore_ascii_case(domain) {
+        return true;
+    }
+    host.len() > domain.len()
+        && host.is_char_boundary(host.len() - domain.len())
+        && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+        && host[..host.len() - domain.len()].ends_with('.')
+}
+
+/// The path prefix under which an application is mounted, provided as a
+/// request extension.
+///
+/// With [`with_path_from_matched_prefix`] enabled, the session cookie's
+/// `"Path"` attribute is derived from this prefix. Insert it ahead of the
+/// session layer wherever a router is nested, e.g. with axum's `Extension`
+/// layer.
+///
+/// [`with_path_from_matched_prefix`]: SessionManagerLayer::with_path_from_matched_prefix
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchedPrefix(Cow<'static, str>);
+
+impl MatchedPrefix {
+    /// Create a new `MatchedPrefix`, such as `/app` for a router nested at
+    /// that path.
+    pub fn new(prefix: impl Into<Cow<'static, str>>) -> Self {
+        Self(prefix.into())
+    }
+
+    /// Returns the prefix.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // The cookie path covering the prefix, as `/app/` wouldn't match requests for `/app`.
+    fn cookie_path(&self) -> Option<String> {
+        let path = self.0.trim_end_matches('/');
+        let path = if path.is_empty() { "/" } else { path };
+        is_valid_path(path).then(|| path.to_string())
+    }
+
+    // Nested routers may strip the prefix from the URI before the session layer sees it.
+    fn request_path(&self, uri: &Uri) -> String {
+        let prefix = self.0.trim_end_matches('/');
+        if path_matches(prefix, uri.path()) {
+            uri.path().to_string()
+        } else {
+            format!("{prefix}{}", uri.path())
+        }
+    }
+}
+
+/// Error returned when a cookie value can't be decoded into a session ID.
+pub type CookieValueError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Encodes session IDs into cookie values and decodes them back.
+///
+/// By default the cookie value is the session ID itself, see [`PlainIdCodec`].
+/// Implementations may instead wrap the ID, e.g. in a signed token carrying
+/// additional claims expected by a gateway.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{
+///     service::{CookieValueCodec, CookieValueError},
+///     session::Id,
+/// };
+///
+/// #[derive(Debug)]
+/// struct TenantCodec;
+///
+/// impl CookieValueCodec for TenantCodec {
+///     fn encode(&self, session_id: &Id) -> String {
+///         format!("acme.{session_id}")
+///     }
+///
+///     fn decode(&self, value: &str) -> Result<Id, CookieValueError> {
+///         let session_id = value.strip_prefix("acme.").ok_or("unknown tenant")?;
+///         Ok(session_id.parse()?)
+///     }
+/// }
+/// ```
+pub trait CookieValueCodec: std::fmt::Debug + Send + Sync + 'static {
+    /// Encodes the session ID as a cookie value.
+    fn encode(&self, session_id: &session::Id) -> String;
+
+    /// Decodes a cookie value into the session ID it carries.
+    fn decode(&self, value: &str) -> Result<session::Id, CookieValueError>;
+}
+
+/// The default [`CookieValueCodec`], which uses the session ID as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainIdCodec;
+
+impl CookieValueCodec for PlainIdCodec {
+    fn encode(&self, session_id: &session::Id) -> String {
+        session_id.to_string()
+    }
+
+    fn decode(&self, value: &str) -> Result<session::Id, CookieValueError> {
+        Ok(value.parse()?)
+    }
+}
+
+/// A [`CookieValueCodec`] binding session IDs to a server-side key with an
+/// HMAC.
+///
+/// Cookie values not issued with the key are rejected before the store is
+/// consulted, so clients can't probe the store with guessed IDs. See
+/// [`Id::to_signed_string`](session::Id::to_signed_string).
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{service::SignedIdCodec, MemoryStore, SessionManagerLayer};
+///
+/// let session_store = MemoryStore::default();
+/// let session_service = SessionManagerLayer::new(session_store)
+///     .with_cookie_value_codec(SignedIdCodec::new(b"a server-side secret key"));
+/// ```
+#[derive(Clone)]
+pub struct SignedIdCodec {
+    key: Arc<[u8]>,
+}
+
+impl SignedIdCodec {
+    /// Create a new `SignedIdCodec` with the given key.
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().into(),
+        }
+    }
+}
+
+impl fmt::Debug for SignedIdCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignedIdCodec").finish_non_exhaustive()
+    }
+}
+
+impl CookieValueCodec for SignedIdCodec {
+    fn encode(&self, session_id: &session::Id) -> String {
+        session_id.to_signed_string(&self.key)
+    }
+
+    fn decode(&self, value: &str) -> Result<session::Id, CookieValueError> {
+        Ok(session::Id::from_signed_str(value, &self.key)?)
+    }
+}
+
+/// Selects the session store used for a request.
+///
+/// This allows a single layer to serve several backends, e.g. a database per
+/// tenant chosen by the `Host` header. The resolver is given the request head;
+/// returning `None` falls back to the store the layer was created with.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::{collections::HashMap, sync::Arc};
+///
+/// use http::{header::HOST, Request};
+/// use tower_sessions::{service::StoreResolver, MemoryStore, SessionManagerLayer};
+///
+/// #[derive(Debug)]
+/// struct TenantResolver {
+///     stores: HashMap<String, Arc<MemoryStore>>,
+/// }
+///
+/// impl StoreResolver<MemoryStore> for TenantResolver {
+///     fn resolve(&self, req: &Request<()>) -> Option<Arc<MemoryStore>> {
+///         let host = req.headers().get(HOST)?.to_str().ok()?;
+///         self.stores.get(host).cloned()
+///     }
+/// }
+///
+/// let resolver = TenantResolver {
+///     stores: HashMap::from([
+///         ("a.example.com".to_string(), Arc::new(MemoryStore::default())),
+///         ("b.example.com".to_string(), Arc::new(MemoryStore::default())),
+///     ]),
+/// };
+///
+/// let session_store = MemoryStore::default();
+/// let session_service = SessionManagerLayer::new(session_store).with_store_resolver(resolver);
+/// ```
+pub trait StoreResolver<Store: SessionStore>: std::fmt::Debug + Send + Sync + 'static {
+    /// Returns the store for the request, if it shouldn't use the default
+    /// store.
+    fn resolve(&self, req: &Request<()>) -> Option<Arc<Store>>;
+}
+
+#[derive(Debug, Clone)]
+struct SessionConfig<'a> {
+    name: Cow<'a, str>,
+    http_only: bool,
+    same_site: SameSite,
+    expiry: Option<Expiry>,
+    secure: bool,
+    partitioned: bool,
+    expires: bool,
+    path: Cow<'a, str>,
+    domain: Option<Cow<'a, str>>,
+    always_save: bool,
+    relaxed_warnings: bool,
+    minimal_set_cookie: bool,
+    set_cookie_granularity: Duration,
+    path_from_prefix: bool,
+    client_binding: Option<ClientBinding>,
+    split_token: bool,
+    split_token_migration: Option<OffsetDateTime>,
+    data_migrator: Option<Arc<dyn SessionDataMigrator>>,
+    expiry_fn: Option<ExpiryFn>,
+    record_validator: Option<RecordValidator>,
+    record_sizes: Option<RecordSizes>,
+    security_events: Option<SecurityEvents>,
+    tombstone_policy: Option<TombstonePolicy>,
+    cookie_value_codec: Arc<dyn CookieValueCodec>,
+    max_lifetime: Option<Duration>,
+    max_cookie_age: Option<Duration>,
+    external_cookie_writer: bool,
+    clear_site_data: Option<HeaderValue>,
+    metadata_key: Option<HeaderName>,
+    companion_cookie: Option<CompanionCookie>,
+    path_fn: Option<CookiePathFn>,
+    deferred_save: Option<DeferredSave>,
+    handle: SessionLayerHandle,
+    creation_token: Option<CreationToken>,
+    transfer_token: Option<TransferToken>,
+    #[cfg(feature = "resilience")]
+    store_timeout: Option<std::time::Duration>,
+}
+
+impl<'a> SessionConfig<'a> {
+    fn validate(&self) -> Result<(), ConfigError> {
+        validate_name(&self.name)?;
+
+        let secure = self.secure || self.partitioned;
+        if self.same_site == SameSite::None && !secure {
+            return Err(ConfigError::SameSiteNoneWithoutSecure);
+        }
+
+        validate_path(&self.path)?;
+        if let Some(ref domain) = self.domain {
+            validate_domain(domain)?;
+        }
+
+        // The path of `__Host-` cookies can't vary by request.
+        let host_only = self.path == "/"
+            && self.domain.is_none()
+            && self.path_fn.is_none()
+            && !self.path_from_prefix;
+        if (has_prefix(&self.name, "__Secure-") && !secure)
+            || (has_prefix(&self.name, "__Host-") && !(secure && host_only))
+        {
+            return Err(ConfigError::PrefixRequirements(self.name.to_string()));
+        }
+
+        if let Some(ref companion_cookie) = self.companion_cookie {
+            validate_name(&companion_cookie.name)?;
+            if companion_cookie.name == self.name {
+                return Err(ConfigError::CompanionNameConflict(
+                    companion_cookie.name.to_string(),
+                ));
+            }
+            if companion_cookie.same_site == Some(SameSite::None) && !secure {
+                return Err(ConfigError::SameSiteNoneWithoutSecure);
+            }
+            if let Some(ref path) = companion_cookie.path {
+                validate_path(path)?;
+            }
+            if let Some(ref domain) = companion_cookie.domain {
+                validate_domain(domain)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Encodes the session ID, followed by the validator of its split token if any.
+    fn cookie_value(&self, session_id: &session::Id, validator: Option<&str>) -> String {
+        let value = self.cookie_value_codec.encode(session_id);
+        match validator {
+            Some(validator) => format!("{value}.{validator}"),
+            None => value,
+        }
+    }
+
+    // Decodes a cookie value into the session ID and, if split tokens are enabled, the
+    // validator it carries.
+    fn decode_cookie_value(
+        &self,
+        value: &str,
+    ) -> Result<(session::Id, Option<String>), CookieValueError> {
+        if !self.split_token {
+            return Ok((self.cookie_value_codec.decode(value)?, None));
+        }
+
+        // Validators are base64 encoded, so the last `.` separates them from the ID.
+        let Some((value, validator)) = value.rsplit_once('.') else {
+            let is_migrating = self
+                .split_token_migration
+                .is_some_and(|until| OffsetDateTime::now_utc() < until);
+            if is_migrating {
+                return Ok((self.cookie_value_codec.decode(value)?, None));
+            }
+            return Err("missing validator".into());
+        };
+        Ok((
+            self.cookie_value_codec.decode(value)?,
+            Some(validator.to_string()),
+        ))
+    }
+
+    fn build_cookie(
+        self,
+        session_id: session::Id,
+        validator: Option<&str>,
+        expiry: Option<Expiry>,
+        absolute_expiry: Option<OffsetDateTime>,
+    ) -> Cookie<'a> {
+        let now = OffsetDateTime::now_utc();
+        let expires_at = self.expires_at(now, expiry, absolute_expiry);
+
+        let value = self.cookie_value(&session_id, validator);
+        let mut cookie_builder = Cookie::build((self.name, value))
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            // Partitioned cookies must be secure.
+            .secure(self.secure || self.partitioned)
+            .partitioned(self.partitioned)
+            .path(self.path);
+
+        if let Some(expires_at) = expires_at {
+            cookie_builder = cookie_builder.max_age(expires_at - now);
+        }
+
+        if self.expires {
+            if let Some(expires_at) = expires_at {
+                cookie_builder = cookie_builder.expires(expires_at);
+            }
+        }
+
+        if let Some(domain) = self.domain {
+            cookie_builder = cookie_builder.domain(domain);
+        }
+
+        cookie_builder.build()
+    }
+
+    // Computes when the cookie expires, if it isn't a session cookie.
+    fn expires_at(
+        &self,
+        now: OffsetDateTime,
+        expiry: Option<Expiry>,
+        absolute_expiry: Option<OffsetDateTime>,
+    ) -> Option<OffsetDateTime> {
+        let expires_at = match expiry {
+            Some(Expiry::OnInactivity(duration)) => Some(now.saturating_add(duration)),
+            Some(Expiry::AtDateTime(datetime)) => Some(datetime),
+            Some(Expiry::Never) => Some(now.saturating_add(Expiry::NEVER_DURATION)),
+            Some(Expiry::OnSessionEnd) | None => None,
+        };
+
+        // The cookie shouldn't outlive the session's maximum lifetime.
+        let expires_at = match (expires_at, absolute_expiry) {
+            (Some(expires_at), Some(absolute_expiry)) => Some(expires_at.min(absolute_expiry)),
+            (expires_at, _) => expires_at,
+        };
+
+        // Only the cookie is capped; the record keeps its own expiry.
+        match (expires_at, self.max_cookie_age) {
+            (Some(expires_at), Some(max_cookie_age)) => {
+                Some(expires_at.min(now.saturating_add(max_cookie_age)))
+            }
+            (expires_at, _) => expires_at,
+        }
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), ConfigError> {
+    // Cookie names must be RFC 6265 tokens.
+    const SEPARATORS: &str = "()<>@,;:\\\"/[]?={} \t";
+
+    if name.is_empty() {
+        return Err(ConfigError::EmptyName);
+    }
+    if name
+        .chars()
+        .any(|c| !c.is_ascii() || c.is_ascii_control() || SEPARATORS.contains(c))
+    {
+        return Err(ConfigError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+// Browsers match cookie name prefixes case-insensitively.
+fn has_prefix(name: &str, prefix: &str) -> bool {
+    name.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+fn validate_path(path: &str) -> Result<(), ConfigError> {
+    if !path.starts_with('/') {
+        return Err(ConfigError::InvalidPath(path.to_string()));
+    }
+    Ok(())
+}
+
+fn validate_domain(domain: &str) -> Result<(), ConfigError> {
+    if domain.starts_with('.') {
+        return Err(ConfigError::DomainLeadingDot(domain.to_string()));
+    }
+    Ok(())
+}
+
+impl Default for SessionConfig<'_> {
+    fn default() -> Self {
+        Self {
+            name: "id".into(), /* See: https://cheatsheetseries.owasp.org/cheatsheets/Session_Management_Cheat_Sheet.html#session-id-name-fingerprinting */
+            http_only: true,
+            same_site: SameSite::Strict,
+            expiry: None, // TODO: Is `Max-Age: "Session"` the right default?
+            secure: true,
+            partitioned: false,
+            expires: false,
+            path: "/".into(),
+            domain: None,
+            always_save: false,
+            relaxed_warnings: false,
+            minimal_set_cookie: false,
+            set_cookie_granularity: Duration::minutes(1),
+            path_from_prefix: false,
+            client_binding: None,
+            split_token: false,
+            split_token_migration: None,
+            data_migrator: None,
+            expiry_fn: None,
+            record_validator: None,
+            record_sizes: None,
+            security_events: None,
+            tombstone_policy: None,
+            cookie_value_codec: Arc::new(PlainIdCodec),
+            max_lifetime: None,
+            max_cookie_age: None,
+            external_cookie_writer: false,
+            clear_site_data: None,
+            metadata_key: None,
+            companion_cookie: None,
+            path_fn: None,
+            deferred_save: None,
+            handle: SessionLayerHandle::default(),
+            creation_token: None,
+            transfer_token: None,
+            #[cfg(feature = "resilience")]
+            store_timeout: None,
+        }
+    }
+}
+
+/// A middleware that provides [`Session`] as a request extension.
+#[derive(Debug, Clone)]
+pub struct SessionManager<S, Store: SessionStore, C: CookieController = PlaintextCookie> {
+    inner: S,
+    session_store: Arc<Store>,
+    store_resolver: Option<Arc<dyn StoreResolver<Store>>>,
+    session_config: SessionConfig<'static>,
+    cookie_controller: C,
+}
+
+impl<S, Store: SessionStore> SessionManager<S, Store> {
+    /// Create a new [`SessionManager`].
+    pub fn new(inner: S, session_store: Store) -> Self {
+        Self {
+            inner,
+            session_store: Arc::new(session_store),
+            store_resolver: None,
+            session_config: Default::default(),
+            cookie_controller: PlaintextCookie,
+        }
+    }
+}
+
+impl<ReqBody, ResBody, S, Store: SessionStore, C: CookieController> Service<Request<ReqBody>>
+    for SessionManager<S, Store, C>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, C>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        #[cfg(not(feature = "opentelemetry"))]
+        let span = tracing::info_span!("call");
+        #[cfg(feature = "opentelemetry")]
+        let span = tracing::info_span!(
+            "session.call",
+            otel.kind = "internal",
+            session.store = std::any::type_name::<Store>(),
+        );
+        let enter = span.enter();
+
+        let Some(cookies) = req.extensions().get::<Cookies>().cloned() else {
+            // This only happens when the cookie manager is expected to be
+            // installed externally but isn't.
+            let res = SessionLayerError::MissingCookies.into_response();
+            drop(enter);
+            return ResponseFuture {
+                state: State::Ready {
+                    output: Some(Ok(res)),
+                },
+                span,
+            };
+        };
+
+        let session_store = match self.store_resolver {
+            Some(ref store_resolver) => {
+                // Resolve against the request head, leaving the body in place.
+                let (parts, body) = req.into_parts();
+                let head = Request::from_parts(parts, ());
+                let session_store = store_resolver.resolve(&head);
+                let (parts, ()) = head.into_parts();
+                req = Request::from_parts(parts, body);
+                session_store.unwrap_or_else(|| self.session_store.clone())
+            }
+            None => self.session_store.clone(),
+        };
+        let mut session_config = self.session_config.clone();
+        let cookie_controller = self.cookie_controller.clone();
+
+        let domain = session_config
+            .domain
+            .as_ref()
+            .filter(|_| !session_config.relaxed_warnings);
+        if let Some(domain) = domain {
+            let host = req
+                .uri()
+                .host()
+                .or_else(|| req.headers().get(http::header::HOST)?.to_str().ok());
+            if let Some(host) = host.filter(|host| !domain_matches(domain, host)) {
+                tracing::warn!(
+                    domain = %domain,
+                    host = %host,
+                    "session cookie domain doesn't cover the request host; \
+                     the client will reject the cookie"
+                );
+            }
+        }
+
+        let matched_prefix = session_config
+            .path_from_prefix
+            .then(|| req.extensions().get::<MatchedPrefix>())
+            .flatten();
+        if let Some(matched_prefix) = matched_prefix {
+            match matched_prefix.cookie_path() {
+                Some(path) => session_config.path = path.into(),
+                None => {
+                    tracing::warn!(prefix = %matched_prefix.as_str(), "ignoring invalid matched prefix")
+                }
+            }
+        }
+
+        if let Some(ref path_fn) = session_config.path_fn {
+            if let Some(path) = path_fn.resolve(req.uri(), req.headers()) {
+                session_config.path = path.into();
+            }
+        }
+
+        if session_config.path_from_prefix && !session_config.relaxed_warnings {
+            let request_path = match matched_prefix {
+                Some(matched_prefix) => matched_prefix.request_path(req.uri()),
+                None => req.uri().path().to_string(),
+            };
+            if !path_matches(&session_config.path, &request_path) {
+                tracing::warn!(
+                    cookie_path = %session_config.path,
+                    request_path = %request_path,
+                    "session cookie path doesn't cover the request path; \
+                     the client won't send the cookie back"
+                );
+            }
+        }
+
+        // gRPC clients carry the session ID in metadata rather than a cookie.
+        let metadata_key = session_config
+            .metadata_key
+            .clone()
+            .filter(|key| req.headers().contains_key(key) || is_grpc(req.headers()));
+
+        let security_events = session_config
+            .security_events
+            .clone()
+            .map(|security_events| {
+                let client = ClientMetadata::new(
+                    req.extensions().get::<ClientIp>().map(|ClientIp(ip)| *ip),
+                    req.headers()
+                        .get(http::header::USER_AGENT)
+                        .and_then(|user_agent| user_agent.to_str().ok())
+                        .map(str::to_string),
+                );
+                (security_events, client)
+            });
+        let security_event = |kind| {
+            if let Some((ref security_events, ref client)) = security_events {
+                security_events.record(&SecurityEvent::new(kind, None, client.clone()));
+            }
+        };
+
+        let decode = |value: &str| {
+            session_config
+                .decode_cookie_value(value)
+                .map_err(|err| {
+                    tracing::warn!(
+                        err = %err,
+                        "possibly suspicious activity: malformed session id"
+                    );
+                    security_event(SecurityEventKind::MalformedId);
+                })
+                .ok()
+        };
+
+        // Session IDs carried by the request, most specific first, along with their
+        // cookie and the validator of their split token.
+        let mut candidates = Vec::new();
+
+        // First session cookie passing verification, removed when it's malformed.
+        let mut verified_cookie = None;
+        match metadata_key {
+            Some(ref key) => {
+                let value = req.headers().get(key).and_then(|value| value.to_str().ok());
+                if let Some((session_id, validator)) = value.and_then(decode) {
+                    candidates.push((None, session_id, validator));
+                }
+            }
+
+            None => {
+                for cookie in request_cookies(req.headers(), &session_config.name) {
+                    // Signed and private cookies which fail verification are ignored.
+                    let Some(cookie) = cookie_controller.verify(cookie) else {
+                        tracing::warn!(
+                            "possibly suspicious activity: session cookie failed verification"
+                        );
+                        security_event(SecurityEventKind::InvalidSignature);
+                        continue;
+                    };
+                    verified_cookie.get_or_insert_with(|| cookie.clone());
+                    let Some((session_id, validator)) = decode(cookie.value()) else {
+                        continue;
+                    };
+                    if candidates.iter().all(|&(_, id, _)| id != session_id) {
+                        candidates.push((Some(cookie), session_id, validator));
+                    }
+                }
+            }
+        }
+
+        // Clients may send several session cookies, e.g. when scoped to different paths
+        // or domains. The most specific one whose record is found is used.
+        if candidates.len() > 1 {
+            tracing::warn!(
+                count = candidates.len(),
+                "multiple session cookies sent; using the most specific one found in the store"
+            );
+        }
+        let mut candidates = candidates.into_iter();
+        let (session_cookie, session_id, validator) = match candidates.next() {
+            Some((session_cookie, session_id, validator)) => {
+                (session_cookie, Some(session_id), validator)
+            }
+            None => (verified_cookie, None, None),
+        };
+
+        // Requests racing to create a session converge on the ID derived from
+        // their creation token.
+        let creation_id = session_config
+            .creation_token
+            .as_ref()
+            .filter(|_| session_id.is_none())
+            .and_then(|creation_token| creation_token.creation_id(req.headers()));
+        let cookie_session_id = session_cookie.as_ref().and(session_id);
+        let cookie_validator = session_cookie.as_ref().and(validator.clone());
+        let session_id = session_id.or(creation_id);
+
+        let mut options = SessionOptions::new().with_max_lifetime(session_config.max_lifetime);
+        if let Some(creation_id) = creation_id {
+            options = options.with_creation_id(creation_id);
+        }
+        if session_config.split_token {
+            options = options.with_split_token(validator);
+            if let Some(until) = session_config.split_token_migration {
+                options = options.with_split_token_migration(until);
+            }
+        }
+        for (_, fallback_id, validator) in candidates {
+            options = options.with_fallback_id(fallback_id, validator);
+        }
+        // Requests which already carry a session don't have it replaced by a token, which
+        // may have been planted in a link.
+        let mut has_transfer_token = false;
+        if let Some(ref transfer_token) = session_config.transfer_token {
+            match transfer_token.token(req.uri()) {
+                Some(_) if cookie_session_id.is_some() => {
+                    tracing::debug!("request carries a session; ignoring transfer token");
+                }
+                Some(token) => {
+                    options = options.with_transfer_token(token, transfer_token.key.clone());
+                    has_transfer_token = true;
+                }
+                None => {}
+            }
+        }
+        if let Some(client_binding) = session_config.client_binding {
+            options = options
+                .with_client_binding(client_binding.fingerprint(&req), client_binding.policy);
+        }
+        if let Some(ref data_migrator) = session_config.data_migrator {
+            options = options.with_data_migrator(data_migrator.clone());
+        }
+        if let Some(ref expiry_fn) = session_config.expiry_fn {
+            options = options.with_expiry_fn(expiry_fn.clone());
+        }
+        if let Some(ref record_validator) = session_config.record_validator {
+            options = options.with_record_validator(record_validator.clone());
+        }
+        if let Some(ref record_sizes) = session_config.record_sizes {
+            options = options.with_record_sizes(record_sizes.clone());
+        }
+        if let Some(tombstone_policy) = session_config.tombstone_policy {
+            options = options.with_tombstone_policy(tombstone_policy);
+        }
+        if let Some((security_events, client)) = security_events {
+            options = options.with_security_events(security_events, client);
+        }
+        #[cfg(feature = "resilience")]
+        if let Some(store_timeout) = session_config.store_timeout {
+            options = options.with_deadline(tokio::time::Instant::now() + store_timeout);
+        }
+        let session =
+            Session::with_options(session_id, session_store, session_config.expiry, options);
+
+        req.extensions_mut().insert(session.clone());
+
+        // Because the inner service can panic until ready, we need to ensure we only
+        // use the ready service.
+        //
+        // See: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let future = inner.call(req);
+
+        drop(enter);
+        ResponseFuture {
+            state: State::Inner {
+                future,
+                finalize: Some(Finalize {
+                    session,
+                    session_cookie,
+                    cookie_session_id,
+                    cookie_validator,
+                    metadata_key,
+                    session_config,
+                    cookie_controller,
+                    cookies,
+                    has_transfer_token,
+                }),
+            },
+            span,
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`SessionManager`].
+    ///
+    /// Requests which neither save the session nor redeem a transfer token
+    /// complete without allocating, unless the application still holds on to
+    /// the session when the inner service completes. Saving the session and
+    /// redeeming a transfer token remain boxed.
+    pub struct ResponseFuture<F, C>
+    where
+        F: Future,
+    {
+        #[pin]
+        state: State<F, C>,
+        span: tracing::Span,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<F, C>
+    where
+        F: Future,
+    {
+        Inner {
+            #[pin]
+            future: F,
+            finalize: Option<Finalize<C>>,
+        },
+        Finalize {
+            future: Pin<Box<dyn Future<Output = F::Output> + Send>>,
+        },
+        Ready {
+            output: Option<F::Output>,
+        },
+    }
+}
+
+impl<F: Future, C> fmt::Debug for ResponseFuture<F, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish_non_exhaustive()
+    }
+}
+
+impl<F, C, B, E> Future for ResponseFuture<F, C>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+    C: CookieController,
+    B: Default + Send + 'static,
+    E: Send + 'static,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        let _enter = this.span.enter();
+
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::Inner { future, finalize } => {
+                    let res = ready!(future.poll(cx))?;
+                    let finalize = finalize.take().expect("polled after completion");
+                    match finalize.finish(res, cx) {
+                        Ok(res) => return Poll::Ready(Ok(res)),
+                        Err(future) => this.state.set(State::Finalize { future }),
+                    }
+                }
+                StateProj::Finalize { future } => return future.as_mut().poll(cx),
+                StateProj::Ready { output } => {
+                    return Poll::Ready(output.take().expect("polled after completion"))
+                }
+            }
+        }
+    }
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+// Applies the session to the response once the inner service has completed.
+struct Finalize<C> {
+    session: Session,
+    session_cookie: Option<Cookie<'static>>,
+
+    // ID of the session the request's cookie referred to, if any.
+    cookie_session_id: Option<session::Id>,
+
+    // Validator of the split token the request's cookie carried, if any.
+    cookie_validator: Option<String>,
+
+    metadata_key: Option<HeaderName>,
+    session_config: SessionConfig<'static>,
+    cookie_controller: C,
+    cookies: Cookies,
+
+    // Set when the request carried a transfer token, which must be redeemed even if the
+    // handler didn't use the session.
+    has_transfer_token: bool,
+}
+
+impl<C: CookieController> Finalize<C> {
+    // Completes synchronously unless the session must be saved, in which case the
+    // remaining work is returned as a future.
+    fn finish<B, E>(
+        mut self,
+        mut res: Response<B>,
+        cx: &mut Context<'_>,
+    ) -> Result<Response<B>, BoxFuture<Result<Response<B>, E>>>
+    where
+        B: Default + Send + 'static,
+        E: Send + 'static,
+    {
+        if self.has_transfer_token {
+            return Err(Box::pin(async move {
+                if let Err(err) = self.session.redeem_pending_transfer().await {
+                    return Ok(SessionLayerError::Transfer(Arc::new(err)).into_response());
+                }
+                Ok(self.finish_async(res).await)
+            }));
+        }
+
+        // The record lock is uncontended once the inner service has completed, unless
+        // the application holds on to the session elsewhere.
+        let is_empty = pin!(self.session.is_empty()).poll(cx);
+        let empty = match is_empty {
+            Poll::Ready(empty) => empty,
+            Poll::Pending => return Err(Box::pin(async move { Ok(self.finish_async(res).await) })),
+        };
+
+        if self.should_save(&mut res, empty) {
+            Err(Box::pin(async move { Ok(self.save(res).await) }))
+        } else {
+            Ok(res)
+        }
+    }
+
+    async fn finish_async<B: Default>(mut self, mut res: Response<B>) -> Response<B> {
+        let empty = self.session.is_empty().await;
+        if self.should_save(&mut res, empty) {
+            self.save(res).await
+        } else {
+            res
+        }
+    }
+
+    // Removes the session cookie if the session is empty and otherwise reports
+    // whether the session should be saved.
+    fn should_save<B>(&mut self, res: &mut Response<B>, empty: bool) -> bool {
+        let modified = self.session.is_modified();
+        let always_save = self
+            .session
+            .always_save()
+            .unwrap_or(self.session_config.always_save);
+        let finalized = self.session.is_finalized();
+
+        tracing::trace!(
+            modified = modified,
+            empty = empty,
+            always_save = always_save,
+            finalized = finalized,
+            "session response state",
+        );
+
+        match self.session_cookie.take() {
+            Some(mut cookie) if empty => {
+                tracing::debug!("removing session cookie");
+
+                if let Some(ref clear_site_data) = self.session_config.clear_site_data {
+                    res.headers_mut().insert(
+                        HeaderName::from_static("clear-site-data"),
+                        clear_site_data.clone(),
+                    );
+                }
+
+                // Path and domain must be manually set to ensure a proper removal cookie is
+                // constructed.
+                //
+                // See: https://docs.rs/cookie/latest/cookie/struct.CookieJar.html#method.remove
+                cookie.set_path(self.session_config.path.clone());
+                if let Some(domain) = self.session_config.domain.clone() {
+                    cookie.set_domain(domain);
+                }
+
+                if self.session_config.external_cookie_writer {
+                    res.extensions_mut()
+                        .insert(SessionCookieUpdate::Remove { cookie });
+                    return false;
+                }
+
+                if let Some(ref companion_cookie) = self.session_config.companion_cookie {
+                    let removal = companion_cookie.removal(
+                        self.session_config.path.clone(),
+                        self.session_config.domain.clone(),
+                    );
+                    self.cookies.remove(removal);
+                }
+
+                self.cookie_controller.remove(&self.cookies, cookie);
+                false
+            }
+
+            _ => {
+                (modified || always_save || finalized) && !empty && !res.status().is_server_error()
+            }
+        }
+    }
+
+    async fn save<B: Default>(self, mut res: Response<B>) -> Response<B> {
+        let deferred = self
+            .session_config
+            .deferred_save
+            .clone()
+            .filter(|_| self.session.id().is_some())
+            .zip(tokio::runtime::Handle::try_current().ok())
+            .and_then(|deferred| Some((deferred, self.session_config.handle.track()?)));
+        match deferred {
+            // The handler already saved the session, leaving only the cookie to set.
+            _ if self.session.is_finalized() && !self.session.is_modified() => {
+                tracing::debug!("session already finalized");
+            }
+
+            Some(((deferred_save, runtime), pending)) => {
+                tracing::debug!("deferring session save");
+                let save = deferred_save.save(self.session.clone());
+                runtime.spawn(
+                    async move {
+                        save.await;
+                        drop(pending);
+                    }
+                    .instrument(tracing::Span::current()),
+                );
+            }
+
+            None => {
+                tracing::debug!("saving session");
+                if let Err(err) = self.session.save().await {
+                    return SessionLayerError::Save(Arc::new(err)).into_response();
+                }
+            }
+        }
+
+        let Some(session_id) = self.session.id() else {
+            return SessionLayerError::MissingId.into_response();
+        };
+
+        if let Some(metadata_key) = self.metadata_key {
+            let value = self
+                .session_config
+                .cookie_value(&session_id, self.session.validator().as_deref());
+            match HeaderValue::try_from(value) {
+                Ok(value) => {
+                    tracing::debug!("adding session metadata");
+                    res.headers_mut().insert(metadata_key, value);
+                }
+                Err(err) => tracing::error!(err = %err, "invalid session metadata value"),
+            }
+            return res;
+        }
+
+        if self.session_config.minimal_set_cookie && self.is_cookie_current(session_id) {
+            tracing::debug!("session cookie unchanged");
+            return res;
+        }
+
+        let expiry = self.session.expiry();
+        let absolute_expiry = self.session.absolute_expiry();
+        let validator = self.session.validator();
+
+        if self.session_config.external_cookie_writer {
+            let expires_at =
+                self.session_config
+                    .expires_at(OffsetDateTime::now_utc(), expiry, absolute_expiry);
+            let cookie = self.session_config.build_cookie(
+                session_id,
+                validator.as_deref(),
+                expiry,
+                absolute_expiry,
+            );
+            tracing::debug!("exposing session cookie update");
+            res.extensions_mut().insert(SessionCookieUpdate::Set {
+                session_id,
+                expires_at,
+                cookie,
+            });
+            return res;
+        }
+
+        let companion_cookie = self.session_config.companion_cookie.clone();
+        let session_cookie = self.session_config.build_cookie(
+            session_id,
+            validator.as_deref(),
+            expiry,
+            absolute_expiry,
+        );
+
+        let companion_cookie =
+            companion_cookie.map(|companion| companion.build(&session_id, &session_cookie));
+
+        tracing::debug!("adding session cookie");
+        self.cookie_controller.add(&self.cookies, session_cookie);
+
+        if let Some(companion_cookie) = companion_cookie {
+            tracing::debug!("adding companion cookie");
+            self.cookies.add(companion_cookie);
+        }
+
+        res
+    }
+
+    // Reports whether the client's cookie already carries the session ID and an expiry within
+    // the configured granularity of the session's.
+    fn is_cookie_current(&self, session_id: session::Id) -> bool {
+        if self.cookie_session_id != Some(session_id)
+            || self.cookie_validator != self.session.validator()
+        {
+            return false;
+        }
+
+        match self.session.expiry() {
+            // The cookie carries no expiry.
+            Some(Expiry::OnSessionEnd) | None => true,
+            Some(_) => self
+                .session
+                .loaded_expiry_date()
+                .is_some_and(|loaded_expiry_date| {
+                    let granularity = self
+                        .session_config
+                        .set_cookie_granularity
+                        .whole_seconds()
+                        .max(1);
+                    loaded_expiry_date.unix_timestamp().div_euclid(granularity)
+                        == self
+                            .session
+                            .expiry_date()
+                            .unix_timestamp()
+                            .div_euclid(granularity)
+                }),
+        }
+    }
+}
+
+// Parses the request's cookies of the given name, in the order they were sent. User agents
+// send cookies with longer paths first.
+//
+// See: https://www.rfc-editor.org/rfc/rfc6265#section-5.4
+fn request_cookies(headers: &HeaderMap, name: &str) -> Vec<Cookie<'static>> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|header| str::from_utf8(header.as_bytes()).ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| Cookie::parse_encoded(cookie.to_owned()).ok())
+        .filter(|cookie| cookie.name() == name)
+        .collect()
+}
+
+fn is_grpc(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/grpc"))
+}
+
+/// A layer for providing [`Session`] as a request extension.
+#[derive(Debug, Clone)]
+pub struct SessionManagerLayer<Store: SessionStore, C: CookieController = PlaintextCookie> {
+    session_store: Arc<Store>,
+    store_resolver: Option<Arc<dyn StoreResolver<Store>>>,
+    session_config: SessionConfig<'static>,
+    cookie_controller: C,
+}
+
+impl<Store: SessionStore, C: CookieController> SessionManagerLayer<Store, C> {
+    /// Configures the name of the cookie used for the session.
+    /// The default value is `"id"`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_name("my.sid");
+    /// ```
+    pub fn with_name<N: Into<Cow<'static, str>>>(mut self, name: N) -> Self {
+        self.session_config.name = name.into();
+        self
+    }
+
+    /// Configures the `"HttpOnly"` attribute of the cookie used for the
+    /// session.
+    ///
+    /// # ⚠️ **Warning: Cross-site scripting risk**
+    ///
+    /// Applications should generally **not** override the default value of
+    /// `true`. If you do, you are exposing your application to increased risk
+    /// of cookie theft via techniques like cross-site scripting.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_http_only(true);
+    /// ```
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.session_config.http_only = http_only;
+        self
+    }
+
+    /// Configures the maximum lifetime of sessions, regardless of activity.
+    /// The default value is `None`.
+    ///
+    /// Sessions created by the layer hard-expire once this duration has
+    /// elapsed, even if [`Expiry::OnInactivity`] would keep extending them.
+    /// The cookie's `"Max-Age"` is capped accordingly.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_expiry(Expiry::OnInactivity(Duration::hours(1)))
+    ///     .with_max_lifetime(Duration::hours(12));
+    /// ```
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.session_config.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Caps the `"Max-Age"` of the cookie used for the session.
+    /// The default value is `None`.
+    ///
+    /// Whatever expiry the session produces, the cookie never outlives this
+    /// duration. Unlike [`with_max_lifetime`](Self::with_max_lifetime), the
+    /// expiry of the record in the store is left untouched. Cookies expiring
+    /// [`Expiry::OnSessionEnd`] carry no `"Max-Age"` and aren't affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_expiry(Expiry::OnInactivity(Duration::days(30)))
+    ///     .with_max_cookie_age(Duration::hours(24));
+    /// ```
+    pub fn with_max_cookie_age(mut self, max_cookie_age: Duration) -> Self {
+        self.session_config.max_cookie_age = Some(max_cookie_age);
+        self
+    }
+
+    /// Configures the `"SameSite"` attribute of the cookie used for the
+    /// session.
+    /// The default value is [`SameSite::Strict`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{cookie::SameSite, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_same_site(SameSite::Lax);
+    /// ```
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.session_config.same_site = same_site;
+        self
+    }
+
+    /// Configures the `"Max-Age"` attribute of the cookie used for the session.
+    /// The default value is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_expiry = Expiry::OnInactivity(Duration::hours(1));
+    /// let session_service = SessionManagerLayer::new(session_store).with_expiry(session_expiry);
+    /// ```
+    pub fn with_expiry(mut self, expiry: Expiry) -> Self {
+        self.session_config.expiry = Some(expiry);
+        self
+    }
+
+    /// Configures a function computing the expiry of each session from its
+    /// data when it's saved, determining both the record's expiry date and
+    /// the cookie's `"Max-Age"`.
+    /// The default value is `None`.
+    ///
+    /// An expiry set by the application via
+    /// [`Session::set_expiry`] during the request takes precedence.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_expiry_fn(|data| {
+    ///     match data.get("role").and_then(|role| role.as_str()) {
+    ///         Some("admin") => Expiry::OnInactivity(Duration::minutes(15)),
+    ///         _ => Expiry::OnInactivity(Duration::hours(8)),
+    ///     }
+    /// });
+    /// ```
+    pub fn with_expiry_fn<F>(mut self, expiry_fn: F) -> Self
+    where
+        F: Fn(&HashMap<String, Value>) -> Expiry + Send + Sync + 'static,
+    {
+        self.session_config.expiry_fn = Some(ExpiryFn::new(expiry_fn));
+        self
+    }
+
+    /// Configures a function validating each session's record before it's
+    /// written to the store, e.g. to guarantee only allowed keys and types
+    /// enter the session.
+    /// The default value is `None`.
+    ///
+    /// A rejected record isn't saved: saves by the application fail with
+    /// [`session::Error::Validation`], while saves by the middleware respond
+    /// with a `500 Internal Server Error` carrying
+    /// [`SessionLayerError::Save`] as a response extension. Keys prefixed
+    /// with `__tower_sessions.` are written by this crate and should be
+    /// allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{session::ValidationError, MemoryStore, SessionManagerLayer};
+    ///
+    /// const ALLOWED_KEYS: [&str; 2] = ["user_id", "csrf_token"];
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_record_validator(|record| {
+    ///     for (key, value) in &record.data {
+    ///         if key.starts_with("__tower_sessions.") {
+    ///             continue;
+    ///         }
+    ///         if !ALLOWED_KEYS.contains(&key.as_str()) {
+    ///             return Err(ValidationError::new(format!("`{key}` isn't allowed")));
+    ///         }
+    ///         if key == "user_id" && !value.is_i64() {
+    ///             return Err(ValidationError::new("`user_id` must be an integer"));
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn with_record_validator<F>(mut self, record_validator: F) -> Self
+    where
+        F: Fn(&session::Record) -> Result<(), ValidationError> + Send + Sync + 'static,
+    {
+        self.session_config.record_validator = Some(RecordValidator::new(record_validator));
+        self
+    }
+
+    /// Configures accounting for the size of each session's record as it's
+    /// written to the store, optionally limiting it to protect the store.
+    /// The default value is `None`.
+    ///
+    /// Records above the limit aren't saved: saves by the application fail
+    /// with [`session::Error::RecordTooLarge`], while saves by the middleware
+    /// respond with a `500 Internal Server Error` carrying
+    /// [`SessionLayerError::Save`] as a response extension. A warning
+    /// including the size is logged either way.
+    ///
+    /// The given [`RecordSizes`] shares its counters with its clones, so a
+    /// clone may be kept to export the observed sizes as metrics.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{session::RecordSizes, MemoryStore, SessionManagerLayer};
+    ///
+    /// let record_sizes = RecordSizes::new().with_limit(64 * 1024);
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_record_sizes(record_sizes.clone());
+    ///
+    /// // E.g. periodically reported as gauges.
+    /// let largest_record = record_sizes.max_bytes();
+    /// ```
+    pub fn with_record_sizes(mut self, record_sizes: RecordSizes) -> Self {
+        self.session_config.record_sizes = Some(record_sizes);
+        self
+    }
+
+    /// Configures a sink for security events, such as malformed or unknown
+    /// session IDs being presented, e.g. for ingestion by a SIEM.
+    /// The default value is `None`.
+    ///
+    /// Each [`SecurityEvent`] carries a hash of the session ID concerned
+    /// rather than the ID itself, along with the client's user agent and IP
+    /// address. The IP address is taken from the [`ClientIp`] request
+    /// extension, if present. Cookies failing signature verification or
+    /// decryption are reported with the `with_signed` and `with_private`
+    /// cookie controllers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{session::SecurityEvents, MemoryStore, SessionManagerLayer};
+    ///
+    /// let security_events = SecurityEvents::new(|event| {
+    ///     tracing::warn!(
+    ///         kind = ?event.kind,
+    ///         session_id_hash = ?event.session_id_hash,
+    ///         ip = ?event.client.ip,
+    ///         "session security event"
+    ///     );
+    /// });
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_security_events(security_events);
+    /// ```
+    pub fn with_security_events(mut self, security_events: SecurityEvents) -> Self {
+        self.session_config.security_events = Some(security_events);
+        self
+    }
+
+    /// Configures whether the cookie used for the session carries an
+    /// `"Expires"` attribute in addition to `"Max-Age"`.
+    /// The default value is `false`.
+    ///
+    /// Some legacy user agents ignore `"Max-Age"`, in which case an absolute
+    /// `"Expires"` date derived from the session's expiry is needed. As with
+    /// `"Max-Age"`, sessions expiring on session end carry neither attribute.
+    /// Removal cookies always carry both.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_expiry(Expiry::OnInactivity(Duration::hours(1)))
+    ///     .with_expires(true);
+    /// ```
+    pub fn with_expires(mut self, expires: bool) -> Self {
+        self.session_config.expires = expires;
+        self
+    }
+
+    /// Configures the `"Secure"` attribute of the cookie used for the session.
+    /// The default value is `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_secure(true);
+    /// ```
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.session_config.secure = secure;
+        self
+    }
+
+    /// Configures the `"Partitioned"` attribute of the cookie used for the
+    /// session.
+    /// The default value is `false`.
+    ///
+    /// Partitioned cookies ([CHIPS]) are keyed to the top-level site they're
+    /// set under, which allows sessions within third-party iframes. They're
+    /// typically used along with [`SameSite::None`]. Because partitioned
+    /// cookies must be secure, enabling this also sets the `"Secure"`
+    /// attribute, regardless of [`with_secure`](Self::with_secure).
+    ///
+    /// [CHIPS]: https://developer.mozilla.org/en-US/docs/Web/Privacy/Privacy_sandbox/Partitioned_cookies
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{cookie::SameSite, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_same_site(SameSite::None)
+    ///     .with_partitioned(true);
+    /// ```
+    pub fn with_partitioned(mut self, partitioned: bool) -> Self {
+        self.session_config.partitioned = partitioned;
+        self
+    }
+
+    /// Configures the `"Path"` attribute of the cookie used for the session.
+    /// The default value is `"/"`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_path("/some/path");
+    /// ```
+    pub fn with_path<P: Into<Cow<'static, str>>>(mut self, path: P) -> Self {
+        self.session_config.path = path.into();
+        self
+    }
+
+    /// Configures the `"Path"` attribute of the cookie used for the session
+    /// to be computed per request from the request's URI and headers.
+    /// The default value is `None`.
+    ///
+    /// When the function returns `None`, or a path which doesn't start with
+    /// `/` or contains `;` or control characters, the path configured via
+    /// [`with_path`](Self::with_path) is used instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_path_fn(|uri, _| {
+    ///     uri.path()
+    ///         .starts_with("/admin")
+    ///         .then(|| "/admin".to_string())
+    /// });
+    /// ```
+    pub fn with_path_fn<F>(mut self, path_fn: F) -> Self
+    where
+        F: Fn(&Uri, &HeaderMap) -> Option<String> + Send + Sync + 'static,
+    {
+        self.session_config.path_fn = Some(CookiePathFn(Arc::new(path_fn)));
+        self
+    }
+
+    /// Configures the `"Path"` attribute of the cookie used for the session
+    /// to be derived from the [`MatchedPrefix`] request extension.
+    /// The default value is `false`.
+    ///
+    /// This is intended for routers nested under a path, where a fixed path
+    /// is easily out of sync with where the router is mounted. Requests
+    /// without the extension use the path configured via
+    /// [`with_path`](Self::with_path), while [`with_path_fn`](Self::with_path_fn)
+    /// takes precedence over the prefix. A warning is logged for requests
+    /// whose path the resulting cookie path doesn't cover, as browsers won't
+    /// send the cookie back for them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use axum::{routing::get, Extension, Router};
+    /// use tower_sessions::{service::MatchedPrefix, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_layer = SessionManagerLayer::new(session_store).with_path_from_matched_prefix(true);
+    ///
+    /// let admin = Router::new()
+    ///     .route("/", get(|| async { "Hello, admin!" }))
+    ///     .layer(session_layer)
+    ///     .layer(Extension(MatchedPrefix::new("/admin")));
+    /// let app: Router = Router::new().nest("/admin", admin);
+    /// ```
+    pub fn with_path_from_matched_prefix(mut self, enable: bool) -> Self {
+        self.session_config.path_from_prefix = enable;
+        self
+    }
+
+    /// Configures the `"Path"` attribute of the cookie used for the session
+    /// to be prefixed with the value of the given request header, such as
+    /// `X-Forwarded-Prefix`.
+    ///
+    /// This is intended for applications mounted under a path by a reverse
+    /// proxy which strips that prefix: the cookie is scoped to the prefix
+    /// rather than leaking to sibling applications. Requests without the
+    /// header use the path configured via [`with_path`](Self::with_path),
+    /// which is otherwise appended to the prefix. The header must only be set
+    /// by a trusted proxy.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use http::HeaderName;
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_path_from_header(HeaderName::from_static("x-forwarded-prefix"));
+    /// ```
+    pub fn with_path_from_header(self, header: HeaderName) -> Self {
+        let path = self.session_config.path.clone();
+        self.with_path_fn(move |_, headers| {
+            let prefix = headers.get(&header)?.to_str().ok()?;
+            let prefix = prefix.trim_end_matches('/');
+            let path = format!("{prefix}{path}");
+            // `/app/` wouldn't match requests for `/app` itself.
+            match path.strip_suffix('/') {
+                Some(trimmed) if !trimmed.is_empty() => Some(trimmed.to_string()),
+                _ => Some(path),
+            }
+        })
+    }
+
+    /// Configures the `"Domain"` attribute of the cookie used for the session.
+    /// The default value is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_domain("localhost");
+    /// ```
+    pub fn with_domain<D: Into<Cow<'static, str>>>(mut self, domain: D) -> Self {
+        self.session_config.domain = Some(domain.into());
+        self
+    }
+
+    /// Configures the cookie used for the session to be shared across the
+    /// subdomains of the given domain, e.g. `app.example.com` and
+    /// `api.example.com` for `"example.com"`.
+    ///
+    /// This sets the `"Domain"` attribute to the given domain, the `"Path"`
+    /// attribute to `/`, `"SameSite"` to `Lax` and `"Secure"`. Subdomains of a
+    /// registrable domain are the same site, so `Lax` keeps the cookie on
+    /// requests between them while still withholding it from cross-site
+    /// subrequests; the cookie is then also readable by every other
+    /// subdomain, which must all be trusted.
+    ///
+    /// The domain must be given without a leading dot, which
+    /// [`try_build`](Self::try_build) checks. A warning is logged for
+    /// requests to hosts outside the domain, as browsers reject cookies for
+    /// other domains.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_cross_subdomain("example.com")
+    ///     .try_build()
+    ///     .unwrap();
+    /// ```
+    pub fn with_cross_subdomain<D: Into<Cow<'static, str>>>(self, domain: D) -> Self {
+        self.with_domain(domain)
+            .with_path("/")
+            .with_same_site(SameSite::Lax)
+            .with_secure(true)
+    }
+
+    /// Configures whether unmodified session should be saved on read or not.
+    /// When the value is `true`, the session will be saved even if it was not
+    /// changed.
     ///
     /// This is useful when you want to reset [`Session`] expiration time
     /// on any valid request at the cost of higher [`SessionStore`] write
     /// activity and transmitting `set-cookie` header with each response.
     ///
-    /// It makes sense to use this setting with relative session expiration
-    /// values, such as `Expiry::OnInactivity(Duration)`. This setting will
-    /// _not_ cause session id to be cycled on save.
+    /// It makes sense to use this setting with relative session expiration
+    /// values, such as `Expiry::OnInactivity(Duration)`. This setting will
+    /// _not_ cause session id to be cycled on save.
+    ///
+    /// Handlers may override this for a single request via
+    /// [`Session::set_always_save`].
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_expiry = Expiry::OnInactivity(Duration::hours(1));
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_expiry(session_expiry)
+    ///     .with_always_save(true);
+    /// ```
+    pub fn with_always_save(mut self, always_save: bool) -> Self {
+        self.session_config.always_save = always_save;
+        self
+    }
+
+    /// Configures whether the session cookie is only set when it would
+    /// change.
+    ///
+    /// Saving a session sets its cookie again, with an updated `"Max-Age"`,
+    /// which prevents caches such as CDNs from storing responses. When
+    /// enabled, the cookie is omitted if the request already carried the same
+    /// session ID and the session's expiry falls within the same interval of
+    /// [`with_set_cookie_granularity`](Self::with_set_cookie_granularity) as
+    /// when it was loaded. The session is still saved as usual.
+    ///
+    /// As a result the cookie may expire up to one interval before the
+    /// session does.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_expiry(Expiry::OnInactivity(Duration::days(1)))
+    ///     .with_always_save(true)
+    ///     .with_minimal_set_cookie(true);
+    /// ```
+    pub fn with_minimal_set_cookie(mut self, minimal_set_cookie: bool) -> Self {
+        self.session_config.minimal_set_cookie = minimal_set_cookie;
+        self
+    }
+
+    /// Configures whether the session cookie is left to an external writer,
+    /// such as an outer layer or a gateway, instead of the middleware.
+    /// The default value is `false`.
+    ///
+    /// When enabled, the middleware never writes `Set-Cookie` headers.
+    /// Instead, pending changes of the session cookie are exposed as a
+    /// [`SessionCookieUpdate`] response extension. The session cookie is still
+    /// read from requests as usual. Companion cookies aren't written either.
+    ///
+    /// The cookie value is encoded by the configured
+    /// [`CookieValueCodec`], but not signed or encrypted by a cookie
+    /// controller such as `with_signed`; use [`SignedIdCodec`] to sign it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{service::SessionCookieUpdate, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_external_cookie_writer(true);
+    ///
+    /// // Within an outer layer.
+    /// # let res = http::Response::new(());
+    /// match res.extensions().get::<SessionCookieUpdate>() {
+    ///     Some(SessionCookieUpdate::Set { cookie, .. }) => { /* Set the cookie. */ }
+    ///     Some(SessionCookieUpdate::Remove { cookie }) => { /* Remove the cookie. */ }
+    ///     _ => {}
+    /// }
+    /// ```
+    pub fn with_external_cookie_writer(mut self, external_cookie_writer: bool) -> Self {
+        self.session_config.external_cookie_writer = external_cookie_writer;
+        self
+    }
+
+    /// Configures the directives of a `Clear-Site-Data` header sent along with
+    /// the removal of the session cookie, e.g. once the session was flushed
+    /// on logout.
+    /// The default value is `None`, sending no such header.
+    ///
+    /// This has browsers also discard data the site stored on the client
+    /// during the session. Passing no directives disables the header.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{service::ClearSiteData, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_clear_site_data([ClearSiteData::Cookies, ClearSiteData::Storage]);
+    /// ```
+    pub fn with_clear_site_data(
+        mut self,
+        directives: impl IntoIterator<Item = ClearSiteData>,
+    ) -> Self {
+        let directives = directives
+            .into_iter()
+            .map(ClearSiteData::as_str)
+            .collect::<Vec<_>>();
+        self.session_config.clear_site_data = (!directives.is_empty()).then(|| {
+            HeaderValue::from_str(&directives.join(", "))
+                .expect("Clear-Site-Data directives are valid header values")
+        });
+        self
+    }
+
+    /// Configures the interval by which changes to the session's expiry are
+    /// bucketed when [`with_minimal_set_cookie`](Self::with_minimal_set_cookie)
+    /// is enabled.
+    ///
+    /// Larger intervals set the cookie less often, but should be small
+    /// relative to the session's expiry. Intervals are rounded down to whole
+    /// seconds, with a minimum of one second.
+    ///
+    /// The default value is one minute.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_minimal_set_cookie(true)
+    ///     .with_set_cookie_granularity(Duration::hours(1));
+    /// ```
+    pub fn with_set_cookie_granularity(mut self, granularity: Duration) -> Self {
+        self.session_config.set_cookie_granularity = granularity;
+        self
+    }
+
+    /// Configures binding sessions to the client that created them.
+    /// The default value is `None`.
+    ///
+    /// This mitigates session hijacking by recording a hash of the client's
+    /// IP and/or user agent with the session and checking it on later
+    /// requests. See [`ClientBinding`] and [`BindingPolicy`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{
+    ///     service::ClientBinding, session::BindingPolicy, MemoryStore, SessionManagerLayer,
+    /// };
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_client_binding(ClientBinding::new(BindingPolicy::CycleId));
+    /// ```
+    pub fn with_client_binding(mut self, client_binding: ClientBinding) -> Self {
+        self.session_config.client_binding = Some(client_binding);
+        self
+    }
+
+    /// Configures whether the session cookie carries a split token.
+    /// The default value is `false`.
+    ///
+    /// The cookie then carries a secret validator after the session ID,
+    /// separated by a `.`, while the store only keeps a hash of it. As the ID
+    /// alone no longer grants access to the session, the contents of a leaked
+    /// store can't be used to impersonate its sessions. Validators are
+    /// compared in constant time, and a new one is issued whenever the session
+    /// ID is cycled. See
+    /// [`SessionOptions::with_split_token`](crate::session::SessionOptions::with_split_token).
+    ///
+    /// Existing sessions are no longer accepted once this is enabled, unless
+    /// migrating via [`with_split_token_migration`](Self::with_split_token_migration),
+    /// nor once it's disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_split_token(true);
+    /// ```
+    pub fn with_split_token(mut self, split_token: bool) -> Self {
+        self.session_config.split_token = split_token;
+        self
+    }
+
+    /// Configures a time until which sessions predating split tokens are
+    /// still accepted, and issued a validator, once
+    /// [`with_split_token`](Self::with_split_token) is enabled.
+    /// The default value is `None`, i.e. they're rejected.
+    ///
+    /// Until then, cookies without a validator are accepted, so the session ID
+    /// alone grants access to sessions which haven't been issued one yet. The
+    /// window should therefore be no longer than needed for active sessions to
+    /// be presented once. See
+    /// [`SessionOptions::with_split_token_migration`](crate::session::SessionOptions::with_split_token_migration).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_split_token(true)
+    ///     .with_split_token_migration(OffsetDateTime::now_utc() + Duration::days(7));
+    /// ```
+    pub fn with_split_token_migration(mut self, until: OffsetDateTime) -> Self {
+        self.session_config.split_token_migration = Some(until);
+        self
+    }
+
+    /// Configures the migrator used to upgrade session data written under
+    /// older schema versions.
+    /// The default value is `None`.
+    ///
+    /// See [`SessionDataMigrator`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    ///
+    /// use serde_json::Value;
+    /// use tower_sessions::{
+    ///     session::{self, SessionDataMigrator},
+    ///     MemoryStore, SessionManagerLayer,
+    /// };
+    ///
+    /// #[derive(Debug)]
+    /// struct Migrator;
+    ///
+    /// impl SessionDataMigrator for Migrator {
+    ///     fn version(&self) -> u8 {
+    ///         1
+    ///     }
+    ///
+    ///     fn migrate_record(
+    ///         &self,
+    ///         _version: u8,
+    ///         _data: &mut HashMap<String, Value>,
+    ///     ) -> session::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_data_migrator(Migrator);
+    /// ```
+    pub fn with_data_migrator(mut self, data_migrator: impl SessionDataMigrator) -> Self {
+        self.session_config.data_migrator = Some(Arc::new(data_migrator));
+        self
+    }
+
+    /// Configures how session IDs are encoded into and decoded from the cookie
+    /// value.
+    /// The default value is [`PlainIdCodec`], i.e. the raw session ID.
+    ///
+    /// See [`CookieValueCodec`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{
+    ///     service::{CookieValueCodec, CookieValueError},
+    ///     session::Id,
+    ///     MemoryStore, SessionManagerLayer,
+    /// };
+    ///
+    /// #[derive(Debug)]
+    /// struct TenantCodec;
+    ///
+    /// impl CookieValueCodec for TenantCodec {
+    ///     fn encode(&self, session_id: &Id) -> String {
+    ///         format!("acme.{session_id}")
+    ///     }
+    ///
+    ///     fn decode(&self, value: &str) -> Result<Id, CookieValueError> {
+    ///         let session_id = value.strip_prefix("acme.").ok_or("unknown tenant")?;
+    ///         Ok(session_id.parse()?)
+    ///     }
+    /// }
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_cookie_value_codec(TenantCodec);
+    /// ```
+    pub fn with_cookie_value_codec(mut self, cookie_value_codec: impl CookieValueCodec) -> Self {
+        self.session_config.cookie_value_codec = Arc::new(cookie_value_codec);
+        self
+    }
+
+    /// Configures the gRPC metadata key carrying the session ID, allowing
+    /// gRPC clients, e.g. of `tonic` services, to share sessions with HTTP
+    /// clients.
+    /// The default value is `None`.
+    ///
+    /// gRPC requests and requests presenting this key read the session ID
+    /// from the key rather than the cookie. When such a session is saved, its
+    /// ID is returned in the response metadata under the same key instead of
+    /// a cookie. The ID is encoded as for the cookie; see
+    /// [`with_cookie_value_codec`](Self::with_cookie_value_codec).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use http::HeaderName;
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_metadata_key(HeaderName::from_static("x-session-id"));
+    /// ```
+    #[cfg(feature = "grpc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "grpc")))]
+    pub fn with_metadata_key(mut self, metadata_key: HeaderName) -> Self {
+        self.session_config.metadata_key = Some(metadata_key);
+        self
+    }
+
+    /// Configures the time budget of store operations per request.
+    /// The default value is `None`, meaning store operations aren't bounded.
+    ///
+    /// The budget starts when the request reaches the middleware and covers
+    /// all store operations of the request, including saving the session
+    /// afterwards. Operations still pending once it's exhausted fail with a
+    /// timeout error. This lets store operations respect an overall request
+    /// timeout, e.g. one set via `tower::timeout`. Handlers may lift the
+    /// deadline via [`Session::clear_deadline`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_store_timeout(Duration::from_millis(500));
+    /// ```
+    #[cfg(feature = "resilience")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+    pub fn with_store_timeout(mut self, store_timeout: std::time::Duration) -> Self {
+        self.session_config.store_timeout = Some(store_timeout);
+        self
+    }
+
+    /// Configures a request header carrying a client-generated token from
+    /// which the ID of new sessions is derived.
+    /// The default value is `None`, meaning new sessions get random IDs.
+    ///
+    /// Without a session cookie, parallel first requests from the same client
+    /// each create a session, and all but the one whose cookie is set last
+    /// are orphaned. Instead, clients may generate a random token and send it
+    /// with their requests until they receive the session cookie. Requests
+    /// presenting the same token share the session whose ID is derived from
+    /// the token with an HMAC under `key`, see
+    /// [`SessionOptions::with_creation_id`].
+    ///
+    /// Anyone presenting the token gains access to the session, so tokens must
+    /// be unguessable and kept secret like session cookies; tokens shorter
+    /// than 16 characters are ignored. Tokens aren't invalidated once used:
+    /// the same token leads to the same session ID for as long as the session
+    /// exists. As the ID is fixed by the client, it should be cycled when
+    /// privileges change, e.g. on login.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use http::HeaderName;
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_creation_token(
+    ///     HeaderName::from_static("x-session-token"),
+    ///     b"a server-side secret key",
+    /// );
+    /// ```
+    pub fn with_creation_token(mut self, header: HeaderName, key: impl AsRef<[u8]>) -> Self {
+        self.session_config.creation_token = Some(CreationToken {
+            header,
+            key: key.as_ref().into(),
+        });
+        self
+    }
+
+    /// Configures the query parameter from which session transfer tokens are
+    /// redeemed, along with the key they're signed with.
+    /// The default value is `None`, meaning sessions are only propagated via
+    /// cookies.
+    ///
+    /// This serves clients which can't receive the session cookie where the
+    /// session was established, e.g. smart TVs completing a device flow. A
+    /// token created via [`Session::create_transfer_token`] with the same key
+    /// is exchanged for the transferred session, whose cookie is set on the
+    /// response. Tokens expire and can only be exchanged once; see
+    /// [`Session::redeem_transfer_token`], which handlers may call directly
+    /// for tokens submitted in form fields.
+    ///
+    /// Anyone presenting a token gains access to the session, so tokens should
+    /// be short-lived and URLs carrying them kept out of logs.
+    ///
+    /// Conversely, a link carrying someone else's token would log the client
+    /// into that session, i.e. login CSRF or session fixation. Tokens are
+    /// therefore not redeemed on requests which already carry a session
+    /// cookie. Handlers which mean to replace the client's session may call
+    /// [`Session::redeem_transfer_token`] themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_transfer_token("session_transfer", b"a server-side secret key");
+    /// ```
+    pub fn with_transfer_token<P: Into<Cow<'static, str>>>(
+        mut self,
+        param: P,
+        key: impl AsRef<[u8]>,
+    ) -> Self {
+        self.session_config.transfer_token = Some(TransferToken {
+            param: param.into(),
+            key: key.as_ref().into(),
+        });
+        self
+    }
+
+    /// Configures the policy applied when a request presents the ID of a
+    /// deleted session.
+    /// The default value is `None`, meaning deleted sessions are not checked
+    /// for.
+    ///
+    /// Checking for deleted sessions costs an additional store roundtrip for
+    /// unknown session IDs and requires a store that remembers deleted
+    /// sessions, such as
+    /// [`TombstoneStore`](crate::session_store::TombstoneStore).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::Duration;
+    /// use tower_sessions::{
+    ///     session::TombstonePolicy, session_store::TombstoneStore, MemoryStore, SessionManagerLayer,
+    /// };
+    ///
+    /// let session_store = TombstoneStore::new(MemoryStore::default(), Duration::days(1));
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_tombstone_policy(TombstonePolicy::Flag);
+    /// ```
+    pub fn with_tombstone_policy(mut self, tombstone_policy: TombstonePolicy) -> Self {
+        self.session_config.tombstone_policy = Some(tombstone_policy);
+        self
+    }
+
+    /// Configures sessions to be saved in the background, after the response
+    /// has been returned.
+    /// The default value is `None`, saving sessions before responding.
+    ///
+    /// See [`DeferredSave`] for the guarantees this trades for lower latency.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{service::DeferredSave, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_deferred_save(DeferredSave::new());
+    /// ```
+    pub fn with_deferred_save(mut self, deferred_save: DeferredSave) -> Self {
+        self.session_config.deferred_save = Some(deferred_save);
+        self
+    }
+
+    /// Returns a handle for flushing sessions saved in the background on
+    /// shutdown.
+    ///
+    /// The handle is shared by clones of this layer and the services it
+    /// creates. See [`SessionLayerHandle`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use tower_sessions::{service::DeferredSave, MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_deferred_save(DeferredSave::new());
+    /// let handle = session_service.handle();
+    ///
+    /// handle.shutdown().await;
+    /// assert!(handle.is_shutdown());
+    /// # });
+    /// ```
+    pub fn handle(&self) -> SessionLayerHandle {
+        self.session_config.handle.clone()
+    }
+
+    /// Configures a companion cookie sent alongside the session cookie, e.g.
+    /// carrying a double-submit token for single-page applications.
+    /// The default value is `None`.
+    ///
+    /// See [`CompanionCookie`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{service::CompanionCookie, MemoryStore, SessionManagerLayer};
+    ///
+    /// let companion_cookie =
+    ///     CompanionCookie::new("csrf-token", |session_id| derive_token(session_id));
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service =
+    ///     SessionManagerLayer::new(session_store).with_companion_cookie(companion_cookie);
+    /// # fn derive_token(_: &tower_sessions::session::Id) -> String { String::new() }
+    /// ```
+    pub fn with_companion_cookie(mut self, companion_cookie: CompanionCookie) -> Self {
+        self.session_config.companion_cookie = Some(companion_cookie);
+        self
+    }
+
+    /// Validates the configuration, returning the layer if it's valid.
+    ///
+    /// Some combinations of cookie attributes are rejected or ignored by
+    /// browsers, which otherwise only becomes apparent at runtime when
+    /// sessions fail to persist. This catches them up front.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{
+    ///     cookie::SameSite,
+    ///     service::ConfigError,
+    ///     MemoryStore, SessionManagerLayer,
+    /// };
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_same_site(SameSite::None)
+    ///     .with_secure(false)
+    ///     .try_build();
+    /// assert_eq!(
+    ///     session_service.unwrap_err(),
+    ///     ConfigError::SameSiteNoneWithoutSecure
+    /// );
+    /// ```
+    pub fn try_build(self) -> Result<Self, ConfigError> {
+        self.session_config.validate()?;
+        Ok(self)
+    }
+
+    /// Validates the configuration, returning the layer.
+    ///
+    /// See [`try_build`](Self::try_build).
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the configuration is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store)
+    ///     .with_name("session")
+    ///     .build();
+    /// ```
+    #[track_caller]
+    pub fn build(self) -> Self {
+        match self.try_build() {
+            Ok(layer) => layer,
+            Err(err) => panic!("invalid session layer configuration: {err}"),
+        }
+    }
+
+    /// Configures a resolver selecting the session store per request.
+    ///
+    /// Requests for which the resolver returns `None` use the store the layer
+    /// was created with. See [`StoreResolver`] for an example.
+    pub fn with_store_resolver(mut self, store_resolver: impl StoreResolver<Store>) -> Self {
+        self.store_resolver = Some(Arc::new(store_resolver));
+        self
+    }
+
+    /// Relies on a `CookieManagerLayer` installed by the application rather
+    /// than wrapping the service in one.
+    ///
+    /// This avoids managing cookies twice when the application already uses
+    /// `tower-cookies`. The returned layer must be wrapped by the external
+    /// `CookieManagerLayer`; otherwise requests fail with
+    /// [`SessionLayerError::MissingCookies`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower::ServiceBuilder;
+    /// use tower_cookies::CookieManagerLayer;
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_layer = SessionManagerLayer::new(session_store).with_external_cookie_manager();
+    /// let layers = ServiceBuilder::new()
+    ///     .layer(CookieManagerLayer::new())
+    ///     .layer(session_layer);
+    /// ```
+    pub fn with_external_cookie_manager(self) -> ExternalCookiesLayer<Store, C> {
+        ExternalCookiesLayer {
+            session_manager_layer: self,
+        }
+    }
+
+    /// Manages the session cookie via a signed interface.
+    ///
+    /// See [`SignedCookies`](tower_cookies::SignedCookies).
+    ///
+    /// ```rust
+    /// use tower_sessions::{cookie::Key, MemoryStore, SessionManagerLayer};
+    ///
+    /// # /*
+    /// let key = { /* a cryptographically random key >= 64 bytes */ };
+    /// # */
+    /// # let key: &Vec<u8> = &(0..64).collect();
+    /// # let key: &[u8] = &key[..];
+    /// # let key = Key::try_from(key).unwrap();
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_signed(key);
+    /// ```
+    #[cfg(feature = "signed")]
+    pub fn with_signed(self, key: Key) -> SessionManagerLayer<Store, SignedCookie> {
+        SessionManagerLayer::<Store, SignedCookie> {
+            session_store: self.session_store,
+            store_resolver: self.store_resolver,
+            session_config: self.session_config,
+            cookie_controller: SignedCookie { key },
+        }
+    }
+
+    /// Manages the session cookie via an encrypted interface.
+    ///
+    /// See [`PrivateCookies`](tower_cookies::PrivateCookies).
+    ///
+    /// ```rust
+    /// use tower_sessions::{cookie::Key, MemoryStore, SessionManagerLayer};
+    ///
+    /// # /*
+    /// let key = { /* a cryptographically random key >= 64 bytes */ };
+    /// # */
+    /// # let key: &Vec<u8> = &(0..64).collect();
+    /// # let key: &[u8] = &key[..];
+    /// # let key = Key::try_from(key).unwrap();
     ///
-    /// The default value is `false`.
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store).with_private(key);
+    /// ```
+    #[cfg(feature = "private")]
+    pub fn with_private(self, key: Key) -> SessionManagerLayer<Store, PrivateCookie> {
+        SessionManagerLayer::<Store, PrivateCookie> {
+            session_store: self.session_store,
+            store_resolver: self.store_resolver,
+            session_config: self.session_config,
+            cookie_controller: PrivateCookie { key },
+        }
+    }
+
+    /// Manages the session cookie via both a signed and an encrypted
+    /// interface, using separate keys.
     ///
-    /// # Examples
+    /// The cookie value is first signed with `signing_key` and then encrypted
+    /// with `encryption_key`. Reading the cookie reverses this, so it's
+    /// equivalent to reading it through
+    /// [`PrivateCookies`](tower_cookies::PrivateCookies) and then
+    /// [`SignedCookies`](tower_cookies::SignedCookies). Cookies protected by
+    /// only one of the two are rejected.
     ///
     /// ```rust
-    /// use time::Duration;
-    /// use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer};
+    /// use tower_sessions::{cookie::Key, MemoryStore, SessionManagerLayer};
+    ///
+    /// # /*
+    /// let signing_key = { /* a cryptographically random key >= 64 bytes */ };
+    /// let encryption_key = { /* another cryptographically random key >= 64 bytes */ };
+    /// # */
+    /// # let signing_key = Key::generate();
+    /// # let encryption_key = Key::generate();
     ///
     /// let session_store = MemoryStore::default();
-    /// let session_expiry = Expiry::OnInactivity(Duration::hours(1));
     /// let session_service = SessionManagerLayer::new(session_store)
-    ///     .with_expiry(session_expiry)
-    ///     .with_always_save(true);
+    ///     .with_signed_and_private(signing_key, encryption_key);
     /// ```
-    pub fn with_always_save(mut self, always_save: bool) -> Self {
-        self.session_config.always_save = always_save;
-        self
+    #[cfg(all(feature = "signed", feature = "private"))]
+    pub fn with_signed_and_private(
+        self,
+        signing_key: Key,
+        encryption_key: Key,
+    ) -> SessionManagerLayer<Store, SignedPrivateCookie> {
+        SessionManagerLayer::<Store, SignedPrivateCookie> {
+            session_store: self.session_store,
+            store_resolver: self.store_resolver,
+            session_config: self.session_config,
+            cookie_controller: SignedPrivateCookie {
+                signing_key,
+                encryption_key,
+            },
+        }
+    }
+}
+
+impl<Store: SessionStore> SessionManagerLayer<Store> {
+    /// Create a new [`SessionManagerLayer`] with the provided session store
+    /// and default cookie configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::new(session_store);
+    /// ```
+    pub fn new(session_store: Store) -> Self {
+        let session_config = SessionConfig::default();
+
+        Self {
+            session_store: Arc::new(session_store),
+            store_resolver: None,
+            session_config,
+            cookie_controller: PlaintextCookie,
+        }
+    }
+
+    /// Create a new [`SessionManagerLayer`] configured for local development.
+    ///
+    /// Some browsers refuse to set `Secure` cookies over plain HTTP, even on
+    /// `localhost`, so the cookie is neither `Secure` nor `Strict`:
+    /// `"SameSite"` is `Lax`. Warnings about cookie domains and paths not
+    /// covering a request are also omitted, as development hosts often
+    /// differ from the configured domain.
+    ///
+    /// This shouldn't be used in production; see
+    /// [`production`](Self::production).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = if cfg!(debug_assertions) {
+    ///     SessionManagerLayer::development(session_store)
+    /// } else {
+    ///     SessionManagerLayer::production(session_store)
+    /// };
+    /// ```
+    pub fn development(session_store: Store) -> Self {
+        let mut layer = Self::new(session_store)
+            .with_secure(false)
+            .with_same_site(SameSite::Lax);
+        layer.session_config.relaxed_warnings = true;
+        layer
+    }
+
+    /// Create a new [`SessionManagerLayer`] configured for production.
+    ///
+    /// Along with the secure defaults of [`new`](Self::new), `"Secure"` and
+    /// `"SameSite=Strict"`, the cookie is named `"__Host-id"`. Browsers only
+    /// accept cookies with the `__Host-` prefix when they're `Secure`, have
+    /// the path `/` and no domain, which prevents subdomains from overwriting
+    /// the session cookie. Changing these attributes later is rejected by
+    /// [`try_build`](Self::try_build).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
+    ///
+    /// let session_store = MemoryStore::default();
+    /// let session_service = SessionManagerLayer::production(session_store)
+    ///     .try_build()
+    ///     .unwrap();
+    /// ```
+    pub fn production(session_store: Store) -> Self {
+        Self::new(session_store)
+            .with_name("__Host-id")
+            .with_secure(true)
+            .with_same_site(SameSite::Strict)
+            .with_path("/")
+    }
+}
+
+impl<S, Store: SessionStore, C: CookieController> Layer<S> for SessionManagerLayer<Store, C> {
+    type Service = CookieManager<SessionManager<S, Store, C>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CookieManager::new(self.session_manager(inner))
+    }
+}
+
+impl<Store: SessionStore, C: CookieController> SessionManagerLayer<Store, C> {
+    fn session_manager<S>(&self, inner: S) -> SessionManager<S, Store, C> {
+        SessionManager {
+            inner,
+            session_store: self.session_store.clone(),
+            store_resolver: self.store_resolver.clone(),
+            session_config: self.session_config.clone(),
+            cookie_controller: self.cookie_controller.clone(),
+        }
+    }
+}
+
+/// A layer for providing [`Session`] as a request extension, relying on an
+/// outer `CookieManagerLayer` to manage cookies.
+///
+/// See [`SessionManagerLayer::with_external_cookie_manager`].
+#[derive(Debug, Clone)]
+pub struct ExternalCookiesLayer<Store: SessionStore, C: CookieController = PlaintextCookie> {
+    session_manager_layer: SessionManagerLayer<Store, C>,
+}
+
+impl<S, Store: SessionStore, C: CookieController> Layer<S> for ExternalCookiesLayer<Store, C> {
+    type Service = SessionManager<S, Store, C>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.session_manager_layer.session_manager(inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use anyhow::anyhow;
+    use axum::body::Body;
+    use tower::{ServiceBuilder, ServiceExt};
+    use tower_sessions_memory_store::MemoryStore;
+
+    use super::*;
+    use crate::{
+        session::{Id, Record},
+        session_store,
+    };
+
+    async fn handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+        let session = req
+            .extensions()
+            .get::<Session>()
+            .ok_or(anyhow!("Missing session"))?;
+
+        session.insert("foo", 42).await?;
+
+        Ok(Response::new(Body::empty()))
+    }
+
+    async fn noop_handler(_: Request<Body>) -> anyhow::Result<Response<Body>> {
+        Ok(Response::new(Body::empty()))
+    }
+
+    #[tokio::test]
+    async fn basic_service_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+
+        let session = res.headers().get(http::header::SET_COOKIE);
+        assert!(session.is_some());
+
+        let req = Request::builder()
+            .header(http::header::COOKIE, session.unwrap())
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bogus_cookie_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+
+        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+
+        let req = Request::builder()
+            .header(http::header::COOKIE, "id=bogus")
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_set_cookie_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(noop_handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn name_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_name("my.sid");
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.starts_with("my.sid=")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn coexisting_cookies_test() -> anyhow::Result<()> {
+        async fn locale_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            session.insert("foo", 42).await?;
+
+            // Cookies set by inner middleware via their own cookie manager.
+            req.extensions()
+                .get::<Cookies>()
+                .ok_or(anyhow!("Missing cookies"))?
+                .add(Cookie::new("csrf", "token"));
+
+            // Cookies set directly on the response.
+            let mut res = Response::new(Body::empty());
+            res.headers_mut().append(
+                http::header::SET_COOKIE,
+                HeaderValue::from_static("locale=en"),
+            );
+            Ok(res)
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .layer(tower_cookies::CookieManagerLayer::new())
+            .service_fn(locale_handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let mut names = res
+            .headers()
+            .get_all(http::header::SET_COOKIE)
+            .iter()
+            .map(|value| {
+                let value = value.to_str().unwrap();
+                value[..value.find('=').unwrap()].to_string()
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["csrf", "id", "locale"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shared_cookie_jar_test() -> anyhow::Result<()> {
+        async fn csrf_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            session.insert("foo", 42).await?;
+
+            // The session layer's cookie jar is shared with the inner service.
+            req.extensions()
+                .get::<Cookies>()
+                .ok_or(anyhow!("Missing cookies"))?
+                .add(Cookie::new("csrf", "token"));
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(csrf_handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let set_cookies = res.headers().get_all(http::header::SET_COOKIE);
+        assert_eq!(set_cookies.iter().count(), 2);
+        assert!(set_cookies
+            .iter()
+            .any(|value| value.to_str().unwrap() == "csrf=token"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn path_from_header_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_path_from_header(HeaderName::from_static("x-forwarded-prefix"));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder()
+            .header("x-forwarded-prefix", "/app/")
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| {
+            s.contains("Path=/app;") || s.ends_with("Path=/app")
+        }));
+
+        let req = Request::builder()
+            .header("x-forwarded-prefix", "/app; Domain=evil.example")
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/") && !s.contains("evil")));
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/") && !s.contains("Path=/app")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn path_fn_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_path("/base")
+            .with_path_fn(|uri, _| {
+                uri.path()
+                    .starts_with("/admin")
+                    .then(|| "/admin".to_string())
+            });
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().uri("/admin/users").body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/admin")));
+
+        let req = Request::builder().uri("/users").body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/base")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn path_from_matched_prefix_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_path("/base")
+            .with_path_from_matched_prefix(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder()
+            .uri("/users")
+            .extension(MatchedPrefix::new("/admin/"))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/admin")
+            && !s.contains("Path=/admin/")));
+
+        let req = Request::builder().uri("/users").body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/base")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn domain_matches_test() {
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(domain_matches("example.com", "app.example.com"));
+        assert!(domain_matches("example.com", "API.Example.com:8443"));
+        assert!(domain_matches("example.com", "app.example.com."));
+        assert!(!domain_matches("example.com", "badexample.com"));
+        assert!(!domain_matches("example.com", "example.org"));
+        assert!(!domain_matches("app.example.com", "api.example.com"));
+        assert!(!domain_matches("example.com", "[::1]:8080"));
+    }
+
+    #[test]
+    fn path_matches_test() {
+        let prefix = MatchedPrefix::new("/admin");
+        assert_eq!(prefix.cookie_path().as_deref(), Some("/admin"));
+        assert_eq!(MatchedPrefix::new("/").cookie_path().as_deref(), Some("/"));
+        assert_eq!(MatchedPrefix::new("admin").cookie_path(), None);
+        assert_eq!(
+            prefix.request_path(&Uri::from_static("/users")),
+            "/admin/users"
+        );
+        assert_eq!(
+            prefix.request_path(&Uri::from_static("/admin/users")),
+            "/admin/users"
+        );
+
+        assert!(path_matches("/", "/admin"));
+        assert!(path_matches("/admin", "/admin"));
+        assert!(path_matches("/admin", "/admin/users"));
+        assert!(path_matches("/admin/", "/admin/users"));
+        assert!(!path_matches("/admin", "/administrator"));
+        assert!(!path_matches("/admin", "/users"));
+        assert!(!path_matches("/admin/", "/admin"));
+    }
+
+    #[tokio::test]
+    async fn store_resolver_test() -> anyhow::Result<()> {
+        #[derive(Debug)]
+        struct HostResolver(Arc<MemoryStore>);
+
+        impl StoreResolver<MemoryStore> for HostResolver {
+            fn resolve(&self, req: &Request<()>) -> Option<Arc<MemoryStore>> {
+                (req.headers().get(http::header::HOST)? == "tenant.example.com")
+                    .then(|| self.0.clone())
+            }
+        }
+
+        let default_store = MemoryStore::default();
+        let tenant_store = Arc::new(MemoryStore::default());
+        let session_layer = SessionManagerLayer::new(default_store.clone())
+            .with_store_resolver(HostResolver(tenant_store.clone()));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder()
+            .header(http::header::HOST, "tenant.example.com")
+            .body(Body::empty())?;
+        svc.clone().oneshot(req).await?;
+        assert_eq!(tenant_store.count().await, 1);
+        assert_eq!(default_store.count().await, 0);
+
+        let req = Request::builder()
+            .header(http::header::HOST, "other.example.com")
+            .body(Body::empty())?;
+        svc.oneshot(req).await?;
+        assert_eq!(tenant_store.count().await, 1);
+        assert_eq!(default_store.count().await, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn http_only_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("HttpOnly")));
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_http_only(false);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| !s.contains("HttpOnly")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn same_site_strict_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_same_site(SameSite::Strict);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=Strict")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn same_site_lax_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_same_site(SameSite::Lax);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=Lax")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn same_site_none_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_same_site(SameSite::None);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=None")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_on_session_end_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_expiry(Expiry::OnSessionEnd);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| !s.contains("Max-Age")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_on_inactivity_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let inactivity_duration = time::Duration::hours(2);
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_expiry(Expiry::OnInactivity(inactivity_duration));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let expected_max_age = inactivity_duration.whole_seconds();
+        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_at_date_time_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let expiry_time = time::OffsetDateTime::now_utc() + time::Duration::weeks(1);
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_expiry(Expiry::AtDateTime(expiry_time));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let expected_max_age = (expiry_time - time::OffsetDateTime::now_utc()).whole_seconds();
+        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_never_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_expiry(Expiry::Never);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let expected_max_age = Expiry::NEVER_DURATION.whole_seconds();
+        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_persistent_test() -> anyhow::Result<()> {
+        async fn remember_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            session.insert("foo", 42).await?;
+            session.set_persistent(req.uri().path() == "/remember");
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_expiry(Expiry::OnSessionEnd);
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(remember_handler);
+
+        let req = Request::builder().uri("/remember").body(Body::empty())?;
+        let res = svc.call(req).await?;
+        let expected_max_age = Expiry::NEVER_DURATION.whole_seconds();
+        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+        let sid = get_session_id(&res);
+
+        let req = Request::builder()
+            .uri("/forget")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert_eq!(get_session_id(&res), sid);
+        assert!(cookie_value_matches(&res, |s| !s.contains("Max-Age")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_fn_test() -> anyhow::Result<()> {
+        async fn role_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            session
+                .insert("role", req.uri().path().trim_matches('/'))
+                .await?;
+            if req.uri().path() == "/pinned" {
+                session.set_expiry(Some(Expiry::OnInactivity(Duration::minutes(1))));
+            }
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_expiry_fn(|data| {
+                match data.get("role").and_then(|role| role.as_str()) {
+                    Some("admin") => Expiry::OnInactivity(Duration::minutes(15)),
+                    _ => Expiry::OnInactivity(Duration::hours(8)),
+                }
+            });
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(role_handler);
+
+        let req = Request::builder().uri("/admin").body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(cookie_has_expected_max_age(&res, 15 * 60));
+
+        let req = Request::builder().uri("/user").body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(cookie_has_expected_max_age(&res, 8 * 60 * 60));
+
+        let req = Request::builder().uri("/pinned").body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(cookie_has_expected_max_age(&res, 60));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_validator_test() -> anyhow::Result<()> {
+        async fn insert_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            let key = req.uri().path().trim_start_matches('/');
+            session.insert(key, 42).await?;
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store.clone()).with_record_validator(|record| {
+                match record.data.keys().find(|key| key.as_str() != "user_id") {
+                    Some(key) => Err(ValidationError::new(format!("`{key}` isn't allowed"))),
+                    None => Ok(()),
+                }
+            });
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(insert_handler);
+
+        let req = Request::builder().uri("/user_id").body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(session_store.count().await, 1);
+
+        let req = Request::builder().uri("/ssn").body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let Some(SessionLayerError::Save(err)) = res.extensions().get::<SessionLayerError>() else {
+            panic!("missing session layer error");
+        };
+        assert!(matches!(**err, session::Error::Validation(_)));
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        assert_eq!(session_store.count().await, 1);
+
+        Ok(())
     }
 
-    /// Manages the session cookie via a signed interface.
-    ///
-    /// See [`SignedCookies`](tower_cookies::SignedCookies).
-    ///
-    /// ```rust
-    /// use tower_sessions::{cookie::Key, MemoryStore, SessionManagerLayer};
-    ///
-    /// # /*
-    /// let key = { /* a cryptographically random key >= 64 bytes */ };
-    /// # */
-    /// # let key: &Vec<u8> = &(0..64).collect();
-    /// # let key: &[u8] = &key[..];
-    /// # let key = Key::try_from(key).unwrap();
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_signed(key);
-    /// ```
-    #[cfg(feature = "signed")]
-    pub fn with_signed(self, key: Key) -> SessionManagerLayer<Store, SignedCookie> {
-        SessionManagerLayer::<Store, SignedCookie> {
-            session_store: self.session_store,
-            session_config: self.session_config,
-            cookie_controller: SignedCookie { key },
+    #[tokio::test]
+    async fn record_sizes_test() -> anyhow::Result<()> {
+        async fn insert_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            let len = req.uri().path().trim_start_matches('/').parse()?;
+            session.insert("bio", "a".repeat(len)).await?;
+            Ok(Response::new(Body::empty()))
         }
+
+        let record_sizes = RecordSizes::new().with_limit(1024);
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store.clone()).with_record_sizes(record_sizes.clone());
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(insert_handler);
+
+        let req = Request::builder().uri("/16").body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(session_store.count().await, 1);
+
+        let req = Request::builder().uri("/2048").body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        let Some(SessionLayerError::Save(err)) = res.extensions().get::<SessionLayerError>() else {
+            panic!("missing session layer error");
+        };
+        assert!(matches!(
+            **err,
+            session::Error::RecordTooLarge { limit: 1024, .. }
+        ));
+        assert_eq!(session_store.count().await, 1);
+
+        assert_eq!(record_sizes.count(), 2);
+        assert_eq!(record_sizes.rejected(), 1);
+        assert!(record_sizes.max_bytes() > 2048);
+
+        Ok(())
     }
 
-    /// Manages the session cookie via an encrypted interface.
-    ///
-    /// See [`PrivateCookies`](tower_cookies::PrivateCookies).
-    ///
-    /// ```rust
-    /// use tower_sessions::{cookie::Key, MemoryStore, SessionManagerLayer};
-    ///
-    /// # /*
-    /// let key = { /* a cryptographically random key >= 64 bytes */ };
-    /// # */
-    /// # let key: &Vec<u8> = &(0..64).collect();
-    /// # let key: &[u8] = &key[..];
-    /// # let key = Key::try_from(key).unwrap();
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store).with_private(key);
-    /// ```
-    #[cfg(feature = "private")]
-    pub fn with_private(self, key: Key) -> SessionManagerLayer<Store, PrivateCookie> {
-        SessionManagerLayer::<Store, PrivateCookie> {
-            session_store: self.session_store,
-            session_config: self.session_config,
-            cookie_controller: PrivateCookie { key },
+    #[tokio::test]
+    async fn expiry_from_claims_test() -> anyhow::Result<()> {
+        async fn login_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            // The upstream token expires in two hours.
+            let exp = time::OffsetDateTime::now_utc() + Duration::hours(2);
+            session.set_expiry_from(&exp);
+            session.insert("sub", "alice").await?;
+
+            Ok(Response::new(Body::empty()))
         }
+
+        let session_layer = SessionManagerLayer::new(MemoryStore::default())
+            .with_expiry(Expiry::OnInactivity(Duration::minutes(30)));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(login_handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_has_expected_max_age(&res, 2 * 60 * 60));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "grpc")]
+    #[tokio::test]
+    async fn metadata_key_test() -> anyhow::Result<()> {
+        let metadata_key = http::HeaderName::from_static("x-session-id");
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_metadata_key(metadata_key.clone());
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder()
+            .header(http::header::CONTENT_TYPE, "application/grpc")
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        let sid = res
+            .headers()
+            .get(&metadata_key)
+            .ok_or(anyhow!("Missing session metadata"))?
+            .clone();
+
+        // HTTP clients presenting the key share the same session, which is
+        // unmodified and so neither saved nor reissued.
+        let req = Request::builder()
+            .header(&metadata_key, sid)
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(res.headers().get(&metadata_key).is_none());
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        // Other HTTP clients continue to use the cookie.
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(res.headers().get(&metadata_key).is_none());
+        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expires_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_expiry(Expiry::OnInactivity(time::Duration::hours(2)))
+            .with_expires(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("Expires=")));
+        assert!(cookie_has_expected_max_age(&res, 7200));
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_expiry(Expiry::OnSessionEnd)
+            .with_expires(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| !s.contains("Expires=")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn minimal_set_cookie_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnInactivity(Duration::days(1)))
+            .with_always_save(true)
+            .with_minimal_set_cookie(true)
+            .with_set_cookie_granularity(Duration::hours(1));
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.call(req).await?;
+        let sid = get_session_id(&res);
+        let rec1 = get_record(&session_store, &sid).await;
+
+        // The expiry is refreshed in the store without setting the cookie again.
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        let rec2 = get_record(&session_store, &sid).await;
+        assert!(rec1.expiry_date < rec2.expiry_date);
+
+        // Once the expiry moves to another interval the cookie is set again.
+        let mut stale = rec2.clone();
+        stale.expiry_date -= Duration::hours(2);
+        session_store.save(&stale).await?;
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert!(cookie_has_expected_max_age(
+            &res,
+            Duration::days(1).whole_seconds()
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_on_session_end_always_save_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnSessionEnd)
+            .with_always_save(true);
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req1 = Request::builder().body(Body::empty())?;
+        let res1 = svc.call(req1).await?;
+        let sid1 = get_session_id(&res1);
+        let rec1 = get_record(&session_store, &sid1).await;
+        let req2 = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid1))
+            .body(Body::empty())?;
+        let res2 = svc.call(req2).await?;
+        let sid2 = get_session_id(&res2);
+        let rec2 = get_record(&session_store, &sid2).await;
+
+        assert!(cookie_value_matches(&res2, |s| !s.contains("Max-Age")));
+        assert!(sid1 == sid2);
+        assert!(rec1.expiry_date < rec2.expiry_date);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_on_inactivity_always_save_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let inactivity_duration = time::Duration::hours(2);
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnInactivity(inactivity_duration))
+            .with_always_save(true);
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req1 = Request::builder().body(Body::empty())?;
+        let res1 = svc.call(req1).await?;
+        let sid1 = get_session_id(&res1);
+        let rec1 = get_record(&session_store, &sid1).await;
+        let req2 = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid1))
+            .body(Body::empty())?;
+        let res2 = svc.call(req2).await?;
+        let sid2 = get_session_id(&res2);
+        let rec2 = get_record(&session_store, &sid2).await;
+
+        let expected_max_age = inactivity_duration.whole_seconds();
+        assert!(cookie_has_expected_max_age(&res2, expected_max_age));
+        assert!(sid1 == sid2);
+        assert!(rec1.expiry_date < rec2.expiry_date);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry_at_date_time_always_save_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let expiry_time = time::OffsetDateTime::now_utc() + time::Duration::weeks(1);
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::AtDateTime(expiry_time))
+            .with_always_save(true);
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req1 = Request::builder().body(Body::empty())?;
+        let res1 = svc.call(req1).await?;
+        let sid1 = get_session_id(&res1);
+        let rec1 = get_record(&session_store, &sid1).await;
+        let req2 = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid1))
+            .body(Body::empty())?;
+        let res2 = svc.call(req2).await?;
+        let sid2 = get_session_id(&res2);
+        let rec2 = get_record(&session_store, &sid2).await;
+
+        let expected_max_age = (expiry_time - time::OffsetDateTime::now_utc()).whole_seconds();
+        assert!(cookie_has_expected_max_age(&res2, expected_max_age));
+        assert!(sid1 == sid2);
+        assert!(rec1.expiry_date == rec2.expiry_date);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn secure_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_secure(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("Secure")));
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store).with_secure(false);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| !s.contains("Secure")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partitioned_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_same_site(SameSite::None)
+            .with_secure(false)
+            .with_partitioned(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        assert!(cookie_value_matches(&res, |s| s.contains("Partitioned") && s.contains("Secure")));
+
+        Ok(())
     }
-}
 
-impl<Store: SessionStore> SessionManagerLayer<Store> {
-    /// Create a new [`SessionManagerLayer`] with the provided session store
-    /// and default cookie configuration.
-    ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// use tower_sessions::{MemoryStore, SessionManagerLayer};
-    ///
-    /// let session_store = MemoryStore::default();
-    /// let session_service = SessionManagerLayer::new(session_store);
-    /// ```
-    pub fn new(session_store: Store) -> Self {
-        let session_config = SessionConfig::default();
+    #[tokio::test]
+    async fn cookie_value_codec_test() -> anyhow::Result<()> {
+        #[derive(Debug)]
+        struct PrefixCodec;
 
-        Self {
-            session_store: Arc::new(session_store),
-            session_config,
-            cookie_controller: PlaintextCookie,
+        impl CookieValueCodec for PrefixCodec {
+            fn encode(&self, session_id: &session::Id) -> String {
+                format!("acme.{session_id}")
+            }
+
+            fn decode(&self, value: &str) -> Result<session::Id, CookieValueError> {
+                let session_id = value.strip_prefix("acme.").ok_or("missing prefix")?;
+                Ok(session_id.parse()?)
+            }
         }
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_cookie_value_codec(PrefixCodec)
+            .with_always_save(true);
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req1 = Request::builder().body(Body::empty())?;
+        let res1 = svc.call(req1).await?;
+        let value1 = get_session_id(&res1);
+        let sid1 = value1.strip_prefix("acme.").expect("Encoded cookie value");
+        assert!(get_record(&session_store, sid1)
+            .await
+            .data
+            .contains_key("foo"));
+
+        let req2 = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", value1))
+            .body(Body::empty())?;
+        let res2 = svc.call(req2).await?;
+        assert_eq!(get_session_id(&res2), value1);
+
+        // The raw ID isn't accepted by the codec.
+        let req3 = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid1))
+            .body(Body::empty())?;
+        let res3 = svc.call(req3).await?;
+        assert_ne!(get_session_id(&res3), value1);
+
+        Ok(())
     }
-}
 
-impl<S, Store: SessionStore, C: CookieController> Layer<S> for SessionManagerLayer<Store, C> {
-    type Service = CookieManager<SessionManager<S, Store, C>>;
+    #[tokio::test]
+    async fn max_lifetime_test() -> anyhow::Result<()> {
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnInactivity(Duration::hours(2)))
+            .with_max_lifetime(Duration::minutes(30));
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
 
-    fn layer(&self, inner: S) -> Self::Service {
-        let session_manager = SessionManager {
-            inner,
-            session_store: self.session_store.clone(),
-            session_config: self.session_config.clone(),
-            cookie_controller: self.cookie_controller.clone(),
-        };
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+
+        let record = get_record(&session_store, &get_session_id(&res)).await;
+        let absolute_expiry = record.absolute_expiry.expect("Absolute expiry");
+        assert!(absolute_expiry <= OffsetDateTime::now_utc() + Duration::minutes(30));
+        assert!(record.expiry_date <= absolute_expiry);
+        assert!(cookie_has_expected_max_age(&res, 1800));
 
-        CookieManager::new(session_manager)
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
+    #[tokio::test]
+    async fn external_cookie_writer_test() -> anyhow::Result<()> {
+        async fn flush_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            session.flush().await?;
+            Ok(Response::new(Body::empty()))
+        }
 
-    use anyhow::anyhow;
-    use axum::body::Body;
-    use tower::{ServiceBuilder, ServiceExt};
-    use tower_sessions_memory_store::MemoryStore;
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnInactivity(Duration::hours(1)))
+            .with_external_cookie_writer(true);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer.clone())
+            .service_fn(handler);
 
-    use super::*;
-    use crate::session::{Id, Record};
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
 
-    async fn handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
-        let session = req
-            .extensions()
-            .get::<Session>()
-            .ok_or(anyhow!("Missing session"))?;
+        let Some(SessionCookieUpdate::Set {
+            session_id,
+            expires_at,
+            cookie,
+        }) = res.extensions().get::<SessionCookieUpdate>().cloned()
+        else {
+            panic!("Missing session cookie update");
+        };
+        assert_eq!(cookie.value(), session_id.to_string());
+        assert!(expires_at.is_some_and(|expires_at| expires_at > OffsetDateTime::now_utc()));
+        assert!(get_record(&session_store, &session_id.to_string())
+            .await
+            .data
+            .contains_key("foo"));
 
-        session.insert("foo", 42).await?;
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(flush_handler);
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", session_id))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        assert!(matches!(
+            res.extensions().get::<SessionCookieUpdate>(),
+            Some(SessionCookieUpdate::Remove { cookie }) if cookie.name() == "id"
+        ));
 
-        Ok(Response::new(Body::empty()))
+        Ok(())
     }
 
-    async fn noop_handler(_: Request<Body>) -> anyhow::Result<Response<Body>> {
-        Ok(Response::new(Body::empty()))
+    #[tokio::test]
+    async fn security_events_test() -> anyhow::Result<()> {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let security_events = SecurityEvents::new({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.clone())
+        });
+
+        let session_store = MemoryStore::default();
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_security_events(security_events);
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder()
+            .header(http::header::COOKIE, "id=bogus")
+            .header(http::header::USER_AGENT, "test-agent")
+            .body(Body::empty())?;
+        svc.clone().oneshot(req).await?;
+
+        let unknown_id = Id::default();
+        let mut req = Request::builder()
+            .header(http::header::COOKIE, format!("id={unknown_id}"))
+            .body(Body::empty())?;
+        req.extensions_mut()
+            .insert(ClientIp(IpAddr::from([127, 0, 0, 1])));
+        svc.oneshot(req).await?;
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, SecurityEventKind::MalformedId);
+        assert_eq!(events[0].session_id_hash, None);
+        assert_eq!(events[0].client.user_agent.as_deref(), Some("test-agent"));
+        assert_eq!(events[1].kind, SecurityEventKind::UnknownId);
+        assert!(events[1].session_id_hash.is_some());
+        assert_ne!(
+            events[1].session_id_hash.as_deref(),
+            Some(unknown_id.to_string().as_str())
+        );
+        assert_eq!(events[1].client.ip, Some(IpAddr::from([127, 0, 0, 1])));
+
+        Ok(())
     }
 
     #[tokio::test]
-    async fn basic_service_test() -> anyhow::Result<()> {
+    async fn split_token_test() -> anyhow::Result<()> {
+        async fn count_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            let count = session.get::<usize>("count").await?.unwrap_or_default();
+            session.insert("count", count + 1).await?;
+            Ok(Response::new(Body::empty()))
+        }
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let security_events = SecurityEvents::new({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.kind)
+        });
+
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store);
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_split_token(true)
+            .with_security_events(security_events);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(handler);
+            .service_fn(count_handler);
 
         let req = Request::builder().body(Body::empty())?;
         let res = svc.clone().oneshot(req).await?;
+        let cookie_value = get_session_id(&res);
+        let (session_id, validator) = cookie_value.split_once('.').unwrap();
 
-        let session = res.headers().get(http::header::SET_COOKIE);
-        assert!(session.is_some());
+        // Only a hash of the validator is stored.
+        let record = get_record(&session_store, session_id).await;
+        let validator_hash = record.validator_hash.unwrap();
+        assert!(!validator_hash.contains(validator));
 
         let req = Request::builder()
-            .header(http::header::COOKIE, session.unwrap())
+            .header(http::header::COOKIE, format!("id={cookie_value}"))
             .body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(get_session_id(&res), cookie_value);
+        let record = get_record(&session_store, session_id).await;
+        assert_eq!(record.data["count"], 2);
+
+        // The session ID alone, as found in the store, isn't enough.
+        for forged in [
+            format!("id={session_id}"),
+            format!("id={session_id}.{validator_hash}"),
+        ] {
+            let req = Request::builder()
+                .header(http::header::COOKIE, forged)
+                .body(Body::empty())?;
+            let res = svc.clone().oneshot(req).await?;
+            assert!(!get_session_id(&res).starts_with(session_id));
+        }
 
-        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                SecurityEventKind::MalformedId,
+                SecurityEventKind::ValidatorMismatch
+            ]
+        );
+
+        // The legitimate session is unaffected.
+        let record = get_record(&session_store, session_id).await;
+        assert_eq!(record.validator_hash.as_deref(), Some(&*validator_hash));
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn bogus_cookie_test() -> anyhow::Result<()> {
+    async fn split_token_migration_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store);
+        let session = Session::new(None, Arc::new(session_store.clone()), None);
+        session.insert("foo", 42).await?;
+        session.save().await?;
+        let session_id = session.id().unwrap();
+
+        // Sessions predating split tokens are rejected by default.
+        let svc = ServiceBuilder::new()
+            .layer(SessionManagerLayer::new(session_store.clone()).with_split_token(true))
+            .service_fn(handler);
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={session_id}"))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(!get_session_id(&res).starts_with(&session_id.to_string()));
+
+        // While migrating, they're issued a validator.
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_split_token(true)
+            .with_split_token_migration(OffsetDateTime::now_utc() + Duration::days(1));
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={session_id}"))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        let cookie_value = get_session_id(&res);
+        let (id, validator) = cookie_value.split_once('.').unwrap();
+        assert_eq!(id, session_id.to_string());
+        let record = get_record(&session_store, id).await;
+        assert_eq!(record.data["foo"], 42);
+        assert!(record
+            .validator_hash
+            .is_some_and(|hash| !hash.contains(validator)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn duplicate_cookies_test() -> anyhow::Result<()> {
+        async fn count_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            let count = session.get::<usize>("count").await?.unwrap_or_default();
+            session.insert("count", count + 1).await?;
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store.clone());
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(count_handler);
 
         let req = Request::builder().body(Body::empty())?;
+        let first = get_session_id(&svc.clone().oneshot(req).await?);
+        let req = Request::builder().body(Body::empty())?;
+        let second = get_session_id(&svc.clone().oneshot(req).await?);
+
+        // A cookie whose record isn't found is skipped in favor of the next one.
+        let stale = Id::default();
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={stale}; id={first}"))
+            .body(Body::empty())?;
         let res = svc.clone().oneshot(req).await?;
+        assert_eq!(get_session_id(&res), first);
+        assert_eq!(get_record(&session_store, &first).await.data["count"], 2);
 
-        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+        // Otherwise the first cookie sent, the most specific one, is used.
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={second}"))
+            .header(http::header::COOKIE, format!("id={first}"))
+            .body(Body::empty())?;
+        svc.clone().oneshot(req).await?;
+        assert_eq!(get_record(&session_store, &second).await.data["count"], 2);
+        assert_eq!(get_record(&session_store, &first).await.data["count"], 2);
 
         let req = Request::builder()
-            .header(http::header::COOKIE, "id=bogus")
+            .header(http::header::COOKIE, format!("id={first}; id={second}"))
             .body(Body::empty())?;
+        svc.oneshot(req).await?;
+        assert_eq!(get_record(&session_store, &first).await.data["count"], 3);
+        assert_eq!(get_record(&session_store, &second).await.data["count"], 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn clear_site_data_test() -> anyhow::Result<()> {
+        async fn flush_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+            session.flush().await?;
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_clear_site_data([ClearSiteData::Cookies, ClearSiteData::Storage]);
+
+        let svc = ServiceBuilder::new()
+            .layer(session_layer.clone())
+            .service_fn(handler);
+        let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
+        assert!(res.headers().get("clear-site-data").is_none());
+        let session_id = get_session_id(&res);
 
-        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(flush_handler);
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={session_id}"))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.contains("Max-Age=0")));
+        assert_eq!(
+            res.headers().get("clear-site-data").unwrap(),
+            r#""cookies", "storage""#
+        );
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn no_set_cookie_test() -> anyhow::Result<()> {
+    async fn max_cookie_age_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store);
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_expiry(Expiry::OnInactivity(Duration::days(30)))
+            .with_max_cookie_age(Duration::hours(24));
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(noop_handler);
+            .service_fn(handler);
 
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
 
+        assert!(cookie_has_expected_max_age(&res, 24 * 60 * 60));
+
+        // The store-side expiry isn't capped.
+        let record = get_record(&session_store, &get_session_id(&res)).await;
+        assert!(record.expiry_date > OffsetDateTime::now_utc() + Duration::days(29));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn always_save_override_test() -> anyhow::Result<()> {
+        async fn refresh_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            if req.uri().path() == "/refresh" {
+                session.set_always_save(true);
+            }
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        let session_store = MemoryStore::default();
+        let session = Session::new(None, Arc::new(session_store.clone()), None);
+        session.insert("foo", 42).await?;
+        session.save().await?;
+        let sid = session.id().expect("Saved session").to_string();
+
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_expiry(Expiry::OnInactivity(Duration::hours(1)));
+        let mut svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(refresh_handler);
+
+        let req = Request::builder()
+            .uri("/")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
         assert!(res.headers().get(http::header::SET_COOKIE).is_none());
 
+        let req = Request::builder()
+            .uri("/refresh")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.call(req).await?;
+        assert_eq!(get_session_id(&res), sid);
+
         Ok(())
     }
 
     #[tokio::test]
-    async fn name_test() -> anyhow::Result<()> {
+    async fn path_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_name("my.sid");
+        let session_layer = SessionManagerLayer::new(session_store).with_path("/foo/bar");
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
@@ -639,15 +4757,15 @@ mod tests {
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
 
-        assert!(cookie_value_matches(&res, |s| s.starts_with("my.sid=")));
+        assert!(cookie_value_matches(&res, |s| s.contains("Path=/foo/bar")));
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn http_only_test() -> anyhow::Result<()> {
+    async fn domain_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store);
+        let session_layer = SessionManagerLayer::new(session_store).with_domain("example.com");
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
@@ -655,10 +4773,32 @@ mod tests {
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
 
-        assert!(cookie_value_matches(&res, |s| s.contains("HttpOnly")));
+        assert!(cookie_value_matches(&res, |s| s.contains("Domain=example.com")));
 
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_http_only(false);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_error_test() -> anyhow::Result<()> {
+        #[derive(Debug)]
+        struct FailingStore;
+
+        #[async_trait::async_trait]
+        impl SessionStore for FailingStore {
+            async fn save(&self, _: &Record) -> session_store::Result<()> {
+                Err(session_store::Error::Backend("unavailable".to_string()))
+            }
+
+            async fn load(&self, _: &Id) -> session_store::Result<Option<Record>> {
+                Ok(None)
+            }
+
+            async fn delete(&self, _: &Id) -> session_store::Result<()> {
+                Ok(())
+            }
+        }
+
+        let session_layer = SessionManagerLayer::new(FailingStore);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
@@ -666,102 +4806,389 @@ mod tests {
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
 
-        assert!(cookie_value_matches(&res, |s| !s.contains("HttpOnly")));
+        assert_eq!(res.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(
+            res.extensions().get::<SessionLayerError>(),
+            Some(SessionLayerError::Save(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn shutdown_flushes_deferred_saves_test() -> anyhow::Result<()> {
+        use tower_sessions_core::testing::{MockSessionStore, Operation};
+
+        let store = MockSessionStore::new().with_latency(std::time::Duration::from_millis(50));
+        let session_layer = SessionManagerLayer::new(store.clone())
+            .with_always_save(true)
+            .with_deferred_save(DeferredSave::new());
+        let handle = session_layer.handle();
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        let sid = get_session_id(&res);
+
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        svc.clone().oneshot(req).await?;
+        assert_eq!(handle.pending(), 1);
+        store.assert_calls(Operation::Save, 0);
+
+        let shutdown = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.shutdown().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(handle.is_shutdown());
+        assert!(!shutdown.is_finished());
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), shutdown).await??;
+        assert_eq!(handle.pending(), 0);
+        store.assert_calls(Operation::Save, 1);
+
+        // Sessions are saved before responding once shut down.
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        svc.oneshot(req).await?;
+        store.assert_calls(Operation::Save, 2);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn same_site_strict_test() -> anyhow::Result<()> {
-        let session_store = MemoryStore::default();
-        let session_layer =
-            SessionManagerLayer::new(session_store).with_same_site(SameSite::Strict);
+    async fn deferred_save_test() -> anyhow::Result<()> {
+        use tower_sessions_core::testing::{MockSessionStore, Operation};
+
+        let store = MockSessionStore::new();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let deferred_save = DeferredSave::new()
+            .with_retries(1)
+            .with_backoff(
+                std::time::Duration::from_millis(10),
+                std::time::Duration::from_millis(10),
+            )
+            .with_error_handler(move |err| {
+                tx.send(err.to_string()).unwrap();
+            });
+        let session_layer = SessionManagerLayer::new(store.clone())
+            .with_always_save(true)
+            .with_deferred_save(deferred_save);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
+        // New sessions are created before responding.
         let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let res = svc.clone().oneshot(req).await?;
+        let sid = get_session_id(&res);
+        store.assert_calls(Operation::Create, 1);
 
-        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=Strict")));
+        // A failed save is retried in the background.
+        store.fail_next(Operation::Save, unavailable());
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(get_session_id(&res), sid);
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while store.call_count(Operation::Save) < 2 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await?;
+
+        // Failures are reported once all attempts are exhausted.
+        store.fail_next(Operation::Save, unavailable());
+        store.fail_next(Operation::Save, unavailable());
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(res.status(), http::StatusCode::OK);
+        assert_eq!(rx.recv().await.unwrap(), unavailable().to_string());
+        store.assert_calls(Operation::Save, 4);
+
+        // Errors which can't be resolved by retrying are reported right away.
+        store.fail_next(
+            Operation::Save,
+            session_store::Error::Encode("invalid".to_string()),
+        );
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        svc.oneshot(req).await?;
+        assert_eq!(rx.recv().await.unwrap(), "Encoding failed with: invalid");
+        store.assert_calls(Operation::Save, 5);
 
         Ok(())
     }
 
+    fn unavailable() -> session_store::Error {
+        session_store::Error::Unavailable("down".to_string())
+    }
+
     #[tokio::test]
-    async fn same_site_lax_test() -> anyhow::Result<()> {
+    async fn client_binding_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_same_site(SameSite::Lax);
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_client_binding(ClientBinding::new(BindingPolicy::CycleId));
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
-        let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let req = Request::builder()
+            .header(http::header::USER_AGENT, "alice")
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        let sid = get_session_id(&res);
 
-        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=Lax")));
+        let req = Request::builder()
+            .header(http::header::USER_AGENT, "alice")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        let req = Request::builder()
+            .header(http::header::USER_AGENT, "mallory")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert_ne!(get_session_id(&res), sid);
 
         Ok(())
     }
 
+    #[test]
+    fn try_build_test() {
+        let layer = || SessionManagerLayer::new(MemoryStore::default());
+
+        assert!(layer().try_build().is_ok());
+        assert_eq!(
+            layer().with_name("").try_build().unwrap_err(),
+            ConfigError::EmptyName
+        );
+        assert_eq!(
+            layer().with_name("my id").try_build().unwrap_err(),
+            ConfigError::InvalidName("my id".to_string())
+        );
+        assert_eq!(
+            layer()
+                .with_same_site(SameSite::None)
+                .with_secure(false)
+                .try_build()
+                .unwrap_err(),
+            ConfigError::SameSiteNoneWithoutSecure
+        );
+        assert!(layer()
+            .with_same_site(SameSite::None)
+            .with_secure(false)
+            .with_partitioned(true)
+            .try_build()
+            .is_ok());
+        assert_eq!(
+            layer().with_domain(".example.com").try_build().unwrap_err(),
+            ConfigError::DomainLeadingDot(".example.com".to_string())
+        );
+        assert_eq!(
+            layer().with_path("app").try_build().unwrap_err(),
+            ConfigError::InvalidPath("app".to_string())
+        );
+        assert_eq!(
+            layer()
+                .with_companion_cookie(CompanionCookie::new("id", |_| String::new()))
+                .try_build()
+                .unwrap_err(),
+            ConfigError::CompanionNameConflict("id".to_string())
+        );
+        assert_eq!(
+            layer()
+                .with_name("__Secure-id")
+                .with_secure(false)
+                .try_build()
+                .unwrap_err(),
+            ConfigError::PrefixRequirements("__Secure-id".to_string())
+        );
+        assert_eq!(
+            layer()
+                .with_name("__host-id")
+                .with_domain("example.com")
+                .try_build()
+                .unwrap_err(),
+            ConfigError::PrefixRequirements("__host-id".to_string())
+        );
+        assert!(layer()
+            .with_name("__Secure-id")
+            .with_domain("example.com")
+            .try_build()
+            .is_ok());
+    }
+
     #[tokio::test]
-    async fn same_site_none_test() -> anyhow::Result<()> {
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_same_site(SameSite::None);
+    async fn profiles_test() -> anyhow::Result<()> {
+        let session_layer = SessionManagerLayer::development(MemoryStore::default()).build();
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
-
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.starts_with("id=")
+            && s.contains("SameSite=Lax")
+            && !s.contains("Secure")));
 
-        assert!(cookie_value_matches(&res, |s| s.contains("SameSite=None")));
+        let session_layer = SessionManagerLayer::production(MemoryStore::default()).build();
+        let svc = ServiceBuilder::new()
+            .layer(session_layer)
+            .service_fn(handler);
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert!(cookie_value_matches(&res, |s| s.starts_with("__Host-id=")
+            && s.contains("SameSite=Strict")
+            && s.contains("Secure")
+            && s.contains("Path=/")
+            && !s.contains("Domain")));
+
+        assert_eq!(
+            SessionManagerLayer::production(MemoryStore::default())
+                .with_cross_subdomain("example.com")
+                .try_build()
+                .unwrap_err(),
+            ConfigError::PrefixRequirements("__Host-id".to_string())
+        );
 
         Ok(())
     }
 
+    #[test]
+    #[should_panic(expected = "invalid session layer configuration")]
+    fn build_panics_test() {
+        SessionManagerLayer::new(MemoryStore::default())
+            .with_name("")
+            .build();
+    }
+
     #[tokio::test]
-    async fn expiry_on_session_end_test() -> anyhow::Result<()> {
+    async fn signed_id_codec_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer =
-            SessionManagerLayer::new(session_store).with_expiry(Expiry::OnSessionEnd);
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_cookie_value_codec(SignedIdCodec::new(b"key"));
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
         let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let res = svc.clone().oneshot(req).await?;
+        let value = get_session_id(&res);
+        let sid = Id::from_signed_str(&value, b"key")?;
+        assert!(session_store.load(&sid).await?.is_some());
 
-        assert!(cookie_value_matches(&res, |s| !s.contains("Max-Age")));
+        // The unsigned ID isn't accepted, so a new session is created.
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={sid}"))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert_ne!(get_session_id(&res), value);
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn expiry_on_inactivity_test() -> anyhow::Result<()> {
+    async fn companion_cookie_test() -> anyhow::Result<()> {
+        async fn companion_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            match req.uri().path() {
+                "/rotate" => session.cycle_id().await?,
+                "/logout" => session.flush().await?,
+                _ => session.insert("foo", 42).await?,
+            }
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        fn set_cookies(res: &Response<Body>) -> Vec<String> {
+            res.headers()
+                .get_all(http::header::SET_COOKIE)
+                .iter()
+                .map(|value| value.to_str().unwrap().to_string())
+                .collect()
+        }
+
+        // The session and companion cookies may be set in either order.
+        fn set_cookie_value(res: &Response<Body>, name: &str) -> String {
+            set_cookies(res)
+                .iter()
+                .find_map(|cookie| cookie.strip_prefix(&format!("{name}=")))
+                .and_then(|cookie| cookie.split(';').next())
+                .unwrap()
+                .to_string()
+        }
+
+        let companion_cookie =
+            CompanionCookie::new("csrf", |session_id: &Id| format!("token-{session_id}"));
         let session_store = MemoryStore::default();
-        let inactivity_duration = time::Duration::hours(2);
-        let session_layer = SessionManagerLayer::new(session_store)
-            .with_expiry(Expiry::OnInactivity(inactivity_duration));
+        let session_layer =
+            SessionManagerLayer::new(session_store).with_companion_cookie(companion_cookie);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(handler);
+            .service_fn(companion_handler);
 
         let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let res = svc.clone().oneshot(req).await?;
+        let sid = set_cookie_value(&res, "id");
+        let cookies = set_cookies(&res);
+        assert_eq!(cookies.len(), 2);
+        let companion = cookies
+            .iter()
+            .find(|cookie| cookie.starts_with("csrf="))
+            .unwrap();
+        assert!(companion.starts_with(&format!("csrf=token-{sid};")));
+        assert!(!companion.contains("HttpOnly"));
+        assert!(companion.contains("SameSite=Strict"));
 
-        let expected_max_age = inactivity_duration.whole_seconds();
-        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+        let req = Request::builder()
+            .uri("/rotate")
+            .header(http::header::COOKIE, format!("id={sid}; csrf=token-{sid}"))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        let cycled_sid = set_cookie_value(&res, "id");
+        assert_ne!(cycled_sid, sid);
+        assert_eq!(
+            set_cookie_value(&res, "csrf"),
+            format!("token-{cycled_sid}")
+        );
+
+        let req = Request::builder()
+            .uri("/logout")
+            .header(
+                http::header::COOKIE,
+                format!("id={cycled_sid}; csrf=token-{cycled_sid}"),
+            )
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        let cookies = set_cookies(&res);
+        assert!(cookies.iter().any(|cookie| cookie.starts_with("id=;")));
+        assert!(cookies.iter().any(|cookie| cookie.starts_with("csrf=;")));
 
         Ok(())
     }
 
+    #[cfg(feature = "signed")]
     #[tokio::test]
-    async fn expiry_at_date_time_test() -> anyhow::Result<()> {
+    async fn signed_test() -> anyhow::Result<()> {
+        let key = Key::generate();
         let session_store = MemoryStore::default();
-        let expiry_time = time::OffsetDateTime::now_utc() + time::Duration::weeks(1);
-        let session_layer =
-            SessionManagerLayer::new(session_store).with_expiry(Expiry::AtDateTime(expiry_time));
+        let session_layer = SessionManagerLayer::new(session_store).with_signed(key);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
@@ -769,191 +5196,347 @@ mod tests {
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
 
-        let expected_max_age = (expiry_time - time::OffsetDateTime::now_utc()).whole_seconds();
-        assert!(cookie_has_expected_max_age(&res, expected_max_age));
+        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
 
         Ok(())
     }
 
+    #[cfg(feature = "signed")]
     #[tokio::test]
-    async fn expiry_on_session_end_always_save_test() -> anyhow::Result<()> {
+    async fn signed_security_events_test() -> anyhow::Result<()> {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let security_events = SecurityEvents::new({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.kind)
+        });
+
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store.clone())
-            .with_expiry(Expiry::OnSessionEnd)
-            .with_always_save(true);
-        let mut svc = ServiceBuilder::new()
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_signed(Key::generate())
+            .with_security_events(security_events);
+        let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
-        let req1 = Request::builder().body(Body::empty())?;
-        let res1 = svc.call(req1).await?;
-        let sid1 = get_session_id(&res1);
-        let rec1 = get_record(&session_store, &sid1).await;
-        let req2 = Request::builder()
-            .header(http::header::COOKIE, format!("id={}", sid1))
+        let req = Request::builder()
+            .header(http::header::COOKIE, format!("id={}", Id::default()))
             .body(Body::empty())?;
-        let res2 = svc.call(req2).await?;
-        let sid2 = get_session_id(&res2);
-        let rec2 = get_record(&session_store, &sid2).await;
+        svc.oneshot(req).await?;
 
-        assert!(cookie_value_matches(&res2, |s| !s.contains("Max-Age")));
-        assert!(sid1 == sid2);
-        assert!(rec1.expiry_date < rec2.expiry_date);
+        assert_eq!(
+            *events.lock().unwrap(),
+            [SecurityEventKind::InvalidSignature]
+        );
 
         Ok(())
     }
 
+    #[cfg(feature = "private")]
     #[tokio::test]
-    async fn expiry_on_inactivity_always_save_test() -> anyhow::Result<()> {
+    async fn private_test() -> anyhow::Result<()> {
+        let key = Key::generate();
         let session_store = MemoryStore::default();
-        let inactivity_duration = time::Duration::hours(2);
-        let session_layer = SessionManagerLayer::new(session_store.clone())
-            .with_expiry(Expiry::OnInactivity(inactivity_duration))
-            .with_always_save(true);
-        let mut svc = ServiceBuilder::new()
+        let session_layer = SessionManagerLayer::new(session_store).with_private(key);
+        let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
-        let req1 = Request::builder().body(Body::empty())?;
-        let res1 = svc.call(req1).await?;
-        let sid1 = get_session_id(&res1);
-        let rec1 = get_record(&session_store, &sid1).await;
-        let req2 = Request::builder()
-            .header(http::header::COOKIE, format!("id={}", sid1))
-            .body(Body::empty())?;
-        let res2 = svc.call(req2).await?;
-        let sid2 = get_session_id(&res2);
-        let rec2 = get_record(&session_store, &sid2).await;
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
 
-        let expected_max_age = inactivity_duration.whole_seconds();
-        assert!(cookie_has_expected_max_age(&res2, expected_max_age));
-        assert!(sid1 == sid2);
-        assert!(rec1.expiry_date < rec2.expiry_date);
+        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
 
         Ok(())
     }
 
+    #[cfg(all(feature = "signed", feature = "private"))]
     #[tokio::test]
-    async fn expiry_at_date_time_always_save_test() -> anyhow::Result<()> {
+    async fn signed_and_private_test() -> anyhow::Result<()> {
+        use tower_cookies::cookie::CookieJar;
+
+        let signing_key = Key::generate();
+        let encryption_key = Key::generate();
         let session_store = MemoryStore::default();
-        let expiry_time = time::OffsetDateTime::now_utc() + time::Duration::weeks(1);
-        let session_layer = SessionManagerLayer::new(session_store.clone())
-            .with_expiry(Expiry::AtDateTime(expiry_time))
-            .with_always_save(true);
-        let mut svc = ServiceBuilder::new()
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_always_save(true)
+            .with_signed_and_private(signing_key.clone(), encryption_key.clone());
+        let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
-        let req1 = Request::builder().body(Body::empty())?;
-        let res1 = svc.call(req1).await?;
-        let sid1 = get_session_id(&res1);
-        let rec1 = get_record(&session_store, &sid1).await;
-        let req2 = Request::builder()
-            .header(http::header::COOKIE, format!("id={}", sid1))
-            .body(Body::empty())?;
-        let res2 = svc.call(req2).await?;
-        let sid2 = get_session_id(&res2);
-        let rec2 = get_record(&session_store, &sid2).await;
+        // Plain jars can decrypt and then verify the cookie.
+        let session_id = |res: &Response<Body>| {
+            let set_cookie = res.headers().get(http::header::SET_COOKIE)?;
+            let cookie = Cookie::parse(set_cookie.to_str().ok()?.to_string()).ok()?;
+            let mut jar = CookieJar::new();
+            jar.add_original(cookie);
+            assert!(jar.signed(&signing_key).get("id").is_none());
+            let signed = jar.private(&encryption_key).get("id")?;
+            let mut jar = CookieJar::new();
+            jar.add_original(signed);
+            let id = jar.signed(&signing_key).get("id")?;
+            id.value().parse::<session::Id>().ok()
+        };
 
-        let expected_max_age = (expiry_time - time::OffsetDateTime::now_utc()).whole_seconds();
-        assert!(cookie_has_expected_max_age(&res2, expected_max_age));
-        assert!(sid1 == sid2);
-        assert!(rec1.expiry_date == rec2.expiry_date);
+        let req = Request::builder().body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        let id = session_id(&res).unwrap();
+
+        // A cookie built by plain jars is accepted.
+        let mut jar = CookieJar::new();
+        jar.signed_mut(&signing_key)
+            .add(Cookie::new("id", id.to_string()));
+        let signed = jar.get("id").unwrap().clone();
+        jar.private_mut(&encryption_key).add(signed);
+        let req = Request::builder()
+            .header(http::header::COOKIE, jar.get("id").unwrap().to_string())
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert_eq!(session_id(&res), Some(id));
+
+        // Cookies protected by only one of the keys are rejected.
+        let mut jar = CookieJar::new();
+        jar.private_mut(&encryption_key)
+            .add(Cookie::new("private", id.to_string()));
+        jar.signed_mut(&signing_key)
+            .add(Cookie::new("signed", id.to_string()));
+        for name in ["private", "signed"] {
+            let value = jar.get(name).unwrap().value().to_string();
+            let req = Request::builder()
+                .header(http::header::COOKIE, format!("id={value}"))
+                .body(Body::empty())?;
+            let res = svc.clone().oneshot(req).await?;
+            assert_ne!(session_id(&res).unwrap(), id);
+        }
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn secure_test() -> anyhow::Result<()> {
+    async fn external_cookie_manager_test() -> anyhow::Result<()> {
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_secure(true);
+        let session_layer = SessionManagerLayer::new(session_store).with_external_cookie_manager();
         let svc = ServiceBuilder::new()
-            .layer(session_layer)
+            .layer(tower_cookies::CookieManagerLayer::new())
+            .layer(session_layer.clone())
             .service_fn(handler);
 
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
-
-        assert!(cookie_value_matches(&res, |s| s.contains("Secure")));
-
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_secure(false);
+        assert_eq!(
+            res.headers()
+                .get_all(http::header::SET_COOKIE)
+                .iter()
+                .count(),
+            1
+        );
+        let sid = get_session_id(&res);
+        assert!(sid.parse::<Id>().is_ok());
+
+        // Without the external cookie manager, requests fail.
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
-
         let req = Request::builder().body(Body::empty())?;
         let res = svc.oneshot(req).await?;
-
-        assert!(cookie_value_matches(&res, |s| !s.contains("Secure")));
+        assert_eq!(res.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(matches!(
+            res.extensions().get::<SessionLayerError>(),
+            Some(SessionLayerError::MissingCookies)
+        ));
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn path_test() -> anyhow::Result<()> {
+    async fn transfer_token_test() -> anyhow::Result<()> {
+        const KEY: &[u8] = b"key";
+
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_path("/foo/bar");
+        let session_layer =
+            SessionManagerLayer::new(session_store.clone()).with_transfer_token("transfer", KEY);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(handler);
+            .service_fn(noop_handler);
 
-        let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let session = Session::new(None, Arc::new(session_store.clone()), None);
+        session.insert("user_id", 42).await?;
+        let token = session
+            .create_transfer_token(KEY, time::Duration::minutes(5))
+            .await?;
+        let session_id = session.id().ok_or(anyhow!("Session was saved"))?;
+
+        let request = |token: &str| {
+            let req = Request::builder()
+                .uri(format!("/?lang=en&transfer={token}"))
+                .body(Body::empty())
+                .unwrap();
+            svc.clone().oneshot(req)
+        };
 
-        assert!(cookie_value_matches(&res, |s| s.contains("Path=/foo/bar")));
+        // The token is exchanged for a cookie even though the handler doesn't touch the
+        // session.
+        let res = request(&token).await?;
+        assert_eq!(get_session_id(&res), session_id.to_string());
+
+        // Tokens can only be exchanged once.
+        let res = request(&token).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        // Tokens signed with another key and expired tokens are rejected.
+        let forged = session
+            .create_transfer_token(b"other", time::Duration::minutes(5))
+            .await?;
+        let res = request(&forged).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        let expired = session
+            .create_transfer_token(KEY, time::Duration::minutes(-5))
+            .await?;
+        let res = request(&expired).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+
+        // Requests carrying a session keep it and leave the token unredeemed.
+        let token = session
+            .create_transfer_token(KEY, time::Duration::minutes(5))
+            .await?;
+        let req = Request::builder().body(Body::empty())?;
+        let res = ServiceBuilder::new()
+            .layer(SessionManagerLayer::new(session_store.clone()))
+            .service_fn(handler)
+            .oneshot(req)
+            .await?;
+        let own_session_id = get_session_id(&res);
+        let req = Request::builder()
+            .uri(format!("/?transfer={token}"))
+            .header(http::header::COOKIE, format!("id={own_session_id}"))
+            .body(Body::empty())?;
+        let res = svc.clone().oneshot(req).await?;
+        assert!(res.headers().get(http::header::SET_COOKIE).is_none());
+        let res = request(&token).await?;
+        assert_eq!(get_session_id(&res), session_id.to_string());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn domain_test() -> anyhow::Result<()> {
+    async fn creation_token_test() -> anyhow::Result<()> {
+        use crate::session_store::SessionStoreStats;
+
+        const TOKEN: &str = "a-random-client-token";
+
+        // Both requests load the session before either saves it.
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        let racing_handler = move |req: Request<Body>| {
+            let barrier = barrier.clone();
+            async move {
+                let res = handler(req).await;
+                barrier.wait().await;
+                res
+            }
+        };
+
         let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_domain("example.com");
+        let session_layer = SessionManagerLayer::new(session_store.clone())
+            .with_creation_token(HeaderName::from_static("x-session-token"), b"key");
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(handler);
+            .service_fn(racing_handler);
 
-        let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let request = |token: Option<&str>| {
+            let mut req = Request::builder();
+            if let Some(token) = token {
+                req = req.header("x-session-token", token);
+            }
+            svc.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
 
-        assert!(cookie_value_matches(&res, |s| s.contains("Domain=example.com")));
+        // Racing requests presenting the same token share a session.
+        let (first, second) = tokio::join!(request(Some(TOKEN)), request(Some(TOKEN)));
+        assert_eq!(get_session_id(&first?), get_session_id(&second?));
+        assert_eq!(session_store.active_count().await?, 1);
+
+        // Short tokens are ignored.
+        let (first, second) = tokio::join!(request(Some("short")), request(Some("short")));
+        assert_ne!(get_session_id(&first?), get_session_id(&second?));
+        assert_eq!(session_store.active_count().await?, 3);
+
+        // Without a token, racing requests each create a session.
+        let (first, second) = tokio::join!(request(None), request(None));
+        assert_ne!(get_session_id(&first?), get_session_id(&second?));
+        assert_eq!(session_store.active_count().await?, 5);
 
         Ok(())
     }
 
-    #[cfg(feature = "signed")]
     #[tokio::test]
-    async fn signed_test() -> anyhow::Result<()> {
-        let key = Key::generate();
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_signed(key);
+    async fn finalize_test() -> anyhow::Result<()> {
+        use tower_sessions_core::testing::{MockSessionStore, Operation};
+
+        async fn finalize_handler(req: Request<Body>) -> anyhow::Result<Response<Body>> {
+            let session = req
+                .extensions()
+                .get::<Session>()
+                .ok_or(anyhow!("Missing session"))?;
+
+            session.insert("foo", 42).await?;
+            session.finalize().await?;
+            if req.uri().path() == "/modify" {
+                session.insert("bar", 43).await?;
+            }
+
+            Ok(Response::new(Body::empty()))
+        }
+
+        let store = MockSessionStore::new();
+        let session_layer = SessionManagerLayer::new(store.clone()).with_always_save(true);
         let svc = ServiceBuilder::new()
             .layer(session_layer)
-            .service_fn(handler);
+            .service_fn(finalize_handler);
 
+        // The middleware doesn't save finalized sessions again.
         let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
+        let res = svc.clone().oneshot(req).await?;
+        let sid = get_session_id(&res);
+        store.assert_calls(Operation::Create, 1);
+        store.assert_calls(Operation::Save, 0);
 
-        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+        // Unless they're modified after being finalized.
+        let req = Request::builder()
+            .uri("/modify")
+            .header(http::header::COOKIE, format!("id={}", sid))
+            .body(Body::empty())?;
+        let res = svc.oneshot(req).await?;
+        assert_eq!(get_session_id(&res), sid);
+        store.assert_calls(Operation::Save, 2);
 
         Ok(())
     }
 
-    #[cfg(feature = "private")]
+    #[cfg(feature = "resilience")]
     #[tokio::test]
-    async fn private_test() -> anyhow::Result<()> {
-        let key = Key::generate();
-        let session_store = MemoryStore::default();
-        let session_layer = SessionManagerLayer::new(session_store).with_private(key);
+    async fn store_timeout_test() -> anyhow::Result<()> {
+        use tower_sessions_core::testing::MockSessionStore;
+
+        let store = MockSessionStore::new().with_latency(std::time::Duration::from_secs(10));
+        let session_layer = SessionManagerLayer::new(store)
+            .with_store_timeout(std::time::Duration::from_millis(20));
         let svc = ServiceBuilder::new()
             .layer(session_layer)
             .service_fn(handler);
 
-        let req = Request::builder().body(Body::empty())?;
-        let res = svc.oneshot(req).await?;
-
-        assert!(res.headers().get(http::header::SET_COOKIE).is_some());
+        // The handler's insertion loads the session, exceeding the deadline.
+        let req = Request::builder()
+            .header(
+                http::header::COOKIE,
+                format!("id={}", session::Id::default()),
+            )
+            .body(Body::empty())?;
+        let res = tokio::time::timeout(std::time::Duration::from_secs(1), svc.oneshot(req)).await?;
+        let err = res.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<session::Error>(),
+            Some(session::Error::Store(session_store::Error::Timeout(_)))
+        ));
 
         Ok(())
     }
@@ -0,0 +1,478 @@
+//! A conformance suite for [`SessionStore`] implementations.
+//!
+//! Store authors can verify their implementation upholds the semantics the
+//! session middleware relies on by invoking [`store_conformance_tests`] in
+//! their test suite:
+//!
+//! ```rust,ignore
+//! #[cfg(test)]
+//! mod tests {
+//!     use my_store::MyStore;
+//!
+//!     tower_sessions_test_kit::store_conformance_tests!(MyStore::new());
+//! }
+//! ```
+//!
+//! The store expression is evaluated once per test, so each test is given a
+//! fresh store. The generated tests use `#[tokio::test]`, so `tokio` must be
+//! available with the `rt` and `macros` features.
+//!
+//! Each check is also exposed as a standalone async function for stores that
+//! need custom test setup.
+//!
+//! With the `harness` feature, [`test_harness`] provides an in-process
+//! application for testing stores through the session middleware.
+#![warn(
+    clippy::all,
+    nonstandard_style,
+    future_incompatible,
+    missing_debug_implementations
+)]
+#![deny(missing_docs)]
+#![forbid(unsafe_code)]
+
+use std::sync::Arc;
+
+#[cfg(feature = "harness")]
+pub mod test_harness;
+
+use time::{Duration, OffsetDateTime};
+use tower_sessions_core::{
+    session::{Id, Record},
+    Session, SessionStore,
+};
+
+/// Generates the full conformance suite for the given store expression.
+#[macro_export]
+macro_rules! store_conformance_tests {
+    ($store:expr) => {
+        mod store_conformance {
+            use super::*;
+
+            #[tokio::test]
+            async fn create_then_load() {
+                $crate::create_then_load($store).await;
+            }
+
+            #[tokio::test]
+            async fn create_mitigates_id_collision() {
+                $crate::create_mitigates_id_collision($store).await;
+            }
+
+            #[tokio::test]
+            async fn save_overwrites() {
+                $crate::save_overwrites($store).await;
+            }
+
+            #[tokio::test]
+            async fn load_missing() {
+                $crate::load_missing($store).await;
+            }
+
+            #[tokio::test]
+            async fn delete_removes() {
+                $crate::delete_removes($store).await;
+            }
+
+            #[tokio::test]
+            async fn delete_missing() {
+                $crate::delete_missing($store).await;
+            }
+
+            #[tokio::test]
+            async fn expired_not_loaded() {
+                $crate::expired_not_loaded($store).await;
+            }
+
+            #[tokio::test]
+            async fn absolute_expired_not_loaded() {
+                $crate::absolute_expired_not_loaded($store).await;
+            }
+
+            #[tokio::test]
+            async fn expired_not_listed() {
+                $crate::expired_not_listed($store).await;
+            }
+
+            #[tokio::test]
+            async fn internal_not_listed() {
+                $crate::internal_not_listed($store).await;
+            }
+
+            #[tokio::test]
+            async fn expired_not_incremented() {
+                $crate::expired_not_incremented($store).await;
+            }
+
+            #[tokio::test]
+            async fn get_or_create() {
+                $crate::get_or_create($store).await;
+            }
+
+            #[tokio::test]
+            async fn cycle_id() {
+                $crate::cycle_id($store).await;
+            }
+
+            #[tokio::test]
+            async fn concurrent_create() {
+                $crate::concurrent_create($store).await;
+            }
+        }
+    };
+}
+
+fn record(expiry_date: OffsetDateTime) -> Record {
+    let mut record = Record {
+        id: Id::default(),
+        data: Default::default(),
+        expiry_date,
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    };
+    record
+        .data
+        .insert("foo".to_string(), serde_json::json!({ "bar": 42 }));
+    record
+}
+
+fn active_record() -> Record {
+    record(OffsetDateTime::now_utc() + Duration::minutes(30))
+}
+
+// Stores may persist expiry dates at a lower precision, so we allow some slack.
+fn assert_same_record(loaded: &Record, expected: &Record) {
+    assert_eq!(loaded.id, expected.id, "loaded record has a different id");
+    assert_eq!(
+        loaded.data, expected.data,
+        "loaded record has different data"
+    );
+    assert!(
+        (loaded.expiry_date - expected.expiry_date).abs() < Duration::seconds(1),
+        "loaded record has a different expiry date"
+    );
+}
+
+/// Records created via `create` can be loaded back.
+pub async fn create_then_load(store: impl SessionStore) {
+    let mut record = active_record();
+    store.create(&mut record).await.expect("create failed");
+
+    let loaded = store
+        .load(&record.id)
+        .await
+        .expect("load failed")
+        .expect("created record was not found");
+    assert_same_record(&loaded, &record);
+}
+
+/// Creating a record with an ID that's already in use assigns a new ID rather
+/// than overwriting the existing record.
+pub async fn create_mitigates_id_collision(store: impl SessionStore) {
+    let mut first = active_record();
+    store.create(&mut first).await.expect("create failed");
+
+    let mut second = active_record();
+    second.id = first.id;
+    second
+        .data
+        .insert("baz".to_string(), serde_json::json!("qux"));
+    store.create(&mut second).await.expect("create failed");
+
+    assert_ne!(first.id, second.id, "colliding id was not replaced");
+
+    let loaded = store
+        .load(&first.id)
+        .await
+        .expect("load failed")
+        .expect("original record was not found");
+    assert_same_record(&loaded, &first);
+}
+
+/// Saving a record replaces the previously stored state.
+pub async fn save_overwrites(store: impl SessionStore) {
+    let mut record = active_record();
+    store.create(&mut record).await.expect("create failed");
+
+    record.data.clear();
+    record.expiry_date = OffsetDateTime::now_utc() + Duration::hours(1);
+    store.save(&record).await.expect("save failed");
+
+    let loaded = store
+        .load(&record.id)
+        .await
+        .expect("load failed")
+        .expect("saved record was not found");
+    assert_same_record(&loaded, &record);
+}
+
+/// Loading an unknown ID yields `None`.
+pub async fn load_missing(store: impl SessionStore) {
+    let loaded = store.load(&Id::default()).await.expect("load failed");
+    assert!(loaded.is_none(), "unknown id was loaded");
+}
+
+/// Deleted records can no longer be loaded.
+pub async fn delete_removes(store: impl SessionStore) {
+    let mut record = active_record();
+    store.create(&mut record).await.expect("create failed");
+    store.delete(&record.id).await.expect("delete failed");
+
+    let loaded = store.load(&record.id).await.expect("load failed");
+    assert!(loaded.is_none(), "deleted record was loaded");
+}
+
+/// Deleting an unknown ID is not an error.
+pub async fn delete_missing(store: impl SessionStore) {
+    store
+        .delete(&Id::default())
+        .await
+        .expect("deleting an unknown id failed");
+}
+
+/// Records past their expiry date are not loaded.
+pub async fn expired_not_loaded(store: impl SessionStore) {
+    let mut record = record(OffsetDateTime::now_utc() - Duration::minutes(1));
+    store.create(&mut record).await.expect("create failed");
+
+    let loaded = store.load(&record.id).await.expect("load failed");
+    assert!(loaded.is_none(), "expired record was loaded");
+}
+
+/// Records past their absolute expiry are not loaded, even if their expiry
+/// date has not been reached.
+pub async fn absolute_expired_not_loaded(store: impl SessionStore) {
+    let mut record = active_record();
+    record.absolute_expiry = Some(OffsetDateTime::now_utc() - Duration::minutes(1));
+    store.create(&mut record).await.expect("create failed");
+
+    let loaded = store.load(&record.id).await.expect("load failed");
+    assert!(
+        loaded.is_none(),
+        "record past its absolute expiry was loaded"
+    );
+}
+
+/// Records past their expiry date are not listed as recent.
+pub async fn expired_not_listed(store: impl SessionStore) {
+    let mut record = record(OffsetDateTime::now_utc() - Duration::minutes(1));
+    store.create(&mut record).await.expect("create failed");
+
+    let recent = store
+        .list_recent(usize::MAX)
+        .await
+        .expect("list_recent failed");
+    assert!(
+        recent.iter().all(|recent| recent.id != record.id),
+        "expired record was listed"
+    );
+}
+
+/// Records which aren't sessions, such as those of transfer tokens, are not
+/// listed as recent.
+pub async fn internal_not_listed(store: impl SessionStore) {
+    let store = Arc::new(store);
+
+    let session = Session::new(None, store.clone(), None);
+    session.insert("foo", 42).await.expect("insert failed");
+    session
+        .create_transfer_token(b"key", Duration::minutes(5))
+        .await
+        .expect("create_transfer_token failed");
+
+    let recent = store
+        .list_recent(usize::MAX)
+        .await
+        .expect("list_recent failed");
+    assert!(
+        recent.iter().all(|recent| !recent.is_internal()),
+        "internal record was listed"
+    );
+}
+
+/// Values of records past their expiry date are not incremented.
+pub async fn expired_not_incremented(store: impl SessionStore) {
+    let mut record = record(OffsetDateTime::now_utc() - Duration::minutes(1));
+    store.create(&mut record).await.expect("create failed");
+
+    let value = store
+        .increment(&record.id, "counter", 1)
+        .await
+        .expect("increment failed");
+    assert_eq!(value, None, "expired record was incremented");
+}
+
+/// `get_or_create` saves missing or expired records under their ID and loads
+/// active ones otherwise.
+pub async fn get_or_create(store: impl SessionStore) {
+    let created = active_record();
+    let loaded = store
+        .get_or_create(&created)
+        .await
+        .expect("get_or_create failed");
+    assert!(loaded.is_none(), "missing record was loaded");
+
+    let mut other = active_record();
+    other.id = created.id;
+    other.data.clear();
+    let loaded = store
+        .get_or_create(&other)
+        .await
+        .expect("get_or_create failed")
+        .expect("existing record was not loaded");
+    assert_same_record(&loaded, &created);
+
+    let mut expired = record(OffsetDateTime::now_utc() - Duration::minutes(1));
+    store.create(&mut expired).await.expect("create failed");
+    let mut replacement = active_record();
+    replacement.id = expired.id;
+    let loaded = store
+        .get_or_create(&replacement)
+        .await
+        .expect("get_or_create failed");
+    assert!(loaded.is_none(), "expired record was loaded");
+
+    let loaded = store
+        .load(&replacement.id)
+        .await
+        .expect("load failed")
+        .expect("created record was not found");
+    assert_same_record(&loaded, &replacement);
+}
+
+/// Cycling a session's ID retains its data under a new ID and removes the old
+/// one.
+pub async fn cycle_id(store: impl SessionStore) {
+    let store = Arc::new(store);
+
+    let session = Session::new(None, store.clone(), None);
+    session.insert("foo", 42).await.expect("insert failed");
+    session.save().await.expect("save failed");
+    let old_id = session.id().expect("saved session has no id");
+
+    let session = Session::new(Some(old_id), store.clone(), None);
+    session.cycle_id().await.expect("cycle_id failed");
+    session.save().await.expect("save failed");
+    let new_id = session.id().expect("cycled session has no id");
+
+    assert_ne!(old_id, new_id, "id was not cycled");
+    assert!(
+        store.load(&old_id).await.expect("load failed").is_none(),
+        "old id was still loaded"
+    );
+
+    let session = Session::new(Some(new_id), store, None);
+    assert_eq!(
+        session.get::<usize>("foo").await.expect("get failed"),
+        Some(42),
+        "data was not retained"
+    );
+}
+
+/// Concurrently created records are all persisted with distinct IDs.
+pub async fn concurrent_create(store: impl SessionStore) {
+    const CONCURRENCY: usize = 32;
+
+    let creates = (0..CONCURRENCY).map(|_| async {
+        let mut record = active_record();
+        store.create(&mut record).await.expect("create failed");
+        record
+    });
+    let records = futures::future::join_all(creates).await;
+
+    let mut ids = records.iter().map(|record| record.id.0).collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), CONCURRENCY, "concurrent creates shared an id");
+
+    for record in &records {
+        let loaded = store
+            .load(&record.id)
+            .await
+            .expect("load failed")
+            .expect("concurrently created record was not found");
+        assert_same_record(&loaded, record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod memory_store {
+        use tower_sessions_memory_store::MemoryStore;
+
+        store_conformance_tests!(MemoryStore::default());
+    }
+
+    // A store ignoring expiry altogether passes once wrapped.
+    mod expiry_enforcing_store {
+        use std::{collections::HashMap, sync::Arc};
+
+        use async_trait::async_trait;
+        use tower_sessions_core::{
+            session::{Id, Record},
+            session_store::{self, ExpiryEnforcingStore},
+            SessionStore,
+        };
+
+        #[derive(Debug, Clone, Default)]
+        struct ExpiryIgnoringStore(Arc<std::sync::Mutex<HashMap<Id, Record>>>);
+
+        #[async_trait]
+        impl SessionStore for ExpiryIgnoringStore {
+            async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+                let mut records = self.0.lock().unwrap();
+                while records.contains_key(&record.id) {
+                    record.id = Id::default();
+                }
+                records.insert(record.id, record.clone());
+                Ok(())
+            }
+
+            async fn save(&self, record: &Record) -> session_store::Result<()> {
+                self.0.lock().unwrap().insert(record.id, record.clone());
+                Ok(())
+            }
+
+            async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+                Ok(self.0.lock().unwrap().get(session_id).cloned())
+            }
+
+            async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+                self.0.lock().unwrap().remove(session_id);
+                Ok(())
+            }
+
+            async fn increment(
+                &self,
+                session_id: &Id,
+                key: &str,
+                by: i64,
+            ) -> session_store::Result<Option<i64>> {
+                let mut records = self.0.lock().unwrap();
+                let Some(record) = records.get_mut(session_id) else {
+                    return Ok(None);
+                };
+                let value = record.data.get(key).and_then(|value| value.as_i64());
+                let value = value.unwrap_or_default().saturating_add(by);
+                record.data.insert(key.to_string(), value.into());
+                Ok(Some(value))
+            }
+
+            async fn list_recent(&self, limit: usize) -> session_store::Result<Vec<Record>> {
+                Ok(self
+                    .0
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .filter(|record| !record.is_internal())
+                    .take(limit)
+                    .cloned()
+                    .collect())
+            }
+        }
+
+        store_conformance_tests!(ExpiryEnforcingStore::new(ExpiryIgnoringStore::default()));
+    }
+}
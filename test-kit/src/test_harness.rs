@@ -0,0 +1,233 @@
+//! An in-process application for end-to-end tests of session stores.
+//!
+//! [`TestApp`] serves a fixed set of routes through [`SessionManagerLayer`]
+//! without binding a socket, so store crates can exercise their store via the
+//! middleware in ordinary `#[tokio::test]`s:
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! use tower_sessions::MemoryStore;
+//! use tower_sessions_test_kit::test_harness::TestApp;
+//!
+//! let app = TestApp::new(MemoryStore::default());
+//!
+//! let res = app.request("/insert").await;
+//! let cookie = res.session_cookie().unwrap();
+//!
+//! let res = app.request_with_cookie("/get", &cookie).await;
+//! assert_eq!(res.body(), "42");
+//! # });
+//! ```
+//!
+//! The routes are:
+//!
+//! - `/`: accesses the session without modifying it.
+//! - `/insert`: inserts `42` under the key `"foo"`.
+//! - `/get`: responds with the value of `"foo"`, or an empty body.
+//! - `/remove`: removes `"foo"`.
+//! - `/cycle_id`: cycles the session ID.
+//! - `/flush`: flushes the session.
+use axum::{routing::get, Router};
+use http::{header, HeaderMap, Request, StatusCode};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+use tower_sessions::{cookie::Cookie, Session, SessionManagerLayer, SessionStore};
+
+/// The key the test routes store their value under.
+pub const KEY: &str = "foo";
+
+/// An application serving the test routes through the session middleware.
+#[derive(Debug, Clone)]
+pub struct TestApp {
+    router: Router,
+}
+
+impl TestApp {
+    /// Create a new `TestApp` using the given store with the default layer
+    /// configuration, except that cookies aren't marked as secure.
+    pub fn new<Store: SessionStore + Clone>(store: Store) -> Self {
+        Self::with_layer(SessionManagerLayer::new(store).with_secure(false))
+    }
+
+    /// Create a new `TestApp` using the given, configured layer.
+    pub fn with_layer<Store: SessionStore + Clone>(layer: SessionManagerLayer<Store>) -> Self {
+        Self {
+            router: routes().layer(layer),
+        }
+    }
+
+    /// Sends a `GET` request for the given path without cookies.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the path isn't a valid URI.
+    pub async fn request(&self, path: &str) -> TestResponse {
+        self.send(path, None).await
+    }
+
+    /// Sends a `GET` request for the given path, carrying the given cookie.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the path isn't a valid URI.
+    pub async fn request_with_cookie(&self, path: &str, cookie: &Cookie<'_>) -> TestResponse {
+        self.send(path, Some(cookie.encoded().to_string())).await
+    }
+
+    async fn send(&self, path: &str, cookie: Option<String>) -> TestResponse {
+        let mut req = Request::builder().uri(path);
+        if let Some(cookie) = cookie {
+            req = req.header(header::COOKIE, cookie);
+        }
+        let req = req
+            .body(axum::body::Body::empty())
+            .expect("invalid request");
+
+        let res = self
+            .router
+            .clone()
+            .oneshot(req)
+            .await
+            .expect("routing is infallible");
+        let (parts, body) = res.into_parts();
+        let body = body
+            .collect()
+            .await
+            .expect("failed to read response body")
+            .to_bytes();
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        }
+    }
+}
+
+/// A response received from a [`TestApp`].
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl TestResponse {
+    /// Returns the status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the headers of the response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the body of the response.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Returns the cookie of the given name set by the response, if any.
+    pub fn cookie(&self, name: &str) -> Option<Cookie<'static>> {
+        self.headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| Cookie::parse_encoded(value.to_string()).ok())
+            .find(|cookie| cookie.name() == name)
+    }
+
+    /// Returns the session cookie set by the response, if any, assuming the
+    /// default cookie name.
+    pub fn session_cookie(&self) -> Option<Cookie<'static>> {
+        self.cookie("id")
+    }
+}
+
+fn routes() -> Router {
+    Router::new()
+        .route("/", get(|_: Session| async {}))
+        .route(
+            "/insert",
+            get(|session: Session| async move {
+                session.insert(KEY, 42).await.expect("insert failed");
+            }),
+        )
+        .route(
+            "/get",
+            get(|session: Session| async move {
+                session
+                    .get::<usize>(KEY)
+                    .await
+                    .expect("get failed")
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            }),
+        )
+        .route(
+            "/remove",
+            get(|session: Session| async move {
+                session.remove::<usize>(KEY).await.expect("remove failed");
+            }),
+        )
+        .route(
+            "/cycle_id",
+            get(|session: Session| async move {
+                session.cycle_id().await.expect("cycle_id failed");
+            }),
+        )
+        .route(
+            "/flush",
+            get(|session: Session| async move {
+                session.flush().await.expect("flush failed");
+            }),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_sessions_memory_store::MemoryStore;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let app = TestApp::new(MemoryStore::default());
+
+        let res = app.request("/").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.session_cookie().is_none());
+
+        let res = app.request("/insert").await;
+        let cookie = res.session_cookie().unwrap();
+        assert_eq!(app.request_with_cookie("/get", &cookie).await.body(), "42");
+        assert_eq!(app.request("/get").await.body(), "");
+
+        let res = app.request_with_cookie("/cycle_id", &cookie).await;
+        let cycled = res.session_cookie().unwrap();
+        assert_ne!(cycled.value(), cookie.value());
+        assert_eq!(app.request_with_cookie("/get", &cookie).await.body(), "");
+        assert_eq!(app.request_with_cookie("/get", &cycled).await.body(), "42");
+
+        let res = app.request_with_cookie("/flush", &cycled).await;
+        assert_eq!(res.session_cookie().unwrap().value(), "");
+        assert_eq!(app.request_with_cookie("/get", &cycled).await.body(), "");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sessions() {
+        let app = TestApp::new(MemoryStore::default());
+
+        let requests = (0..32).map(|_| app.request("/insert"));
+        let cookies = futures::future::join_all(requests)
+            .await
+            .iter()
+            .map(|res| res.session_cookie().unwrap())
+            .collect::<Vec<_>>();
+
+        for cookie in &cookies {
+            assert_eq!(app.request_with_cookie("/get", cookie).await.body(), "42");
+        }
+    }
+}
@@ -4,7 +4,7 @@ use http::{header, HeaderMap};
 use http_body_util::BodyExt;
 use time::{Duration, OffsetDateTime};
 use tower_cookies::{cookie, Cookie};
-use tower_sessions::{Expiry, Session, SessionManagerLayer, SessionStore};
+use tower_sessions::{extract::LoadedSession, Expiry, Session, SessionManagerLayer, SessionStore};
 
 fn routes() -> Router {
     Router::new()
@@ -21,6 +21,12 @@ fn routes() -> Router {
                 format!("{}", session.get::<usize>("foo").await.unwrap().unwrap())
             }),
         )
+        .route(
+            "/get_loaded",
+            get(|session: LoadedSession| async move {
+                format!("{}", session.get::<usize>("foo").await.unwrap().unwrap())
+            }),
+        )
         .route(
             "/get_value",
             get(|session: Session| async move {
@@ -224,6 +230,35 @@ macro_rules! route_tests {
             assert_eq!(body_string(res.into_body()).await, "42");
         }
 
+        #[tokio::test]
+        async fn get_loaded_session() {
+            let app = $create_app(Some(Duration::hours(1)), None).await;
+
+            let req = Request::builder()
+                .uri("/get_loaded")
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+            let req = Request::builder()
+                .uri("/insert")
+                .body(Body::empty())
+                .unwrap();
+            let res = app.clone().oneshot(req).await.unwrap();
+            let session_cookie = get_session_cookie(res.headers()).unwrap();
+
+            let req = Request::builder()
+                .uri("/get_loaded")
+                .header(header::COOKIE, session_cookie.encoded().to_string())
+                .body(Body::empty())
+                .unwrap();
+            let res = app.oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+
+            assert_eq!(body_string(res.into_body()).await, "42");
+        }
+
         #[tokio::test]
         async fn get_no_value() {
             let app = $create_app(Some(Duration::hours(1)), None).await;
@@ -16,3 +16,52 @@ mod memory_store_tests {
 
     route_tests!(app);
 }
+
+#[cfg(all(test, feature = "axum-core", feature = "memory-store"))]
+mod cross_subdomain_tests {
+    use axum::body::Body;
+    use http::{header, Request, StatusCode};
+    use tower::ServiceExt;
+    use tower_cookies::cookie::SameSite;
+    use tower_sessions::{MemoryStore, SessionManagerLayer};
+
+    use crate::common::{body_string, build_app, get_session_cookie};
+
+    #[tokio::test]
+    async fn shared_across_subdomains() {
+        let session_layer =
+            SessionManagerLayer::new(MemoryStore::default()).with_cross_subdomain("example.com");
+        let app = build_app(session_layer, None, None);
+
+        let req = Request::builder()
+            .uri("/insert")
+            .header(header::HOST, "app.example.com")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let session_cookie = get_session_cookie(res.headers()).unwrap();
+        assert_eq!(session_cookie.domain(), Some("example.com"));
+        assert_eq!(session_cookie.path(), Some("/"));
+        assert_eq!(session_cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(session_cookie.secure(), Some(true));
+
+        // The other subdomain sees the same session.
+        let req = Request::builder()
+            .uri("/get")
+            .header(header::HOST, "api.example.com")
+            .header(header::COOKIE, session_cookie.encoded().to_string())
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res.into_body()).await, "42");
+    }
+
+    #[test]
+    fn rejects_leading_dot() {
+        let session_layer = SessionManagerLayer::new(MemoryStore::default())
+            .with_cross_subdomain(".example.com")
+            .try_build();
+        assert!(session_layer.is_err());
+    }
+}
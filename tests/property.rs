@@ -0,0 +1,295 @@
+//! Randomized property tests for session ID parsing, cookie handling in the
+//! middleware and record serialization.
+//!
+//! These are gated behind the `property-tests` feature as they run many cases:
+//!
+//! ```sh
+//! cargo test --features property-tests --test property
+//! ```
+//!
+//! Set `PROPERTY_SEED` to replay a failing run and `PROPERTY_CASES` to change
+//! the number of cases per property.
+use std::{collections::HashMap, env};
+
+use axum::{routing::get, Router};
+use axum_core::body::Body;
+use http::{header, HeaderValue, Request, StatusCode};
+use http_body_util::BodyExt;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde_json::Value;
+use time::{OffsetDateTime, UtcOffset};
+use tower::ServiceExt;
+use tower_sessions::{
+    service::{CookieValueCodec, PlainIdCodec, SignedIdCodec},
+    session::{Id, Record},
+    MemoryStore, Session, SessionManagerLayer, SessionStore,
+};
+
+const BASE64_URL: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const SIGNING_KEY: &[u8] = b"property-test-signing-key";
+
+/// Source of cases for a property, reporting the seed if the property fails.
+struct Cases {
+    rng: StdRng,
+    seed: u64,
+    count: usize,
+}
+
+impl Cases {
+    fn new() -> Self {
+        let seed = env::var("PROPERTY_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(rand::random);
+        let count = env::var("PROPERTY_CASES")
+            .ok()
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(256);
+
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            count,
+        }
+    }
+}
+
+impl Drop for Cases {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            eprintln!("property failed, replay with PROPERTY_SEED={}", self.seed);
+        }
+    }
+}
+
+fn arbitrary_id(rng: &mut StdRng) -> Id {
+    Id(rng.gen())
+}
+
+fn arbitrary_string(rng: &mut StdRng) -> String {
+    // Bias lengths towards those of encoded and signed IDs.
+    let len = match rng.gen_range(0..4) {
+        0 => Id::ENCODED_LEN,
+        1 => Id::SIGNED_LEN,
+        _ => rng.gen_range(0..80),
+    };
+
+    match rng.gen_range(0..4) {
+        0 => (0..len)
+            .map(|_| char::from(*BASE64_URL.choose(rng).unwrap()))
+            .collect(),
+        1 => (0..len)
+            .map(|_| char::from(rng.gen_range(b' '..=b'~')))
+            .collect(),
+        2 => (0..len).map(|_| rng.gen::<char>()).collect(),
+        _ => {
+            let encoded = arbitrary_id(rng).to_string();
+            mutate(rng, &encoded)
+        }
+    }
+}
+
+// Replaces, inserts or removes a single character.
+fn mutate(rng: &mut StdRng, s: &str) -> String {
+    let mut chars = s.chars().collect::<Vec<_>>();
+    let index = rng.gen_range(0..=chars.len());
+    match rng.gen_range(0..3) {
+        0 if index < chars.len() => chars[index] = char::from(*BASE64_URL.choose(rng).unwrap()),
+        1 => chars.insert(index, rng.gen()),
+        _ if index < chars.len() => {
+            chars.remove(index);
+        }
+        _ => chars.push('='),
+    }
+    chars.into_iter().collect()
+}
+
+fn arbitrary_cookie_value(rng: &mut StdRng) -> String {
+    match rng.gen_range(0..4) {
+        0 => arbitrary_id(rng).to_string(),
+        1 => ["%", "%zz", "%E2%82", "\"", "\"\"", "=", "%00"]
+            .choose(rng)
+            .unwrap()
+            .to_string(),
+        _ => arbitrary_string(rng),
+    }
+    // Separators would split the value into further cookies.
+    .replace(';', "")
+}
+
+fn arbitrary_value(rng: &mut StdRng, depth: usize) -> Value {
+    let kinds = if depth == 0 { 6 } else { 8 };
+    match rng.gen_range(0..kinds) {
+        0 => Value::Null,
+        1 => rng.gen::<bool>().into(),
+        2 => rng.gen::<i64>().into(),
+        3 => rng.gen::<u64>().into(),
+        // Without its `float_roundtrip` feature, `serde_json` only parses short
+        // decimals exactly.
+        4 => (f64::from(rng.gen::<i16>()) / 8.0).into(),
+        5 => arbitrary_string(rng).into(),
+        6 => (0..rng.gen_range(0..4))
+            .map(|_| arbitrary_value(rng, depth - 1))
+            .collect(),
+        _ => (0..rng.gen_range(0..4))
+            .map(|_| (arbitrary_string(rng), arbitrary_value(rng, depth - 1)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+    }
+}
+
+fn arbitrary_datetime(rng: &mut StdRng) -> OffsetDateTime {
+    let nanos = rng.gen_range(0..7_258_118_400_000_000_000i128); // Until the year 2200.
+    let offset = UtcOffset::from_whole_seconds(rng.gen_range(-86_399..=86_399)).unwrap();
+    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .unwrap()
+        .to_offset(offset)
+}
+
+fn arbitrary_record(rng: &mut StdRng) -> Record {
+    Record {
+        id: arbitrary_id(rng),
+        data: (0..rng.gen_range(0..6))
+            .map(|_| (arbitrary_string(rng), arbitrary_value(rng, 3)))
+            .collect::<HashMap<_, _>>(),
+        expiry_date: arbitrary_datetime(rng),
+        client_fingerprint: rng.gen::<bool>().then(|| arbitrary_string(rng)),
+        version: rng.gen(),
+        absolute_expiry: rng.gen::<bool>().then(|| arbitrary_datetime(rng)),
+        validator_hash: rng.gen::<bool>().then(|| arbitrary_string(rng)),
+    }
+}
+
+#[test]
+fn id_from_str() {
+    let mut cases = Cases::new();
+    for _ in 0..cases.count {
+        let id = arbitrary_id(&mut cases.rng);
+        let encoded = id.to_string();
+        assert_eq!(encoded.len(), Id::ENCODED_LEN);
+        assert_eq!(encoded.parse::<Id>().unwrap(), id);
+
+        let s = arbitrary_string(&mut cases.rng);
+        if let Ok(id) = s.parse::<Id>() {
+            assert_eq!(
+                id.to_string().parse::<Id>().unwrap(),
+                id,
+                "parsed from {s:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn cookie_value_codecs() {
+    let codecs: [&dyn CookieValueCodec; 2] = [&PlainIdCodec, &SignedIdCodec::new(SIGNING_KEY)];
+
+    let mut cases = Cases::new();
+    for _ in 0..cases.count {
+        for codec in codecs {
+            let id = arbitrary_id(&mut cases.rng);
+            assert_eq!(codec.decode(&codec.encode(&id)).unwrap(), id, "{codec:?}");
+
+            let s = arbitrary_string(&mut cases.rng);
+            if let Ok(id) = codec.decode(&s) {
+                assert_eq!(codec.decode(&codec.encode(&id)).unwrap(), id, "{codec:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn record_round_trips() {
+    let mut cases = Cases::new();
+    for _ in 0..cases.count {
+        let record = arbitrary_record(&mut cases.rng);
+
+        let json = serde_json::to_vec(&record).unwrap();
+        assert_eq!(serde_json::from_slice::<Record>(&json).unwrap(), record);
+
+        let msgpack = rmp_serde::to_vec(&record).unwrap();
+        assert_eq!(rmp_serde::from_slice::<Record>(&msgpack).unwrap(), record);
+    }
+}
+
+#[tokio::test]
+async fn cookie_header_parsing() {
+    let store = MemoryStore::default();
+    let mut record = Record {
+        id: Id::default(),
+        data: HashMap::from([("foo".to_string(), 42.into())]),
+        expiry_date: OffsetDateTime::now_utc() + time::Duration::hours(1),
+        client_fingerprint: None,
+        version: 0,
+        absolute_expiry: None,
+        validator_hash: None,
+    };
+    store.create(&mut record).await.unwrap();
+    let session_cookie = format!("id={}", record.id);
+
+    let app = Router::new()
+        .route(
+            "/",
+            get(|session: Session| async move {
+                session
+                    .get::<usize>("foo")
+                    .await
+                    .unwrap()
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            }),
+        )
+        .layer(SessionManagerLayer::new(store).with_secure(false));
+
+    let mut cases = Cases::new();
+    for _ in 0..cases.count {
+        let rng = &mut cases.rng;
+
+        let mut pairs = (0..rng.gen_range(0..6))
+            .map(|_| {
+                let name = match rng.gen_range(0..4) {
+                    0 => "id".to_string(),
+                    _ => format!("x{}", rng.gen_range(0..8)),
+                };
+                format!("{name}={}", arbitrary_cookie_value(rng))
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..rng.gen_range(0..2) {
+            pairs.push(
+                ["", ";", "=", "id", " = ", "id=="]
+                    .choose(rng)
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        let has_other_id = pairs.iter().any(|pair| pair.trim_start().starts_with("id"));
+        let has_session = rng.gen::<bool>();
+        if has_session {
+            pairs.push(session_cookie.clone());
+        }
+        pairs.shuffle(rng);
+
+        // Spread the cookies over one or more `Cookie` headers.
+        let mut req = Request::builder().uri("/");
+        let chunk_size = rng.gen_range(1..=pairs.len().max(1));
+        for chunk in pairs.chunks(chunk_size) {
+            let value = chunk
+                .join("; ")
+                .replace(|c: char| c.is_control() && c != '\t', "");
+            req = req.header(header::COOKIE, HeaderValue::from_str(&value).unwrap());
+        }
+        let req = req.body(Body::empty()).unwrap();
+        let headers = format!("{:?}", req.headers());
+
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{headers}");
+
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        if has_session && !has_other_id {
+            assert_eq!(&body[..], b"42", "{headers}");
+        } else if !has_session {
+            assert!(body.is_empty(), "{headers}");
+        }
+    }
+}
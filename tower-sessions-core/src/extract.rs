@@ -1,18 +1,358 @@
-use axum_core::extract::FromRequestParts;
-use http::{request::Parts, StatusCode};
-
-use crate::session::Session;
-
-impl<S> FromRequestParts<S> for Session
-where
-    S: Sync + Send,
-{
-    type Rejection = (http::StatusCode, &'static str);
-
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        parts.extensions.get::<Session>().cloned().ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Can't extract session. Is `SessionManagerLayer` enabled?",
-        ))
+//! Framework integrations for extracting [`Session`] from requests.
+//!
+//! Each integration looks up the [`Session`] that `SessionManagerLayer`
+//! inserted into the request extensions. Frameworks other than `axum` must
+//! host the layer via their `tower` compatibility shims, while plain `hyper`
+//! and `tower` services use [`RequestSessionExt`].
+use std::{fmt, marker::PhantomData, ops::Deref};
+
+use http::{request::Parts, Extensions, Request};
+
+use crate::session::{self, Session};
+
+const MISSING_SESSION: &str = "Can't extract session. Is `SessionManagerLayer` enabled?";
+
+/// The request carries no session, as `SessionManagerLayer` isn't installed
+/// in front of the service handling it.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("{}", MISSING_SESSION)]
+pub struct MissingSession;
+
+/// Retrieves the [`Session`] of a request without a framework extractor, e.g.
+/// in plain `hyper` or `tower` services.
+///
+/// This is implemented for requests, their parts and their extensions.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// use std::convert::Infallible;
+///
+/// use http::{Request, Response};
+/// use tower::{ServiceBuilder, ServiceExt};
+/// use tower_sessions::{MemoryStore, RequestSessionExt, SessionManagerLayer};
+///
+/// async fn handler(req: Request<String>) -> Result<Response<String>, Infallible> {
+///     let session = req.session().expect("session layer is installed");
+///     let visits = session.get::<usize>("visits").await.unwrap().unwrap_or_default() + 1;
+///     session.insert("visits", visits).await.unwrap();
+///     Ok(Response::new(format!("{visits} visits")))
+/// }
+///
+/// let session_store = MemoryStore::default();
+/// let svc = ServiceBuilder::new()
+///     .layer(SessionManagerLayer::new(session_store))
+///     .service_fn(handler);
+///
+/// let res = svc.oneshot(Request::new(String::new())).await.unwrap();
+/// assert_eq!(res.into_body(), "1 visits");
+/// # });
+/// ```
+pub trait RequestSessionExt {
+    /// Returns the session of the request.
+    ///
+    /// The session is shared with other clones, so changes made through it
+    /// are saved by the middleware after the response.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`MissingSession`] when `SessionManagerLayer` isn't
+    /// installed.
+    fn session(&self) -> Result<Session, MissingSession>;
+}
+
+impl RequestSessionExt for Extensions {
+    fn session(&self) -> Result<Session, MissingSession> {
+        self.get::<Session>().cloned().ok_or(MissingSession)
+    }
+}
+
+impl RequestSessionExt for Parts {
+    fn session(&self) -> Result<Session, MissingSession> {
+        self.extensions.session()
+    }
+}
+
+impl<B> RequestSessionExt for Request<B> {
+    fn session(&self) -> Result<Session, MissingSession> {
+        self.extensions().session()
+    }
+}
+
+/// A session which exists in the store, loaded before the handler runs.
+///
+/// As an `axum` extractor, requests without an existing session are rejected
+/// with `R`, which defaults to [`Unauthorized`]. This spares authenticated
+/// routes from checking for the session themselves. The session is
+/// available through `Deref`.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// use axum::{
+///     body::Body,
+///     http::{Request, StatusCode},
+///     response::{IntoResponse, Redirect, Response},
+///     routing::get,
+///     Router,
+/// };
+/// use tower::ServiceExt;
+/// use tower_sessions::{extract::LoadedSession, MemoryStore, SessionManagerLayer};
+///
+/// async fn profile(session: LoadedSession) -> String {
+///     let name = session.get::<String>("name").await.unwrap();
+///     format!("Hello, {}!", name.unwrap_or_default())
+/// }
+///
+/// #[derive(Default)]
+/// struct ToLogin;
+///
+/// impl IntoResponse for ToLogin {
+///     fn into_response(self) -> Response {
+///         Redirect::to("/login").into_response()
+///     }
+/// }
+///
+/// async fn settings(session: LoadedSession<ToLogin>) -> String {
+///     format!("Settings of {:?}", session.id())
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/profile", get(profile))
+///     .route("/settings", get(settings))
+///     .layer(SessionManagerLayer::new(MemoryStore::default()));
+///
+/// let req = Request::get("/profile").body(Body::empty()).unwrap();
+/// let res = app.clone().oneshot(req).await.unwrap();
+/// assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+///
+/// let req = Request::get("/settings").body(Body::empty()).unwrap();
+/// let res = app.oneshot(req).await.unwrap();
+/// assert_eq!(res.status(), StatusCode::SEE_OTHER);
+/// # });
+/// ```
+pub struct LoadedSession<R = Unauthorized> {
+    session: Session,
+    _rejection: PhantomData<fn() -> R>,
+}
+
+impl<R> LoadedSession<R> {
+    /// Loads the session, returning `None` when it doesn't exist in the
+    /// store.
+    ///
+    /// # Errors
+    ///
+    /// Fails when loading the session from the store fails.
+    pub async fn load(session: Session) -> session::Result<Option<Self>> {
+        if session.is_new().await? {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            session,
+            _rejection: PhantomData,
+        }))
+    }
+
+    /// Returns the session.
+    pub fn into_inner(self) -> Session {
+        self.session
+    }
+}
+
+impl<R> Deref for LoadedSession<R> {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl<R> Clone for LoadedSession<R> {
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            _rejection: PhantomData,
+        }
+    }
+}
+
+impl<R> fmt::Debug for LoadedSession<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LoadedSession").field(&self.session).finish()
+    }
+}
+
+/// Rejects requests without an existing session with `401 Unauthorized`, see
+/// [`LoadedSession`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Unauthorized;
+
+#[cfg(feature = "axum-core")]
+mod with_axum {
+    use axum_core::{
+        extract::FromRequestParts,
+        response::{IntoResponse, Response},
+    };
+    use http::{request::Parts, StatusCode};
+
+    use super::{LoadedSession, Unauthorized, MISSING_SESSION};
+    use crate::session::Session;
+
+    impl<S> FromRequestParts<S> for Session
+    where
+        S: Sync + Send,
+    {
+        type Rejection = (http::StatusCode, &'static str);
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            _state: &S,
+        ) -> Result<Self, Self::Rejection> {
+            parts
+                .extensions
+                .get::<Session>()
+                .cloned()
+                .ok_or((StatusCode::INTERNAL_SERVER_ERROR, MISSING_SESSION))
+        }
+    }
+
+    impl<S, R> FromRequestParts<S> for LoadedSession<R>
+    where
+        S: Sync + Send,
+        R: Default + IntoResponse,
+    {
+        type Rejection = Response;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            let session = Session::from_request_parts(parts, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            match LoadedSession::load(session).await {
+                Ok(Some(session)) => Ok(session),
+                Ok(None) => Err(R::default().into_response()),
+                Err(err) => {
+                    tracing::error!(err = %err, "failed to load session");
+                    Err(
+                        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to load session")
+                            .into_response(),
+                    )
+                }
+            }
+        }
+    }
+
+    impl IntoResponse for Unauthorized {
+        fn into_response(self) -> Response {
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+    }
+}
+
+#[cfg(feature = "actix-web")]
+mod with_actix_web {
+    use std::future::{ready, Ready};
+
+    use actix_web::{
+        dev::Payload, error::ErrorInternalServerError, FromRequest, HttpMessage as _, HttpRequest,
+    };
+
+    use super::{Session, MISSING_SESSION};
+
+    impl FromRequest for Session {
+        type Error = actix_web::Error;
+        type Future = Ready<Result<Self, Self::Error>>;
+
+        fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+            ready(
+                req.extensions()
+                    .get::<Session>()
+                    .cloned()
+                    .ok_or_else(|| ErrorInternalServerError(MISSING_SESSION)),
+            )
+        }
+    }
+}
+
+#[cfg(feature = "poem")]
+mod with_poem {
+    use poem::{http::StatusCode, FromRequest, Request, RequestBody};
+
+    use super::{Session, MISSING_SESSION};
+
+    impl<'a> FromRequest<'a> for Session {
+        async fn from_request(req: &'a Request, _body: &mut RequestBody) -> poem::Result<Self> {
+            req.extensions().get::<Session>().cloned().ok_or_else(|| {
+                poem::Error::from_string(MISSING_SESSION, StatusCode::INTERNAL_SERVER_ERROR)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use mockall::mock;
+
+    use super::*;
+    use crate::{
+        session::{Id, Record},
+        session_store, SessionStore,
+    };
+
+    mock! {
+        #[derive(Debug)]
+        pub Store {}
+
+        #[async_trait]
+        impl SessionStore for Store {
+            async fn save(&self, record: &Record) -> session_store::Result<()>;
+            async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>>;
+            async fn delete(&self, session_id: &Id) -> session_store::Result<()>;
+        }
+    }
+
+    #[test]
+    fn test_request_session_ext() {
+        let mut req = Request::new(());
+        assert_eq!(req.session().unwrap_err(), MissingSession);
+
+        let session_id = Id::default();
+        let session = Session::new(Some(session_id), Arc::new(MockStore::new()), None);
+        req.extensions_mut().insert(session);
+        assert_eq!(req.session().unwrap().id(), Some(session_id));
+
+        let (parts, ()) = req.into_parts();
+        assert_eq!(parts.session().unwrap().id(), Some(session_id));
+        assert_eq!(parts.extensions.session().unwrap().id(), Some(session_id));
+    }
+
+    #[tokio::test]
+    async fn test_loaded_session() {
+        let mut store = MockStore::new();
+        let record = Record::new(time::OffsetDateTime::now_utc() + time::Duration::hours(1));
+        let session_id = record.id;
+        store
+            .expect_load()
+            .returning(move |id| Ok((*id == session_id).then(|| record.clone())));
+        let store = Arc::new(store);
+
+        let session = Session::new(Some(session_id), store.clone(), None);
+        let loaded = LoadedSession::<Unauthorized>::load(session).await.unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.id(), Some(session_id));
+        assert!(loaded.is_loaded().await);
+
+        let session = Session::new(Some(Id::default()), store.clone(), None);
+        let loaded = LoadedSession::<Unauthorized>::load(session).await.unwrap();
+        assert!(loaded.is_none());
+
+        let session = Session::new(None, store, None);
+        let loaded = LoadedSession::<Unauthorized>::load(session).await.unwrap();
+        assert!(loaded.is_none());
     }
 }
@@ -0,0 +1,241 @@
+//! One-shot flash messages stored in the session.
+//!
+//! Flash messages are pushed onto the session by one request and consumed by
+//! the next request that reads them, typically to report the outcome of a form
+//! submission after a redirect.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! use std::sync::Arc;
+//!
+//! use tower_sessions::{
+//!     flash::{Flashes, Level},
+//!     MemoryStore, Session,
+//! };
+//!
+//! let store = Arc::new(MemoryStore::default());
+//! let session = Session::new(None, store, None);
+//! let flashes = Flashes::new(session);
+//!
+//! flashes.success("Profile updated.").await.unwrap();
+//!
+//! let messages = flashes.take().await.unwrap();
+//! assert_eq!(messages.len(), 1);
+//! assert_eq!(messages[0].level, Level::Success);
+//!
+//! // Messages are removed once read.
+//! assert!(flashes.take().await.unwrap().is_empty());
+//! # });
+//! ```
+use serde::{Deserialize, Serialize};
+
+use crate::session::{self, Session};
+
+/// The severity of a flash message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// An informational message.
+    Info,
+
+    /// A message reporting that an operation succeeded.
+    Success,
+
+    /// A message reporting that an operation failed.
+    Error,
+}
+
+/// A single flash message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    /// The severity of the message.
+    pub level: Level,
+
+    /// The text of the message.
+    pub text: String,
+}
+
+/// Access to the flash messages of a session.
+///
+/// With the `axum-core` feature enabled this can be used as an extractor,
+/// provided `SessionManagerLayer` is installed.
+#[derive(Clone, Debug)]
+pub struct Flashes {
+    session: Session,
+}
+
+impl Flashes {
+    const FLASHES_KEY: &'static str = "__tower_sessions.flash";
+
+    /// Create flash message access for the given session.
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+
+    /// Push a message with the given level.
+    ///
+    /// Messages are kept in the order they were pushed until they're read.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the session can't be loaded from or written to.
+    pub async fn push(&self, level: Level, text: impl Into<String>) -> session::Result<()> {
+        let mut messages = self
+            .session
+            .get::<Vec<Message>>(Self::FLASHES_KEY)
+            .await?
+            .unwrap_or_default();
+        messages.push(Message {
+            level,
+            text: text.into(),
+        });
+        self.session.insert(Self::FLASHES_KEY, messages).await
+    }
+
+    /// Push an informational message.
+    ///
+    /// # Errors
+    ///
+    /// See [`Flashes::push`].
+    pub async fn info(&self, text: impl Into<String>) -> session::Result<()> {
+        self.push(Level::Info, text).await
+    }
+
+    /// Push a success message.
+    ///
+    /// # Errors
+    ///
+    /// See [`Flashes::push`].
+    pub async fn success(&self, text: impl Into<String>) -> session::Result<()> {
+        self.push(Level::Success, text).await
+    }
+
+    /// Push an error message.
+    ///
+    /// # Errors
+    ///
+    /// See [`Flashes::push`].
+    pub async fn error(&self, text: impl Into<String>) -> session::Result<()> {
+        self.push(Level::Error, text).await
+    }
+
+    /// Return the pending messages without removing them.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the session can't be loaded.
+    pub async fn peek(&self) -> session::Result<Vec<Message>> {
+        Ok(self
+            .session
+            .get::<Vec<Message>>(Self::FLASHES_KEY)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Remove and return the pending messages.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the session can't be loaded.
+    pub async fn take(&self) -> session::Result<Vec<Message>> {
+        Ok(self
+            .session
+            .remove::<Vec<Message>>(Self::FLASHES_KEY)
+            .await?
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(feature = "axum-core")]
+mod with_axum {
+    use axum_core::extract::FromRequestParts;
+    use http::request::Parts;
+
+    use super::Flashes;
+    use crate::session::Session;
+
+    impl<S> FromRequestParts<S> for Flashes
+    where
+        S: Sync + Send,
+    {
+        type Rejection = <Session as FromRequestParts<S>>::Rejection;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            Session::from_request_parts(parts, state)
+                .await
+                .map(Flashes::new)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use mockall::mock;
+
+    use super::*;
+    use crate::{
+        session::{Id, Record},
+        session_store, SessionStore,
+    };
+
+    mock! {
+        #[derive(Debug)]
+        pub Store {}
+
+        #[async_trait]
+        impl SessionStore for Store {
+            async fn save(&self, record: &Record) -> session_store::Result<()>;
+            async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>>;
+            async fn delete(&self, session_id: &Id) -> session_store::Result<()>;
+        }
+    }
+
+    fn flashes() -> Flashes {
+        Flashes::new(Session::new(None, Arc::new(MockStore::new()), None))
+    }
+
+    #[tokio::test]
+    async fn test_take_removes_messages() {
+        let flashes = flashes();
+        assert!(flashes.take().await.unwrap().is_empty());
+
+        flashes.info("first").await.unwrap();
+        flashes.error("second").await.unwrap();
+        flashes.success("third").await.unwrap();
+
+        let expected = vec![
+            Message {
+                level: Level::Info,
+                text: "first".to_string(),
+            },
+            Message {
+                level: Level::Error,
+                text: "second".to_string(),
+            },
+            Message {
+                level: Level::Success,
+                text: "third".to_string(),
+            },
+        ];
+        assert_eq!(flashes.peek().await.unwrap(), expected);
+        assert_eq!(flashes.take().await.unwrap(), expected);
+        assert!(flashes.take().await.unwrap().is_empty());
+        assert!(flashes.peek().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shares_session() {
+        let session = Session::new(None, Arc::new(MockStore::new()), None);
+        Flashes::new(session.clone()).info("hello").await.unwrap();
+
+        assert!(session.is_modified());
+        let messages = Flashes::new(session).take().await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "hello");
+    }
+}
@@ -1,11 +1,17 @@
 #[doc(inline)]
 pub use self::{
+    extract::RequestSessionExt,
     session::{Expiry, Session},
-    session_store::{CachingSessionStore, ExpiredDeletion, SessionStore},
+    session_store::{
+        CachingSessionStore, ExpiredDeletion, SessionStore, SessionStoreHealth, SessionStoreStats,
+        ShardedSessionStore,
+    },
 };
 
-#[cfg(feature = "axum-core")]
-#[cfg_attr(docsrs, doc(cfg(feature = "axum-core")))]
 pub mod extract;
+pub mod flash;
 pub mod session;
 pub mod session_store;
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
@@ -1,19 +1,26 @@
 //! A session which allows HTTP applications to associate data with visitors.
 use std::{
+    borrow::Cow,
     collections::HashMap,
     fmt::{self, Display},
-    hash::Hash,
+    future::Future,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    net::IpAddr,
     result,
     str::{self, FromStr},
     sync::{
-        atomic::{self, AtomicBool},
+        atomic::{self, AtomicBool, AtomicU64, AtomicUsize},
         Arc,
     },
 };
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, DecodeError, Engine as _};
+use hmac::{Hmac, Mac};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
 use time::{Duration, OffsetDateTime};
 use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 
@@ -21,7 +28,49 @@ use crate::{session_store, SessionStore};
 
 const DEFAULT_DURATION: Duration = Duration::weeks(2);
 
-type Result<T> = result::Result<T, Error>;
+static LOG_SESSION_CONTENT: AtomicBool = AtomicBool::new(false);
+
+/// Configures whether session data is included in `Debug` output, e.g. when a
+/// [`Session`] or [`Record`] is captured by a tracing event.
+/// The default value is `false`.
+///
+/// When disabled, data and client fingerprints are redacted so that no
+/// session payload ends up in logs. Session IDs are always shown hashed,
+/// regardless of this setting.
+///
+/// This is a process-wide setting meant to be enabled for local debugging.
+///
+/// # Examples
+///
+/// ```rust
+/// use time::OffsetDateTime;
+/// use tower_sessions::session::{self, Id, Record};
+///
+/// let mut record = Record {
+///     id: Id::default(),
+///     data: Default::default(),
+///     expiry_date: OffsetDateTime::now_utc(),
+///     client_fingerprint: None,
+///     version: 0,
+///     absolute_expiry: None,
+///     validator_hash: None,
+/// };
+/// record.data.insert("email".to_string(), "user@example.com".into());
+/// assert!(!format!("{record:?}").contains("user@example.com"));
+///
+/// session::log_session_content(true);
+/// assert!(format!("{record:?}").contains("user@example.com"));
+/// ```
+pub fn log_session_content(enabled: bool) {
+    LOG_SESSION_CONTENT.store(enabled, atomic::Ordering::Relaxed);
+}
+
+fn logs_session_content() -> bool {
+    LOG_SESSION_CONTENT.load(atomic::Ordering::Relaxed)
+}
+
+/// A specialized `Result` type for session operations.
+pub type Result<T> = result::Result<T, Error>;
 
 type Data = HashMap<String, Value>;
 
@@ -35,16 +84,47 @@ pub enum Error {
     /// Maps `session_store::Error` errors.
     #[error(transparent)]
     Store(#[from] session_store::Error),
+
+    /// Migrating session data from an older schema version failed.
+    #[error("Migration failed with: {0}")]
+    Migration(String),
+
+    /// The session was bound to a different client than the one presenting it
+    /// and the binding policy is [`BindingPolicy::Reject`].
+    #[error("Session is bound to a different client")]
+    ClientMismatch,
+
+    /// The session was presented after having been deleted and the tombstone
+    /// policy is [`TombstonePolicy::Reject`].
+    #[error("Session has been deleted")]
+    Tombstoned,
+
+    /// The record was rejected by the session's [`RecordValidator`] and not
+    /// saved.
+    #[error("Session record failed validation: {0}")]
+    Validation(#[from] ValidationError),
+
+    /// The record exceeded the size limit of the session's [`RecordSizes`]
+    /// and wasn't saved.
+    #[error("Session record of {size} bytes exceeds the limit of {limit} bytes")]
+    RecordTooLarge {
+        /// The size of the record, in bytes.
+        size: usize,
+
+        /// The configured limit, in bytes.
+        limit: usize,
+    },
 }
 
-#[derive(Debug)]
+// State of a session for the duration of a request, shared by its clones.
+//
+// Synchronous state is guarded by `parking_lot` locks, which are never held across await
+// points and don't poison. See: https://docs.rs/tokio/latest/tokio/sync/struct.Mutex.html#which-kind-of-mutex-should-you-use
 struct Inner {
     // This will be `None` when:
     //
     // 1. We have not been provided a session cookie or have failed to parse it,
     // 2. The store has not found the session.
-    //
-    // Sync lock, see: https://docs.rs/tokio/latest/tokio/sync/struct.Mutex.html#which-kind-of-mutex-should-you-use
     session_id: parking_lot::Mutex<Option<Id>>,
 
     // A lazy representation of the session's value, hydrated on a just-in-time basis. A
@@ -52,21 +132,84 @@ struct Inner {
     // contain `Some(Record)`.
     record: Mutex<Option<Record>>,
 
-    // Sync lock, see: https://docs.rs/tokio/latest/tokio/sync/struct.Mutex.html#which-kind-of-mutex-should-you-use
     expiry: parking_lot::Mutex<Option<Expiry>>,
 
+    // Set when the expiry was set explicitly during this request.
+    is_expiry_set: AtomicBool,
+
+    options: Arc<SessionOptions>,
+
+    // Validator of the session's split token, if split tokens are enabled.
+    split_token: parking_lot::Mutex<Option<SplitToken>>,
+
+    // Index of the next fallback ID to try.
+    next_fallback: AtomicUsize,
+
+    // Set until the transfer token, if any, was redeemed.
+    is_transfer_pending: AtomicBool,
+
+    // Type name of the store, used to identify the backend in traces.
+    store_name: &'static str,
+
+    // Absolute expiry of the current record, mirrored here so it's available to synchronous
+    // expiry accessors.
+    absolute_expiry: parking_lot::Mutex<Option<OffsetDateTime>>,
+
+    // Expiry date of the record as it was loaded from the store, before this request.
+    loaded_expiry_date: parking_lot::Mutex<Option<OffsetDateTime>>,
+
+    // Overrides the middleware's `always_save` setting for the current request.
+    always_save: parking_lot::Mutex<Option<bool>>,
+
     is_modified: AtomicBool,
+
+    // Set when the session was cleared before its record was loaded, so the data is cleared
+    // once it is.
+    is_cleared: AtomicBool,
+
+    // Set when the record was created during this request rather than loaded from the store.
+    is_new: AtomicBool,
+
+    // Set once the session was saved via `Session::finalize`.
+    is_finalized: AtomicBool,
+
+    // Set once the deadline was cleared, e.g. for saves after responding.
+    #[cfg(feature = "resilience")]
+    is_deadline_cleared: AtomicBool,
 }
 
 /// A session which allows HTTP applications to associate key-value pairs with
 /// visitors.
-#[derive(Debug, Clone)]
+///
+/// Clones of a session share the same state. The record is loaded from the
+/// store lazily and at most once, no matter how many clones access it, unless
+/// [`load`](Session::load) is called explicitly.
+#[derive(Clone)]
 pub struct Session {
     store: Arc<dyn SessionStore>,
     inner: Arc<Inner>,
 }
 
+impl fmt::Debug for Session {
+    // The store is identified by name only, as its state may contain other
+    // sessions, and the client fingerprint is omitted.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("store", &self.inner.store_name)
+            .field("session_id", &*self.inner.session_id.lock())
+            .field("record", &self.inner.record)
+            .field("expiry", &*self.inner.expiry.lock())
+            .field("is_modified", &self.is_modified())
+            .field("is_new", &self.inner.is_new.load(atomic::Ordering::Acquire))
+            .finish_non_exhaustive()
+    }
+}
+
 impl Session {
+    const TRANSFER_KEY: &'static str = "__tower_sessions.transfer";
+    const REDEEMED_KEY: &'static str = "__tower_sessions.transfer_redeemed";
+    const REFERENCE_KEY: &'static str = "__tower_sessions.reference";
+
     /// Creates a new session with the session ID, store, and expiry.
     ///
     /// This method is lazy and does not invoke the overhead of talking to the
@@ -82,16 +225,46 @@ impl Session {
     /// let store = Arc::new(MemoryStore::default());
     /// Session::new(None, store, None);
     /// ```
-    pub fn new(
+    pub fn new<Store: SessionStore>(
+        session_id: Option<Id>,
+        store: Arc<Store>,
+        expiry: Option<Expiry>,
+    ) -> Self {
+        Self::with_options(session_id, store, expiry, SessionOptions::default())
+    }
+
+    /// Creates a new session with the session ID, store, expiry and options.
+    ///
+    /// This is generally called by the middleware, with options reflecting
+    /// its configuration. See [`SessionOptions`] for examples.
+    pub fn with_options<Store: SessionStore>(
         session_id: Option<Id>,
-        store: Arc<impl SessionStore>,
+        store: Arc<Store>,
         expiry: Option<Expiry>,
+        options: impl Into<Arc<SessionOptions>>,
     ) -> Self {
+        let options = options.into();
         let inner = Inner {
             session_id: parking_lot::Mutex::new(session_id),
             record: Mutex::new(None), // `None` indicates we have not loaded from store.
             expiry: parking_lot::Mutex::new(expiry),
+            is_expiry_set: AtomicBool::new(false),
+            split_token: parking_lot::Mutex::new(
+                options.split_token.clone().map(SplitToken::Validator),
+            ),
+            next_fallback: AtomicUsize::new(0),
+            is_transfer_pending: AtomicBool::new(options.transfer_token.is_some()),
+            options,
+            store_name: std::any::type_name::<Store>(),
+            absolute_expiry: parking_lot::Mutex::new(None),
+            loaded_expiry_date: parking_lot::Mutex::new(None),
+            always_save: parking_lot::Mutex::new(None),
             is_modified: AtomicBool::new(false),
+            is_cleared: AtomicBool::new(false),
+            is_new: AtomicBool::new(false),
+            is_finalized: AtomicBool::new(false),
+            #[cfg(feature = "resilience")]
+            is_deadline_cleared: AtomicBool::new(false),
         };
 
         Self {
@@ -100,50 +273,16 @@ impl Session {
         }
     }
 
-    fn create_record(&self) -> Record {
-        Record::new(self.expiry_date())
-    }
-
-    #[tracing::instrument(skip(self), err)]
-    async fn get_record(&self) -> Result<MappedMutexGuard<Record>> {
-        let mut record_guard = self.inner.record.lock().await;
-
-        // Lazily load the record since `None` here indicates we have no yet loaded it.
-        if record_guard.is_none() {
-            tracing::trace!("record not loaded from store; loading");
-
-            let session_id = *self.inner.session_id.lock();
-            *record_guard = Some(if let Some(session_id) = session_id {
-                match self.store.load(&session_id).await? {
-                    Some(loaded_record) => {
-                        tracing::trace!("record found in store");
-                        loaded_record
-                    }
-
-                    None => {
-                        // A well-behaved user agent should not send session cookies after
-                        // expiration. Even so it's possible for an expired session to be removed
-                        // from the store after a request was initiated. However, such a race should
-                        // be relatively uncommon and as such entering this branch could indicate
-                        // malicious behavior.
-                        tracing::warn!("possibly suspicious activity: record not found in store");
-                        *self.inner.session_id.lock() = None;
-                        self.create_record()
-                    }
-                }
-            } else {
-                tracing::trace!("session id not found");
-                self.create_record()
-            })
-        }
-
-        Ok(MutexGuard::map(record_guard, |opt| {
-            opt.as_mut()
-                .expect("Record should always be `Option::Some` at this point")
-        }))
-    }
-
-    /// Inserts a `impl Serialize` value into the session.
+    /// Loads an existing session directly from the store, outside of a
+    /// request.
+    ///
+    /// This allows e.g. background jobs to update sessions. Unlike
+    /// [`Session::new`], `None` is returned when the session doesn't exist
+    /// rather than starting a new session, and the stored expiry date is kept
+    /// when the session is saved.
+    ///
+    /// Note that configuration otherwise applied by the middleware, such as a
+    /// data migrator, is not applied.
     ///
     /// # Examples
     ///
@@ -151,36 +290,61 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{MemoryStore, Session};
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
-    ///
+    /// let session = Session::new(None, store.clone(), None);
     /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let session_id = session.id().unwrap();
     ///
-    /// let value = session.get::<usize>("foo").await.unwrap();
-    /// assert_eq!(value, Some(42));
+    /// // E.g. within a background job.
+    /// let session = Session::from_store(store.clone(), session_id)
+    ///     .await
+    ///     .unwrap()
+    ///     .expect("Session exists");
+    /// session.insert("notification", true).await.unwrap();
+    /// session.save().await.unwrap();
+    /// assert_eq!(session.id(), Some(session_id));
+    ///
+    /// let missing = Session::from_store(store, Id::default()).await.unwrap();
+    /// assert!(missing.is_none());
     /// # });
     /// ```
     ///
     /// # Errors
     ///
-    /// - This method can fail when [`serde_json::to_value`] fails.
-    /// - If the session has not been hydrated and loading from the store fails,
-    ///   we fail with [`Error::Store`].
-    pub async fn insert(&self, key: &str, value: impl Serialize) -> Result<()> {
-        self.insert_value(key, serde_json::to_value(&value)?)
-            .await?;
-        Ok(())
+    /// - If loading from the store fails, we fail with [`Error::Store`].
+    pub async fn from_store<Store: SessionStore>(
+        store: Arc<Store>,
+        session_id: Id,
+    ) -> Result<Option<Self>> {
+        let Some(record) = store.load(&session_id).await? else {
+            return Ok(None);
+        };
+
+        let expiry = Expiry::AtDateTime(record.expiry_date);
+        let session = Self::new(Some(session_id), store, Some(expiry));
+        *session.inner.absolute_expiry.lock() = record.absolute_expiry;
+        *session.inner.loaded_expiry_date.lock() = Some(record.expiry_date);
+        *session.inner.record.lock().await = Some(record);
+
+        Ok(Some(session))
     }
 
-    /// Inserts a `serde_json::Value` into the session.
+    /// Loads the session with the given ID directly from the store, creating
+    /// it with the data returned by `f` if it doesn't exist.
     ///
-    /// If the key was not present in the underlying map, `None` is returned and
-    /// `modified` is set to `true`.
+    /// Loading and creating take a single store operation, see
+    /// [`SessionStore::get_or_create`], so concurrent callers agree on one
+    /// record. Because the record is created by the store, `f` is called
+    /// whether or not the session exists. Its value must serialize to a map,
+    /// whose entries become the session's keys.
     ///
-    /// If the underlying map did have the key and its value is the same as the
-    /// provided value, `None` is returned and `modified` is not set.
+    /// As with [`from_store`](Session::from_store), the stored expiry date is
+    /// kept when an existing session is saved; new sessions expire according
+    /// to `expiry`. The session ID is taken as given, so it must not come from
+    /// the client.
     ///
     /// # Examples
     ///
@@ -188,48 +352,102 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{MemoryStore, Session};
+    /// use serde::Serialize;
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
     ///
-    /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
+    /// #[derive(Default, Serialize)]
+    /// struct Job {
+    ///     attempts: usize,
+    /// }
     ///
-    /// let value = session
-    ///     .insert_value("foo", serde_json::json!(42))
-    ///     .await
-    ///     .unwrap();
-    /// assert!(value.is_none());
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session_id = Id::default();
     ///
-    /// let value = session
-    ///     .insert_value("foo", serde_json::json!(42))
+    /// let session = Session::load_or_create_with(store.clone(), session_id, None, Job::default)
     ///     .await
     ///     .unwrap();
-    /// assert!(value.is_none());
+    /// assert!(session.is_new().await.unwrap());
+    /// session.insert("attempts", 1).await.unwrap();
+    /// session.save().await.unwrap();
     ///
-    /// let value = session
-    ///     .insert_value("foo", serde_json::json!("bar"))
+    /// let session = Session::load_or_create_with(store, session_id, None, Job::default)
     ///     .await
     ///     .unwrap();
-    /// assert_eq!(value, Some(serde_json::json!(42)));
+    /// assert!(!session.is_new().await.unwrap());
+    /// assert_eq!(session.get::<usize>("attempts").await.unwrap(), Some(1));
     /// # });
     /// ```
     ///
     /// # Errors
     ///
-    /// - If the session has not been hydrated and loading from the store fails,
-    ///   we fail with [`Error::Store`].
-    pub async fn insert_value(&self, key: &str, value: Value) -> Result<Option<Value>> {
-        let mut record_guard = self.get_record().await?;
-        Ok(if record_guard.data.get(key) != Some(&value) {
-            self.inner
-                .is_modified
-                .store(true, atomic::Ordering::Release);
-            record_guard.data.insert(key.to_string(), value)
-        } else {
-            None
-        })
+    /// - This method can fail when [`serde_json::to_value`] fails or the value
+    ///   isn't a map.
+    /// - If loading from or creating in the store fails, we fail with
+    ///   [`Error::Store`].
+    pub async fn load_or_create_with<Store, T, F>(
+        store: Arc<Store>,
+        session_id: Id,
+        expiry: Option<Expiry>,
+        f: F,
+    ) -> Result<Self>
+    where
+        Store: SessionStore,
+        T: Serialize,
+        F: FnOnce() -> T,
+    {
+        let session = Self::new(Some(session_id), store.clone(), expiry);
+        let mut record = Record::new(session.expiry_date());
+        record.id = session_id;
+        record.data = serde_json::from_value(serde_json::to_value(f())?)?;
+
+        let record = match store.get_or_create(&record).await? {
+            Some(loaded_record) => {
+                *session.inner.expiry.lock() = Some(Expiry::AtDateTime(loaded_record.expiry_date));
+                *session.inner.absolute_expiry.lock() = loaded_record.absolute_expiry;
+                loaded_record
+            }
+
+            None => {
+                session.inner.is_new.store(true, atomic::Ordering::Release);
+                record
+            }
+        };
+        *session.inner.loaded_expiry_date.lock() = Some(record.expiry_date);
+        *session.inner.record.lock().await = Some(record);
+
+        Ok(session)
     }
 
-    /// Gets a value from the store.
+    /// Returns the validator of the session's split token, if split tokens
+    /// are enabled and the session has one.
+    ///
+    /// See [`SessionOptions::with_split_token`].
+    pub fn validator(&self) -> Option<String> {
+        match *self.inner.split_token.lock() {
+            Some(SplitToken::Validator(ref validator)) => validator.clone(),
+            _ => None,
+        }
+    }
+
+    // Replaces the validator of the record with a new one, if split tokens are enabled.
+    fn issue_validator(&self, record: &mut Record) {
+        let mut split_token = self.inner.split_token.lock();
+        if split_token.is_some() {
+            let validator = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+            record.validator_hash = Some(hash_validator(&validator));
+            *split_token = Some(SplitToken::Validator(Some(validator)));
+        }
+    }
+
+    /// Creates a token which transfers this session to another client, e.g.
+    /// a device which can't receive cookies from the page the session was
+    /// established on.
+    ///
+    /// The token is signed with the given key and may be passed in a URL or
+    /// form field. It can be redeemed once via
+    /// [`redeem_transfer_token`](Session::redeem_transfer_token) with the same
+    /// key, within `ttl`. The session ID itself is never part of the token.
+    /// Sessions which haven't been saved yet are saved first.
     ///
     /// # Examples
     ///
@@ -237,62 +455,195 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
+    /// use time::Duration;
     /// use tower_sessions::{MemoryStore, Session};
     ///
+    /// let key = b"a server-side secret key";
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("user_id", 42).await.unwrap();
+    /// let token = session
+    ///     .create_transfer_token(key, Duration::minutes(5))
+    ///     .await
+    ///     .unwrap();
     ///
-    /// session.insert("foo", 42).await.unwrap();
+    /// let device_session = Session::new(None, store.clone(), None);
+    /// assert!(device_session.redeem_transfer_token(&token, key).await.unwrap());
+    /// assert_eq!(device_session.id(), session.id());
+    /// assert_eq!(device_session.get::<i32>("user_id").await.unwrap(), Some(42));
     ///
-    /// let value = session.get::<usize>("foo").await.unwrap();
-    /// assert_eq!(value, Some(42));
+    /// // Tokens can only be redeemed once.
+    /// let other_session = Session::new(None, store, None);
+    /// assert!(!other_session.redeem_transfer_token(&token, key).await.unwrap());
     /// # });
     /// ```
     ///
     /// # Errors
     ///
-    /// - This method can fail when [`serde_json::from_value`] fails.
-    /// - If the session has not been hydrated and loading from the store fails,
-    ///   we fail with [`Error::Store`].
-    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
-        Ok(self
-            .get_value(key)
-            .await?
-            .map(serde_json::from_value)
-            .transpose()?)
+    /// - If saving the session or the transfer to the store fails, we fail
+    ///   with [`Error::Store`].
+    pub async fn create_transfer_token(&self, key: &[u8], ttl: Duration) -> Result<String> {
+        if self.id().is_none() {
+            self.save().await?;
+        }
+        let session_id = self
+            .id()
+            .ok_or_else(|| session_store::Error::Backend("Session has no ID".to_string()))?;
+
+        let nonce = Id::default();
+        let mut transfer = Record::new_internal(OffsetDateTime::now_utc() + ttl);
+        transfer.id = Id::derive("transfer-token", &nonce.to_string(), key);
+        transfer.data.insert(
+            Self::TRANSFER_KEY.to_string(),
+            session_id.to_string().into(),
+        );
+        self.within_deadline(self.store.save(&transfer)).await?;
+
+        Ok(nonce.to_signed_string(key))
     }
 
-    /// Gets a `serde_json::Value` from the store.
+    /// Switches to the session transferred by a token created with
+    /// [`create_transfer_token`](Session::create_transfer_token), returning
+    /// `false` if the token is invalid, expired or was already redeemed.
     ///
-    /// # Examples
-    ///
-    /// ```rust
-    /// # tokio_test::block_on(async {
-    /// use std::sync::Arc;
-    ///
-    /// use tower_sessions::{MemoryStore, Session};
+    /// The session is then marked as modified, so the middleware sets a
+    /// cookie for the transferred session. Redeeming is atomic with stores
+    /// supporting [`SessionStore::increment`], while with other stores
+    /// concurrent requests presenting the same token may both succeed.
     ///
-    /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
+    /// Any session the client already had is replaced. As a token may have
+    /// been planted by someone else, e.g. in a link, handlers should only
+    /// redeem tokens the client is expected to present.
     ///
-    /// session.insert("foo", 42).await.unwrap();
+    /// # Errors
     ///
-    /// let value = session.get_value("foo").await.unwrap().unwrap();
-    /// assert_eq!(value, serde_json::json!(42));
-    /// # });
-    /// ```
+    /// - If the store fails, we fail with [`Error::Store`].
+    pub async fn redeem_transfer_token(&self, token: &str, key: &[u8]) -> Result<bool> {
+        let mut record_guard = self.inner.record.lock().await;
+        let Some(session_id) = self.claim_transfer(token, key).await? else {
+            return Ok(false);
+        };
+
+        self.switch_to_transferred(session_id);
+        *record_guard = None;
+        self.inner
+            .is_cleared
+            .store(false, atomic::Ordering::Release);
+        Ok(true)
+    }
+
+    /// Redeems the transfer token configured via
+    /// [`SessionOptions::with_transfer_token`] right away, rather than when
+    /// the record is first needed, returning whether the session was switched.
     ///
     /// # Errors
     ///
-    /// - If the session has not been hydrated and loading from the store fails,
-    ///   we fail with [`Error::Store`].
-    pub async fn get_value(&self, key: &str) -> Result<Option<Value>> {
-        let record_guard = self.get_record().await?;
-        Ok(record_guard.data.get(key).cloned())
+    /// - If the store fails, we fail with [`Error::Store`].
+    pub async fn redeem_pending_transfer(&self) -> Result<bool> {
+        let mut record_guard = self.inner.record.lock().await;
+        let redeemed = self.apply_pending_transfer().await?;
+        if redeemed {
+            *record_guard = None;
+            self.inner
+                .is_cleared
+                .store(false, atomic::Ordering::Release);
+        }
+        Ok(redeemed)
     }
 
-    /// Removes a value from the store, retuning the value of the key if it was
-    /// present in the underlying map.
+    // Callers must hold the record lock.
+    async fn apply_pending_transfer(&self) -> Result<bool> {
+        if !self
+            .inner
+            .is_transfer_pending
+            .swap(false, atomic::Ordering::AcqRel)
+        {
+            return Ok(false);
+        }
+        let Some((ref token, ref key)) = self.inner.options.transfer_token else {
+            return Ok(false);
+        };
+        let Some(session_id) = self.claim_transfer(token, key).await? else {
+            return Ok(false);
+        };
+
+        self.switch_to_transferred(session_id);
+        Ok(true)
+    }
+
+    // Switches to the transferred session. Callers must hold the record lock.
+    fn switch_to_transferred(&self, session_id: Id) {
+        *self.inner.session_id.lock() = Some(session_id);
+        // The transferred session is new to the client, so it's issued a validator
+        // for this session only.
+        if let Some(ref mut split_token) = *self.inner.split_token.lock() {
+            *split_token = SplitToken::Reissue(session_id);
+        }
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+    }
+
+    // Returns the ID of the session transferred by the token, consuming the transfer.
+    async fn claim_transfer(&self, token: &str, key: &[u8]) -> Result<Option<Id>> {
+        let nonce = match Id::from_signed_str(token, key) {
+            Ok(nonce) => nonce,
+            Err(err) => {
+                tracing::warn!(err = %err, "possibly suspicious activity: invalid transfer token");
+                return Ok(None);
+            }
+        };
+        let transfer_id = Id::derive("transfer-token", &nonce.to_string(), key);
+
+        let Some(transfer) = self
+            .within_deadline(self.store.load(&transfer_id))
+            .await?
+            .filter(Record::is_active)
+        else {
+            return Ok(None);
+        };
+        let Some(session_id) = transfer
+            .data
+            .get(Self::TRANSFER_KEY)
+            .and_then(Value::as_str)
+            .and_then(|session_id| session_id.parse::<Id>().ok())
+        else {
+            return Ok(None);
+        };
+
+        // Only the first request to count the redemption may proceed.
+        let redemptions = self
+            .within_deadline(self.store.increment(&transfer_id, Self::REDEEMED_KEY, 1))
+            .await?;
+        match redemptions {
+            Some(1) => {}
+            Some(_) => return Ok(None),
+            None => {
+                // Either already redeemed or the store can't count atomically.
+                if self
+                    .within_deadline(self.store.load(&transfer_id))
+                    .await?
+                    .is_none()
+                {
+                    return Ok(None);
+                }
+            }
+        }
+        self.within_deadline(self.store.delete(&transfer_id))
+            .await?;
+
+        Ok(Some(session_id))
+    }
+
+    /// Creates a token referring to this session, allowing a background job
+    /// to update the session later via
+    /// [`from_reference_token`](Session::from_reference_token).
+    ///
+    /// The token is signed with the given key and expires after `ttl`. Unlike
+    /// transfer tokens, it may be redeemed any number of times until then,
+    /// e.g. by retried jobs. The session ID itself is never part of the token,
+    /// so tokens leaking from a job queue can't be used as session cookies.
+    /// Sessions which haven't been saved yet are saved first.
     ///
     /// # Examples
     ///
@@ -300,41 +651,448 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
+    /// use time::Duration;
     /// use tower_sessions::{MemoryStore, Session};
     ///
+    /// let key = b"a server-side secret key";
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
-    ///
-    /// session.insert("foo", 42).await.unwrap();
-    ///
-    /// let value: Option<usize> = session.remove("foo").await.unwrap();
-    /// assert_eq!(value, Some(42));
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("user_id", 42).await.unwrap();
+    /// let token = session
+    ///     .create_reference_token(key, Duration::hours(1))
+    ///     .await
+    ///     .unwrap();
     ///
-    /// let value: Option<usize> = session.get("foo").await.unwrap();
-    /// assert!(value.is_none());
+    /// // Within a worker sharing the store and key.
+    /// let job_session = Session::from_reference_token(store, &token, key)
+    ///     .await
+    ///     .unwrap()
+    ///     .expect("Session exists");
+    /// job_session.insert("report_ready", true).await.unwrap();
+    /// job_session.save().await.unwrap();
+    /// assert_eq!(job_session.id(), session.id());
     /// # });
     /// ```
     ///
     /// # Errors
     ///
-    /// - This method can fail when [`serde_json::from_value`] fails.
-    /// - If the session has not been hydrated and loading from the store fails,
-    ///   we fail with [`Error::Store`].
-    pub async fn remove<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
-        Ok(self
-            .remove_value(key)
-            .await?
-            .map(serde_json::from_value)
-            .transpose()?)
+    /// - If saving the session or the reference to the store fails, we fail
+    ///   with [`Error::Store`].
+    pub async fn create_reference_token(&self, key: &[u8], ttl: Duration) -> Result<String> {
+        if self.id().is_none() {
+            self.save().await?;
+        }
+        let session_id = self
+            .id()
+            .ok_or_else(|| session_store::Error::Backend("Session has no ID".to_string()))?;
+
+        let nonce = Id::default();
+        let mut reference = Record::new_internal(OffsetDateTime::now_utc() + ttl);
+        reference.id = Id::derive("reference-token", &nonce.to_string(), key);
+        reference.data.insert(
+            Self::REFERENCE_KEY.to_string(),
+            session_id.to_string().into(),
+        );
+        self.within_deadline(self.store.save(&reference)).await?;
+
+        Ok(nonce.to_signed_string(key))
     }
 
-    /// Removes a `serde_json::Value` from the session.
+    /// Loads the session referred to by a token created with
+    /// [`create_reference_token`](Session::create_reference_token), returning
+    /// `None` if the token is invalid or expired, or the session no longer
+    /// exists.
     ///
-    /// # Examples
+    /// As with [`from_store`](Session::from_store), the session's expiry is
+    /// preserved when it's saved. Saves replace the whole record, so changes
+    /// made concurrently by requests may be lost; with the `session-lock`
+    /// feature, [`lock`](Session::lock) can coordinate with them.
     ///
-    /// ```rust
-    /// # tokio_test::block_on(async {
-    /// use std::sync::Arc;
+    /// # Errors
+    ///
+    /// - If loading from the store fails, we fail with [`Error::Store`].
+    pub async fn from_reference_token<Store: SessionStore>(
+        store: Arc<Store>,
+        token: &str,
+        key: &[u8],
+    ) -> Result<Option<Self>> {
+        let nonce = match Id::from_signed_str(token, key) {
+            Ok(nonce) => nonce,
+            Err(err) => {
+                tracing::warn!(err = %err, "possibly suspicious activity: invalid reference token");
+                return Ok(None);
+            }
+        };
+        let reference_id = Id::derive("reference-token", &nonce.to_string(), key);
+
+        let Some(reference) = store.load(&reference_id).await?.filter(Record::is_active) else {
+            return Ok(None);
+        };
+        let Some(session_id) = reference
+            .data
+            .get(Self::REFERENCE_KEY)
+            .and_then(Value::as_str)
+            .and_then(|session_id| session_id.parse::<Id>().ok())
+        else {
+            return Ok(None);
+        };
+
+        Self::from_store(store, session_id).await
+    }
+
+    /// Returns the deadline by which store operations must complete, if any,
+    /// see [`SessionOptions::with_deadline`].
+    #[cfg(feature = "resilience")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+    pub fn deadline(&self) -> Option<tokio::time::Instant> {
+        if self
+            .inner
+            .is_deadline_cleared
+            .load(atomic::Ordering::Acquire)
+        {
+            return None;
+        }
+        self.inner.options.deadline
+    }
+
+    /// Clears the deadline by which store operations must complete, e.g.
+    /// for saving the session after the response was sent.
+    #[cfg(feature = "resilience")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+    pub fn clear_deadline(&self) {
+        self.inner
+            .is_deadline_cleared
+            .store(true, atomic::Ordering::Release);
+    }
+
+    // Bounds a store operation by the deadline, if any.
+    async fn within_deadline<T>(
+        &self,
+        op: impl Future<Output = session_store::Result<T>>,
+    ) -> session_store::Result<T> {
+        #[cfg(feature = "resilience")]
+        if let Some(deadline) = self.deadline() {
+            return tokio::time::timeout_at(deadline, op)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(session_store::Error::Timeout(
+                        "Store operation exceeded the request deadline".to_string(),
+                    ))
+                });
+        }
+
+        op.await
+    }
+
+    // Loads the record from the store, applying the tombstone policy when it isn't
+    // found.
+    #[cfg_attr(
+        feature = "opentelemetry",
+        tracing::instrument(
+            name = "session.load",
+            skip_all,
+            fields(
+                otel.kind = "client",
+                session.store = self.inner.store_name,
+                session.id_hash = %hash_id(session_id),
+            ),
+            err
+        )
+    )]
+    async fn load_record(&self, session_id: &Id) -> Result<Option<Record>> {
+        let loaded_record = self.within_deadline(self.store.load(session_id)).await?;
+
+        let tombstone_policy = self.inner.options.tombstone_policy;
+        if let (None, Some(tombstone_policy)) = (&loaded_record, tombstone_policy) {
+            if self
+                .within_deadline(self.store.is_tombstoned(session_id))
+                .await?
+            {
+                self.security_event(SecurityEventKind::Tombstoned, Some(session_id));
+                match tombstone_policy {
+                    TombstonePolicy::Flag => {
+                        tracing::warn!(
+                            "possibly suspicious activity: deleted session presented; starting a \
+                             new session"
+                        );
+                    }
+
+                    TombstonePolicy::Reject => {
+                        tracing::warn!(
+                            "possibly suspicious activity: deleted session presented; rejecting \
+                             session"
+                        );
+                        return Err(Error::Tombstoned);
+                    }
+                }
+            }
+        }
+
+        Ok(loaded_record)
+    }
+
+    // Loads the record of the session's ID, moving on to the fallback IDs in turn while no
+    // record is found.
+    async fn load_with_fallbacks(&self) -> Result<Option<Record>> {
+        loop {
+            let session_id = *self.inner.session_id.lock();
+            let Some(session_id) = session_id else {
+                return Ok(None);
+            };
+            let loaded_record = self.load_record(&session_id).await?;
+            if loaded_record.is_some() {
+                return Ok(loaded_record);
+            }
+
+            let next_fallback = self
+                .inner
+                .next_fallback
+                .fetch_add(1, atomic::Ordering::AcqRel);
+            let Some((fallback_id, validator)) =
+                self.inner.options.fallback_ids.get(next_fallback).cloned()
+            else {
+                return Ok(None);
+            };
+            tracing::debug!("record not found in store; trying fallback id");
+            *self.inner.session_id.lock() = Some(fallback_id);
+            let mut split_token = self.inner.split_token.lock();
+            if let Some(SplitToken::Validator(_)) = *split_token {
+                *split_token = Some(SplitToken::Validator(validator));
+            }
+        }
+    }
+
+    fn create_record(&self) -> Record {
+        let mut record = Record::new(self.expiry_date());
+        if let Some(creation_id) = self.inner.options.creation_id {
+            record.id = creation_id;
+        }
+        record.client_fingerprint = self
+            .inner
+            .options
+            .client_binding
+            .as_ref()
+            .map(|(fingerprint, _)| fingerprint.clone());
+        if let Some(ref data_migrator) = self.inner.options.data_migrator {
+            record.version = data_migrator.version();
+        }
+        if let Some(max_lifetime) = self.inner.options.max_lifetime {
+            let absolute_expiry = OffsetDateTime::now_utc().saturating_add(max_lifetime);
+            record.absolute_expiry = Some(absolute_expiry);
+            record.expiry_date = record.expiry_date.min(absolute_expiry);
+        }
+        *self.inner.absolute_expiry.lock() = record.absolute_expiry;
+        self.issue_validator(&mut record);
+        record
+    }
+
+    fn migrate_record(&self, record: &mut Record) -> Result<()> {
+        let data_migrator = self.inner.options.data_migrator.clone();
+        let Some(data_migrator) = data_migrator else {
+            return Ok(());
+        };
+
+        let version = data_migrator.version();
+        if record.version < version {
+            tracing::debug!(from = record.version, to = version, "migrating record");
+            data_migrator.migrate_record(record.version, &mut record.data)?;
+            record.version = version;
+            self.inner
+                .is_modified
+                .store(true, atomic::Ordering::Release);
+        } else if record.version > version {
+            tracing::warn!(
+                version = record.version,
+                current = version,
+                "record version is newer than the current version"
+            );
+        }
+
+        Ok(())
+    }
+
+    // Applies the client binding policy to a record loaded from the store,
+    // returning `None` when the record must be abandoned in favor of a new one.
+    fn check_client_binding(&self, mut record: Record) -> Result<Option<Record>> {
+        let client_binding = &self.inner.options.client_binding;
+        let Some((ref fingerprint, policy)) = *client_binding else {
+            return Ok(Some(record));
+        };
+
+        match record.client_fingerprint {
+            Some(ref stored) if stored != fingerprint => match policy {
+                BindingPolicy::Ignore => {
+                    tracing::warn!("possibly suspicious activity: client binding mismatch");
+                    Ok(Some(record))
+                }
+
+                BindingPolicy::CycleId => {
+                    tracing::warn!(
+                        "possibly suspicious activity: client binding mismatch; starting a new \
+                         session"
+                    );
+                    Ok(None)
+                }
+
+                BindingPolicy::Reject => {
+                    tracing::warn!(
+                        "possibly suspicious activity: client binding mismatch; rejecting session"
+                    );
+                    Err(Error::ClientMismatch)
+                }
+            },
+
+            Some(_) => Ok(Some(record)),
+
+            None => {
+                // Records that predate binding adopt the current client.
+                record.client_fingerprint = Some(fingerprint.clone());
+                Ok(Some(record))
+            }
+        }
+    }
+
+    // Checks the validator of the split token against a record loaded from the
+    // store, returning `None` when the record must be abandoned in favor of a
+    // new one.
+    fn check_validator(&self, mut record: Record) -> Option<Record> {
+        let split_token = self.inner.split_token.lock().clone();
+        match (split_token, record.validator_hash.as_deref()) {
+            (None, _) => return Some(record),
+
+            (Some(SplitToken::Validator(validator)), Some(validator_hash)) => {
+                let is_valid = validator.is_some_and(|validator| {
+                    hash_validator(&validator)
+                        .as_bytes()
+                        .ct_eq(validator_hash.as_bytes())
+                        .into()
+                });
+                if is_valid {
+                    return Some(record);
+                }
+            }
+
+            // Records that predate split tokens are only accepted while migrating.
+            (Some(SplitToken::Validator(_)), None) => {
+                let is_migrating = self
+                    .inner
+                    .options
+                    .split_token_migration
+                    .is_some_and(|until| OffsetDateTime::now_utc() < until);
+                if is_migrating {
+                    tracing::debug!("issuing a validator to a record without one");
+                    self.issue_validator(&mut record);
+                    self.inner
+                        .is_modified
+                        .store(true, atomic::Ordering::Release);
+                    return Some(record);
+                }
+            }
+
+            // The transferred record is new to the client, so it can't know its validator.
+            (Some(SplitToken::Reissue(transferred_id)), _) if record.id == transferred_id => {
+                self.issue_validator(&mut record);
+                return Some(record);
+            }
+
+            (Some(SplitToken::Reissue(_)), _) => {}
+        }
+
+        tracing::warn!(
+            "possibly suspicious activity: split token validator mismatch; starting a new session"
+        );
+        self.security_event(SecurityEventKind::ValidatorMismatch, Some(&record.id));
+        *self.inner.session_id.lock() = None;
+        None
+    }
+
+    // Turns the result of loading from the store into the record used for the
+    // remainder of the request, creating a new record when none could be
+    // loaded.
+    fn hydrate_record(&self, loaded_record: Option<Record>) -> Result<Record> {
+        let loaded_record = match loaded_record {
+            Some(loaded_record) => self
+                .check_client_binding(loaded_record)?
+                .and_then(|loaded_record| self.check_validator(loaded_record)),
+            None => None,
+        };
+        let session_id = *self.inner.session_id.lock();
+
+        let mut record = match loaded_record {
+            Some(mut loaded_record) => {
+                tracing::trace!("record found in store");
+                self.migrate_record(&mut loaded_record)?;
+                *self.inner.absolute_expiry.lock() = loaded_record.absolute_expiry;
+                *self.inner.loaded_expiry_date.lock() = Some(loaded_record.expiry_date);
+                loaded_record
+            }
+
+            None if session_id.is_some() && session_id == self.inner.options.creation_id => {
+                tracing::trace!("session not yet created");
+                *self.inner.session_id.lock() = None;
+                self.create_record()
+            }
+
+            None if session_id.is_some() => {
+                // A well-behaved user agent should not send session cookies after
+                // expiration. Even so it's possible for an expired session to be removed
+                // from the store after a request was initiated. However, such a race should
+                // be relatively uncommon and as such entering this branch could indicate
+                // malicious behavior.
+                tracing::warn!("possibly suspicious activity: record not found in store");
+                self.security_event(SecurityEventKind::UnknownId, session_id.as_ref());
+                *self.inner.session_id.lock() = None;
+                self.create_record()
+            }
+
+            None => {
+                tracing::trace!("session id not found");
+                self.create_record()
+            }
+        };
+
+        if self.inner.is_cleared.swap(false, atomic::Ordering::AcqRel) {
+            record.data.clear();
+        }
+
+        // Session ID is `None` only if we could not load a record and created one
+        // instead.
+        let is_new = self.inner.session_id.lock().is_none();
+        self.inner.is_new.store(is_new, atomic::Ordering::Release);
+
+        Ok(record)
+    }
+
+    #[tracing::instrument(skip(self), err)]
+    async fn get_record(&self) -> Result<MappedMutexGuard<'_, Record>> {
+        let mut record_guard = self.inner.record.lock().await;
+
+        // Lazily load the record since `None` here indicates we have no yet loaded it.
+        //
+        // Because all clones of a session share the same record, the store is only hit
+        // once per request no matter how many handles, e.g. extractors, access
+        // the session.
+        if record_guard.is_none() {
+            tracing::trace!("record not loaded from store; loading");
+
+            self.apply_pending_transfer().await?;
+            let loaded_record = self.load_with_fallbacks().await?;
+            *record_guard = Some(self.hydrate_record(loaded_record)?);
+        }
+
+        Ok(MutexGuard::map(record_guard, |opt| {
+            opt.as_mut()
+                .expect("Record should always be `Option::Some` at this point")
+        }))
+    }
+
+    /// Inserts a `impl Serialize` value into the session.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
     ///
     /// use tower_sessions::{MemoryStore, Session};
     ///
@@ -342,27 +1100,30 @@ impl Session {
     /// let session = Session::new(None, store, None);
     ///
     /// session.insert("foo", 42).await.unwrap();
-    /// let value = session.remove_value("foo").await.unwrap().unwrap();
-    /// assert_eq!(value, serde_json::json!(42));
     ///
-    /// let value: Option<usize> = session.get("foo").await.unwrap();
-    /// assert!(value.is_none());
+    /// let value = session.get::<usize>("foo").await.unwrap();
+    /// assert_eq!(value, Some(42));
     /// # });
     /// ```
     ///
     /// # Errors
     ///
+    /// - This method can fail when [`serde_json::to_value`] fails.
     /// - If the session has not been hydrated and loading from the store fails,
     ///   we fail with [`Error::Store`].
-    pub async fn remove_value(&self, key: &str) -> Result<Option<Value>> {
-        let mut record_guard = self.get_record().await?;
-        self.inner
-            .is_modified
-            .store(true, atomic::Ordering::Release);
-        Ok(record_guard.data.remove(key))
+    pub async fn insert<T: Serialize>(&self, key: impl AsKey<T>, value: T) -> Result<()> {
+        self.insert_value(key.as_key(), serde_json::to_value(&value)?)
+            .await?;
+        Ok(())
     }
 
-    /// Clears the session of all data but does not delete it from the store.
+    /// Inserts a `serde_json::Value` into the session.
+    ///
+    /// If the key was not present in the underlying map, `None` is returned and
+    /// `modified` is set to `true`.
+    ///
+    /// If the underlying map did have the key and its value is the same as the
+    /// provided value, `None` is returned and `modified` is not set.
     ///
     /// # Examples
     ///
@@ -373,46 +1134,78 @@ impl Session {
     /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
     ///
-    /// let session = Session::new(None, store.clone(), None);
-    /// session.insert("foo", 42).await.unwrap();
-    /// assert!(!session.is_empty().await);
+    /// let value = session
+    ///     .insert_value("foo", serde_json::json!(42))
+    ///     .await
+    ///     .unwrap();
+    /// assert!(value.is_none());
     ///
-    /// session.save().await.unwrap();
+    /// let value = session
+    ///     .insert_value("foo", serde_json::json!(42))
+    ///     .await
+    ///     .unwrap();
+    /// assert!(value.is_none());
     ///
-    /// session.clear().await;
+    /// let value = session
+    ///     .insert_value("foo", serde_json::json!("bar"))
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(value, Some(serde_json::json!(42)));
+    /// # });
+    /// ```
     ///
-    /// // Not empty! (We have an ID still.)
-    /// assert!(!session.is_empty().await);
-    /// // Data is cleared...
-    /// assert!(session.get::<usize>("foo").await.unwrap().is_none());
+    /// # Errors
     ///
-    /// // ...data is cleared before loading from the backend...
-    /// let session = Session::new(session.id(), store.clone(), None);
-    /// session.clear().await;
-    /// assert!(session.get::<usize>("foo").await.unwrap().is_none());
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn insert_value(&self, key: &str, value: Value) -> Result<Option<Value>> {
+        let mut record_guard = self.get_record().await?;
+        Ok(if record_guard.data.get(key) != Some(&value) {
+            self.inner
+                .is_modified
+                .store(true, atomic::Ordering::Release);
+            record_guard.data.insert(key.to_string(), value)
+        } else {
+            None
+        })
+    }
+
+    /// Gets a value from the store.
     ///
-    /// let session = Session::new(session.id(), store, None);
-    /// // ...but data is not deleted from the store.
-    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), Some(42));
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    ///
+    /// let value = session.get::<usize>("foo").await.unwrap();
+    /// assert_eq!(value, Some(42));
     /// # });
     /// ```
-    pub async fn clear(&self) {
-        let mut record_guard = self.inner.record.lock().await;
-        if let Some(record) = record_guard.as_mut() {
-            record.data.clear();
-        } else if let Some(session_id) = *self.inner.session_id.lock() {
-            let mut new_record = self.create_record();
-            new_record.id = session_id;
-            *record_guard = Some(new_record);
-        }
-
-        self.inner
-            .is_modified
-            .store(true, atomic::Ordering::Release);
+    ///
+    /// # Errors
+    ///
+    /// - This method can fail when [`serde_json::from_value`] fails.
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn get<T: DeserializeOwned>(&self, key: impl AsKey<T>) -> Result<Option<T>> {
+        Ok(self
+            .get_value(key.as_key())
+            .await?
+            .map(serde_json::from_value)
+            .transpose()?)
     }
 
-    /// Returns `true` if there is no session ID and the session is empty.
+    /// Gets a `serde_json::Value` from the store.
     ///
     /// # Examples
     ///
@@ -420,191 +1213,276 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
     ///
-    /// let session = Session::new(None, store.clone(), None);
-    /// // Empty if we have no ID and record is not loaded.
-    /// assert!(session.is_empty().await);
-    ///
-    /// let session = Session::new(Some(Id::default()), store.clone(), None);
-    /// // Not empty if we have an ID but no record. (Record is not loaded here.)
-    /// assert!(!session.is_empty().await);
-    ///
-    /// let session = Session::new(Some(Id::default()), store.clone(), None);
     /// session.insert("foo", 42).await.unwrap();
-    /// // Not empty after inserting.
-    /// assert!(!session.is_empty().await);
-    /// session.save().await.unwrap();
-    /// // Not empty after saving.
-    /// assert!(!session.is_empty().await);
     ///
-    /// let session = Session::new(session.id(), store.clone(), None);
-    /// session.load().await.unwrap();
-    /// // Not empty after loading from store...
-    /// assert!(!session.is_empty().await);
-    /// // ...and not empty after accessing the session.
-    /// session.get::<usize>("foo").await.unwrap();
-    /// assert!(!session.is_empty().await);
-    ///
-    /// let session = Session::new(session.id(), store.clone(), None);
-    /// session.delete().await.unwrap();
-    /// // Not empty after deleting from store...
-    /// assert!(!session.is_empty().await);
-    /// session.get::<usize>("foo").await.unwrap();
-    /// // ...but empty after trying to access the deleted session.
-    /// assert!(session.is_empty().await);
-    ///
-    /// let session = Session::new(None, store, None);
-    /// session.insert("foo", 42).await.unwrap();
-    /// session.flush().await.unwrap();
-    /// // Empty after flushing.
-    /// assert!(session.is_empty().await);
+    /// let value = session.get_value("foo").await.unwrap().unwrap();
+    /// assert_eq!(value, serde_json::json!(42));
     /// # });
     /// ```
-    pub async fn is_empty(&self) -> bool {
-        let record_guard = self.inner.record.lock().await;
-
-        // N.B.: Session IDs are `None` if:
-        //
-        // 1. The cookie was not provided or otherwise could not be parsed,
-        // 2. Or the session could not be loaded from the store.
-        let session_id = self.inner.session_id.lock();
-
-        let Some(record) = record_guard.as_ref() else {
-            return session_id.is_none();
-        };
-
-        session_id.is_none() && record.data.is_empty()
+    ///
+    /// # Errors
+    ///
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn get_value(&self, key: &str) -> Result<Option<Value>> {
+        let record_guard = self.get_record().await?;
+        Ok(record_guard.data.get(key).cloned())
     }
 
-    /// Get the session ID.
+    /// Gets a value from the session, inserting the value returned by `f` if
+    /// the key isn't present.
+    ///
+    /// The lookup and insertion happen under the session's lock, so concurrent
+    /// handlers of the same request see a single value.
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
     ///
-    /// let session = Session::new(None, store.clone(), None);
-    /// assert!(session.id().is_none());
+    /// let value = session.get_or_insert_with("foo", || 42).await.unwrap();
+    /// assert_eq!(value, 42);
     ///
-    /// let id = Some(Id::default());
-    /// let session = Session::new(id, store, None);
-    /// assert_eq!(id, session.id());
+    /// let value = session.get_or_insert_with("foo", || 43).await.unwrap();
+    /// assert_eq!(value, 42);
+    /// # });
     /// ```
-    pub fn id(&self) -> Option<Id> {
-        *self.inner.session_id.lock()
+    ///
+    /// # Errors
+    ///
+    /// - This method can fail when [`serde_json::to_value`] or
+    ///   [`serde_json::from_value`] fails.
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn get_or_insert_with<T, F>(&self, key: impl AsKey<T>, f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> T,
+    {
+        let key = key.as_key();
+        let mut record_guard = self.get_record().await?;
+        if let Some(value) = record_guard.data.get(key) {
+            return Ok(serde_json::from_value(value.clone())?);
+        }
+
+        let value = f();
+        record_guard
+            .data
+            .insert(key.to_string(), serde_json::to_value(&value)?);
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+        Ok(value)
     }
 
-    /// Get the session expiry.
+    /// Updates a value in the session in place, returning the updated value
+    /// if the key was present.
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
+    /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
     /// let session = Session::new(None, store, None);
     ///
-    /// assert_eq!(session.expiry(), None);
+    /// session.insert("cart", vec![1, 2]).await.unwrap();
+    ///
+    /// let cart = session
+    ///     .update("cart", |cart: &mut Vec<i32>| cart.push(3))
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(cart, Some(vec![1, 2, 3]));
+    ///
+    /// let missing = session
+    ///     .update("wishlist", |wishlist: &mut Vec<i32>| wishlist.push(3))
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(missing, None);
+    /// # });
     /// ```
-    pub fn expiry(&self) -> Option<Expiry> {
-        *self.inner.expiry.lock()
+    ///
+    /// # Errors
+    ///
+    /// - This method can fail when [`serde_json::to_value`] or
+    ///   [`serde_json::from_value`] fails.
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn update<T, F>(&self, key: impl AsKey<T>, f: F) -> Result<Option<T>>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&mut T),
+    {
+        let mut record_guard = self.get_record().await?;
+        let Some(value) = record_guard.data.get_mut(key.as_key()) else {
+            return Ok(None);
+        };
+
+        let mut typed = serde_json::from_value(value.clone())?;
+        f(&mut typed);
+        *value = serde_json::to_value(&typed)?;
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+        Ok(Some(typed))
     }
 
-    /// Set `expiry` to the given value.
+    /// Increments the integer stored under `key` by `by`, returning the new
+    /// value. Missing keys count from zero and the addition saturates.
     ///
-    /// This may be used within applications directly to alter the session's
-    /// time to live.
+    /// For sessions which exist in the store, the increment is applied by the
+    /// store when it supports atomic increments, see
+    /// [`SessionStore::increment`]. This avoids losing updates from
+    /// concurrent requests, which a `get` followed by an `insert` is prone to.
+    /// Because the store is already up to date, the session is then not
+    /// marked as modified. Otherwise, the value is incremented in the session
+    /// and saved with it as usual.
+    ///
+    /// Note that this only holds while nothing else in the request modifies
+    /// the session: saving a modified session writes the whole record,
+    /// overwriting increments made by concurrent requests in the meantime.
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use time::OffsetDateTime;
-    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
+    /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
-    ///
-    /// let expiry = Expiry::AtDateTime(OffsetDateTime::now_utc());
-    /// session.set_expiry(Some(expiry));
+    /// let session = Session::new(None, store.clone(), None);
+    /// assert_eq!(session.increment("visits", 1).await.unwrap(), 1);
+    /// session.save().await.unwrap();
     ///
-    /// assert_eq!(session.expiry(), Some(expiry));
+    /// let session = Session::new(session.id(), store, None);
+    /// assert_eq!(session.increment("visits", 2).await.unwrap(), 3);
+    /// assert!(!session.is_modified());
+    /// # });
     /// ```
-    pub fn set_expiry(&self, expiry: Option<Expiry>) {
-        *self.inner.expiry.lock() = expiry;
+    ///
+    /// # Errors
+    ///
+    /// - This method can fail when the existing value isn't an integer.
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   or the store fails to increment, we fail with [`Error::Store`].
+    pub async fn increment(&self, key: impl AsKey<i64>, by: i64) -> Result<i64> {
+        let key = key.as_key();
+        let mut record_guard = self.get_record().await?;
+
+        let session_id = *self.inner.session_id.lock();
+        if let Some(ref session_id) = session_id {
+            if let Some(value) = self
+                .within_deadline(self.store.increment(session_id, key, by))
+                .await?
+            {
+                record_guard.data.insert(key.to_string(), value.into());
+                return Ok(value);
+            }
+        }
+
+        let current = match record_guard.data.get(key) {
+            Some(value) => serde_json::from_value::<i64>(value.clone())?,
+            None => 0,
+        };
+        let value = current.saturating_add(by);
+        record_guard.data.insert(key.to_string(), value.into());
         self.inner
             .is_modified
             .store(true, atomic::Ordering::Release);
+        Ok(value)
     }
 
-    /// Get session expiry as `OffsetDateTime`.
+    /// Removes a value from the store, retuning the value of the key if it was
+    /// present in the underlying map.
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use time::{Duration, OffsetDateTime};
     /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
     /// let session = Session::new(None, store, None);
     ///
-    /// // Our default duration is two weeks.
-    /// let expected_expiry = OffsetDateTime::now_utc().saturating_add(Duration::weeks(2));
+    /// session.insert("foo", 42).await.unwrap();
     ///
-    /// assert!(session.expiry_date() > expected_expiry.saturating_sub(Duration::seconds(1)));
-    /// assert!(session.expiry_date() < expected_expiry.saturating_add(Duration::seconds(1)));
+    /// let value: Option<usize> = session.remove("foo").await.unwrap();
+    /// assert_eq!(value, Some(42));
+    ///
+    /// let value: Option<usize> = session.get("foo").await.unwrap();
+    /// assert!(value.is_none());
+    /// # });
     /// ```
-    pub fn expiry_date(&self) -> OffsetDateTime {
-        let expiry = self.inner.expiry.lock();
-        match *expiry {
-            Some(Expiry::OnInactivity(duration)) => {
-                OffsetDateTime::now_utc().saturating_add(duration)
-            }
-            Some(Expiry::AtDateTime(datetime)) => datetime,
-            Some(Expiry::OnSessionEnd) | None => {
-                OffsetDateTime::now_utc().saturating_add(DEFAULT_DURATION) // TODO: The default should probably be configurable.
-            }
-        }
+    ///
+    /// # Errors
+    ///
+    /// - This method can fail when [`serde_json::from_value`] fails.
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn remove<T: DeserializeOwned>(&self, key: impl AsKey<T>) -> Result<Option<T>> {
+        Ok(self
+            .remove_value(key.as_key())
+            .await?
+            .map(serde_json::from_value)
+            .transpose()?)
     }
 
-    /// Get session expiry as `Duration`.
+    /// Removes a `serde_json::Value` from the session.
     ///
     /// # Examples
     ///
     /// ```rust
+    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use time::Duration;
     /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
     /// let session = Session::new(None, store, None);
     ///
-    /// let expected_duration = Duration::weeks(2);
+    /// session.insert("foo", 42).await.unwrap();
+    /// let value = session.remove_value("foo").await.unwrap().unwrap();
+    /// assert_eq!(value, serde_json::json!(42));
     ///
-    /// assert!(session.expiry_age() > expected_duration.saturating_sub(Duration::seconds(1)));
-    /// assert!(session.expiry_age() < expected_duration.saturating_add(Duration::seconds(1)));
+    /// let value: Option<usize> = session.get("foo").await.unwrap();
+    /// assert!(value.is_none());
+    /// # });
     /// ```
-    pub fn expiry_age(&self) -> Duration {
-        std::cmp::max(
-            self.expiry_date() - OffsetDateTime::now_utc(),
-            Duration::ZERO,
-        )
+    ///
+    /// # Errors
+    ///
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn remove_value(&self, key: &str) -> Result<Option<Value>> {
+        let mut record_guard = self.get_record().await?;
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+        Ok(record_guard.data.remove(key))
     }
 
-    /// Returns `true` if the session has been modified during the request.
+    /// Clears the session of all data but does not delete it from the store.
+    ///
+    /// If the session hasn't been loaded yet, its data is cleared once it is.
+    /// The record is otherwise kept as is, so clearing doesn't reset its
+    /// absolute expiry or client binding.
     ///
     /// # Examples
     ///
@@ -615,29 +1493,51 @@ impl Session {
     /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store, None);
     ///
-    /// // Not modified initially.
-    /// assert!(!session.is_modified());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// assert!(!session.is_empty().await);
     ///
-    /// // Getting doesn't count as a modification.
-    /// session.get::<usize>("foo").await.unwrap();
-    /// assert!(!session.is_modified());
+    /// session.save().await.unwrap();
     ///
-    /// // Insertions and removals do though.
-    /// session.insert("foo", 42).await.unwrap();
-    /// assert!(session.is_modified());
+    /// session.clear().await;
+    ///
+    /// // Not empty! (We have an ID still.)
+    /// assert!(!session.is_empty().await);
+    /// // Data is cleared...
+    /// assert!(session.get::<usize>("foo").await.unwrap().is_none());
+    ///
+    /// // ...data is cleared before loading from the backend...
+    /// let session = Session::new(session.id(), store.clone(), None);
+    /// session.clear().await;
+    /// assert!(session.get::<usize>("foo").await.unwrap().is_none());
+    ///
+    /// let session = Session::new(session.id(), store, None);
+    /// // ...but data is not deleted from the store.
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), Some(42));
     /// # });
     /// ```
-    pub fn is_modified(&self) -> bool {
-        self.inner.is_modified.load(atomic::Ordering::Acquire)
+    pub async fn clear(&self) {
+        let mut record_guard = self.inner.record.lock().await;
+        if let Some(record) = record_guard.as_mut() {
+            record.data.clear();
+        } else if self.inner.session_id.lock().is_some() {
+            // The record is loaded as usual, keeping its expiry and binding, and cleared
+            // then.
+            self.inner.is_cleared.store(true, atomic::Ordering::Release);
+        }
+
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
     }
 
-    /// Saves the session record to the store.
+    /// Returns a view of the session whose keys are prefixed with the given
+    /// namespace.
     ///
-    /// Note that this method is generally not needed and is reserved for
-    /// situations where the session store must be updated during the
-    /// request.
+    /// This lets libraries keep their data in the application's session
+    /// without colliding with the keys of the application or other libraries.
+    /// A key `key` of the namespace `name` is stored as `name.key`.
     ///
     /// # Examples
     ///
@@ -648,45 +1548,75 @@ impl Session {
     /// use tower_sessions::{MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store.clone(), None);
+    /// let session = Session::new(None, store, None);
     ///
-    /// session.insert("foo", 42).await.unwrap();
-    /// session.save().await.unwrap();
+    /// session.insert("user", "app user").await.unwrap();
+    /// let auth = session.namespace("auth");
+    /// auth.insert("user", 42).await.unwrap();
     ///
-    /// let session = Session::new(session.id(), store, None);
-    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
+    /// assert_eq!(session.get::<String>("user").await.unwrap().unwrap(), "app user");
+    /// assert_eq!(auth.get::<i32>("user").await.unwrap(), Some(42));
+    /// assert_eq!(session.get::<i32>("auth.user").await.unwrap(), Some(42));
     /// # });
     /// ```
     ///
-    /// # Errors
-    ///
-    /// - If saving to the store fails, we fail with [`Error::Store`].
-    #[tracing::instrument(skip(self), err)]
-    pub async fn save(&self) -> Result<()> {
-        let mut record_guard = self.get_record().await?;
-        record_guard.expiry_date = self.expiry_date();
+    /// # Panics
+    ///
+    /// Panics if the namespace is empty or contains a `.`, which would make
+    /// keys of different namespaces ambiguous.
+    pub fn namespace(&self, name: impl Into<Cow<'static, str>>) -> Namespace {
+        let name = name.into();
+        assert!(
+            !name.is_empty() && !name.contains('.'),
+            "Session namespace `{name}` must be non-empty and not contain `.`"
+        );
+        Namespace {
+            session: self.clone(),
+            name,
+        }
+    }
 
-        // Session ID is `None` if:
-        //
-        //  1. No valid cookie was found on the request or,
-        //  2. No valid session was found in the store.
-        //
-        // In either case, we must create a new session via the store interface.
-        //
-        // Potential ID collisions must be handled by session store implementers.
-        if self.inner.session_id.lock().is_none() {
-            self.store.create(&mut record_guard).await?;
-            *self.inner.session_id.lock() = Some(record_guard.id);
-        } else {
-            self.store.save(&record_guard).await?;
+    /// Returns a typed handle to the value stored under `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// let visits = session.entry::<u32>("visits");
+    /// assert_eq!(visits.get_or_insert_with(|| 0).await.unwrap(), 0);
+    /// visits.update(|visits| *visits += 1).await.unwrap();
+    /// assert_eq!(visits.get().await.unwrap(), Some(1));
+    /// # });
+    /// ```
+    pub fn entry<T>(&self, key: impl AsKey<T>) -> Entry<T> {
+        Entry {
+            session: self.clone(),
+            key: key.as_key().to_string(),
+            _marker: PhantomData,
+        }
+    }
+
+    // Removes all keys starting with the given prefix.
+    async fn remove_prefixed(&self, prefix: &str) -> Result<()> {
+        let mut record_guard = self.get_record().await?;
+        let len = record_guard.data.len();
+        record_guard.data.retain(|key, _| !key.starts_with(prefix));
+        if record_guard.data.len() != len {
+            self.inner
+                .is_modified
+                .store(true, atomic::Ordering::Release);
         }
         Ok(())
     }
 
-    /// Loads the session record from the store.
-    ///
-    /// Note that this method is generally not needed and is reserved for
-    /// situations where the session must be updated during the request.
+    /// Returns `true` if there is no session ID and the session is empty.
     ///
     /// # Examples
     ///
@@ -697,114 +1627,146 @@ impl Session {
     /// use tower_sessions::{session::Id, MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let id = Some(Id::default());
-    /// let session = Session::new(id, store.clone(), None);
     ///
+    /// let session = Session::new(None, store.clone(), None);
+    /// // Empty if we have no ID and record is not loaded.
+    /// assert!(session.is_empty().await);
+    ///
+    /// let session = Session::new(Some(Id::default()), store.clone(), None);
+    /// // Not empty if we have an ID but no record. (Record is not loaded here.)
+    /// assert!(!session.is_empty().await);
+    ///
+    /// let session = Session::new(Some(Id::default()), store.clone(), None);
     /// session.insert("foo", 42).await.unwrap();
+    /// // Not empty after inserting.
+    /// assert!(!session.is_empty().await);
     /// session.save().await.unwrap();
+    /// // Not empty after saving.
+    /// assert!(!session.is_empty().await);
     ///
-    /// let session = Session::new(session.id(), store, None);
+    /// let session = Session::new(session.id(), store.clone(), None);
     /// session.load().await.unwrap();
+    /// // Not empty after loading from store...
+    /// assert!(!session.is_empty().await);
+    /// // ...and not empty after accessing the session.
+    /// session.get::<usize>("foo").await.unwrap();
+    /// assert!(!session.is_empty().await);
     ///
-    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
+    /// let session = Session::new(session.id(), store.clone(), None);
+    /// session.delete().await.unwrap();
+    /// // Not empty after deleting from store...
+    /// assert!(!session.is_empty().await);
+    /// session.get::<usize>("foo").await.unwrap();
+    /// // ...but empty after trying to access the deleted session.
+    /// assert!(session.is_empty().await);
+    ///
+    /// let session = Session::new(None, store, None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.flush().await.unwrap();
+    /// // Empty after flushing.
+    /// assert!(session.is_empty().await);
     /// # });
     /// ```
-    ///
-    /// # Errors
-    ///
-    /// - If loading from the store fails, we fail with [`Error::Store`].
-    #[tracing::instrument(skip(self), err)]
-    pub async fn load(&self) -> Result<()> {
-        let session_id = *self.inner.session_id.lock();
-        let Some(ref id) = session_id else {
-            tracing::warn!("called load with no session id");
-            return Ok(());
+    pub async fn is_empty(&self) -> bool {
+        let record_guard = self.inner.record.lock().await;
+
+        // N.B.: Session IDs are `None` if:
+        //
+        // 1. The cookie was not provided or otherwise could not be parsed,
+        // 2. Or the session could not be loaded from the store.
+        let session_id = self.inner.session_id.lock();
+
+        let Some(record) = record_guard.as_ref() else {
+            return session_id.is_none();
         };
-        let loaded_record = self.store.load(id).await.map_err(Error::Store)?;
-        let mut record_guard = self.inner.record.lock().await;
-        *record_guard = loaded_record;
-        Ok(())
+
+        session_id.is_none() && record.data.is_empty()
     }
 
-    /// Deletes the session from the store.
+    /// Get the session ID.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{session::Id, MemoryStore, Session, SessionStore};
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(Some(Id::default()), store.clone(), None);
-    ///
-    /// // Save before deleting.
-    /// session.save().await.unwrap();
     ///
-    /// // Delete from the store.
-    /// session.delete().await.unwrap();
+    /// let session = Session::new(None, store.clone(), None);
+    /// assert!(session.id().is_none());
     ///
-    /// assert!(store.load(&session.id().unwrap()).await.unwrap().is_none());
-    /// # });
+    /// let id = Some(Id::default());
+    /// let session = Session::new(id, store, None);
+    /// assert_eq!(id, session.id());
     /// ```
-    ///
-    /// # Errors
-    ///
-    /// - If deleting from the store fails, we fail with [`Error::Store`].
-    #[tracing::instrument(skip(self), err)]
-    pub async fn delete(&self) -> Result<()> {
-        let session_id = *self.inner.session_id.lock();
-        let Some(ref session_id) = session_id else {
-            tracing::warn!("called delete with no session id");
-            return Ok(());
-        };
-        self.store.delete(session_id).await.map_err(Error::Store)?;
-        Ok(())
+    pub fn id(&self) -> Option<Id> {
+        *self.inner.session_id.lock()
     }
 
-    /// Flushes the session by removing all data contained in the session and
-    /// then deleting it from the store.
+    // A non-reversible representation of the session ID suitable for traces.
+    #[cfg_attr(not(feature = "opentelemetry"), allow(dead_code))]
+    fn id_hash(&self) -> String {
+        self.id().as_ref().map(hash_id).unwrap_or_default()
+    }
+
+    /// Get the session expiry.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{MemoryStore, Session, SessionStore};
+    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store.clone(), None);
+    /// let session = Session::new(None, store, None);
     ///
-    /// session.insert("foo", "bar").await.unwrap();
-    /// session.save().await.unwrap();
+    /// assert_eq!(session.expiry(), None);
+    /// ```
+    pub fn expiry(&self) -> Option<Expiry> {
+        *self.inner.expiry.lock()
+    }
+
+    /// Set `expiry` to the given value.
     ///
-    /// let id = session.id().unwrap();
+    /// This may be used within applications directly to alter the session's
+    /// time to live.
     ///
-    /// session.flush().await.unwrap();
+    /// # Examples
     ///
-    /// assert!(session.id().is_none());
-    /// assert!(session.is_empty().await);
-    /// assert!(store.load(&id).await.unwrap().is_none());
-    /// # });
-    /// ```
+    /// ```rust
+    /// use std::sync::Arc;
     ///
-    /// # Errors
+    /// use time::OffsetDateTime;
+    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
     ///
-    /// - If deleting from the store fails, we fail with [`Error::Store`].
-    pub async fn flush(&self) -> Result<()> {
-        self.clear().await;
-        self.delete().await?;
-        *self.inner.session_id.lock() = None;
-        Ok(())
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// let expiry = Expiry::AtDateTime(OffsetDateTime::now_utc());
+    /// session.set_expiry(Some(expiry));
+    ///
+    /// assert_eq!(session.expiry(), Some(expiry));
+    /// ```
+    pub fn set_expiry(&self, expiry: Option<Expiry>) {
+        *self.inner.expiry.lock() = expiry;
+        self.inner
+            .is_expiry_set
+            .store(true, atomic::Ordering::Release);
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
     }
 
-    /// Cycles the session ID while retaining any data that was associated with
-    /// it.
+    /// Set `expiry` to when the given value expires, e.g. the claims of an
+    /// upstream identity provider's token, so that the session's record and
+    /// cookie expire exactly when the token does.
     ///
-    /// Using this method helps prevent session fixation attacks by ensuring a
-    /// new ID is assigned to the session.
+    /// The expiry is left unchanged when the value doesn't expire. Like
+    /// [`set_expiry`](Session::set_expiry), this takes precedence over a
+    /// function set via [`SessionOptions::with_expiry_fn`].
     ///
     /// # Examples
     ///
@@ -812,69 +1774,2104 @@ impl Session {
     /// # tokio_test::block_on(async {
     /// use std::sync::Arc;
     ///
-    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    /// use serde::{Deserialize, Serialize};
+    /// use time::OffsetDateTime;
+    /// use tower_sessions::{
+    ///     session::{Expires, Expiry},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Claims {
+    ///     sub: String,
+    ///     exp: i64,
+    /// }
+    ///
+    /// impl Expires for Claims {
+    ///     fn expires_at(&self) -> Option<OffsetDateTime> {
+    ///         OffsetDateTime::from_unix_timestamp(self.exp).ok()
+    ///     }
+    /// }
     ///
     /// let store = Arc::new(MemoryStore::default());
-    /// let session = Session::new(None, store.clone(), None);
+    /// let session = Session::new(None, store, None);
     ///
-    /// session.insert("foo", 42).await.unwrap();
-    /// session.save().await.unwrap();
-    /// let id = session.id();
+    /// let claims = Claims {
+    ///     sub: "alice".to_string(),
+    ///     exp: 2_000_000_000,
+    /// };
+    /// session.set_expiry_from(&claims);
+    /// session.insert("claims", claims).await.unwrap();
     ///
-    /// let session = Session::new(session.id(), store.clone(), None);
-    /// session.cycle_id().await.unwrap();
+    /// let expires_at = OffsetDateTime::from_unix_timestamp(2_000_000_000).unwrap();
+    /// assert_eq!(session.expiry(), Some(Expiry::AtDateTime(expires_at)));
+    /// assert_eq!(session.expiry_date(), expires_at);
+    /// # });
+    /// ```
+    pub fn set_expiry_from<T: Expires + ?Sized>(&self, value: &T) {
+        if let Some(expires_at) = value.expires_at() {
+            self.set_expiry(Some(Expiry::AtDateTime(expires_at)));
+        }
+    }
+
+    fn security_event(&self, kind: SecurityEventKind, session_id: Option<&Id>) {
+        if let Some((ref security_events, ref client)) = self.inner.options.security_events {
+            security_events.record(&SecurityEvent::new(kind, session_id, client.clone()));
+        }
+    }
+
+    fn validate_record(&self, record: &Record) -> Result<()> {
+        if let Some(ref record_validator) = self.inner.options.record_validator {
+            record_validator.validate(record)?;
+        }
+        match self.inner.options.record_sizes {
+            Some(ref record_sizes) => record_sizes.check(record),
+            None => Ok(()),
+        }
+    }
+
+    /// Switch the session between a persistent cookie, kept across browser
+    /// restarts, and a session cookie, removed when the browser session ends.
     ///
-    /// assert!(!session.is_empty().await);
-    /// assert!(session.is_modified());
+    /// This sets the expiry to [`Expiry::Never`] or [`Expiry::OnSessionEnd`]
+    /// respectively and the cookie is reissued accordingly at the end of the
+    /// request. Use [`set_expiry`](Session::set_expiry) directly for other
+    /// lifetimes, such as a thirty day "remember me" window.
     ///
-    /// session.save().await.unwrap();
+    /// As with any change in privilege, consider also calling
+    /// [`cycle_id`](Session::cycle_id) when a user opts into a persistent
+    /// session on login.
     ///
-    /// let session = Session::new(session.id(), store, None);
+    /// # Examples
     ///
-    /// assert_ne!(id, session.id());
-    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
-    /// # });
-    /// ```
+    /// ```rust
+    /// use std::sync::Arc;
     ///
-    /// # Errors
+    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
     ///
-    /// - If deleting from the store fails or saving to the store fails, we fail
-    ///   with [`Error::Store`].
-    pub async fn cycle_id(&self) -> Result<()> {
-        let mut record_guard = self.get_record().await?;
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, Some(Expiry::OnSessionEnd));
+    /// assert!(!session.is_persistent());
+    ///
+    /// session.set_persistent(true);
+    /// assert_eq!(session.expiry(), Some(Expiry::Never));
+    /// assert!(session.is_persistent());
+    ///
+    /// session.set_persistent(false);
+    /// assert_eq!(session.expiry(), Some(Expiry::OnSessionEnd));
+    /// ```
+    pub fn set_persistent(&self, persistent: bool) {
+        let expiry = if persistent {
+            Expiry::Never
+        } else {
+            Expiry::OnSessionEnd
+        };
+        self.set_expiry(Some(expiry));
+    }
 
-        let old_session_id = record_guard.id;
-        record_guard.id = Id::default();
-        *self.inner.session_id.lock() = None; // Setting `None` ensures `save` invokes the store's
-                                              // `create` method.
+    /// Returns `true` if the session's cookie outlives the browser session.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{session::Expiry, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    /// assert!(!session.is_persistent());
+    ///
+    /// session.set_expiry(Some(Expiry::OnInactivity(Duration::days(30))));
+    /// assert!(session.is_persistent());
+    /// ```
+    pub fn is_persistent(&self) -> bool {
+        !matches!(self.expiry(), Some(Expiry::OnSessionEnd) | None)
+    }
 
-        self.store
-            .delete(&old_session_id)
-            .await
-            .map_err(Error::Store)?;
+    /// Get session expiry as `OffsetDateTime`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// // Our default duration is two weeks.
+    /// let expected_expiry = OffsetDateTime::now_utc().saturating_add(Duration::weeks(2));
+    ///
+    /// assert!(session.expiry_date() > expected_expiry.saturating_sub(Duration::seconds(1)));
+    /// assert!(session.expiry_date() < expected_expiry.saturating_add(Duration::seconds(1)));
+    /// ```
+    pub fn expiry_date(&self) -> OffsetDateTime {
+        let expiry_date = self.relative_expiry_date();
+        match self.absolute_expiry() {
+            Some(absolute_expiry) => expiry_date.min(absolute_expiry),
+            None => expiry_date,
+        }
+    }
+
+    /// Get the absolute expiry of the session, if it has a maximum lifetime.
+    ///
+    /// This is only known once the record has been loaded or created.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{session::SessionOptions, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let options = SessionOptions::new().with_max_lifetime(Some(Duration::hours(8)));
+    /// let session = Session::with_options(None, store, None, options);
+    /// assert!(session.absolute_expiry().is_none());
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// assert!(session.absolute_expiry().is_some());
+    /// # });
+    /// ```
+    pub fn absolute_expiry(&self) -> Option<OffsetDateTime> {
+        *self.inner.absolute_expiry.lock()
+    }
+
+    /// Get the expiry date of the record as it was loaded from the store,
+    /// before any changes made during this request.
+    ///
+    /// This is `None` until a record has been loaded and for new sessions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// assert!(session.loaded_expiry_date().is_none());
+    ///
+    /// let session = Session::new(session.id(), store, None);
+    /// session.load().await.unwrap();
+    /// assert!(session.loaded_expiry_date().is_some());
+    /// # });
+    /// ```
+    pub fn loaded_expiry_date(&self) -> Option<OffsetDateTime> {
+        *self.inner.loaded_expiry_date.lock()
+    }
+
+    fn relative_expiry_date(&self) -> OffsetDateTime {
+        let expiry = self.inner.expiry.lock();
+        match *expiry {
+            Some(Expiry::OnInactivity(duration)) => {
+                OffsetDateTime::now_utc().saturating_add(duration)
+            }
+            Some(Expiry::AtDateTime(datetime)) => datetime,
+            Some(Expiry::Never) => OffsetDateTime::now_utc().saturating_add(Expiry::NEVER_DURATION),
+            Some(Expiry::OnSessionEnd) | None => {
+                OffsetDateTime::now_utc().saturating_add(DEFAULT_DURATION) // TODO: The default should probably be configurable.
+            }
+        }
+    }
+
+    /// Get session expiry as `Duration`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// let expected_duration = Duration::weeks(2);
+    ///
+    /// assert!(session.expiry_age() > expected_duration.saturating_sub(Duration::seconds(1)));
+    /// assert!(session.expiry_age() < expected_duration.saturating_add(Duration::seconds(1)));
+    /// ```
+    pub fn expiry_age(&self) -> Duration {
+        std::cmp::max(
+            self.expiry_date() - OffsetDateTime::now_utc(),
+            Duration::ZERO,
+        )
+    }
+
+    /// Returns `true` if the session has been modified during the request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// // Not modified initially.
+    /// assert!(!session.is_modified());
+    ///
+    /// // Getting doesn't count as a modification.
+    /// session.get::<usize>("foo").await.unwrap();
+    /// assert!(!session.is_modified());
+    ///
+    /// // Insertions and removals do though.
+    /// session.insert("foo", 42).await.unwrap();
+    /// assert!(session.is_modified());
+    /// # });
+    /// ```
+    pub fn is_modified(&self) -> bool {
+        self.inner.is_modified.load(atomic::Ordering::Acquire)
+    }
+
+    /// Saves the session ahead of the end of the request, unless it's empty.
+    ///
+    /// This is intended for streaming responses, such as server-sent events,
+    /// whose handlers return before the response completes and may hold on to
+    /// the session while streaming. Afterwards the session is no longer
+    /// considered modified, so the middleware only sets the session cookie
+    /// rather than saving the session again, unless it's modified further.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.finalize().await.unwrap();
+    /// assert!(session.is_finalized());
+    /// assert!(!session.is_modified());
+    /// assert!(session.id().is_some());
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If saving to the store fails, we fail with [`Error::Store`].
+    pub async fn finalize(&self) -> Result<()> {
+        if !self.is_empty().await {
+            self.save().await?;
+        }
+        self.inner
+            .is_modified
+            .store(false, atomic::Ordering::Release);
+        self.inner
+            .is_finalized
+            .store(true, atomic::Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns `true` if the session was saved via
+    /// [`finalize`](Session::finalize) during the current request.
+    pub fn is_finalized(&self) -> bool {
+        self.inner.is_finalized.load(atomic::Ordering::Acquire)
+    }
+
+    /// Overrides whether the session is saved at the end of the current
+    /// request even if it hasn't been modified.
+    ///
+    /// This takes precedence over `SessionManagerLayer::with_always_save`,
+    /// allowing e.g. expiry to be refreshed only on certain routes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// assert_eq!(session.always_save(), None);
+    /// session.set_always_save(true);
+    /// assert_eq!(session.always_save(), Some(true));
+    /// ```
+    pub fn set_always_save(&self, always_save: bool) {
+        *self.inner.always_save.lock() = Some(always_save);
+    }
+
+    /// Returns the override set via
+    /// [`set_always_save`](Session::set_always_save) for the current
+    /// request, if any.
+    pub fn always_save(&self) -> Option<bool> {
+        *self.inner.always_save.lock()
+    }
+
+    /// Returns `true` if the session was started during the current request
+    /// rather than loaded from the store.
+    ///
+    /// Because sessions are loaded lazily, this will load the record from the
+    /// store if that has not happened yet.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    ///
+    /// let session = Session::new(None, store.clone(), None);
+    /// // New when there's no ID.
+    /// assert!(session.is_new().await.unwrap());
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// // Still new after saving since it was created during this request.
+    /// assert!(session.is_new().await.unwrap());
+    ///
+    /// let session = Session::new(session.id(), store.clone(), None);
+    /// // Not new when loaded from the store.
+    /// assert!(!session.is_new().await.unwrap());
+    ///
+    /// let session = Session::new(Some(Id::default()), store, None);
+    /// // New when the ID is not found in the store.
+    /// assert!(session.is_new().await.unwrap());
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn is_new(&self) -> Result<bool> {
+        let _record_guard = self.get_record().await?;
+        Ok(self.inner.is_new.load(atomic::Ordering::Acquire))
+    }
+
+    /// Returns `true` if the session record has been loaded from the store or
+    /// otherwise initialized during the current request.
+    ///
+    /// Unlike the other accessors, this never talks to the store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store, None);
+    ///
+    /// // Not loaded initially.
+    /// assert!(!session.is_loaded().await);
+    ///
+    /// // Any access will load the record.
+    /// session.get::<usize>("foo").await.unwrap();
+    /// assert!(session.is_loaded().await);
+    /// # });
+    /// ```
+    pub async fn is_loaded(&self) -> bool {
+        self.inner.record.lock().await.is_some()
+    }
+
+    /// Saves the session record to the store.
+    ///
+    /// Note that this method is generally not needed and is reserved for
+    /// situations where the session store must be updated during the
+    /// request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let session = Session::new(session.id(), store, None);
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If saving to the store fails, we fail with [`Error::Store`].
+    #[cfg_attr(not(feature = "opentelemetry"), tracing::instrument(skip(self), err))]
+    #[cfg_attr(
+        feature = "opentelemetry",
+        tracing::instrument(
+            name = "session.save",
+            skip_all,
+            fields(
+                otel.kind = "client",
+                session.store = self.inner.store_name,
+                session.id_hash = %self.id_hash(),
+            ),
+            err
+        )
+    )]
+    pub async fn save(&self) -> Result<()> {
+        let mut record_guard = self.get_record().await?;
+        self.update_expiry_date(&mut record_guard);
+        self.validate_record(&record_guard)?;
+
+        // Session ID is `None` if:
+        //
+        //  1. No valid cookie was found on the request or,
+        //  2. No valid session was found in the store.
+        //
+        // In either case, we must create a new session via the store interface.
+        //
+        // Potential ID collisions must be handled by session store implementers,
+        // except for creation IDs which are meant to collide.
+        if self.inner.session_id.lock().is_none() {
+            if self.inner.options.creation_id == Some(record_guard.id) {
+                self.within_deadline(self.store.save(&record_guard)).await?;
+            } else {
+                self.within_deadline(self.store.create(&mut record_guard))
+                    .await?;
+            }
+            *self.inner.session_id.lock() = Some(record_guard.id);
+        } else {
+            self.within_deadline(self.store.save(&record_guard)).await?;
+        }
+        Ok(())
+    }
+
+    fn update_expiry_date(&self, record: &mut Record) {
+        if !self.inner.is_expiry_set.load(atomic::Ordering::Acquire) {
+            if let Some(ref expiry_fn) = self.inner.options.expiry_fn {
+                *self.inner.expiry.lock() = Some(expiry_fn.call(&record.data));
+            }
+        }
+        record.expiry_date = self.expiry_date();
+    }
+
+    /// Loads the session record from the store.
+    ///
+    /// Note that this method is generally not needed and is reserved for
+    /// situations where the session must be updated during the request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let id = Some(Id::default());
+    /// let session = Session::new(id, store.clone(), None);
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let session = Session::new(session.id(), store, None);
+    /// session.load().await.unwrap();
+    ///
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If loading from the store fails, we fail with [`Error::Store`].
+    #[tracing::instrument(skip(self), err)]
+    pub async fn load(&self) -> Result<()> {
+        let mut record_guard = self.inner.record.lock().await;
+        self.apply_pending_transfer().await?;
+        if self.inner.session_id.lock().is_none() {
+            tracing::warn!("called load with no session id");
+            return Ok(());
+        }
+        let loaded_record = self.load_with_fallbacks().await?;
+        *record_guard = Some(self.hydrate_record(loaded_record)?);
+        Ok(())
+    }
+
+    /// Deletes the session from the store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{session::Id, MemoryStore, Session, SessionStore};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(Some(Id::default()), store.clone(), None);
+    ///
+    /// // Save before deleting.
+    /// session.save().await.unwrap();
+    ///
+    /// // Delete from the store.
+    /// session.delete().await.unwrap();
+    ///
+    /// assert!(store.load(&session.id().unwrap()).await.unwrap().is_none());
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If deleting from the store fails, we fail with [`Error::Store`].
+    #[cfg_attr(not(feature = "opentelemetry"), tracing::instrument(skip(self), err))]
+    #[cfg_attr(
+        feature = "opentelemetry",
+        tracing::instrument(
+            name = "session.delete",
+            skip_all,
+            fields(
+                otel.kind = "client",
+                session.store = self.inner.store_name,
+                session.id_hash = %self.id_hash(),
+            ),
+            err
+        )
+    )]
+    pub async fn delete(&self) -> Result<()> {
+        let session_id = *self.inner.session_id.lock();
+        let Some(ref session_id) = session_id else {
+            tracing::warn!("called delete with no session id");
+            return Ok(());
+        };
+        self.within_deadline(self.store.delete(session_id))
+            .await
+            .map_err(Error::Store)?;
+        Ok(())
+    }
+
+    /// Flushes the session by removing all data contained in the session and
+    /// then deleting it from the store.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session, SessionStore};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    ///
+    /// session.insert("foo", "bar").await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let id = session.id().unwrap();
+    ///
+    /// session.flush().await.unwrap();
+    ///
+    /// assert!(session.id().is_none());
+    /// assert!(session.is_empty().await);
+    /// assert!(store.load(&id).await.unwrap().is_none());
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If deleting from the store fails, we fail with [`Error::Store`].
+    pub async fn flush(&self) -> Result<()> {
+        self.clear().await;
+        self.delete().await?;
+        *self.inner.session_id.lock() = None;
+        Ok(())
+    }
+
+    /// Cycles the session ID while retaining any data that was associated with
+    /// it.
+    ///
+    /// Using this method helps prevent session fixation attacks by ensuring a
+    /// new ID is assigned to the session.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{session::Id, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    ///
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let id = session.id();
+    ///
+    /// let session = Session::new(session.id(), store.clone(), None);
+    /// session.cycle_id().await.unwrap();
+    ///
+    /// assert!(!session.is_empty().await);
+    /// assert!(session.is_modified());
+    ///
+    /// session.save().await.unwrap();
+    ///
+    /// let session = Session::new(session.id(), store, None);
+    ///
+    /// assert_ne!(id, session.id());
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap().unwrap(), 42);
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If deleting from the store fails or saving to the store fails, we fail
+    ///   with [`Error::Store`].
+    #[cfg_attr(
+        feature = "opentelemetry",
+        tracing::instrument(
+            name = "session.cycle_id",
+            skip_all,
+            fields(
+                otel.kind = "client",
+                session.store = self.inner.store_name,
+                session.id_hash = %self.id_hash(),
+            ),
+            err
+        )
+    )]
+    pub async fn cycle_id(&self) -> Result<()> {
+        let mut record_guard = self.get_record().await?;
+
+        let old_session_id = record_guard.id;
+        record_guard.id = Id::default();
+        self.issue_validator(&mut record_guard);
+        *self.inner.session_id.lock() = None; // Setting `None` ensures `save` invokes the store's
+                                              // `create` method.
+
+        self.within_deadline(self.store.delete(&old_session_id))
+            .await
+            .map_err(Error::Store)?;
+        self.security_event(SecurityEventKind::IdCycled, Some(&old_session_id));
+
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Cycles the session ID like [`cycle_id`](Session::cycle_id), keeping the
+    /// session's current expiry date rather than computing a new one.
+    ///
+    /// With [`Expiry::OnInactivity`], cycling otherwise extends the session as
+    /// any other modification does. Preserving the remaining time to live
+    /// ensures cycling can't be used to prolong a session, e.g. where an
+    /// absolute timeout is mandated. The expiry is set to
+    /// [`Expiry::AtDateTime`] for the remainder of the request, so the cookie
+    /// is issued with the same lifetime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{Expiry, MemoryStore, Session, SessionStore};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let expiry = Some(Expiry::OnInactivity(Duration::minutes(30)));
+    /// let session = Session::new(None, store.clone(), expiry);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let id = session.id().unwrap();
+    /// let record = store.load(&id).await.unwrap().unwrap();
+    ///
+    /// let session = Session::new(Some(id), store.clone(), expiry);
+    /// session.cycle_id_preserving_expiry().await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let cycled = store.load(&session.id().unwrap()).await.unwrap().unwrap();
+    /// assert_ne!(cycled.id, id);
+    /// assert_eq!(cycled.expiry_date, record.expiry_date);
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If loading, deleting from or saving to the store fails, we fail with
+    ///   [`Error::Store`].
+    pub async fn cycle_id_preserving_expiry(&self) -> Result<()> {
+        let expiry_date = self.get_record().await?.expiry_date;
+        self.set_expiry(Some(Expiry::AtDateTime(expiry_date)));
+        self.cycle_id().await
+    }
+
+    /// Applies the given mutation to the session data and cycles the session
+    /// ID, saving the result to the store.
+    ///
+    /// This is intended for privilege changes, such as logging in, where the
+    /// session ID must be replaced along with the data it grants access to.
+    /// Unlike [`cycle_id`](Session::cycle_id) followed by
+    /// [`save`](Session::save), the old session is replaced with the new one
+    /// in a single store call, see [`SessionStore::cycle_and_save`]. The
+    /// response carries the new session ID as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("cart", vec![1, 2, 3]).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let anonymous_id = session.id();
+    ///
+    /// session
+    ///     .escalate(|data| {
+    ///         data.insert("user_id".to_string(), 42.into());
+    ///     })
+    ///     .await
+    ///     .unwrap();
+    ///
+    /// assert_ne!(session.id(), anonymous_id);
+    /// let session = Session::new(session.id(), store, None);
+    /// assert_eq!(session.get::<i32>("user_id").await.unwrap(), Some(42));
+    /// assert_eq!(session.get::<Vec<i32>>("cart").await.unwrap(), Some(vec![1, 2, 3]));
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If saving to the store fails, we fail with [`Error::Store`]. The
+    ///   session is then left unchanged, keeping its data and ID.
+    #[cfg_attr(
+        feature = "opentelemetry",
+        tracing::instrument(
+            name = "session.escalate",
+            skip_all,
+            fields(
+                otel.kind = "client",
+                session.store = self.inner.store_name,
+                session.id_hash = %self.id_hash(),
+            ),
+            err
+        )
+    )]
+    pub async fn escalate<F>(&self, mutate: F) -> Result<()>
+    where
+        F: FnOnce(&mut HashMap<String, Value>),
+    {
+        let mut record_guard = self.get_record().await?;
+
+        // The mutation is applied to a copy of the record, so the session is left as it was
+        // should the store fail.
+        let mut record = record_guard.clone();
+        let expiry = *self.inner.expiry.lock();
+        let split_token = self.inner.split_token.lock().clone();
+        let old_session_id = *self.inner.session_id.lock();
+        let saved = async {
+            mutate(&mut record.data);
+            self.update_expiry_date(&mut record);
+            self.validate_record(&record)?;
+
+            record.id = Id::default();
+            self.issue_validator(&mut record);
+            match old_session_id {
+                Some(ref old_session_id) => {
+                    self.within_deadline(self.store.cycle_and_save(old_session_id, &mut record))
+                        .await?
+                }
+                None => self.within_deadline(self.store.create(&mut record)).await?,
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        if let Err(err) = saved {
+            *self.inner.expiry.lock() = expiry;
+            *self.inner.split_token.lock() = split_token;
+            return Err(err);
+        }
+
+        if let Some(ref old_session_id) = old_session_id {
+            self.security_event(SecurityEventKind::IdCycled, Some(old_session_id));
+        }
+        *self.inner.session_id.lock() = Some(record.id);
+        *record_guard = record;
+
+        self.inner
+            .is_modified
+            .store(true, atomic::Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Acquires the lock named `key` of this session, waiting while another
+    /// request holds it.
+    ///
+    /// This serializes work across concurrent requests of the same session,
+    /// e.g. to ignore a payment form submitted twice. The lock is held by the
+    /// store, see [`SessionStore::try_lock`], so it's shared across processes.
+    /// It's released when the returned guard is dropped, or once `ttl` elapses
+    /// should the request never complete. Sessions which haven't been saved
+    /// yet can't be presented by other requests, so their locks are granted
+    /// without involving the store.
+    ///
+    /// Waiting isn't bounded other than by `ttl` of the holder; see
+    /// [`try_lock`](Session::try_lock) for failing fast instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let lock = session.lock("payment", Duration::seconds(30)).await.unwrap();
+    /// let other = Session::new(session.id(), store, None);
+    /// assert!(other
+    ///     .try_lock("payment", Duration::seconds(30))
+    ///     .await
+    ///     .unwrap()
+    ///     .is_none());
+    ///
+    /// lock.release().await.unwrap();
+    /// # });
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - If the store fails to acquire the lock, we fail with [`Error::Store`].
+    #[cfg(feature = "session-lock")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "session-lock")))]
+    pub async fn lock(&self, key: &str, ttl: Duration) -> Result<SessionLock> {
+        const MAX_BACKOFF: std::time::Duration = std::time::Duration::from_millis(200);
+
+        let mut backoff = std::time::Duration::from_millis(10);
+        loop {
+            if let Some(lock) = self.try_lock(key, ttl).await? {
+                return Ok(lock);
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Acquires the lock named `key` of this session, returning `None` if
+    /// another request holds it.
+    ///
+    /// See [`lock`](Session::lock).
+    ///
+    /// # Errors
+    ///
+    /// - If the store fails to acquire the lock, we fail with [`Error::Store`].
+    #[cfg(feature = "session-lock")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "session-lock")))]
+    pub async fn try_lock(&self, key: &str, ttl: Duration) -> Result<Option<SessionLock>> {
+        let Some(session_id) = self.id() else {
+            return Ok(Some(SessionLock {
+                store: self.store.clone(),
+                held: None,
+            }));
+        };
+
+        let token = rand::random::<u128>();
+        let acquired = self
+            .within_deadline(self.store.try_lock(&session_id, key, token, ttl))
+            .await?;
+        Ok(acquired.then(|| SessionLock {
+            store: self.store.clone(),
+            held: Some((session_id, key.to_string(), token)),
+        }))
+    }
+}
+
+/// Configuration of a session for the duration of a request, see
+/// [`Session::with_options`].
+///
+/// This is generally built by the middleware from its own configuration, as
+/// well as the request, e.g. the client's fingerprint.
+#[derive(Clone, Default)]
+pub struct SessionOptions {
+    client_binding: Option<(String, BindingPolicy)>,
+    split_token: Option<Option<String>>,
+    split_token_migration: Option<OffsetDateTime>,
+    data_migrator: Option<Arc<dyn SessionDataMigrator>>,
+    tombstone_policy: Option<TombstonePolicy>,
+    max_lifetime: Option<Duration>,
+    creation_id: Option<Id>,
+    fallback_ids: Vec<(Id, Option<String>)>,
+    transfer_token: Option<(String, Arc<[u8]>)>,
+    expiry_fn: Option<ExpiryFn>,
+    record_validator: Option<RecordValidator>,
+    record_sizes: Option<RecordSizes>,
+    security_events: Option<(SecurityEvents, ClientMetadata)>,
+    #[cfg(feature = "resilience")]
+    deadline: Option<tokio::time::Instant>,
+}
+
+impl SessionOptions {
+    /// Create a new `SessionOptions`, leaving every option unset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the session to the client identified by `fingerprint`.
+    ///
+    /// New records will carry the fingerprint with them and records loaded
+    /// from the store are checked against it, applying `policy` when the
+    /// stored fingerprint differs. Records without a fingerprint adopt the
+    /// provided one. The middleware sets this when configured via
+    /// `SessionManagerLayer::with_client_binding`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{
+    ///     session::{BindingPolicy, Error, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    ///
+    /// let options = SessionOptions::new().with_client_binding("alice", BindingPolicy::Reject);
+    /// let session = Session::with_options(None, store.clone(), None, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let options = SessionOptions::new().with_client_binding("mallory", BindingPolicy::Reject);
+    /// let session = Session::with_options(session.id(), store, None, options);
+    /// assert!(matches!(
+    ///     session.get::<usize>("foo").await,
+    ///     Err(Error::ClientMismatch)
+    /// ));
+    /// # });
+    /// ```
+    pub fn with_client_binding(
+        mut self,
+        fingerprint: impl Into<String>,
+        policy: BindingPolicy,
+    ) -> Self {
+        self.client_binding = Some((fingerprint.into(), policy));
+        self
+    }
+
+    /// Enables split tokens, with the validator presented by the client, if
+    /// any.
+    ///
+    /// The session ID then only selects the record, while the client must
+    /// also present a secret validator, of which records carry only a hash.
+    /// Hence the IDs of a leaked store can't be used to impersonate its
+    /// sessions. Records loaded from the store whose validator hash doesn't
+    /// match, compared in constant time, are abandoned in favor of a new
+    /// record. So are records without a validator hash, unless migrating, see
+    /// [`with_split_token_migration`](SessionOptions::with_split_token_migration).
+    ///
+    /// A new validator is issued whenever the record is created or its ID
+    /// cycled, and must be sent to the client along with the session ID, see
+    /// [`Session::validator`]. The middleware sets this when configured via
+    /// `SessionManagerLayer::with_split_token`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{session::SessionOptions, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    ///
+    /// let options = SessionOptions::new().with_split_token(None);
+    /// let session = Session::with_options(None, store.clone(), None, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let validator = session.validator();
+    ///
+    /// let options = SessionOptions::new().with_split_token(validator);
+    /// let session = Session::with_options(session.id(), store.clone(), None, options);
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), Some(42));
+    ///
+    /// let options = SessionOptions::new().with_split_token(Some("forged".to_string()));
+    /// let session = Session::with_options(session.id(), store, None, options);
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), None);
+    /// # });
+    /// ```
+    pub fn with_split_token(mut self, validator: Option<String>) -> Self {
+        self.split_token = Some(validator);
+        self
+    }
+
+    /// Accepts records without a validator hash until the given time, issuing
+    /// them a validator, so that sessions which predate split tokens survive
+    /// enabling them.
+    ///
+    /// Until then, a client presenting only the ID of such a session is issued
+    /// its validator, so the window should be no longer than needed for active
+    /// sessions to be presented once. The middleware sets this when configured
+    /// via `SessionManagerLayer::with_split_token_migration`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::{session::SessionOptions, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let options = SessionOptions::new()
+    ///     .with_split_token(None)
+    ///     .with_split_token_migration(OffsetDateTime::now_utc() + Duration::days(7));
+    /// let session = Session::with_options(session.id(), store, None, options);
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), Some(42));
+    /// assert!(session.validator().is_some());
+    /// # });
+    /// ```
+    pub fn with_split_token_migration(mut self, until: OffsetDateTime) -> Self {
+        self.split_token_migration = Some(until);
+        self
+    }
+
+    /// Configures the migrator used to upgrade session data written under
+    /// older schema versions.
+    ///
+    /// Records loaded from the store with a version lower than
+    /// [`SessionDataMigrator::version`] are migrated in place and the session
+    /// is marked as modified so the upgraded record is saved. New records are
+    /// written with the current version. The middleware sets this when
+    /// configured via `SessionManagerLayer::with_data_migrator`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::{collections::HashMap, sync::Arc};
+    ///
+    /// use serde_json::Value;
+    /// use tower_sessions::{
+    ///     session::{self, SessionDataMigrator, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// #[derive(Debug)]
+    /// struct RenameCounter;
+    ///
+    /// impl SessionDataMigrator for RenameCounter {
+    ///     fn version(&self) -> u8 {
+    ///         1
+    ///     }
+    ///
+    ///     fn migrate_record(
+    ///         &self,
+    ///         version: u8,
+    ///         data: &mut HashMap<String, Value>,
+    ///     ) -> session::Result<()> {
+    ///         if version < 1 {
+    ///             if let Some(count) = data.remove("count") {
+    ///                 data.insert("counter".to_string(), count);
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    ///
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("count", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let options = SessionOptions::new().with_data_migrator(Arc::new(RenameCounter));
+    /// let session = Session::with_options(session.id(), store, None, options);
+    /// assert_eq!(session.get::<usize>("counter").await.unwrap(), Some(42));
+    /// assert!(session.is_modified());
+    /// # });
+    /// ```
+    pub fn with_data_migrator(mut self, data_migrator: Arc<dyn SessionDataMigrator>) -> Self {
+        self.data_migrator = Some(data_migrator);
+        self
+    }
+
+    /// Configures the policy applied when the session ID refers to a deleted
+    /// session.
+    ///
+    /// When set and the store does not find the session, the store is asked
+    /// whether the ID was deleted via [`SessionStore::is_tombstoned`]. Stores
+    /// remember deleted IDs when wrapped in
+    /// [`TombstoneStore`](crate::session_store::TombstoneStore). The
+    /// middleware sets this when configured via
+    /// `SessionManagerLayer::with_tombstone_policy`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{
+    ///     session::{Error, SessionOptions, TombstonePolicy},
+    ///     session_store::TombstoneStore,
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(TombstoneStore::new(
+    ///     MemoryStore::default(),
+    ///     Duration::days(1),
+    /// ));
+    ///
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// let id = session.id();
+    /// session.delete().await.unwrap();
+    ///
+    /// let options = SessionOptions::new().with_tombstone_policy(TombstonePolicy::Reject);
+    /// let session = Session::with_options(id, store, None, options);
+    /// assert!(matches!(
+    ///     session.get::<usize>("foo").await,
+    ///     Err(Error::Tombstoned)
+    /// ));
+    /// # });
+    /// ```
+    pub fn with_tombstone_policy(mut self, tombstone_policy: TombstonePolicy) -> Self {
+        self.tombstone_policy = Some(tombstone_policy);
+        self
+    }
+
+    /// Configures the maximum lifetime of new sessions, regardless of
+    /// activity.
+    ///
+    /// Records created by the session carry an absolute expiry of now plus
+    /// `max_lifetime`. The record's expiry date never extends past it, and
+    /// stores refuse to load the record afterwards, even if the session
+    /// remained active. The middleware sets this when configured via
+    /// `SessionManagerLayer::with_max_lifetime`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::{
+    ///     session::{Expiry, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let expiry = Some(Expiry::OnInactivity(Duration::days(30)));
+    /// let options = SessionOptions::new().with_max_lifetime(Some(Duration::hours(8)));
+    /// let session = Session::with_options(None, store, expiry, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// assert!(session.expiry_date() <= OffsetDateTime::now_utc() + Duration::hours(8));
+    /// # });
+    /// ```
+    pub fn with_max_lifetime(mut self, max_lifetime: Option<Duration>) -> Self {
+        self.max_lifetime = max_lifetime;
+        self
+    }
+
+    /// Configures the ID used should a new session be created, instead of a
+    /// random one.
+    ///
+    /// The new session is saved under this ID rather than created via
+    /// [`SessionStore::create`], so concurrent requests with the same creation
+    /// ID share a single record: whichever request saves last determines its
+    /// data. The creation ID should therefore be derived from a secret the
+    /// client holds, e.g. via [`Id::from_token`]. The middleware sets this
+    /// when configured via `SessionManagerLayer::with_creation_token`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{
+    ///     session::{Id, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let creation_id = Id::from_token("a client-generated token", b"a server-side secret key");
+    /// let options = SessionOptions::new().with_creation_id(creation_id);
+    /// let session = Session::with_options(None, store, None, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// assert_eq!(session.id(), Some(creation_id));
+    /// # });
+    /// ```
+    pub fn with_creation_id(mut self, creation_id: Id) -> Self {
+        self.creation_id = Some(creation_id);
+        self
+    }
+
+    /// Adds a session ID to fall back to when the record of the session's ID
+    /// isn't found in the store, e.g. because the client sent several session
+    /// cookies. Fallback IDs are tried in the order they were added, along
+    /// with the validator of their split token if split tokens are enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{
+    ///     session::{Id, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let session = Session::new(None, store.clone(), None);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// let session_id = session.id().unwrap();
+    /// let options = SessionOptions::new().with_fallback_id(session_id, None);
+    /// let session = Session::with_options(Some(Id::default()), store, None, options);
+    /// assert_eq!(session.get::<usize>("foo").await.unwrap(), Some(42));
+    /// assert_eq!(session.id(), Some(session_id));
+    /// # });
+    /// ```
+    pub fn with_fallback_id(mut self, session_id: Id, validator: Option<String>) -> Self {
+        self.fallback_ids.push((session_id, validator));
+        self
+    }
+
+    /// Configures a transfer token to redeem when the record is first needed,
+    /// see [`Session::redeem_transfer_token`]. The middleware sets this when
+    /// configured via `SessionManagerLayer::with_transfer_token`.
+    pub fn with_transfer_token(mut self, token: impl Into<String>, key: Arc<[u8]>) -> Self {
+        self.transfer_token = Some((token.into(), key));
+        self
+    }
+
+    /// Computes the expiry from the session data whenever the session is
+    /// saved.
+    ///
+    /// This allows the lifetime of a session to depend on its contents, e.g.
+    /// the role of the user. An expiry set explicitly via
+    /// [`Session::set_expiry`] during the request takes precedence. The
+    /// middleware sets this when configured via
+    /// `SessionManagerLayer::with_expiry_fn`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use time::Duration;
+    /// use tower_sessions::{
+    ///     session::{Expiry, ExpiryFn, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let options = SessionOptions::new().with_expiry_fn(ExpiryFn::new(|data| {
+    ///     if data.get("role").and_then(|role| role.as_str()) == Some("admin") {
+    ///         Expiry::OnInactivity(Duration::minutes(15))
+    ///     } else {
+    ///         Expiry::OnInactivity(Duration::hours(8))
+    ///     }
+    /// }));
+    /// let session = Session::with_options(None, store, None, options);
+    ///
+    /// session.insert("role", "admin").await.unwrap();
+    /// session.save().await.unwrap();
+    /// assert_eq!(
+    ///     session.expiry(),
+    ///     Some(Expiry::OnInactivity(Duration::minutes(15)))
+    /// );
+    /// # });
+    /// ```
+    pub fn with_expiry_fn(mut self, expiry_fn: ExpiryFn) -> Self {
+        self.expiry_fn = Some(expiry_fn);
+        self
+    }
+
+    /// Configures a validator checking the record before each write to the
+    /// store.
+    ///
+    /// A rejected record isn't written and the write fails with
+    /// [`Error::Validation`]. The middleware sets this when configured via
+    /// `SessionManagerLayer::with_record_validator`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{
+    ///     session::{Error, RecordValidator, SessionOptions, ValidationError},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let options = SessionOptions::new().with_record_validator(RecordValidator::new(|record| {
+    ///     match record.data.keys().find(|key| key.as_str() != "user_id") {
+    ///         Some(key) => Err(ValidationError::new(format!("`{key}` isn't allowed"))),
+    ///         None => Ok(()),
+    ///     }
+    /// }));
+    /// let session = Session::with_options(None, store, None, options);
+    ///
+    /// session.insert("user_id", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    ///
+    /// session.insert("ssn", "078-05-1120").await.unwrap();
+    /// assert!(matches!(session.save().await, Err(Error::Validation(_))));
+    /// # });
+    /// ```
+    pub fn with_record_validator(mut self, record_validator: RecordValidator) -> Self {
+        self.record_validator = Some(record_validator);
+        self
+    }
+
+    /// Accounts for the size of the session's record whenever it's written to
+    /// the store, rejecting records above the limit, if any.
+    ///
+    /// A record above the limit isn't written and the write fails with
+    /// [`Error::RecordTooLarge`]. The middleware sets this when configured
+    /// via `SessionManagerLayer::with_record_sizes`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::Arc;
+    ///
+    /// use tower_sessions::{
+    ///     session::{Error, RecordSizes, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let record_sizes = RecordSizes::new().with_limit(1024);
+    /// let options = SessionOptions::new().with_record_sizes(record_sizes.clone());
+    /// let session = Session::with_options(None, store, None, options);
+    ///
+    /// session.insert("user_id", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// assert_eq!(record_sizes.count(), 1);
+    ///
+    /// session.insert("bio", "a".repeat(2048)).await.unwrap();
+    /// assert!(matches!(
+    ///     session.save().await,
+    ///     Err(Error::RecordTooLarge { limit: 1024, .. })
+    /// ));
+    /// # });
+    /// ```
+    pub fn with_record_sizes(mut self, record_sizes: RecordSizes) -> Self {
+        self.record_sizes = Some(record_sizes);
+        self
+    }
+
+    /// Configures a sink for the security events of the session, recorded on
+    /// behalf of the given client.
+    ///
+    /// The middleware sets this when configured via
+    /// `SessionManagerLayer::with_security_events`, which also records events
+    /// detected before the session is created, such as malformed session IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use tower_sessions::{
+    ///     session::{ClientMetadata, SecurityEventKind, SecurityEvents, SessionOptions},
+    ///     MemoryStore, Session,
+    /// };
+    ///
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let security_events = SecurityEvents::new({
+    ///     let events = events.clone();
+    ///     move |event| events.lock().unwrap().push(event.kind)
+    /// });
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let options =
+    ///     SessionOptions::new().with_security_events(security_events, ClientMetadata::default());
+    /// let session = Session::with_options(None, store, None, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// session.cycle_id().await.unwrap();
+    ///
+    /// assert_eq!(*events.lock().unwrap(), [SecurityEventKind::IdCycled]);
+    /// # });
+    /// ```
+    pub fn with_security_events(
+        mut self,
+        security_events: SecurityEvents,
+        client: ClientMetadata,
+    ) -> Self {
+        self.security_events = Some((security_events, client));
+        self
+    }
+
+    /// Configures the point in time by which store operations must complete.
+    ///
+    /// Operations still pending at the deadline fail with
+    /// [`session_store::Error::Timeout`], so a slow store can't hold up a
+    /// request beyond its budget. The middleware sets this when configured
+    /// via `SessionManagerLayer::with_store_timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// use tokio::time::Instant;
+    /// use tower_sessions::{session::SessionOptions, MemoryStore, Session};
+    ///
+    /// let store = Arc::new(MemoryStore::default());
+    /// let options = SessionOptions::new().with_deadline(Instant::now() + Duration::from_millis(100));
+    /// let session = Session::with_options(None, store, None, options);
+    /// session.insert("foo", 42).await.unwrap();
+    /// session.save().await.unwrap();
+    /// # });
+    /// ```
+    #[cfg(feature = "resilience")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+    pub fn with_deadline(mut self, deadline: tokio::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+impl fmt::Debug for SessionOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Fingerprints, validators and tokens are left out as they're secrets of the
+        // client.
+        let mut debug = f.debug_struct("SessionOptions");
+        debug
+            .field(
+                "client_binding",
+                &self.client_binding.as_ref().map(|(_, policy)| policy),
+            )
+            .field("split_token", &self.split_token.is_some())
+            .field("split_token_migration", &self.split_token_migration)
+            .field("data_migrator", &self.data_migrator)
+            .field("tombstone_policy", &self.tombstone_policy)
+            .field("max_lifetime", &self.max_lifetime)
+            .field("fallback_ids", &self.fallback_ids.len())
+            .field("transfer_token", &self.transfer_token.is_some())
+            .field("expiry_fn", &self.expiry_fn)
+            .field("record_validator", &self.record_validator)
+            .field("record_sizes", &self.record_sizes)
+            .field("security_events", &self.security_events.is_some());
+        #[cfg(feature = "resilience")]
+        debug.field("deadline", &self.deadline);
+        debug.finish_non_exhaustive()
+    }
+}
+
+/// A view of a session whose keys are prefixed with a namespace, see
+/// [`Session::namespace`].
+///
+/// Changes made through the view apply to the session, which is saved as
+/// usual.
+#[derive(Clone, Debug)]
+pub struct Namespace {
+    session: Session,
+    name: Cow<'static, str>,
+}
+
+impl Namespace {
+    /// Returns the name of the namespace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the session this is a view of.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}.{key}", self.name)
+    }
+
+    /// Inserts a value under `key` in the namespace, see [`Session::insert`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::insert`].
+    pub async fn insert<T: Serialize>(&self, key: impl AsKey<T>, value: T) -> Result<()> {
+        self.session.insert(&self.key(key.as_key()), value).await
+    }
+
+    /// Gets the value under `key` in the namespace, see [`Session::get`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::get`].
+    pub async fn get<T: DeserializeOwned>(&self, key: impl AsKey<T>) -> Result<Option<T>> {
+        self.session.get(&self.key(key.as_key())).await
+    }
+
+    /// Removes the value under `key` in the namespace, see
+    /// [`Session::remove`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::remove`].
+    pub async fn remove<T: DeserializeOwned>(&self, key: impl AsKey<T>) -> Result<Option<T>> {
+        self.session.remove(&self.key(key.as_key())).await
+    }
+
+    /// Returns a typed handle to the value under `key` in the namespace, see
+    /// [`Session::entry`].
+    pub fn entry<T>(&self, key: impl AsKey<T>) -> Entry<T> {
+        self.session.entry(self.key(key.as_key()))
+    }
+
+    /// Removes all values in the namespace, leaving other data in place.
+    ///
+    /// # Errors
+    ///
+    /// - If the session has not been hydrated and loading from the store fails,
+    ///   we fail with [`Error::Store`].
+    pub async fn clear(&self) -> Result<()> {
+        self.session.remove_prefixed(&self.key("")).await
+    }
+}
+
+/// A typed handle to a value of a session, see [`Session::entry`].
+///
+/// Each method accesses the session anew, so the handle may be kept around,
+/// e.g. in a library's extractor.
+pub struct Entry<T> {
+    session: Session,
+    key: String,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Entry<T> {
+    /// Returns the key of the entry, including the namespace if any.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Entry<T> {
+    /// Gets the value, see [`Session::get`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::get`].
+    pub async fn get(&self) -> Result<Option<T>> {
+        self.session.get(&self.key).await
+    }
+
+    /// Inserts the value, see [`Session::insert`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::insert`].
+    pub async fn insert(&self, value: T) -> Result<()> {
+        self.session.insert(&self.key, value).await
+    }
+
+    /// Removes the value, see [`Session::remove`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::remove`].
+    pub async fn remove(&self) -> Result<Option<T>> {
+        self.session.remove(&self.key).await
+    }
+
+    /// Gets the value, inserting the one returned by `f` if it's missing, see
+    /// [`Session::get_or_insert_with`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::get_or_insert_with`].
+    pub async fn get_or_insert_with(&self, f: impl FnOnce() -> T) -> Result<T> {
+        self.session.get_or_insert_with(&self.key, f).await
+    }
+
+    /// Updates the value in place, see [`Session::update`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Session::update`].
+    pub async fn update(&self, f: impl FnOnce(&mut T)) -> Result<Option<T>> {
+        self.session.update(&self.key, f).await
+    }
+}
+
+impl<T> Clone for Entry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            session: self.session.clone(),
+            key: self.key.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Entry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("session", &self.session)
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+/// A key of a session's data, along with the type of its value.
+///
+/// Declaring keys as constants keeps their names and types in one place, so
+/// that e.g. [`Session::get`] can't be called with a misspelled key or read
+/// a value as the wrong type.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// use std::sync::Arc;
+///
+/// use tower_sessions::{session::SessionKey, MemoryStore, Session};
+///
+/// const COUNTER: SessionKey<usize> = SessionKey::new("counter");
+///
+/// let store = Arc::new(MemoryStore::default());
+/// let session = Session::new(None, store, None);
+///
+/// session.insert(COUNTER, 42).await.unwrap();
+/// let counter = session.get(COUNTER).await.unwrap();
+/// assert_eq!(counter, Some(42));
+/// # });
+/// ```
+///
+/// Keys may also be declared with [`session_keys!`](crate::session_keys),
+/// which fails to compile if two of them share a name.
+pub struct SessionKey<T> {
+    name: &'static str,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SessionKey<T> {
+    /// Creates a key with the given name.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the name of the key.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for SessionKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SessionKey<T> {}
+
+impl<T> fmt::Debug for SessionKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SessionKey").field(&self.name).finish()
+    }
+}
+
+/// A key of a session's data holding values of type `T`.
+///
+/// This is implemented by [`SessionKey<T>`], as well as by strings for any
+/// `T`, in which case the type is picked by the caller.
+pub trait AsKey<T> {
+    /// Returns the name of the key.
+    fn as_key(&self) -> &str;
+}
+
+impl<T> AsKey<T> for SessionKey<T> {
+    fn as_key(&self) -> &str {
+        self.name
+    }
+}
+
+impl<T, K: AsRef<str> + ?Sized> AsKey<T> for &K {
+    fn as_key(&self) -> &str {
+        (**self).as_ref()
+    }
+}
+
+impl<T> AsKey<T> for String {
+    fn as_key(&self) -> &str {
+        self
+    }
+}
+
+impl<T> AsKey<T> for Cow<'_, str> {
+    fn as_key(&self) -> &str {
+        self
+    }
+}
+
+/// Declares [`SessionKey`] constants, failing to compile if two of them
+/// share a name.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::session_keys;
+///
+/// session_keys! {
+///     /// The number of visits.
+///     pub COUNTER: usize = "counter";
+///     pub USER_ID: i64 = "user_id";
+/// }
+///
+/// assert_eq!(COUNTER.name(), "counter");
+/// ```
+///
+/// Names are only checked against the keys of the same invocation:
+///
+/// ```rust,compile_fail
+/// use tower_sessions::session_keys;
+///
+/// session_keys! {
+///     COUNTER: usize = "counter";
+///     VISITS: usize = "counter";
+/// }
+/// ```
+#[macro_export]
+macro_rules! session_keys {
+    ($($(#[$meta:meta])* $vis:vis $ident:ident: $ty:ty = $name:expr;)*) => {
+        $(
+            $(#[$meta])*
+            $vis const $ident: $crate::session::SessionKey<$ty> =
+                $crate::session::SessionKey::new($name);
+        )*
+
+        const _: () = $crate::session::assert_unique_keys(&[$($ident.name()),*]);
+    };
+}
+
+// Panics if any two of the given names are equal, which fails compilation when
+// evaluated in a constant, see `session_keys!`.
+#[doc(hidden)]
+pub const fn assert_unique_keys(names: &[&str]) {
+    let mut i = 0;
+    while i < names.len() {
+        let mut j = i + 1;
+        while j < names.len() {
+            if const_str_eq(names[i], names[j]) {
+                panic!("session keys must have unique names");
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+const fn const_str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A lock of a session, acquired via [`Session::lock`].
+///
+/// The lock is released when the guard is dropped, in the background if a
+/// Tokio runtime is available and otherwise once its time-to-live elapses.
+/// [`release`](SessionLock::release) releases it in place, surfacing errors.
+#[cfg(feature = "session-lock")]
+#[cfg_attr(docsrs, doc(cfg(feature = "session-lock")))]
+#[must_use = "the lock is released when the guard is dropped"]
+pub struct SessionLock {
+    store: Arc<dyn SessionStore>,
+    held: Option<(Id, String, u128)>,
+}
+
+#[cfg(feature = "session-lock")]
+impl SessionLock {
+    /// Releases the lock.
+    ///
+    /// # Errors
+    ///
+    /// - If the store fails to release the lock, we fail with [`Error::Store`].
+    pub async fn release(mut self) -> Result<()> {
+        if let Some((session_id, key, token)) = self.held.take() {
+            self.store.unlock(&session_id, &key, token).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "session-lock")]
+impl fmt::Debug for SessionLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionLock")
+            .field("key", &self.held.as_ref().map(|(_, key, _)| key))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "session-lock")]
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        let Some((session_id, key, token)) = self.held.take() else {
+            return;
+        };
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(
+                key,
+                "no runtime to release session lock, it expires instead"
+            );
+            return;
+        };
+
+        let store = self.store.clone();
+        handle.spawn(async move {
+            if let Err(err) = store.unlock(&session_id, &key, token).await {
+                tracing::error!(err = %err, key, "failed to release session lock");
+            }
+        });
+    }
+}
+
+// State of a session's split token.
+#[derive(Clone)]
+enum SplitToken {
+    // The validator presented by the client, or issued during this request, which records
+    // loaded from the store must match.
+    Validator(Option<String>),
+
+    // The record of the given session, once a transfer to it was redeemed, is issued a new
+    // validator.
+    Reissue(Id),
+}
+
+// Hashes a split token validator such that the store alone can't be used to
+// derive it.
+fn hash_validator(validator: &str) -> String {
+    use sha2::Digest;
+
+    Sha256::digest(validator.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Hashes the session ID such that traces may correlate sessions without
+// disclosing their IDs.
+fn hash_id(session_id: &Id) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(session_id.0.to_le_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// ID type for sessions.
+///
+/// Wraps an array of 16 bytes.
+///
+/// IDs are compared in constant time to avoid leaking how much of a guessed ID
+/// matches. Their `Debug` output is a hash of the ID, which allows correlating
+/// sessions in logs without disclosing their IDs.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::session::Id;
+///
+/// Id::default();
+/// ```
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct Id(pub i128); // TODO: By this being public, it may be possible to override the
+                         // session ID, which is undesirable.
+
+type HmacSha256 = Hmac<Sha256>;
+
+impl Id {
+    /// Length of the encoded ID, as produced by its `Display` implementation.
+    pub const ENCODED_LEN: usize = 22;
+
+    /// Length of the signed ID, as produced by [`Id::to_signed_string`].
+    pub const SIGNED_LEN: usize = Self::ENCODED_LEN + 1 + 43;
+
+    /// Compares two IDs in constant time.
+    pub fn ct_eq(&self, other: &Id) -> bool {
+        self.0.to_le_bytes().ct_eq(&other.0.to_le_bytes()).into()
+    }
+
+    /// Encodes the ID along with an HMAC binding it to the given key.
+    ///
+    /// Signed IDs are decoded with [`Id::from_signed_str`], which rejects IDs
+    /// not issued with the same key before decoding them. This prevents
+    /// clients from probing the store with random IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::session::Id;
+    ///
+    /// let key = b"a server-side secret key";
+    /// let session_id = Id::default();
+    /// let signed = session_id.to_signed_string(key);
+    /// assert_eq!(Id::from_signed_str(&signed, key).unwrap(), session_id);
+    /// assert!(Id::from_signed_str(&signed, b"another key").is_err());
+    /// ```
+    pub fn to_signed_string(&self, key: &[u8]) -> String {
+        let encoded = self.to_string();
+        let tag = Self::mac(&encoded, key).finalize().into_bytes();
+        format!("{encoded}.{}", URL_SAFE_NO_PAD.encode(tag))
+    }
+
+    /// Decodes an ID signed with [`Id::to_signed_string`], verifying it was
+    /// signed with the given key.
+    ///
+    /// Values of the wrong length are rejected before any other work is done
+    /// and the signature is verified in constant time.
+    pub fn from_signed_str(s: &str, key: &[u8]) -> result::Result<Self, IdError> {
+        if s.len() != Self::SIGNED_LEN {
+            return Err(IdError::Malformed);
+        }
+        let (encoded, tag) = s
+            .split_at_checked(Self::ENCODED_LEN)
+            .ok_or(IdError::Malformed)?;
+        let tag = tag.strip_prefix('.').ok_or(IdError::Malformed)?;
+
+        let mut decoded_tag = [0; 32];
+        let tag_len = URL_SAFE_NO_PAD
+            .decode_slice(tag, &mut decoded_tag)
+            .map_err(|_| IdError::Malformed)?;
+        Self::mac(encoded, key)
+            .verify_slice(&decoded_tag[..tag_len])
+            .map_err(|_| IdError::InvalidSignature)?;
+
+        encoded.parse().map_err(|_| IdError::Malformed)
+    }
+
+    /// Derives an ID from a client-provided token with an HMAC under the given
+    /// key.
+    ///
+    /// The same token and key always derive the same ID, while the ID can't
+    /// be predicted without the key. See [`SessionOptions::with_creation_id`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tower_sessions::session::Id;
+    ///
+    /// let key = b"a server-side secret key";
+    /// let token = "a client-generated token";
+    /// assert_eq!(Id::from_token(token, key), Id::from_token(token, key));
+    /// assert_ne!(Id::from_token(token, key), Id::from_token(token, b"another key"));
+    /// ```
+    pub fn from_token(token: &str, key: &[u8]) -> Self {
+        Self::derive("creation-token", token, key)
+    }
+
+    // Prefixed by purpose so that derived IDs are unrelated to signatures of IDs and to IDs
+    // derived for other purposes.
+    fn derive(purpose: &str, token: &str, key: &[u8]) -> Self {
+        let tag = Self::mac(&format!("{purpose}.{token}"), key)
+            .finalize()
+            .into_bytes();
+        let mut bytes = [0; 16];
+        bytes.copy_from_slice(&tag[..16]);
+        Self(i128::from_le_bytes(bytes))
+    }
+
+    fn mac(encoded: &str, key: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+        mac.update(encoded.as_bytes());
+        mac
+    }
+}
+
+impl PartialEq for Id {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
 
-        self.inner
-            .is_modified
-            .store(true, atomic::Ordering::Release);
+impl Eq for Id {}
 
-        Ok(())
+impl Hash for Id {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
     }
 }
 
-/// ID type for sessions.
-///
-/// Wraps an array of 16 bytes.
-///
-/// # Examples
-///
-/// ```rust
-/// use tower_sessions::session::Id;
-///
-/// Id::default();
-/// ```
-#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
-pub struct Id(pub i128); // TODO: By this being public, it may be possible to override the
-                         // session ID, which is undesirable.
+/// Errors decoding a signed [`Id`].
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdError {
+    /// The value isn't a well-formed signed ID.
+    #[error("Malformed session id")]
+    Malformed,
+
+    /// The signature doesn't match the ID, i.e. it wasn't issued with the
+    /// given key.
+    #[error("Invalid session id signature")]
+    InvalidSignature,
+}
 
 impl Default for Id {
     fn default() -> Self {
@@ -884,6 +3881,12 @@ impl Default for Id {
     }
 }
 
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Id").field(&hash_id(self)).finish()
+    }
+}
+
 impl Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut encoded = [0; 22];
@@ -900,6 +3903,12 @@ impl FromStr for Id {
     type Err = base64::DecodeSliceError;
 
     fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        // Reject values of the wrong length without decoding them.
+        if s.len() != Self::ENCODED_LEN {
+            let err = DecodeError::InvalidLength(s.len());
+            return Err(base64::DecodeSliceError::DecodeError(err));
+        }
+
         let mut decoded = [0; 16];
         let bytes_decoded = URL_SAFE_NO_PAD.decode_slice(s.as_bytes(), &mut decoded)?;
         if bytes_decoded != 16 {
@@ -913,21 +3922,293 @@ impl FromStr for Id {
 
 /// Record type that's appropriate for encoding and decoding sessions to and
 /// from session stores.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// The `Debug` output redacts the data and client fingerprint unless
+/// [`log_session_content`] is enabled.
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
 pub struct Record {
     pub id: Id,
     pub data: Data,
     pub expiry_date: OffsetDateTime,
+
+    /// Fingerprint of the client the session was created for, if client
+    /// binding is enabled.
+    #[serde(default)]
+    pub client_fingerprint: Option<String>,
+
+    /// Schema version of `data`, see [`SessionDataMigrator`].
+    ///
+    /// Records written before versioning was introduced have version `0`.
+    #[serde(default)]
+    pub version: u8,
+
+    /// Point in time after which the session expires regardless of activity,
+    /// if it has a maximum lifetime.
+    #[serde(default)]
+    pub absolute_expiry: Option<OffsetDateTime>,
+
+    /// Hash of the validator the session's cookie must carry, if split tokens
+    /// are enabled. See [`SessionOptions::with_split_token`].
+    #[serde(default)]
+    pub validator_hash: Option<String>,
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Redacted;
+
+        impl fmt::Debug for Redacted {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("[redacted]")
+            }
+        }
+
+        let mut debug = f.debug_struct("Record");
+        debug.field("id", &self.id);
+        if logs_session_content() {
+            debug
+                .field("data", &self.data)
+                .field("client_fingerprint", &self.client_fingerprint);
+        } else {
+            debug.field("data", &Redacted).field(
+                "client_fingerprint",
+                &self.client_fingerprint.as_ref().map(|_| Redacted),
+            );
+        }
+        debug
+            .field("expiry_date", &self.expiry_date)
+            .field("version", &self.version)
+            .field("absolute_expiry", &self.absolute_expiry)
+            .field(
+                "validator_hash",
+                &self.validator_hash.as_ref().map(|_| Redacted),
+            )
+            .finish()
+    }
 }
 
 impl Record {
-    fn new(expiry_date: OffsetDateTime) -> Self {
+    /// The data key marking records which aren't sessions, see
+    /// [`is_internal`](Record::is_internal).
+    pub const INTERNAL_KEY: &'static str = "__tower_sessions.internal";
+
+    /// Creates an empty record with a random ID, expiring at `expiry_date`.
+    ///
+    /// Stores decoding records from their own representation should construct
+    /// them via this method and assign the fields they persist, rather than
+    /// with a struct literal, which breaks whenever a field is added.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use time::{Duration, OffsetDateTime};
+    /// use tower_sessions::session::Record;
+    ///
+    /// let mut record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+    /// record.data.insert("foo".to_string(), 42.into());
+    /// assert!(record.is_active());
+    /// ```
+    pub fn new(expiry_date: OffsetDateTime) -> Self {
         Self {
             id: Id::default(),
             data: Data::default(),
             expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    // Creates a record which isn't a session of its own.
+    pub(crate) fn new_internal(expiry_date: OffsetDateTime) -> Self {
+        let mut record = Self::new(expiry_date);
+        record
+            .data
+            .insert(Self::INTERNAL_KEY.to_string(), true.into());
+        record
+    }
+
+    /// Returns `true` if the record isn't a session but is kept by this
+    /// crate alongside sessions, e.g. for a transfer token.
+    ///
+    /// Such records are loaded by their ID as any other, but should be left
+    /// out when stores enumerate their sessions, e.g. via
+    /// [`SessionStore::list_recent`].
+    pub fn is_internal(&self) -> bool {
+        self.data.contains_key(Self::INTERNAL_KEY)
+    }
+
+    /// Returns `true` if the record has neither reached its expiry date nor
+    /// its absolute expiry.
+    ///
+    /// Stores should not load records which aren't active.
+    pub fn is_active(&self) -> bool {
+        let now = OffsetDateTime::now_utc();
+        self.expiry_date > now
+            && self
+                .absolute_expiry
+                .is_none_or(|absolute_expiry| absolute_expiry > now)
+    }
+}
+
+/// The fields of a serialized [`Record`] needed to tell whether it's active,
+/// decoded without its data.
+///
+/// Decoding the data dominates the cost of decoding large records. Stores
+/// holding serialized records may decode this instead when they only need to
+/// check expiry, e.g. when removing expired sessions, and decode the full
+/// record only for those they return. Any format records are serialized with
+/// may be used.
+///
+/// # Examples
+///
+/// ```rust
+/// use time::{Duration, OffsetDateTime};
+/// use tower_sessions::session::{Id, Record, RecordHeader};
+///
+/// let record = Record {
+///     id: Id::default(),
+///     data: Default::default(),
+///     expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+///     client_fingerprint: None,
+///     version: 0,
+///     absolute_expiry: None,
+///     validator_hash: None,
+/// };
+///
+/// let bytes = serde_json::to_vec(&record).unwrap();
+/// let header: RecordHeader = serde_json::from_slice(&bytes).unwrap();
+/// assert_eq!(header.id, record.id);
+/// assert!(header.is_active());
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RecordHeader {
+    /// The session ID.
+    pub id: Id,
+
+    // Skipped without being materialized. Positional formats need these in
+    // place of the corresponding `Record` fields.
+    #[serde(default, rename = "data")]
+    _data: serde::de::IgnoredAny,
+
+    /// The date after which the session expires.
+    pub expiry_date: OffsetDateTime,
+
+    #[serde(default, rename = "client_fingerprint")]
+    _client_fingerprint: serde::de::IgnoredAny,
+
+    #[serde(default, rename = "version")]
+    _version: serde::de::IgnoredAny,
+
+    /// Point in time after which the session expires regardless of activity,
+    /// if it has a maximum lifetime.
+    #[serde(default)]
+    pub absolute_expiry: Option<OffsetDateTime>,
+
+    #[serde(default, rename = "validator_hash")]
+    _validator_hash: serde::de::IgnoredAny,
+}
+
+impl RecordHeader {
+    /// Creates a header from its fields, for codecs that store them apart
+    /// from the record's data.
+    pub fn new(
+        id: Id,
+        expiry_date: OffsetDateTime,
+        absolute_expiry: Option<OffsetDateTime>,
+    ) -> Self {
+        Self {
+            id,
+            _data: serde::de::IgnoredAny,
+            expiry_date,
+            _client_fingerprint: serde::de::IgnoredAny,
+            _version: serde::de::IgnoredAny,
+            absolute_expiry,
+            _validator_hash: serde::de::IgnoredAny,
         }
     }
+
+    /// Returns `true` if the record hasn't reached its expiry date, nor its
+    /// absolute expiry. See [`Record::is_active`].
+    pub fn is_active(&self) -> bool {
+        let now = OffsetDateTime::now_utc();
+        self.expiry_date > now
+            && self
+                .absolute_expiry
+                .is_none_or(|absolute_expiry| absolute_expiry > now)
+    }
+}
+
+impl From<&Record> for RecordHeader {
+    fn from(record: &Record) -> Self {
+        Self::new(record.id, record.expiry_date, record.absolute_expiry)
+    }
+}
+
+/// Policy applied when a session that has been deleted is presented again.
+///
+/// See [`SessionOptions::with_tombstone_policy`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TombstonePolicy {
+    /// Log the event and start a new, empty session, just as with any unknown
+    /// session ID.
+    Flag,
+
+    /// Reject the session: operations requiring the record fail with
+    /// [`Error::Tombstoned`].
+    Reject,
+}
+
+/// Upgrades session data written under an older schema version.
+///
+/// Applications that change the shape of the data they keep in sessions may
+/// implement this to convert old payloads rather than have them fail to
+/// deserialize. See [`SessionOptions::with_data_migrator`].
+pub trait SessionDataMigrator: fmt::Debug + Send + Sync + 'static {
+    /// The current schema version; new records are written with it.
+    fn version(&self) -> u8;
+
+    /// Migrates `data` written under `version` to the current version.
+    ///
+    /// This is only invoked when `version` is lower than
+    /// [`version`](SessionDataMigrator::version).
+    fn migrate_record(&self, version: u8, data: &mut HashMap<String, Value>) -> Result<()>;
+}
+
+/// Policy applied when a session is presented by a client other than the one
+/// it was bound to.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::session::BindingPolicy;
+///
+/// // Only log the mismatch.
+/// let policy = BindingPolicy::Ignore;
+///
+/// // Abandon the presented session and start a new, empty one.
+/// let policy = BindingPolicy::CycleId;
+///
+/// // Fail session operations with `Error::ClientMismatch`.
+/// let policy = BindingPolicy::Reject;
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BindingPolicy {
+    /// Log the mismatch but otherwise use the session as usual.
+    #[default]
+    Ignore,
+
+    /// Abandon the presented session and start a new, empty session with a new
+    /// ID in its place.
+    ///
+    /// The original session is left untouched in the store so that the client
+    /// it was bound to may continue using it.
+    CycleId,
+
+    /// Reject the session: operations requiring the record fail with
+    /// [`Error::ClientMismatch`].
+    Reject,
 }
 
 /// Session expiry configuration.
@@ -947,6 +4228,9 @@ impl Record {
 /// // Will be expired at the given timestamp.
 /// let expired_at = OffsetDateTime::now_utc().saturating_add(Duration::weeks(2));
 /// let expiry = Expiry::AtDateTime(expired_at);
+///
+/// // Will be kept for as long as the browser allows.
+/// let expiry = Expiry::Never;
 /// ```
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Expiry {
@@ -968,6 +4252,311 @@ pub enum Expiry {
     /// This value may be extended manually with
     /// [`set_expiry`](Session::set_expiry).
     AtDateTime(OffsetDateTime),
+
+    /// Never expire, e.g. for "remember me" sessions.
+    ///
+    /// Browsers cap the lifetime of cookies, so in practice this behaves as
+    /// [`Expiry::OnInactivity`] with [`Expiry::NEVER_DURATION`]: the session
+    /// is extended whenever it's saved.
+    Never,
+}
+
+impl Expiry {
+    /// The lifetime used for [`Expiry::Never`], which is the longest lifetime
+    /// browsers accept for a cookie.
+    pub const NEVER_DURATION: Duration = Duration::days(400);
+}
+
+/// A value carrying its own expiration, such as the claims of an
+/// authentication token.
+///
+/// See [`Session::set_expiry_from`].
+pub trait Expires {
+    /// Returns when the value expires, or `None` if it doesn't.
+    fn expires_at(&self) -> Option<OffsetDateTime>;
+}
+
+impl Expires for OffsetDateTime {
+    fn expires_at(&self) -> Option<OffsetDateTime> {
+        Some(*self)
+    }
+}
+
+impl<T: Expires> Expires for Option<T> {
+    fn expires_at(&self) -> Option<OffsetDateTime> {
+        self.as_ref().and_then(Expires::expires_at)
+    }
+}
+
+/// Computes the expiry of a session from its data.
+///
+/// See [`SessionOptions::with_expiry_fn`].
+#[derive(Clone)]
+pub struct ExpiryFn(Arc<dyn Fn(&Data) -> Expiry + Send + Sync>);
+
+impl ExpiryFn {
+    /// Create a new `ExpiryFn` from the given function.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&HashMap<String, Value>) -> Expiry + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    fn call(&self, data: &HashMap<String, Value>) -> Expiry {
+        (self.0)(data)
+    }
+}
+
+impl fmt::Debug for ExpiryFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ExpiryFn").finish_non_exhaustive()
+    }
+}
+
+/// A record rejected by a [`RecordValidator`], along with the reason.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{0}")]
+pub struct ValidationError(Cow<'static, str>);
+
+impl ValidationError {
+    /// Create a new `ValidationError` with the given reason.
+    pub fn new(reason: impl Into<Cow<'static, str>>) -> Self {
+        Self(reason.into())
+    }
+
+    /// Returns the reason the record was rejected.
+    pub fn reason(&self) -> &str {
+        &self.0
+    }
+}
+
+type ValidateFn = dyn Fn(&Record) -> result::Result<(), ValidationError> + Send + Sync;
+
+/// Checks records against an application's policy before they're saved, e.g.
+/// that only allowed keys and types enter the session.
+///
+/// Keys prefixed with `__tower_sessions.` are written by this crate itself,
+/// e.g. for flash messages, and should be allowed.
+///
+/// See [`SessionOptions::with_record_validator`].
+#[derive(Clone)]
+pub struct RecordValidator(Arc<ValidateFn>);
+
+impl RecordValidator {
+    /// Create a new `RecordValidator` from the given function.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Record) -> result::Result<(), ValidationError> + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    fn validate(&self, record: &Record) -> result::Result<(), ValidationError> {
+        (self.0)(record)
+    }
+}
+
+impl fmt::Debug for RecordValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RecordValidator").finish_non_exhaustive()
+    }
+}
+
+/// Accounts for the size of records as they're saved, optionally rejecting
+/// those above a limit to protect the store.
+///
+/// Sizes are measured as the length of the record encoded as JSON, which is
+/// independent of how a particular store encodes records. Clones share their
+/// counters, so the application may keep a clone to export the observed sizes
+/// as metrics for capacity planning.
+///
+/// See [`SessionOptions::with_record_sizes`].
+#[derive(Debug, Clone, Default)]
+pub struct RecordSizes {
+    limit: Option<usize>,
+    counters: Arc<RecordSizeCounters>,
+}
+
+#[derive(Debug, Default)]
+struct RecordSizeCounters {
+    count: AtomicU64,
+    total_bytes: AtomicU64,
+    max_bytes: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl RecordSizes {
+    /// Create a new `RecordSizes` without a limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures the maximum size of a record, in bytes.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the maximum size of a record, if any.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Returns the number of records measured, including rejected ones.
+    pub fn count(&self) -> u64 {
+        self.counters.count.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the total size of the records measured, in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.counters.total_bytes.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the size of the largest record measured, in bytes.
+    pub fn max_bytes(&self) -> u64 {
+        self.counters.max_bytes.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the number of records rejected for exceeding the limit.
+    pub fn rejected(&self) -> u64 {
+        self.counters.rejected.load(atomic::Ordering::Relaxed)
+    }
+
+    fn check(&self, record: &Record) -> Result<()> {
+        let size = serde_json::to_vec(record)?.len();
+        let counters = &self.counters;
+        counters.count.fetch_add(1, atomic::Ordering::Relaxed);
+        counters
+            .total_bytes
+            .fetch_add(size as u64, atomic::Ordering::Relaxed);
+        counters
+            .max_bytes
+            .fetch_max(size as u64, atomic::Ordering::Relaxed);
+
+        match self.limit {
+            Some(limit) if size > limit => {
+                counters.rejected.fetch_add(1, atomic::Ordering::Relaxed);
+                tracing::warn!(
+                    size,
+                    limit,
+                    "session record exceeds the size limit; not saving"
+                );
+                Err(Error::RecordTooLarge { size, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Kinds of [`SecurityEvent`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SecurityEventKind {
+    /// A session ID which couldn't be decoded was presented.
+    MalformedId,
+
+    /// A session cookie which failed signature verification or decryption
+    /// was presented.
+    InvalidSignature,
+
+    /// A session ID which isn't in the store was presented, e.g. that of an
+    /// expired session.
+    UnknownId,
+
+    /// The ID of a deleted session was presented, as detected by a
+    /// [`TombstonePolicy`].
+    Tombstoned,
+
+    /// The session ID was cycled.
+    IdCycled,
+
+    /// A split token whose validator doesn't match that of the session was
+    /// presented. See [`SessionOptions::with_split_token`].
+    ValidatorMismatch,
+}
+
+/// Metadata of the client a [`SecurityEvent`] was recorded for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClientMetadata {
+    /// The IP address of the client, if known.
+    pub ip: Option<IpAddr>,
+
+    /// The user agent of the client, if any.
+    pub user_agent: Option<String>,
+}
+
+impl ClientMetadata {
+    /// Create a new `ClientMetadata` from the given IP address and user
+    /// agent.
+    pub fn new(ip: Option<IpAddr>, user_agent: Option<String>) -> Self {
+        Self { ip, user_agent }
+    }
+}
+
+/// A security-relevant occurrence during a request, see [`SecurityEvents`].
+///
+/// Session IDs are never included; the session is identified by a hash of
+/// its ID instead, which allows correlating events of the same session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SecurityEvent {
+    /// What happened.
+    pub kind: SecurityEventKind,
+
+    /// A hash of the ID of the session concerned, if known. For
+    /// [`SecurityEventKind::IdCycled`] this is the ID being replaced.
+    pub session_id_hash: Option<String>,
+
+    /// The client making the request.
+    pub client: ClientMetadata,
+
+    /// When the event occurred.
+    pub occurred_at: OffsetDateTime,
+}
+
+impl SecurityEvent {
+    /// Create a new `SecurityEvent` of the given kind, hashing the session
+    /// ID, if any.
+    pub fn new(kind: SecurityEventKind, session_id: Option<&Id>, client: ClientMetadata) -> Self {
+        Self {
+            kind,
+            session_id_hash: session_id.map(hash_id),
+            client,
+            occurred_at: OffsetDateTime::now_utc(),
+        }
+    }
+}
+
+/// Receives [`SecurityEvent`]s, e.g. to forward them to a SIEM.
+///
+/// Events are recorded synchronously as they occur, so the sink should hand
+/// them off rather than block.
+///
+/// See [`SessionOptions::with_security_events`].
+#[derive(Clone)]
+pub struct SecurityEvents(Arc<dyn Fn(&SecurityEvent) + Send + Sync>);
+
+impl SecurityEvents {
+    /// Create a new `SecurityEvents` from the given function.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&SecurityEvent) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Records the given event.
+    pub fn record(&self, event: &SecurityEvent) {
+        (self.0)(event)
+    }
+}
+
+impl fmt::Debug for SecurityEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecurityEvents").finish_non_exhaustive()
+    }
 }
 
 #[cfg(test)]
@@ -980,17 +4569,144 @@ mod tests {
 
     use super::*;
 
-    mock! {
-        #[derive(Debug)]
-        pub Store {}
+    mock! {
+        #[derive(Debug)]
+        pub Store {}
+
+        #[async_trait]
+        impl SessionStore for Store {
+            async fn create(&self, record: &mut Record) -> session_store::Result<()>;
+            async fn save(&self, record: &Record) -> session_store::Result<()>;
+            async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>>;
+            async fn delete(&self, session_id: &Id) -> session_store::Result<()>;
+            async fn cycle_and_save(
+                &self,
+                old_session_id: &Id,
+                record: &mut Record,
+            ) -> session_store::Result<()>;
+            async fn increment(
+                &self,
+                session_id: &Id,
+                key: &str,
+                by: i64,
+            ) -> session_store::Result<Option<i64>>;
+            async fn try_lock(
+                &self,
+                session_id: &Id,
+                key: &str,
+                token: u128,
+                ttl: Duration,
+            ) -> session_store::Result<bool>;
+            async fn unlock(
+                &self,
+                session_id: &Id,
+                key: &str,
+                token: u128,
+            ) -> session_store::Result<()>;
+        }
+    }
+
+    #[test]
+    fn test_id_parsing() {
+        let session_id = Id::default();
+        assert_eq!(session_id.to_string().parse::<Id>().unwrap(), session_id);
+        assert!("".parse::<Id>().is_err());
+        assert!("a".repeat(Id::ENCODED_LEN + 1).parse::<Id>().is_err());
+        assert!(session_id.ct_eq(&session_id));
+        assert!(!session_id.ct_eq(&Id(session_id.0 ^ 1)));
+    }
+
+    #[test]
+    fn test_signed_id() {
+        let key = b"key";
+        let session_id = Id::default();
+        let signed = session_id.to_signed_string(key);
+        assert_eq!(signed.len(), Id::SIGNED_LEN);
+        assert_eq!(Id::from_signed_str(&signed, key), Ok(session_id));
+
+        let forged = format!("{}{}", Id::default(), &signed[Id::ENCODED_LEN..]);
+        assert_eq!(
+            Id::from_signed_str(&forged, key),
+            Err(IdError::InvalidSignature)
+        );
+        assert_eq!(
+            Id::from_signed_str(&signed, b"other"),
+            Err(IdError::InvalidSignature)
+        );
+        assert_eq!(
+            Id::from_signed_str(&session_id.to_string(), key),
+            Err(IdError::Malformed)
+        );
+
+        // The expected length in bytes, with a character straddling the separator.
+        let straddling = format!("{}é{}", "a".repeat(21), "a".repeat(43));
+        assert_eq!(straddling.len(), Id::SIGNED_LEN);
+        assert_eq!(
+            Id::from_signed_str(&straddling, key),
+            Err(IdError::Malformed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_once_across_clones() {
+        let mut mock_store = MockStore::new();
+
+        let session_id = Id::default();
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(|_| Ok(None));
+
+        let session = Session::new(Some(session_id), Arc::new(mock_store), None);
+        let clone = session.clone();
+
+        assert!(session.get::<i32>("foo").await.unwrap().is_none());
+        assert!(clone.get::<i32>("foo").await.unwrap().is_none());
+        clone.insert("foo", 42).await.unwrap();
+        assert_eq!(session.get::<i32>("foo").await.unwrap(), Some(42));
+        assert!(session.is_new().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_clear_before_load_keeps_record() {
+        let mut mock_store = MockStore::new();
+
+        let session_id = Id::default();
+        let absolute_expiry = OffsetDateTime::now_utc() + Duration::hours(1);
+        let record = Record {
+            id: session_id,
+            data: [("foo".to_string(), 42.into())].into(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: Some(absolute_expiry),
+            validator_hash: None,
+        };
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(move |_| Ok(Some(record.clone())));
+        mock_store
+            .expect_save()
+            .withf(move |record| {
+                record.id == session_id
+                    && record.data.is_empty()
+                    && record.absolute_expiry == Some(absolute_expiry)
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let options = SessionOptions::new().with_max_lifetime(Some(Duration::days(1)));
+        let session = Session::with_options(Some(session_id), Arc::new(mock_store), None, options);
+        session.clear().await;
+        assert!(session.is_modified());
+        assert!(!session.is_empty().await);
 
-        #[async_trait]
-        impl SessionStore for Store {
-            async fn create(&self, record: &mut Record) -> session_store::Result<()>;
-            async fn save(&self, record: &Record) -> session_store::Result<()>;
-            async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>>;
-            async fn delete(&self, session_id: &Id) -> session_store::Result<()>;
-        }
+        assert!(session.get::<i32>("foo").await.unwrap().is_none());
+        assert_eq!(session.absolute_expiry(), Some(absolute_expiry));
+        session.save().await.unwrap();
     }
 
     #[tokio::test]
@@ -1015,6 +4731,10 @@ mod tests {
                     id: initial_id,
                     data: Data::default(),
                     expiry_date: OffsetDateTime::now_utc(),
+                    client_fingerprint: None,
+                    version: 0,
+                    absolute_expiry: None,
+                    validator_hash: None,
                 }))
             });
         mock_store
@@ -1049,4 +4769,628 @@ mod tests {
         session.save().await.unwrap();
         assert_eq!(session.id(), Some(new_id));
     }
+
+    #[tokio::test]
+    async fn test_split_token_rejects_records_without_validator_hash() {
+        let session_id = Id::default();
+        let record = Record {
+            id: session_id,
+            data: [("foo".to_string(), 42.into())].into(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        let mut mock_store = MockStore::new();
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(2)
+            .returning(move |_| Ok(Some(record.clone())));
+        let store = Arc::new(mock_store);
+
+        let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let security_events = SecurityEvents::new({
+            let events = events.clone();
+            move |event| events.lock().push(event.kind)
+        });
+        let options = SessionOptions::new()
+            .with_split_token(Some("guessed".to_string()))
+            .with_security_events(security_events, ClientMetadata::default());
+        let session = Session::with_options(Some(session_id), store.clone(), None, options);
+        assert!(session.get::<i32>("foo").await.unwrap().is_none());
+        assert!(session.id().is_none());
+        assert_eq!(*events.lock(), [SecurityEventKind::ValidatorMismatch]);
+
+        // Unless migrating, in which case the record is issued a validator.
+        let options = SessionOptions::new()
+            .with_split_token(None)
+            .with_split_token_migration(OffsetDateTime::now_utc() + Duration::days(1));
+        let session = Session::with_options(Some(session_id), store, None, options);
+        assert_eq!(session.get::<i32>("foo").await.unwrap(), Some(42));
+        assert_eq!(session.id(), Some(session_id));
+        assert!(session.validator().is_some());
+        assert!(session.is_modified());
+    }
+
+    #[tokio::test]
+    async fn test_record_validator() {
+        let mut mock_store = MockStore::new();
+        mock_store
+            .expect_create()
+            .withf(|record| !record.data.contains_key("ssn"))
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_store.expect_save().never();
+        mock_store.expect_cycle_and_save().never();
+
+        let options = SessionOptions::new().with_record_validator(RecordValidator::new(|record| {
+            if record.data.contains_key("ssn") {
+                return Err(ValidationError::new("`ssn` isn't allowed"));
+            }
+            Ok(())
+        }));
+        let session = Session::with_options(None, Arc::new(mock_store), None, options);
+
+        session.insert("user_id", 42).await.unwrap();
+        session.save().await.unwrap();
+
+        session.insert("ssn", "078-05-1120").await.unwrap();
+        match session.save().await {
+            Err(Error::Validation(err)) => assert_eq!(err.reason(), "`ssn` isn't allowed"),
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        // Escalating a rejected record leaves the stored session as is.
+        let res = session.escalate(|_| {}).await;
+        assert!(matches!(res, Err(Error::Validation(_))));
+        assert!(session.id().is_some());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_reference_token() {
+        let key = b"a server-side secret key";
+        let store = Arc::new(crate::testing::MockSessionStore::new());
+        let session = Session::new(None, store.clone(), None);
+        session.insert("user_id", 42).await.unwrap();
+        let token = session
+            .create_reference_token(key, Duration::hours(1))
+            .await
+            .unwrap();
+
+        // Tokens may be redeemed repeatedly, but only with the same key.
+        for _ in 0..2 {
+            let job_session = Session::from_reference_token(store.clone(), &token, key)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(job_session.id(), session.id());
+            assert_eq!(job_session.get::<i32>("user_id").await.unwrap(), Some(42));
+        }
+        assert!(
+            Session::from_reference_token(store.clone(), &token, b"another key")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Transfer tokens aren't reference tokens.
+        let transfer_token = session
+            .create_transfer_token(key, Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(
+            Session::from_reference_token(store.clone(), &transfer_token, key)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Expired tokens and deleted sessions aren't loaded.
+        let expired = session
+            .create_reference_token(key, Duration::seconds(-1))
+            .await
+            .unwrap();
+        assert!(Session::from_reference_token(store.clone(), &expired, key)
+            .await
+            .unwrap()
+            .is_none());
+        session.delete().await.unwrap();
+        assert!(Session::from_reference_token(store, &token, key)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record_sizes() {
+        let mut mock_store = MockStore::new();
+        mock_store.expect_create().times(1).returning(|_| Ok(()));
+        mock_store.expect_save().never();
+
+        let record_sizes = RecordSizes::new().with_limit(256);
+        let options = SessionOptions::new().with_record_sizes(record_sizes.clone());
+        let session = Session::with_options(None, Arc::new(mock_store), None, options);
+
+        session.insert("user_id", 42).await.unwrap();
+        session.save().await.unwrap();
+        assert_eq!(record_sizes.count(), 1);
+        let size = record_sizes.max_bytes();
+        assert!(size > 0 && size <= 256);
+        assert_eq!(record_sizes.total_bytes(), size);
+
+        session.insert("bio", "a".repeat(256)).await.unwrap();
+        match session.save().await {
+            Err(Error::RecordTooLarge { size, limit }) => {
+                assert!(size > 256);
+                assert_eq!(limit, 256);
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+        assert_eq!(record_sizes.count(), 2);
+        assert_eq!(record_sizes.rejected(), 1);
+        assert!(record_sizes.max_bytes() > 256);
+    }
+
+    #[test]
+    fn test_set_expiry_from() {
+        struct Claims {
+            exp: Option<OffsetDateTime>,
+        }
+
+        impl Expires for Claims {
+            fn expires_at(&self) -> Option<OffsetDateTime> {
+                self.exp
+            }
+        }
+
+        let expiry = Some(Expiry::OnInactivity(Duration::minutes(30)));
+        let session = Session::new(None, Arc::new(MockStore::new()), expiry);
+
+        // Values which don't expire leave the expiry as is.
+        session.set_expiry_from(&Claims { exp: None });
+        assert_eq!(session.expiry(), expiry);
+        assert!(!session.is_modified());
+
+        let exp = OffsetDateTime::now_utc() + Duration::minutes(5);
+        session.set_expiry_from(&Claims { exp: Some(exp) });
+        assert_eq!(session.expiry(), Some(Expiry::AtDateTime(exp)));
+        assert_eq!(session.expiry_date(), exp);
+        assert!(session.is_modified());
+
+        session.set_expiry_from(&Some(exp + Duration::minutes(5)));
+        assert_eq!(session.expiry_date(), exp + Duration::minutes(5));
+    }
+
+    #[tokio::test]
+    async fn test_cycle_id_preserving_expiry() {
+        let mut mock_store = MockStore::new();
+
+        let session_id = Id::default();
+        let expiry_date = OffsetDateTime::now_utc() + Duration::minutes(5);
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(move |_| {
+                Ok(Some(Record {
+                    id: session_id,
+                    data: Data::default(),
+                    expiry_date,
+                    client_fingerprint: None,
+                    version: 0,
+                    absolute_expiry: None,
+                    validator_hash: None,
+                }))
+            });
+        mock_store
+            .expect_delete()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(|_| Ok(()));
+        mock_store
+            .expect_create()
+            .withf(move |record| record.expiry_date == expiry_date)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let expiry = Some(Expiry::OnInactivity(Duration::minutes(30)));
+        let session = Session::new(Some(session_id), Arc::new(mock_store), expiry);
+        session.cycle_id_preserving_expiry().await.unwrap();
+        session.save().await.unwrap();
+
+        assert_ne!(session.id(), Some(session_id));
+        assert_eq!(session.expiry(), Some(Expiry::AtDateTime(expiry_date)));
+    }
+
+    #[tokio::test]
+    async fn test_creation_id() {
+        let mut mock_store = MockStore::new();
+
+        let creation_id = Id::from_token("token", b"key");
+        mock_store
+            .expect_load()
+            .with(predicate::eq(creation_id))
+            .times(1)
+            .returning(|_| Ok(None));
+        mock_store.expect_create().never();
+        mock_store
+            .expect_save()
+            .withf(move |record| record.id == creation_id)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        // The middleware presents the creation ID as the session ID.
+        let options = SessionOptions::new().with_creation_id(creation_id);
+        let session = Session::with_options(Some(creation_id), Arc::new(mock_store), None, options);
+        session.insert("foo", 42).await.unwrap();
+        assert!(session.is_new().await.unwrap());
+        session.save().await.unwrap();
+
+        assert_eq!(session.id(), Some(creation_id));
+    }
+
+    #[tokio::test]
+    async fn test_increment() {
+        let mut mock_store = MockStore::new();
+
+        let session_id = Id::default();
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(move |_| {
+                let mut data = Data::default();
+                data.insert("unsupported".to_string(), 1.into());
+                Ok(Some(Record {
+                    id: session_id,
+                    data,
+                    expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+                    client_fingerprint: None,
+                    version: 0,
+                    absolute_expiry: None,
+                    validator_hash: None,
+                }))
+            });
+        mock_store
+            .expect_increment()
+            .withf(|_, key, by| key == "atomic" && *by == 2)
+            .times(1)
+            .returning(|_, _, _| Ok(Some(5)));
+        mock_store
+            .expect_increment()
+            .withf(|_, key, _| key == "unsupported")
+            .times(1)
+            .returning(|_, _, _| Ok(None));
+
+        let session = Session::new(Some(session_id), Arc::new(mock_store), None);
+
+        assert_eq!(session.increment("atomic", 2).await.unwrap(), 5);
+        assert_eq!(session.get::<i64>("atomic").await.unwrap(), Some(5));
+        assert!(!session.is_modified());
+
+        const UNSUPPORTED: SessionKey<i64> = SessionKey::new("unsupported");
+        assert_eq!(session.increment(UNSUPPORTED, 2).await.unwrap(), 3);
+        assert!(session.is_modified());
+    }
+
+    #[test]
+    fn test_record_header() {
+        let mut record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+        record.data.insert("foo".to_string(), "bar".into());
+        record.absolute_expiry = Some(OffsetDateTime::now_utc() - Duration::minutes(1));
+
+        let bytes = serde_json::to_vec(&record).unwrap();
+        let header: RecordHeader = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(header.id, record.id);
+        assert_eq!(header.expiry_date, record.expiry_date);
+        assert_eq!(header.absolute_expiry, record.absolute_expiry);
+        assert!(!header.is_active());
+    }
+
+    #[tokio::test]
+    async fn test_debug_redaction() {
+        let mut mock_store = MockStore::new();
+        mock_store.expect_create().times(1).returning(|_| Ok(()));
+
+        let options =
+            SessionOptions::new().with_client_binding("fingerprint", BindingPolicy::Reject);
+        let session = Session::with_options(None, Arc::new(mock_store), None, options);
+        session.insert("email", "user@example.com").await.unwrap();
+        session.save().await.unwrap();
+
+        let id = session.id().unwrap();
+        let debug = format!("{session:?}");
+        assert!(debug.contains(&hash_id(&id)));
+        assert!(!debug.contains(&id.to_string()));
+        assert!(!debug.contains(&id.0.to_string()));
+        assert!(!debug.contains("user@example.com"));
+        assert!(!debug.contains("fingerprint\""));
+    }
+
+    #[tokio::test]
+    async fn test_escalate() {
+        let mut mock_store = MockStore::new();
+
+        let initial_id = Id::default();
+        mock_store
+            .expect_load()
+            .with(predicate::eq(initial_id))
+            .times(1)
+            .returning(move |_| {
+                Ok(Some(Record {
+                    id: initial_id,
+                    data: Data::default(),
+                    expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+                    client_fingerprint: None,
+                    version: 0,
+                    absolute_expiry: None,
+                    validator_hash: None,
+                }))
+            });
+        mock_store
+            .expect_cycle_and_save()
+            .withf(move |old_session_id, record| {
+                *old_session_id == initial_id
+                    && record.id != initial_id
+                    && record.data.contains_key("user_id")
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mock_store.expect_delete().never();
+        mock_store.expect_create().never();
+
+        let session = Session::new(Some(initial_id), Arc::new(mock_store), None);
+        session
+            .escalate(|data| {
+                data.insert("user_id".to_string(), 42.into());
+            })
+            .await
+            .unwrap();
+
+        assert!(session.id().is_some_and(|id| id != initial_id));
+        assert!(session.is_modified());
+        assert_eq!(session.get::<i32>("user_id").await.unwrap(), Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_escalate_failure_keeps_session() {
+        let mut mock_store = MockStore::new();
+
+        let initial_id = Id::default();
+        mock_store
+            .expect_load()
+            .with(predicate::eq(initial_id))
+            .times(1)
+            .returning(move |_| {
+                Ok(Some(Record {
+                    id: initial_id,
+                    data: Data::from([("role".to_string(), "guest".into())]),
+                    expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+                    client_fingerprint: None,
+                    version: 0,
+                    absolute_expiry: None,
+                    validator_hash: None,
+                }))
+            });
+        mock_store
+            .expect_cycle_and_save()
+            .times(1)
+            .returning(|_, _| Err(session_store::Error::Backend("down".to_string())));
+        mock_store.expect_create().never();
+
+        let session = Session::new(Some(initial_id), Arc::new(mock_store), None);
+        let result = session
+            .escalate(|data| {
+                data.insert("role".to_string(), "admin".into());
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Store(_))));
+        assert_eq!(session.id(), Some(initial_id));
+        assert!(!session.is_modified());
+        assert_eq!(
+            session.get::<String>("role").await.unwrap().as_deref(),
+            Some("guest")
+        );
+        let record_guard = session.inner.record.lock().await;
+        assert_eq!(
+            record_guard.as_ref().map(|record| record.id),
+            Some(initial_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_store_keeps_expiry() {
+        let mut mock_store = MockStore::new();
+
+        let session_id = Id::default();
+        let expiry_date = OffsetDateTime::now_utc() + Duration::days(3);
+        let record = Record {
+            id: session_id,
+            data: Data::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+
+        mock_store
+            .expect_load()
+            .with(predicate::eq(session_id))
+            .times(1)
+            .returning(move |_| Ok(Some(record.clone())));
+        mock_store
+            .expect_save()
+            .withf(move |record| record.id == session_id && record.expiry_date == expiry_date)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let session = Session::from_store(Arc::new(mock_store), session_id)
+            .await
+            .unwrap()
+            .expect("Session exists");
+        session.insert("foo", 42).await.unwrap();
+        session.save().await.unwrap();
+        assert!(!session.is_new().await.unwrap());
+    }
+
+    #[cfg(feature = "session-lock")]
+    #[tokio::test]
+    async fn test_lock() {
+        let mut mock_store = MockStore::new();
+        let session_id = Id::default();
+        let token = Arc::new(parking_lot::Mutex::new(None));
+
+        let mut attempts = 0;
+        let acquired_token = token.clone();
+        mock_store
+            .expect_try_lock()
+            .withf(move |id, key, _, ttl| {
+                *id == session_id && key == "payment" && *ttl == Duration::seconds(30)
+            })
+            .times(2)
+            .returning(move |_, _, token, _| {
+                // Held by another request at first.
+                attempts += 1;
+                *acquired_token.lock() = Some(token);
+                Ok(attempts > 1)
+            });
+        let released_token = token.clone();
+        mock_store
+            .expect_unlock()
+            .withf(move |id, key, token| {
+                *id == session_id && key == "payment" && Some(*token) == *released_token.lock()
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let session = Session::new(Some(session_id), Arc::new(mock_store), None);
+        let lock = session
+            .lock("payment", Duration::seconds(30))
+            .await
+            .unwrap();
+        lock.release().await.unwrap();
+    }
+
+    #[cfg(feature = "session-lock")]
+    #[tokio::test]
+    async fn test_lock_released_on_drop() {
+        let mut mock_store = MockStore::new();
+        let session_id = Id::default();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = parking_lot::Mutex::new(Some(tx));
+
+        mock_store
+            .expect_try_lock()
+            .times(1)
+            .returning(|_, _, _, _| Ok(true));
+        mock_store
+            .expect_unlock()
+            .times(1)
+            .returning(move |_, _, _| {
+                tx.lock().take().unwrap().send(()).unwrap();
+                Ok(())
+            });
+
+        let session = Session::new(Some(session_id), Arc::new(mock_store), None);
+        let lock = session
+            .try_lock("payment", Duration::seconds(30))
+            .await
+            .unwrap();
+        drop(lock);
+        rx.await.unwrap();
+    }
+
+    #[cfg(feature = "session-lock")]
+    #[tokio::test]
+    async fn test_lock_new_session() {
+        // Locks of unsaved sessions don't involve the store.
+        let session = Session::new(None, Arc::new(MockStore::new()), None);
+        let lock = session
+            .try_lock("payment", Duration::seconds(30))
+            .await
+            .unwrap();
+        assert!(lock.is_some());
+        drop(lock);
+    }
+
+    #[tokio::test]
+    async fn test_namespace() {
+        let session = Session::new(None, Arc::new(MockStore::new()), None);
+        let auth = session.namespace("auth");
+        let app = session.namespace("app");
+        assert_eq!(auth.name(), "auth");
+
+        session.insert("user", "plain").await.unwrap();
+        auth.insert("user", 1).await.unwrap();
+        app.insert("user", 2).await.unwrap();
+        assert_eq!(auth.get::<i32>("user").await.unwrap(), Some(1));
+        assert_eq!(app.get::<i32>("user").await.unwrap(), Some(2));
+        assert_eq!(
+            session.get::<String>("user").await.unwrap().as_deref(),
+            Some("plain")
+        );
+
+        let entry = auth.entry::<Vec<i32>>("roles");
+        assert_eq!(entry.key(), "auth.roles");
+        assert!(entry.get_or_insert_with(Vec::new).await.unwrap().is_empty());
+        entry.update(|roles| roles.push(7)).await.unwrap();
+        assert_eq!(
+            session.get::<Vec<i32>>("auth.roles").await.unwrap(),
+            Some(vec![7])
+        );
+
+        // Clearing a namespace leaves other data in place.
+        auth.clear().await.unwrap();
+        assert_eq!(auth.get::<i32>("user").await.unwrap(), None);
+        assert_eq!(entry.get().await.unwrap(), None);
+        assert_eq!(app.remove::<i32>("user").await.unwrap(), Some(2));
+        assert!(session.get::<String>("user").await.unwrap().is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "must be non-empty and not contain `.`")]
+    fn test_namespace_separator() {
+        Session::new(None, Arc::new(MockStore::new()), None).namespace("auth.v2");
+    }
+
+    crate::session_keys! {
+        COUNTER: usize = "counter";
+        ROLES: Vec<String> = "roles";
+    }
+
+    #[tokio::test]
+    async fn test_session_key() {
+        let session = Session::new(None, Arc::new(MockStore::new()), None);
+        assert_eq!(COUNTER.name(), "counter");
+
+        session.insert(COUNTER, 1).await.unwrap();
+        assert_eq!(session.get(COUNTER).await.unwrap(), Some(1));
+        assert_eq!(session.update(COUNTER, |n| *n += 1).await.unwrap(), Some(2));
+        assert_eq!(session.get::<i64>("counter").await.unwrap(), Some(2));
+        assert_eq!(session.remove(COUNTER).await.unwrap(), Some(2));
+
+        let roles = session
+            .get_or_insert_with(ROLES, || vec!["admin".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(roles, ["admin"]);
+        assert_eq!(session.entry(ROLES).get().await.unwrap(), Some(roles));
+
+        let auth = session.namespace("auth");
+        auth.insert(COUNTER, 3).await.unwrap();
+        assert_eq!(auth.get(COUNTER).await.unwrap(), Some(3));
+        assert_eq!(auth.entry(COUNTER).key(), "auth.counter");
+    }
+
+    #[test]
+    #[should_panic(expected = "unique names")]
+    fn test_assert_unique_keys() {
+        assert_unique_keys(&["counter", "roles"]);
+        assert_unique_keys(&["counter", "roles", "counter"]);
+    }
 }
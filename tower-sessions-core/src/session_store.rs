@@ -76,19 +76,54 @@
 //! performance by reducing the need to access the backend store for frequently
 //! accessed sessions.
 //!
+//! # ShardedSessionStore
+//!
+//! The [`ShardedSessionStore`] distributes sessions across a number of stores
+//! via consistent hashing of the session ID, optionally replicating each
+//! session to several of them.
+//!
+//! # TombstoneStore
+//!
+//! The [`TombstoneStore`] remembers deleted sessions for a window of time so
+//! that replays of deleted session IDs may be detected.
+//!
+//! # ResilientStore
+//!
+//! The [`ResilientStore`] bounds store operations with timeouts and a
+//! concurrency limit, and falls back via a circuit breaker when the store is
+//! unhealthy. This requires the `resilience` feature.
+//!
 //! # ExpiredDeletion
 //!
 //! The [`ExpiredDeletion`] trait provides a method for deleting expired
 //! sessions. Implementations can optionally provide a method for continuously
 //! deleting expired sessions at a specified interval.
-use std::fmt::Debug;
+use std::{borrow::Cow, collections::HashMap, fmt::Debug, sync::Arc};
 
 use async_trait::async_trait;
+use futures::StreamExt;
 
-use crate::session::{Id, Record};
+use crate::session::{Id, Record, RecordHeader};
 
 /// Stores must map any errors that might occur during their use to this type.
+///
+/// Beyond its message, an error exposes its [`ErrorKind`], whether it's
+/// transient and the backend it originated from, if known. This allows retry
+/// policies and metrics to be built without matching on messages.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::session_store::{Error, ErrorKind};
+///
+/// let err = Error::Timeout("load took too long".to_string()).with_backend("redis");
+///
+/// assert_eq!(err.kind(), ErrorKind::Timeout);
+/// assert_eq!(err.backend(), Some("redis"));
+/// assert!(err.is_retryable());
+/// ```
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("Encoding failed with: {0}")]
     Encode(String),
@@ -98,13 +133,266 @@ pub enum Error {
 
     #[error("{0}")]
     Backend(String),
+
+    /// The operation did not complete in time.
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// The backend is temporarily unable to serve requests, e.g. because a
+    /// connection could not be established.
+    #[error("Unavailable: {0}")]
+    Unavailable(String),
+
+    /// An error attributed to a named backend, see
+    /// [`with_backend`](Error::with_backend).
+    #[error("{backend}: {source}")]
+    Attributed {
+        backend: Cow<'static, str>,
+        source: Box<Error>,
+    },
+}
+
+/// The kind of a store [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See [`Error::Encode`].
+    Encode,
+
+    /// See [`Error::Decode`].
+    Decode,
+
+    /// See [`Error::Backend`].
+    Backend,
+
+    /// See [`Error::Timeout`].
+    Timeout,
+
+    /// See [`Error::Unavailable`].
+    Unavailable,
+}
+
+impl Error {
+    /// Attributes the error to the named backend, e.g. `"redis"`.
+    pub fn with_backend(self, backend: impl Into<Cow<'static, str>>) -> Self {
+        Self::Attributed {
+            backend: backend.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Encode(_) => ErrorKind::Encode,
+            Self::Decode(_) => ErrorKind::Decode,
+            Self::Backend(_) => ErrorKind::Backend,
+            Self::Timeout(_) => ErrorKind::Timeout,
+            Self::Unavailable(_) => ErrorKind::Unavailable,
+            Self::Attributed { source, .. } => source.kind(),
+        }
+    }
+
+    /// Returns the backend the error originated from, if known.
+    pub fn backend(&self) -> Option<&str> {
+        match self {
+            Self::Attributed { backend, .. } => Some(backend),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the error is expected to resolve by itself, such as
+    /// a timeout.
+    pub fn is_transient(&self) -> bool {
+        matches!(self.kind(), ErrorKind::Timeout | ErrorKind::Unavailable)
+    }
+
+    /// Returns `true` if the operation may succeed when retried.
+    ///
+    /// This is the case for transient errors, while e.g. encoding errors will
+    /// fail again.
+    pub fn is_retryable(&self) -> bool {
+        self.is_transient()
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Encodes records to bytes and decodes them back, for stores persisting
+/// records as opaque values.
+///
+/// This lets applications choose the representation of records at rest, e.g.
+/// a fixed binary layout, independently of the store. Stores accepting a codec
+/// default to a serde-based format.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{
+///     session::Record,
+///     session_store::{self, RecordCodec},
+/// };
+///
+/// #[derive(Debug)]
+/// struct JsonCodec;
+///
+/// impl RecordCodec for JsonCodec {
+///     fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+///         serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+///     }
+///
+///     fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+///         serde_json::from_slice(bytes)
+///             .map_err(|err| session_store::Error::Decode(err.to_string()))
+///     }
+/// }
+/// ```
+pub trait RecordCodec: Debug + Send + Sync + 'static {
+    /// Encodes the record.
+    fn encode(&self, record: &Record) -> Result<Vec<u8>>;
+
+    /// Decodes a record encoded with [`RecordCodec::encode`].
+    fn decode(&self, bytes: &[u8]) -> Result<Record>;
+
+    /// Decodes only the header of an encoded record.
+    ///
+    /// The default implementation decodes the full record. Codecs should
+    /// override it when they can skip decoding the record's data.
+    fn decode_header(&self, bytes: &[u8]) -> Result<RecordHeader> {
+        self.decode(bytes).map(|record| RecordHeader::from(&record))
+    }
+}
+
+/// A [`RecordCodec`] prefixing encoded records with a byte identifying the
+/// codec that encoded them, so that a store's codec can change without
+/// making existing records unreadable.
+///
+/// Records are encoded with the current codec and decoded with whichever
+/// registered codec their prefix identifies. Records written before the
+/// envelope was adopted carry no prefix; they're decoded with the unversioned
+/// codec, if any, when their prefix isn't recognized or they fail to decode.
+/// Tags should thus not collide with the first byte of unversioned records.
+/// Those of the bundled codecs are below `0x80`, which MessagePack-encoded
+/// records never start with.
+///
+/// Records are rewritten with the current codec whenever they're saved, so a
+/// store migrates lazily as its sessions are used. Stores may also offer a
+/// `migrate_codec` method for rewriting all of their records at once.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{
+///     session::Record,
+///     session_store::{self, CodecEnvelope, RecordCodec},
+/// };
+///
+/// #[derive(Debug)]
+/// struct JsonCodec;
+///
+/// impl RecordCodec for JsonCodec {
+///     fn encode(&self, record: &Record) -> session_store::Result<Vec<u8>> {
+///         serde_json::to_vec(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+///     }
+///
+///     fn decode(&self, bytes: &[u8]) -> session_store::Result<Record> {
+///         serde_json::from_slice(bytes)
+///             .map_err(|err| session_store::Error::Decode(err.to_string()))
+///     }
+/// }
+///
+/// // Records written with plain JSON remain readable.
+/// let codec = CodecEnvelope::new(100, JsonCodec).with_unversioned(JsonCodec);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CodecEnvelope {
+    tag: u8,
+    codecs: HashMap<u8, Arc<dyn RecordCodec>>,
+    unversioned: Option<Arc<dyn RecordCodec>>,
+}
+
+impl CodecEnvelope {
+    /// Create a new `CodecEnvelope` encoding records with `codec`, tagged
+    /// with `tag`.
+    pub fn new(tag: u8, codec: impl RecordCodec) -> Self {
+        Self {
+            tag,
+            codecs: HashMap::from([(tag, Arc::new(codec) as Arc<dyn RecordCodec>)]),
+            unversioned: None,
+        }
+    }
+
+    /// Additionally decodes records tagged with `tag` using `codec`, e.g.
+    /// those of a previous codec.
+    ///
+    /// The codec records are encoded with can't be replaced.
+    pub fn with_codec(mut self, tag: u8, codec: impl RecordCodec) -> Self {
+        if tag != self.tag {
+            self.codecs.insert(tag, Arc::new(codec));
+        }
+        self
+    }
+
+    /// Decodes records written without an envelope using `codec`.
+    pub fn with_unversioned(mut self, codec: impl RecordCodec) -> Self {
+        self.unversioned = Some(Arc::new(codec));
+        self
+    }
+
+    /// Returns the tag of the codec records are encoded with.
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    fn decode_with<T>(
+        &self,
+        bytes: &[u8],
+        decode: impl Fn(&dyn RecordCodec, &[u8]) -> Result<T>,
+    ) -> Result<T> {
+        let decoded = match bytes.split_first() {
+            Some((tag, encoded)) => match self.codecs.get(tag) {
+                Some(codec) => decode(&**codec, encoded),
+                None => Err(Error::Decode(format!("unknown codec tag {tag}"))),
+            },
+            None => Err(Error::Decode("missing codec tag".to_string())),
+        };
+
+        match self.unversioned {
+            Some(ref unversioned) => {
+                decoded.or_else(|err| decode(&**unversioned, bytes).map_err(|_| err))
+            }
+            None => decoded,
+        }
+    }
+}
+
+impl RecordCodec for CodecEnvelope {
+    fn encode(&self, record: &Record) -> Result<Vec<u8>> {
+        let encoded = self.codecs[&self.tag].encode(record)?;
+        let mut bytes = Vec::with_capacity(encoded.len() + 1);
+        bytes.push(self.tag);
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Record> {
+        self.decode_with(bytes, |codec, bytes| codec.decode(bytes))
+    }
+
+    fn decode_header(&self, bytes: &[u8]) -> Result<RecordHeader> {
+        self.decode_with(bytes, |codec, bytes| codec.decode_header(bytes))
+    }
+}
+
 /// Defines the interface for session management.
 ///
 /// See [`session_store`](crate::session_store) for more details.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't a session store",
+    label = "expected a type implementing `SessionStore`",
+    note = "stores from other crates must depend on the same version of `tower-sessions-core` \
+            as `tower-sessions`"
+)]
 #[async_trait]
 pub trait SessionStore: Debug + Send + Sync + 'static {
     /// Creates a new session in the store with the provided session record.
@@ -134,6 +422,109 @@ pub trait SessionStore: Debug + Send + Sync + 'static {
     ///
     /// If the session exists, it is removed from the store.
     async fn delete(&self, session_id: &Id) -> Result<()>;
+
+    /// Returns `true` if the provided ID belongs to a session that was
+    /// recently deleted.
+    ///
+    /// Stores that don't keep track of deleted sessions return `false`, which
+    /// is the default. See [`TombstoneStore`].
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        let _ = session_id;
+        Ok(false)
+    }
+
+    /// Loads the session record stored under the ID of the given record,
+    /// saving the given record under its ID if none exists.
+    ///
+    /// Returns the loaded record, or `None` if the given record was saved.
+    /// Unlike [`create`](SessionStore::create), the ID is never changed; an
+    /// existing record which isn't active is replaced. This is used by
+    /// `Session::load_or_create_with`. The default implementation loads and
+    /// then saves the record, so concurrent callers may each save their own;
+    /// stores which can do both atomically, e.g. with `SET NX` and `GET` in
+    /// Redis or `INSERT ... ON CONFLICT ... RETURNING` in SQL, should
+    /// override it.
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        if let Some(loaded_record) = self.load(&record.id).await? {
+            return Ok(Some(loaded_record));
+        }
+        self.save(record).await?;
+        Ok(None)
+    }
+
+    /// Replaces the session stored under `old_session_id` with the given
+    /// record, which is created under a new ID as with
+    /// [`create`](SessionStore::create).
+    ///
+    /// This is used when cycling the session ID on privilege changes, see
+    /// `Session::escalate`. The default implementation deletes the old session
+    /// and then creates the new one; stores which can do both in a single
+    /// roundtrip or atomically should override it.
+    ///
+    /// The new session must expire at the record's `expiry_date`, rather than
+    /// at a time-to-live the store derives itself, so that callers preserving
+    /// the remaining lifetime of a session across cycles are honored.
+    async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+        self.delete(old_session_id).await?;
+        self.create(record).await
+    }
+
+    /// Atomically increments the integer stored under `key` in the data of the
+    /// given session, returning the new value.
+    ///
+    /// Missing keys count from zero. Returns `None` if the session doesn't
+    /// exist or the store doesn't support atomic increments, which is the
+    /// default, in which case `Session::increment` falls back to incrementing
+    /// the value in the session.
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        let _ = (session_id, key, by);
+        Ok(None)
+    }
+
+    /// Returns up to `limit` active session records, most recently active
+    /// first, i.e. those with the latest expiry dates.
+    ///
+    /// This is used to warm caches, see
+    /// [`CachingSessionStore::preload_recent`]. Records which aren't sessions,
+    /// see [`Record::is_internal`], are left out. Stores that can't enumerate
+    /// their sessions return none, which is the default.
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        let _ = limit;
+        Ok(Vec::new())
+    }
+
+    /// Acquires the lock named `key` of the given session on behalf of the
+    /// holder identified by `token`, returning `false` if it's already held.
+    ///
+    /// The lock is released by [`unlock`](SessionStore::unlock) or once `ttl`
+    /// elapses, so that locks of crashed holders don't persist. Locks must be
+    /// shared by all processes using the store, as with `SET NX PX` in Redis or
+    /// advisory locks in SQL databases. This is used by `Session::lock`. Stores
+    /// that don't support locks fail with [`Error::Backend`], which is the
+    /// default.
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        let _ = (session_id, key, token, ttl);
+        Err(Error::Backend(
+            "Session locks aren't supported by this store".to_string(),
+        ))
+    }
+
+    /// Releases the lock named `key` of the given session if it's still held
+    /// by the holder identified by `token`.
+    ///
+    /// See [`try_lock`](SessionStore::try_lock).
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        let _ = (session_id, key, token);
+        Err(Error::Backend(
+            "Session locks aren't supported by this store".to_string(),
+        ))
+    }
 }
 
 async fn default_create<S: SessionStore + ?Sized>(
@@ -149,6 +540,108 @@ async fn default_create<S: SessionStore + ?Sized>(
     Ok(())
 }
 
+// Forwards every method, so that overrides of the pointee are preserved.
+macro_rules! forward_session_store {
+    ($($impl_header:tt)+) => {
+        #[async_trait]
+        $($impl_header)+ {
+            async fn create(&self, session_record: &mut Record) -> Result<()> {
+                (**self).create(session_record).await
+            }
+
+            async fn save(&self, session_record: &Record) -> Result<()> {
+                (**self).save(session_record).await
+            }
+
+            async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+                (**self).load(session_id).await
+            }
+
+            async fn delete(&self, session_id: &Id) -> Result<()> {
+                (**self).delete(session_id).await
+            }
+
+            async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+                (**self).is_tombstoned(session_id).await
+            }
+
+            async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+                (**self).get_or_create(record).await
+            }
+
+            async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+                (**self).cycle_and_save(old_session_id, record).await
+            }
+
+            async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+                (**self).increment(session_id, key, by).await
+            }
+
+            async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+                (**self).list_recent(limit).await
+            }
+
+            async fn try_lock(
+                &self,
+                session_id: &Id,
+                key: &str,
+                token: u128,
+                ttl: time::Duration,
+            ) -> Result<bool> {
+                (**self).try_lock(session_id, key, token, ttl).await
+            }
+
+            async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+                (**self).unlock(session_id, key, token).await
+            }
+        }
+    };
+}
+
+forward_session_store!(impl<S: SessionStore + ?Sized> SessionStore for Arc<S>);
+forward_session_store!(impl<S: SessionStore + ?Sized> SessionStore for Box<S>);
+
+/// A type-erased session store, for choosing the store at runtime.
+///
+/// [`SessionStore`] is object safe, so any store may be wrapped and used
+/// wherever a concrete store type is required, e.g. as the store of a
+/// `SessionManagerLayer`. Clones share the wrapped store.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{session_store::DynSessionStore, MemoryStore, SessionManagerLayer};
+///
+/// // E.g. depending on configuration.
+/// let session_store = DynSessionStore::new(MemoryStore::default());
+/// let session_service = SessionManagerLayer::new(session_store);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynSessionStore(Arc<dyn SessionStore>);
+
+impl DynSessionStore {
+    /// Create a new `DynSessionStore` wrapping the given store.
+    pub fn new<S: SessionStore>(store: S) -> Self {
+        Self(Arc::new(store))
+    }
+}
+
+impl From<Arc<dyn SessionStore>> for DynSessionStore {
+    fn from(store: Arc<dyn SessionStore>) -> Self {
+        Self(store)
+    }
+}
+
+impl std::ops::Deref for DynSessionStore {
+    type Target = dyn SessionStore;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+forward_session_store!(impl SessionStore for DynSessionStore);
+
 /// Provides a layered caching mechanism with a cache as the frontend and a
 /// store as the backend..
 ///
@@ -159,6 +652,9 @@ async fn default_create<S: SessionStore + ?Sized>(
 /// reads need only interact with the frontend, forgoing the cost of retrieving
 /// the session record from the backend.
 ///
+/// Concurrent cache misses for the same session are coalesced, such that only
+/// one of them loads the record from the backend and hydrates the cache.
+///
 /// # Examples
 ///
 /// ```rust,ignore
@@ -176,12 +672,87 @@ async fn default_create<S: SessionStore + ?Sized>(
 pub struct CachingSessionStore<Cache: SessionStore, Store: SessionStore> {
     cache: Cache,
     store: Store,
+    in_flight: Arc<parking_lot::Mutex<HashMap<Id, InFlightLoad>>>,
 }
 
+// A backend load shared by concurrent cache misses for the same session.
+type InFlightLoad = Arc<tokio::sync::OnceCell<Option<Record>>>;
+
 impl<Cache: SessionStore, Store: SessionStore> CachingSessionStore<Cache, Store> {
     /// Create a new `CachingSessionStore`.
     pub fn new(cache: Cache, store: Store) -> Self {
-        Self { cache, store }
+        Self {
+            cache,
+            store,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Loads the given sessions from the store into the cache, e.g. at
+    /// startup, returning the number of sessions cached.
+    ///
+    /// Sessions which don't exist in the store are skipped.
+    pub async fn warm_up(&self, session_ids: impl IntoIterator<Item = Id>) -> Result<usize> {
+        let mut cached = 0;
+        for session_id in session_ids {
+            if let Some(record) = self.store.load(&session_id).await? {
+                self.cache.save(&record).await?;
+                cached += 1;
+            }
+        }
+        Ok(cached)
+    }
+
+    /// Loads up to `limit` of the most recently active sessions from the
+    /// store into the cache, returning the number of sessions cached.
+    ///
+    /// This requires the store to support
+    /// [`list_recent`](SessionStore::list_recent).
+    pub async fn preload_recent(&self, limit: usize) -> Result<usize> {
+        let mut records = self.store.list_recent(limit).await?;
+        records.retain(|record| !record.is_internal());
+        for record in &records {
+            self.cache.save(record).await?;
+        }
+        Ok(records.len())
+    }
+
+    /// Evicts the given session from the cache, e.g. after it was changed or
+    /// deleted in the store by another process.
+    ///
+    /// The next load of the session is read from the store.
+    pub async fn invalidate(&self, session_id: &Id) -> Result<()> {
+        self.in_flight.lock().remove(session_id);
+        self.cache.delete(session_id).await
+    }
+
+    /// Evicts each session ID yielded by `invalidations` from the cache,
+    /// returning once the stream ends.
+    ///
+    /// This is intended to be run as a task fed by the backend's change
+    /// notifications, such as the IDs of updated or deleted documents from a
+    /// MongoDB change stream, so that caches in other processes don't serve
+    /// stale records.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let caching_store = Arc::new(CachingSessionStore::new(moka_store, mongodb_store));
+    /// let invalidations = mongodb_store.changed_ids().await?;
+    /// tokio::task::spawn({
+    ///     let caching_store = caching_store.clone();
+    ///     async move { caching_store.watch_invalidations(invalidations).await }
+    /// });
+    /// ```
+    pub async fn watch_invalidations<S>(&self, invalidations: S) -> Result<()>
+    where
+        S: futures::Stream<Item = Id> + Send,
+    {
+        futures::pin_mut!(invalidations);
+        while let Some(session_id) = invalidations.next().await {
+            self.invalidate(&session_id).await?;
+        }
+        Ok(())
     }
 }
 
@@ -209,33 +780,1242 @@ where
     async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
         match self.cache.load(session_id).await {
             // We found a session in the cache, so let's use it.
-            Ok(Some(session_record)) => Ok(Some(session_record)),
+            Ok(Some(session_record)) => {
+                tracing::trace!(session.cache_hit = true, "session cache hit");
+                Ok(Some(session_record))
+            }
 
             // We didn't find a session in the cache, so we'll try loading from the backend.
             //
             // When we find a session in the backend, we'll hydrate our cache with it.
+            //
+            // Concurrent misses share a single load: the first initializes the cell while
+            // the others wait for its result. Should it fail, the next waiter tries in turn.
             Ok(None) => {
-                let session_record = self.store.load(session_id).await?;
+                tracing::trace!(session.cache_hit = false, "session cache miss");
+                let in_flight_load = self
+                    .in_flight
+                    .lock()
+                    .entry(*session_id)
+                    .or_default()
+                    .clone();
 
-                if let Some(ref session_record) = session_record {
-                    self.cache.save(session_record).await?;
+                let result = in_flight_load
+                    .get_or_try_init(|| async {
+                        let session_record = self.store.load(session_id).await?;
+
+                        if let Some(ref session_record) = session_record {
+                            self.cache.save(session_record).await?;
+                        }
+
+                        Ok(session_record)
+                    })
+                    .await
+                    .cloned();
+
+                // Later misses should observe the backend afresh.
+                let mut in_flight = self.in_flight.lock();
+                if in_flight
+                    .get(session_id)
+                    .is_some_and(|load| Arc::ptr_eq(load, &in_flight_load))
+                {
+                    in_flight.remove(session_id);
                 }
 
-                Ok(session_record)
+                result
+            }
+
+            // Some error occurred with our cache so we'll bubble this up.
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        let store_delete_fut = self.store.delete(session_id);
+        let cache_delete_fut = self.cache.delete(session_id);
+
+        futures::try_join!(store_delete_fut, cache_delete_fut)?;
+
+        Ok(())
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        self.store.is_tombstoned(session_id).await
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        let loaded_record = self.store.get_or_create(record).await?;
+        self.cache
+            .save(loaded_record.as_ref().unwrap_or(record))
+            .await?;
+        Ok(loaded_record)
+    }
+
+    async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+        self.store.cycle_and_save(old_session_id, record).await?;
+        self.cache.delete(old_session_id).await?;
+        self.cache.create(record).await?;
+        Ok(())
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        let value = self.store.increment(session_id, key, by).await?;
+        if value.is_some() {
+            // The cached record is now stale.
+            self.cache.delete(session_id).await?;
+        }
+        Ok(value)
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        self.store.list_recent(limit).await
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        self.store.try_lock(session_id, key, token, ttl).await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        self.store.unlock(session_id, key, token).await
+    }
+}
+
+/// Distributes sessions across a number of stores.
+///
+/// Each session is assigned to shards by hashing its ID onto a consistent hash
+/// ring. This means adding or removing a shard only moves a fraction of the
+/// sessions to other shards. With a replication factor greater than one, each
+/// session is written to that many distinct shards and loads fall back to the
+/// replicas when a shard fails or does not have the session.
+///
+/// Note that shard placement depends on the order of the provided stores and
+/// so this must be stable across deployments.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use tower_sessions::session_store::ShardedSessionStore;
+/// use tower_sessions_redis_store::RedisStore;
+///
+/// let stores = vec![RedisStore::new(pool_a), RedisStore::new(pool_b)];
+/// let sharded_store = ShardedSessionStore::new(stores).with_replication(2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShardedSessionStore<Store: SessionStore> {
+    shards: Vec<Store>,
+    ring: Vec<(u64, usize)>,
+    replication: usize,
+}
+
+impl<Store: SessionStore> ShardedSessionStore<Store> {
+    const VIRTUAL_NODES: u64 = 64;
+
+    /// Create a new `ShardedSessionStore` over the given stores.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `shards` is empty.
+    pub fn new(shards: Vec<Store>) -> Self {
+        assert!(!shards.is_empty(), "at least one shard is required");
+
+        let mut ring = (0..shards.len())
+            .flat_map(|shard| {
+                (0..Self::VIRTUAL_NODES).map(move |node| (mix((shard as u64) << 32 | node), shard))
+            })
+            .collect::<Vec<_>>();
+        ring.sort_unstable();
+
+        Self {
+            shards,
+            ring,
+            replication: 1,
+        }
+    }
+
+    /// Configures the number of shards each session is written to.
+    /// The default value is `1`.
+    ///
+    /// This is capped at the number of shards.
+    pub fn with_replication(mut self, replication: usize) -> Self {
+        self.replication = replication.clamp(1, self.shards.len());
+        self
+    }
+
+    // Returns the indexes of the shards responsible for the given ID, primary
+    // first.
+    fn shard_indexes(&self, session_id: &Id) -> Vec<usize> {
+        let hash = mix(session_id.0 as u64 ^ (session_id.0 >> 64) as u64);
+        let start = self.ring.partition_point(|&(point, _)| point < hash);
+
+        let mut indexes = Vec::with_capacity(self.replication);
+        for &(_, shard) in self.ring.iter().cycle().skip(start) {
+            if !indexes.contains(&shard) {
+                indexes.push(shard);
+                if indexes.len() == self.replication {
+                    break;
+                }
             }
+        }
+        indexes
+    }
+}
+
+// SplitMix64 finalizer: a stable hash so placement agrees across processes.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[async_trait]
+impl<Store> SessionStore for ShardedSessionStore<Store>
+where
+    Store: SessionStore,
+{
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        // The primary shard mitigates ID collisions. Because a new ID may belong to
+        // another shard, we undo the creation and try again on the shard it
+        // belongs to.
+        loop {
+            let session_id = record.id;
+            let indexes = self.shard_indexes(&session_id);
+            let primary = &self.shards[indexes[0]];
+
+            primary.create(record).await?;
+            if record.id != session_id && self.shard_indexes(&record.id)[0] != indexes[0] {
+                primary.delete(&record.id).await?;
+                continue;
+            }
+
+            let replica_saves = indexes[1..]
+                .iter()
+                .map(|&index| self.shards[index].save(record));
+            futures::future::try_join_all(replica_saves).await?;
+
+            return Ok(());
+        }
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        let saves = self
+            .shard_indexes(&record.id)
+            .into_iter()
+            .map(|index| self.shards[index].save(record));
+        futures::future::try_join_all(saves).await?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        let mut last_err = None;
+        for index in self.shard_indexes(session_id) {
+            match self.shards[index].load(session_id).await {
+                Ok(Some(record)) => return Ok(Some(record)),
+                Ok(None) => {}
+                Err(err) => {
+                    tracing::warn!(err = %err, shard = index, "failed to load from shard");
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        match last_err {
+            Some(err) => Err(err),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        let deletes = self
+            .shard_indexes(session_id)
+            .into_iter()
+            .map(|index| self.shards[index].delete(session_id));
+        futures::future::try_join_all(deletes).await?;
+        Ok(())
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        let index = self.shard_indexes(session_id)[0];
+        self.shards[index].is_tombstoned(session_id).await
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        // The primary shard decides whether the record exists.
+        let indexes = self.shard_indexes(&record.id);
+        let loaded_record = self.shards[indexes[0]].get_or_create(record).await?;
+
+        let replicated = loaded_record.as_ref().unwrap_or(record);
+        let replica_saves = indexes[1..]
+            .iter()
+            .map(|&index| self.shards[index].save(replicated));
+        futures::future::try_join_all(replica_saves).await?;
+
+        Ok(loaded_record)
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        // The primary shard applies the increment, which is then copied to the
+        // replicas so that loads falling back to them see it.
+        let indexes = self.shard_indexes(session_id);
+        let primary = &self.shards[indexes[0]];
+        let value = primary.increment(session_id, key, by).await?;
+        if value.is_none() || indexes.len() == 1 {
+            return Ok(value);
+        }
+
+        if let Some(record) = primary.load(session_id).await? {
+            let replica_saves = indexes[1..]
+                .iter()
+                .map(|&index| self.shards[index].save(&record));
+            futures::future::try_join_all(replica_saves).await?;
+        }
+
+        Ok(value)
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        let lists = self.shards.iter().map(|shard| shard.list_recent(limit));
+        let mut records = futures::future::try_join_all(lists)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        // Replicas may return the same session.
+        records.sort_by_key(|record| std::cmp::Reverse(record.expiry_date));
+        let mut seen = std::collections::HashSet::new();
+        records.retain(|record| seen.insert(record.id));
+        records.truncate(limit);
+
+        Ok(records)
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        // Locks are only held on the primary shard.
+        let index = self.shard_indexes(session_id)[0];
+        self.shards[index]
+            .try_lock(session_id, key, token, ttl)
+            .await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        let index = self.shard_indexes(session_id)[0];
+        self.shards[index].unlock(session_id, key, token).await
+    }
+}
+
+/// Remembers deleted sessions for a period of time.
+///
+/// When a session is deleted, for instance on logout, a tombstone record is
+/// left in its place until the window elapses. Loading a tombstoned session
+/// yields `None` just as with any deleted session, but the middleware may
+/// detect the replay of a deleted session via
+/// [`is_tombstoned`](SessionStore::is_tombstoned) and apply a
+/// [`TombstonePolicy`](crate::session::TombstonePolicy).
+///
+/// Because tombstones are regular records of the wrapped store, they're shared
+/// across processes and expire as any other record. Tombstoned IDs are never
+/// reused by [`create`](SessionStore::create) for as long as they're
+/// remembered. Tombstones are [internal](Record::is_internal) records, so
+/// stores leave them out of their session counts, see [`SessionStoreStats`].
+///
+/// # Examples
+///
+/// ```rust
+/// use time::Duration;
+/// use tower_sessions::{session_store::TombstoneStore, MemoryStore};
+///
+/// let store = TombstoneStore::new(MemoryStore::default(), Duration::days(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TombstoneStore<Store: SessionStore> {
+    store: Store,
+    window: time::Duration,
+}
+
+impl<Store: SessionStore> TombstoneStore<Store> {
+    const TOMBSTONE_KEY: &'static str = "__tower_sessions.tombstone";
+
+    /// Create a new `TombstoneStore` remembering deleted sessions for the
+    /// given window.
+    pub fn new(store: Store, window: time::Duration) -> Self {
+        Self { store, window }
+    }
+
+    fn is_tombstone(record: &Record) -> bool {
+        record.data.contains_key(Self::TOMBSTONE_KEY)
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStore for TombstoneStore<Store>
+where
+    Store: SessionStore,
+{
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        self.store.create(record).await
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        self.store.save(record).await
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        Ok(self
+            .store
+            .load(session_id)
+            .await?
+            .filter(|record| !Self::is_tombstone(record)))
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        let mut tombstone = Record::new_internal(time::OffsetDateTime::now_utc() + self.window);
+        tombstone.id = *session_id;
+        tombstone
+            .data
+            .insert(Self::TOMBSTONE_KEY.to_string(), true.into());
+        self.store.save(&tombstone).await
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        Ok(self
+            .store
+            .load(session_id)
+            .await?
+            .is_some_and(|record| Self::is_tombstone(&record)))
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        match self.store.get_or_create(record).await? {
+            // As with `save`, records replace tombstones.
+            Some(loaded_record) if Self::is_tombstone(&loaded_record) => {
+                self.store.save(record).await?;
+                Ok(None)
+            }
+            loaded_record => Ok(loaded_record),
+        }
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        // Tombstones are never incremented.
+        if self.is_tombstoned(session_id).await? {
+            return Ok(None);
+        }
+        self.store.increment(session_id, key, by).await
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        let mut records = self.store.list_recent(limit).await?;
+        records.retain(|record| !Self::is_tombstone(record));
+        Ok(records)
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        self.store.try_lock(session_id, key, token, ttl).await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        self.store.unlock(session_id, key, token).await
+    }
+}
+
+/// Enforces expiry uniformly, regardless of the wrapped store.
+///
+/// Stores are expected not to load records which aren't
+/// [active](Record::is_active), but backends differ in how strictly they
+/// follow this, e.g. when relying on the backend's own key expiry. This
+/// wrapper filters inactive records from [`load`](SessionStore::load) and
+/// [`list_recent`](SessionStore::list_recent) and refuses to
+/// [`increment`](SessionStore::increment) them, so the middleware behaves the
+/// same with any backend. Checking increments costs an additional load.
+///
+/// # Examples
+///
+/// ```rust
+/// use tower_sessions::{session_store::ExpiryEnforcingStore, MemoryStore};
+///
+/// let store = ExpiryEnforcingStore::new(MemoryStore::default());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExpiryEnforcingStore<Store: SessionStore> {
+    store: Store,
+}
+
+impl<Store: SessionStore> ExpiryEnforcingStore<Store> {
+    /// Create a new `ExpiryEnforcingStore` wrapping the given store.
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStore for ExpiryEnforcingStore<Store>
+where
+    Store: SessionStore,
+{
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        self.store.create(record).await
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        self.store.save(record).await
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        Ok(self.store.load(session_id).await?.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        self.store.delete(session_id).await
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        self.store.is_tombstoned(session_id).await
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        match self.store.get_or_create(record).await? {
+            Some(loaded_record) if !loaded_record.is_active() => {
+                self.store.save(record).await?;
+                Ok(None)
+            }
+            loaded_record => Ok(loaded_record),
+        }
+    }
+
+    async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+        self.store.cycle_and_save(old_session_id, record).await
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        if self.load(session_id).await?.is_none() {
+            return Ok(None);
+        }
+        self.store.increment(session_id, key, by).await
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        let mut records = self.store.list_recent(limit).await?;
+        records.retain(Record::is_active);
+        Ok(records)
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        self.store.try_lock(session_id, key, token, ttl).await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        self.store.unlock(session_id, key, token).await
+    }
+}
+
+/// What a [`ResilientStore`] does while its circuit is open.
+#[cfg(feature = "resilience")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Fail operations with [`Error::Unavailable`].
+    #[default]
+    Error,
+
+    /// Behave as an empty store which discards writes.
+    ///
+    /// Requests then proceed with fresh sessions which aren't persisted.
+    Ephemeral,
+}
+
+/// Protects applications from a slow or unhealthy store.
+///
+/// Operations may be bounded by a timeout and the number of operations in
+/// flight by a concurrency limit, where excess operations wait for a slot
+/// within the timeout.
+///
+/// With a circuit breaker configured, consecutive failures and timeouts are
+/// counted. Once the threshold is reached the circuit opens and operations
+/// short-circuit to the configured [`Fallback`] without touching the store.
+/// After the cooldown a single operation is let through: success closes the
+/// circuit again while failure reopens it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use tower_sessions::{
+///     session_store::{Fallback, ResilientStore},
+///     MemoryStore,
+/// };
+///
+/// let store = ResilientStore::new(MemoryStore::default())
+///     .with_timeout(Duration::from_millis(250))
+///     .with_concurrency_limit(64)
+///     .with_circuit_breaker(5, Duration::from_secs(30))
+///     .with_fallback(Fallback::Ephemeral);
+/// ```
+#[cfg(feature = "resilience")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+#[derive(Debug, Clone)]
+pub struct ResilientStore<Store: SessionStore> {
+    store: Store,
+    timeout: Option<std::time::Duration>,
+    limit: Option<std::sync::Arc<tokio::sync::Semaphore>>,
+    circuit_breaker: Option<(usize, std::time::Duration)>,
+    fallback: Fallback,
+    circuit: std::sync::Arc<parking_lot::Mutex<Circuit>>,
+}
+
+#[cfg(feature = "resilience")]
+#[derive(Debug, Default)]
+struct Circuit {
+    failures: usize,
+    open_until: Option<std::time::Instant>,
+}
+
+#[cfg(feature = "resilience")]
+impl<Store: SessionStore> ResilientStore<Store> {
+    /// Create a new `ResilientStore` wrapping the given store.
+    ///
+    /// By default no timeout, concurrency limit or circuit breaker is
+    /// applied.
+    pub fn new(store: Store) -> Self {
+        Self {
+            store,
+            timeout: None,
+            limit: None,
+            circuit_breaker: None,
+            fallback: Fallback::default(),
+            circuit: Default::default(),
+        }
+    }
+
+    /// Configures the maximum duration of each operation, including any time
+    /// spent waiting for a concurrency slot.
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Configures the maximum number of operations in flight.
+    pub fn with_concurrency_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(std::sync::Arc::new(tokio::sync::Semaphore::new(limit)));
+        self
+    }
+
+    /// Configures the circuit to open after `threshold` consecutive failures
+    /// and to remain open for `cooldown`.
+    pub fn with_circuit_breaker(mut self, threshold: usize, cooldown: std::time::Duration) -> Self {
+        self.circuit_breaker = Some((threshold.max(1), cooldown));
+        self
+    }
+
+    /// Configures what operations do while the circuit is open.
+    ///
+    /// The default value is [`Fallback::Error`].
+    pub fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    async fn call<T>(
+        &self,
+        op: impl std::future::Future<Output = Result<T>>,
+        ephemeral: T,
+    ) -> Result<T> {
+        if self.is_open() {
+            tracing::debug!("store circuit open, short-circuiting");
+            return match self.fallback {
+                Fallback::Error => Err(Error::Unavailable("Store circuit is open".to_string())),
+                Fallback::Ephemeral => Ok(ephemeral),
+            };
+        }
+
+        let limited_op = async {
+            let _permit = match &self.limit {
+                Some(limit) => Some(
+                    limit
+                        .acquire()
+                        .await
+                        .map_err(|err| Error::Backend(err.to_string()))?,
+                ),
+                None => None,
+            };
+            op.await
+        };
+
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, limited_op)
+                .await
+                .unwrap_or_else(|_| Err(Error::Timeout("Store operation timed out".to_string()))),
+            None => limited_op.await,
+        };
+
+        self.record(result.is_ok());
+
+        result
+    }
+
+    fn is_open(&self) -> bool {
+        let Some((_, cooldown)) = self.circuit_breaker else {
+            return false;
+        };
+
+        let mut circuit = self.circuit.lock();
+        match circuit.open_until {
+            Some(open_until) if std::time::Instant::now() < open_until => true,
+            Some(_) => {
+                // Let this operation probe the store while others continue to
+                // short-circuit.
+                circuit.open_until = Some(std::time::Instant::now() + cooldown);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let Some((threshold, cooldown)) = self.circuit_breaker else {
+            return;
+        };
+
+        let mut circuit = self.circuit.lock();
+        if success {
+            circuit.failures = 0;
+            circuit.open_until = None;
+        } else {
+            circuit.failures += 1;
+            if circuit.failures >= threshold {
+                tracing::warn!(failures = circuit.failures, "opening store circuit");
+                circuit.open_until = Some(std::time::Instant::now() + cooldown);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStore for ResilientStore<Store>
+where
+    Store: SessionStore,
+{
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        self.call(self.store.create(record), ()).await
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        self.call(self.store.save(record), ()).await
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        self.call(self.store.load(session_id), None).await
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        self.call(self.store.delete(session_id), ()).await
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        self.call(self.store.is_tombstoned(session_id), false).await
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        self.call(self.store.get_or_create(record), None).await
+    }
+
+    async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+        self.call(self.store.cycle_and_save(old_session_id, record), ())
+            .await
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        self.call(self.store.increment(session_id, key, by), None)
+            .await
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        self.call(self.store.list_recent(limit), Vec::new()).await
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        // Ephemeral sessions aren't shared, so their locks are uncontended.
+        self.call(self.store.try_lock(session_id, key, token, ttl), true)
+            .await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        self.call(self.store.unlock(session_id, key, token), ())
+            .await
+    }
+}
+
+/// Retries store operations which fail with transient errors.
+///
+/// Operations failing with an error for which [`Error::is_retryable`] holds,
+/// such as a timeout, are attempted again after an exponential backoff with
+/// jitter, up to the configured maximum number of attempts. Other errors are
+/// returned immediately.
+///
+/// Creating, saving, loading, deleting and cycling sessions are retried.
+/// Increments and locks aren't, as a timed out attempt may still have been
+/// applied by the store.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use tower_sessions::{session_store::RetryStore, MemoryStore};
+///
+/// let store = RetryStore::new(MemoryStore::default())
+///     .with_max_attempts(5)
+///     .with_backoff(Duration::from_millis(20), Duration::from_millis(500));
+/// ```
+#[cfg(feature = "resilience")]
+#[cfg_attr(docsrs, doc(cfg(feature = "resilience")))]
+#[derive(Debug, Clone)]
+pub struct RetryStore<Store: SessionStore> {
+    store: Store,
+    max_attempts: u32,
+    base_delay: std::time::Duration,
+    max_delay: std::time::Duration,
+    jitter: bool,
+}
+
+#[cfg(feature = "resilience")]
+impl<Store: SessionStore> RetryStore<Store> {
+    /// Create a new `RetryStore` wrapping the given store.
+    pub fn new(store: Store) -> Self {
+        Self {
+            store,
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_millis(50),
+            max_delay: std::time::Duration::from_secs(1),
+            jitter: true,
+        }
+    }
+
+    /// Configures the maximum number of attempts made for each operation,
+    /// including the first.
+    ///
+    /// The default value is `3`.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Configures the delay before the first retry, which doubles for each
+    /// subsequent retry up to `max_delay`.
+    ///
+    /// The default values are 50 milliseconds and 1 second.
+    pub fn with_backoff(
+        mut self,
+        base_delay: std::time::Duration,
+        max_delay: std::time::Duration,
+    ) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay.max(base_delay);
+        self
+    }
+
+    /// Configures whether delays are randomized, between half and all of the
+    /// backoff, to avoid retries from many requests arriving together.
+    ///
+    /// The default value is `true`.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn delay(&self, attempt: u32) -> std::time::Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        if self.jitter {
+            use rand::Rng;
+            let half = delay / 2;
+            half + rand::thread_rng().gen_range(std::time::Duration::ZERO..=half)
+        } else {
+            delay
+        }
+    }
+
+    // Waits before the next attempt, returning `false` if the error shouldn't
+    // be retried.
+    async fn backoff(&self, err: &Error, attempt: u32) -> bool {
+        if !err.is_retryable() || attempt >= self.max_attempts {
+            return false;
+        }
+        let delay = self.delay(attempt);
+        tracing::debug!(%err, attempt, ?delay, "retrying store operation");
+        tokio::time::sleep(delay).await;
+        true
+    }
+
+    async fn retry<T, F>(&self, mut op: impl FnMut() -> F) -> Result<T>
+    where
+        F: std::future::Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match op().await {
+                Err(err) => err,
+                result => return result,
+            };
+            if !self.backoff(&err, attempt).await {
+                return Err(err);
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStore for RetryStore<Store>
+where
+    Store: SessionStore,
+{
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        // The record is borrowed mutably by each attempt, so this can't use
+        // `retry`.
+        let mut attempt = 1;
+        loop {
+            let err = match self.store.create(record).await {
+                Err(err) => err,
+                result => return result,
+            };
+            if !self.backoff(&err, attempt).await {
+                return Err(err);
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        self.retry(|| self.store.save(record)).await
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        self.retry(|| self.store.load(session_id)).await
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        self.retry(|| self.store.delete(session_id)).await
+    }
+
+    async fn is_tombstoned(&self, session_id: &Id) -> Result<bool> {
+        self.retry(|| self.store.is_tombstoned(session_id)).await
+    }
+
+    async fn get_or_create(&self, record: &Record) -> Result<Option<Record>> {
+        self.retry(|| self.store.get_or_create(record)).await
+    }
+
+    async fn cycle_and_save(&self, old_session_id: &Id, record: &mut Record) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let err = match self.store.cycle_and_save(old_session_id, record).await {
+                Err(err) => err,
+                result => return result,
+            };
+            if !self.backoff(&err, attempt).await {
+                return Err(err);
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>> {
+        self.store.increment(session_id, key, by).await
+    }
+
+    async fn list_recent(&self, limit: usize) -> Result<Vec<Record>> {
+        self.retry(|| self.store.list_recent(limit)).await
+    }
+
+    async fn try_lock(
+        &self,
+        session_id: &Id,
+        key: &str,
+        token: u128,
+        ttl: time::Duration,
+    ) -> Result<bool> {
+        self.store.try_lock(session_id, key, token, ttl).await
+    }
+
+    async fn unlock(&self, session_id: &Id, key: &str, token: u128) -> Result<()> {
+        self.store.unlock(session_id, key, token).await
+    }
+}
+
+/// Provides a method for checking the connectivity of a store.
+///
+/// This is intended for health check endpoints, see [`health_check`].
+#[async_trait]
+pub trait SessionStoreHealth: Send + Sync {
+    /// Checks that the store is reachable, returning an error otherwise.
+    async fn ping(&self) -> Result<()>;
+}
+
+#[async_trait]
+impl<Cache, Store> SessionStoreHealth for CachingSessionStore<Cache, Store>
+where
+    Cache: SessionStore + SessionStoreHealth,
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        futures::try_join!(self.cache.ping(), self.store.ping())?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreHealth for ShardedSessionStore<Store>
+where
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        futures::future::try_join_all(self.shards.iter().map(|shard| shard.ping())).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreHealth for TombstoneStore<Store>
+where
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        self.store.ping().await
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreHealth for ExpiryEnforcingStore<Store>
+where
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        self.store.ping().await
+    }
+}
+
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStoreHealth for ResilientStore<Store>
+where
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        // An open circuit is unhealthy even when falling back to ephemeral
+        // sessions.
+        let open_until = self.circuit.lock().open_until;
+        if open_until.is_some_and(|open_until| std::time::Instant::now() < open_until) {
+            return Err(Error::Unavailable("Store circuit is open".to_string()));
+        }
+        self.call(self.store.ping(), ()).await
+    }
+}
+
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStoreHealth for RetryStore<Store>
+where
+    Store: SessionStore + SessionStoreHealth,
+{
+    async fn ping(&self) -> Result<()> {
+        // Health checks should reflect the store's current state.
+        self.store.ping().await
+    }
+}
+
+/// Pings the store, responding with `200 OK` if it's reachable and
+/// `503 Service Unavailable` otherwise.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use axum_core::response::Response;
+/// use tower_sessions::{session_store::health_check, MemoryStore};
+///
+/// async fn healthz(session_store: Arc<MemoryStore>) -> Response {
+///     health_check(session_store.as_ref()).await
+/// }
+/// ```
+#[cfg(feature = "axum-core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum-core")))]
+pub async fn health_check<Store>(session_store: &Store) -> axum_core::response::Response
+where
+    Store: SessionStoreHealth + ?Sized,
+{
+    use axum_core::response::IntoResponse;
+
+    match session_store.ping().await {
+        Ok(()) => (http::StatusCode::OK, "ok").into_response(),
+        Err(err) => {
+            tracing::error!(err = %err, "session store health check failed");
+            (
+                http::StatusCode::SERVICE_UNAVAILABLE,
+                "session store unavailable",
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Provides statistics about the sessions held by a store.
+///
+/// This is intended for dashboards, e.g. to display the number of users
+/// online, see [`active_sessions`].
+#[async_trait]
+pub trait SessionStoreStats: Send + Sync {
+    /// Returns the number of sessions which haven't expired.
+    ///
+    /// The count is approximate: stores should answer it as cheaply as their
+    /// backend allows, for instance from an entry count or an index, and may
+    /// include sessions which expired but haven't been deleted yet. Records
+    /// which aren't sessions, see [`Record::is_internal`], should be left out
+    /// where the backend can tell them apart without decoding each record.
+    async fn active_count(&self) -> Result<u64>;
+}
+
+#[async_trait]
+impl<Cache, Store> SessionStoreStats for CachingSessionStore<Cache, Store>
+where
+    Cache: SessionStore,
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        // The cache only holds a subset of the backing store's sessions.
+        self.store.active_count().await
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreStats for ShardedSessionStore<Store>
+where
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        let counts =
+            futures::future::try_join_all(self.shards.iter().map(|shard| shard.active_count()))
+                .await?;
+        // Each session is held by as many shards as the replication factor.
+        Ok(counts.into_iter().sum::<u64>() / self.replication as u64)
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreStats for TombstoneStore<Store>
+where
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        self.store.active_count().await
+    }
+}
+
+#[async_trait]
+impl<Store> SessionStoreStats for ExpiryEnforcingStore<Store>
+where
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        self.store.active_count().await
+    }
+}
 
-            // Some error occurred with our cache so we'll bubble this up.
-            Err(err) => Err(err),
-        }
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStoreStats for ResilientStore<Store>
+where
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        self.call(self.store.active_count(), 0).await
     }
+}
 
-    async fn delete(&self, session_id: &Id) -> Result<()> {
-        let store_delete_fut = self.store.delete(session_id);
-        let cache_delete_fut = self.cache.delete(session_id);
+#[cfg(feature = "resilience")]
+#[async_trait]
+impl<Store> SessionStoreStats for RetryStore<Store>
+where
+    Store: SessionStore + SessionStoreStats,
+{
+    async fn active_count(&self) -> Result<u64> {
+        self.retry(|| self.store.active_count()).await
+    }
+}
 
-        futures::try_join!(store_delete_fut, cache_delete_fut)?;
+/// Counts the store's active sessions, responding with `200 OK` and a JSON
+/// body such as `{"active_sessions":42}`, or `503 Service Unavailable` if the
+/// store can't be reached.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// use axum_core::response::Response;
+/// use tower_sessions::{session_store::active_sessions, MemoryStore};
+///
+/// async fn users_online(session_store: Arc<MemoryStore>) -> Response {
+///     active_sessions(session_store.as_ref()).await
+/// }
+/// ```
+#[cfg(feature = "axum-core")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum-core")))]
+pub async fn active_sessions<Store>(session_store: &Store) -> axum_core::response::Response
+where
+    Store: SessionStoreStats + ?Sized,
+{
+    use axum_core::response::IntoResponse;
 
-        Ok(())
+    match session_store.active_count().await {
+        Ok(count) => (
+            [(http::header::CONTENT_TYPE, "application/json")],
+            serde_json::json!({ "active_sessions": count }).to_string(),
+        )
+            .into_response(),
+        Err(err) => {
+            tracing::error!(err = %err, "failed to count active sessions");
+            (
+                http::StatusCode::SERVICE_UNAVAILABLE,
+                "session store unavailable",
+            )
+                .into_response()
+        }
     }
 }
 
@@ -289,6 +2069,16 @@ where
     }
 }
 
+#[async_trait]
+impl<Store> ExpiredDeletion for ExpiryEnforcingStore<Store>
+where
+    Store: ExpiredDeletion,
+{
+    async fn delete_expired(&self) -> Result<()> {
+        self.store.delete_expired().await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use mockall::{
@@ -322,6 +2112,16 @@ mod tests {
             async fn save(&self, record: &Record) -> Result<()>;
             async fn load(&self, session_id: &Id) -> Result<Option<Record>>;
             async fn delete(&self, session_id: &Id) -> Result<()>;
+            async fn get_or_create(&self, record: &Record) -> Result<Option<Record>>;
+            async fn increment(&self, session_id: &Id, key: &str, by: i64) -> Result<Option<i64>>;
+            async fn list_recent(&self, limit: usize) -> Result<Vec<Record>>;
+            async fn try_lock(
+                &self,
+                session_id: &Id,
+                key: &str,
+                token: u128,
+                ttl: Duration,
+            ) -> Result<bool>;
         }
     }
 
@@ -344,6 +2144,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
 
         store
@@ -362,6 +2166,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         store
             .expect_save()
@@ -381,6 +2189,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         let expected_record = record.clone();
 
@@ -410,6 +2222,19 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_error_kind() {
+        let err = Error::Decode("bad payload".to_string()).with_backend("redis");
+        assert_eq!(err.kind(), ErrorKind::Decode);
+        assert_eq!(err.backend(), Some("redis"));
+        assert_eq!(err.to_string(), "redis: Decoding failed with: bad payload");
+        assert!(!err.is_retryable());
+
+        let err = Error::Unavailable("connection refused".to_string());
+        assert_eq!(err.backend(), None);
+        assert!(err.is_transient());
+    }
+
     #[tokio::test]
     async fn test_caching_store_create() {
         let mut cache = MockCache::new();
@@ -418,6 +2243,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
 
         cache.expect_create().times(1).returning(|_| Ok(()));
@@ -436,6 +2265,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
 
         cache
@@ -463,6 +2296,10 @@ mod tests {
             id: Default::default(),
             data: Default::default(),
             expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
         };
         let expected_record = record.clone();
 
@@ -480,6 +2317,310 @@ mod tests {
         assert_eq!(result.unwrap(), Some(expected_record));
     }
 
+    #[tokio::test]
+    async fn test_caching_store_load_coalesces_misses() {
+        #[derive(Debug, Default)]
+        struct CountingStore {
+            loads: std::sync::atomic::AtomicUsize,
+        }
+
+        #[async_trait]
+        impl SessionStore for CountingStore {
+            async fn save(&self, _: &Record) -> Result<()> {
+                Ok(())
+            }
+
+            async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+                self.loads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                // Let the other loads catch up with this one.
+                tokio::task::yield_now().await;
+                let mut record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+                record.id = *session_id;
+                Ok(Some(record))
+            }
+
+            async fn delete(&self, _: &Id) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut cache = MockCache::new();
+        cache.expect_load().times(5).returning(|_| Ok(None));
+        cache.expect_save().times(1).returning(|_| Ok(()));
+
+        let caching_store = CachingSessionStore::new(cache, CountingStore::default());
+        let session_id = Id::default();
+        let loads = (0..5).map(|_| caching_store.load(&session_id));
+        let results = futures::future::join_all(loads).await;
+
+        assert!(results.into_iter().all(|result| result
+            .unwrap()
+            .is_some_and(|record| record.id == session_id)));
+        assert_eq!(
+            caching_store
+                .store
+                .loads
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+        assert!(caching_store.in_flight.lock().is_empty());
+    }
+
+    #[derive(Debug)]
+    struct PingStore(bool);
+
+    #[async_trait]
+    impl SessionStore for PingStore {
+        async fn save(&self, _record: &Record) -> Result<()> {
+            Ok(())
+        }
+
+        async fn load(&self, _session_id: &Id) -> Result<Option<Record>> {
+            Ok(None)
+        }
+
+        async fn delete(&self, _session_id: &Id) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl SessionStoreHealth for PingStore {
+        async fn ping(&self) -> Result<()> {
+            if self.0 {
+                Ok(())
+            } else {
+                Err(Error::Unavailable("down".to_string()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_health() {
+        assert!(CachingSessionStore::new(PingStore(true), PingStore(true))
+            .ping()
+            .await
+            .is_ok());
+        assert!(CachingSessionStore::new(PingStore(true), PingStore(false))
+            .ping()
+            .await
+            .is_err());
+        assert!(
+            ShardedSessionStore::new(vec![PingStore(true), PingStore(false)])
+                .ping()
+                .await
+                .is_err()
+        );
+        assert!(TombstoneStore::new(PingStore(true), Duration::minutes(1))
+            .ping()
+            .await
+            .is_ok());
+    }
+
+    #[async_trait]
+    impl SessionStoreStats for PingStore {
+        async fn active_count(&self) -> Result<u64> {
+            if self.0 {
+                Ok(2)
+            } else {
+                Err(Error::Unavailable("down".to_string()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_stats() {
+        let caching_store = CachingSessionStore::new(PingStore(false), PingStore(true));
+        assert_eq!(caching_store.active_count().await.unwrap(), 2);
+        let sharded_store = ShardedSessionStore::new(vec![PingStore(true), PingStore(true)]);
+        assert_eq!(sharded_store.active_count().await.unwrap(), 4);
+        let sharded_store = ShardedSessionStore::new(vec![PingStore(true), PingStore(false)]);
+        assert!(sharded_store.active_count().await.is_err());
+
+        // Replicas are counted once.
+        let sharded_store =
+            ShardedSessionStore::new(vec![PingStore(true), PingStore(true), PingStore(true)])
+                .with_replication(3);
+        assert_eq!(sharded_store.active_count().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_store_locks() {
+        let session_id = Id::default();
+        let ttl = Duration::seconds(30);
+        assert!(PingStore(true)
+            .try_lock(&session_id, "key", 1, ttl)
+            .await
+            .is_err());
+
+        // Locks are held by the backing store, which is shared.
+        let mut store = MockStore::new();
+        store
+            .expect_try_lock()
+            .withf(move |id, key, token, _| *id == session_id && key == "key" && *token == 1)
+            .times(1)
+            .returning(|_, _, _, _| Ok(true));
+        let caching_store = CachingSessionStore::new(MockCache::new(), store);
+        assert!(caching_store
+            .try_lock(&session_id, "key", 1, ttl)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_caching_store_warm_up() {
+        let mut cache = MockCache::new();
+        let mut store = MockStore::new();
+        let existing_id = Id::default();
+        let missing_id = Id::default();
+
+        store
+            .expect_load()
+            .with(predicate::eq(existing_id))
+            .times(1)
+            .returning(move |_| {
+                let mut record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+                record.id = existing_id;
+                Ok(Some(record))
+            });
+        store
+            .expect_load()
+            .with(predicate::eq(missing_id))
+            .times(1)
+            .returning(|_| Ok(None));
+        cache
+            .expect_save()
+            .withf(move |record| record.id == existing_id)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let caching_store = CachingSessionStore::new(cache, store);
+        let cached = caching_store.warm_up([existing_id, missing_id]).await;
+        assert_eq!(cached.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_caching_store_watch_invalidations() {
+        let mut cache = MockCache::new();
+        let mut store = MockStore::new();
+        let changed_id = Id::default();
+        let deleted_id = Id::default();
+
+        cache
+            .expect_delete()
+            .withf(move |id| *id == changed_id || *id == deleted_id)
+            .times(2)
+            .returning(|_| Ok(()));
+        store.expect_delete().never();
+
+        let caching_store = CachingSessionStore::new(cache, store);
+        let invalidations = futures::stream::iter([changed_id, deleted_id]);
+        assert!(caching_store
+            .watch_invalidations(invalidations)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_caching_store_preload_recent() {
+        let mut cache = MockCache::new();
+        let mut store = MockStore::new();
+
+        store
+            .expect_list_recent()
+            .with(predicate::eq(2))
+            .times(1)
+            .returning(|limit| {
+                Ok((0..limit)
+                    .map(|_| Record::new(OffsetDateTime::now_utc() + Duration::minutes(30)))
+                    .collect())
+            });
+        cache.expect_save().times(2).returning(|_| Ok(()));
+
+        let caching_store = CachingSessionStore::new(cache, store);
+        assert_eq!(caching_store.preload_recent(2).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_caching_store_get_or_create() {
+        let mut cache = MockCache::new();
+        let mut store = MockStore::new();
+        let record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+        let mut existing_record = record.clone();
+        existing_record
+            .data
+            .insert("foo".to_string(), serde_json::json!(42));
+        let expected_record = existing_record.clone();
+
+        store
+            .expect_get_or_create()
+            .times(1)
+            .returning(move |_| Ok(Some(existing_record.clone())));
+        cache
+            .expect_save()
+            .withf(move |record| record.data.contains_key("foo"))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let caching_store = CachingSessionStore::new(cache, store);
+        let result = caching_store.get_or_create(&record).await;
+        assert_eq!(result.unwrap(), Some(expected_record));
+    }
+
+    #[tokio::test]
+    async fn test_pointer_stores_forward_overrides() {
+        let mut store = MockStore::new();
+        store
+            .expect_list_recent()
+            .with(predicate::eq(1))
+            .times(3)
+            .returning(|_| Ok(vec![Record::new(OffsetDateTime::now_utc())]));
+        let store = Arc::new(store);
+
+        assert_eq!(store.list_recent(1).await.unwrap().len(), 1);
+        let boxed: Box<dyn SessionStore> = Box::new(store.clone());
+        assert_eq!(boxed.list_recent(1).await.unwrap().len(), 1);
+        let dyn_store = DynSessionStore::new(store);
+        assert_eq!(dyn_store.list_recent(1).await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_codec_envelope() {
+        #[derive(Debug)]
+        struct JsonCodec;
+
+        impl RecordCodec for JsonCodec {
+            fn encode(&self, record: &Record) -> Result<Vec<u8>> {
+                serde_json::to_vec(record).map_err(|err| Error::Encode(err.to_string()))
+            }
+
+            fn decode(&self, bytes: &[u8]) -> Result<Record> {
+                serde_json::from_slice(bytes).map_err(|err| Error::Decode(err.to_string()))
+            }
+        }
+
+        let mut record = Record::new(OffsetDateTime::now_utc() + Duration::minutes(30));
+        record.data.insert("foo".to_string(), 42.into());
+        let unversioned = JsonCodec.encode(&record).unwrap();
+
+        let previous = CodecEnvelope::new(1, JsonCodec);
+        let tagged = previous.encode(&record).unwrap();
+        assert_eq!(tagged[0], 1);
+        assert_eq!(&tagged[1..], unversioned);
+        assert_eq!(previous.decode(&tagged).unwrap(), record);
+        assert!(previous.decode(&unversioned).is_err());
+        assert!(previous.decode(&[]).is_err());
+
+        let codec = CodecEnvelope::new(2, JsonCodec)
+            .with_codec(1, JsonCodec)
+            .with_unversioned(JsonCodec);
+        assert_eq!(codec.encode(&record).unwrap()[0], 2);
+        assert_eq!(codec.decode(&tagged).unwrap(), record);
+        assert_eq!(codec.decode(&unversioned).unwrap(), record);
+        assert_eq!(codec.decode_header(&tagged).unwrap().id, record.id);
+        assert!(codec.decode(&[3, b'{', b'}']).is_err());
+    }
+
     #[tokio::test]
     async fn test_caching_store_delete() {
         let mut cache = MockCache::new();
@@ -501,4 +2642,288 @@ mod tests {
         let result = caching_store.delete(&session_id).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_sharded_store_shard_indexes() {
+        let shards = (0..4).map(|_| MockStore::new()).collect();
+        let sharded_store = ShardedSessionStore::new(shards).with_replication(2);
+
+        let mut counts = [0; 4];
+        for _ in 0..1_000 {
+            let session_id = Id::default();
+            let indexes = sharded_store.shard_indexes(&session_id);
+            assert_eq!(indexes.len(), 2);
+            assert_ne!(indexes[0], indexes[1]);
+            assert_eq!(indexes, sharded_store.shard_indexes(&session_id));
+            counts[indexes[0]] += 1;
+        }
+
+        // Every shard should be primary for a reasonable share of sessions.
+        assert!(counts.iter().all(|&count| count > 100));
+    }
+
+    #[tokio::test]
+    async fn test_sharded_store_load_falls_back_to_replica() {
+        let session_id = Id::default();
+        let record = Record {
+            id: session_id,
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        let expected_record = record.clone();
+
+        let indexes = ShardedSessionStore::new((0..3).map(|_| MockStore::new()).collect())
+            .with_replication(2)
+            .shard_indexes(&session_id);
+
+        let shards = (0..3)
+            .map(|index| {
+                let mut store = MockStore::new();
+                if index == indexes[0] {
+                    store
+                        .expect_load()
+                        .times(1)
+                        .returning(|_| Err(Error::Backend("unavailable".to_string())));
+                } else if index == indexes[1] {
+                    let record = record.clone();
+                    store
+                        .expect_load()
+                        .times(1)
+                        .returning(move |_| Ok(Some(record.clone())));
+                } else {
+                    store.expect_load().times(0);
+                }
+                store
+            })
+            .collect();
+
+        let sharded_store = ShardedSessionStore::new(shards).with_replication(2);
+        let result = sharded_store.load(&session_id).await;
+        assert_eq!(result.unwrap(), Some(expected_record));
+    }
+
+    #[tokio::test]
+    async fn test_sharded_store_increment_updates_replicas() {
+        let session_id = Id::default();
+        let record = Record {
+            id: session_id,
+            data: [("visits".to_string(), 3.into())].into(),
+            expiry_date: OffsetDateTime::now_utc() + Duration::minutes(30),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+
+        let indexes = ShardedSessionStore::new((0..3).map(|_| MockStore::new()).collect())
+            .with_replication(2)
+            .shard_indexes(&session_id);
+
+        let shards = (0..3)
+            .map(|index| {
+                let mut store = MockStore::new();
+                if index == indexes[0] {
+                    let record = record.clone();
+                    store
+                        .expect_increment()
+                        .with(
+                            predicate::eq(session_id),
+                            predicate::eq("visits"),
+                            predicate::eq(1),
+                        )
+                        .times(1)
+                        .returning(|_, _, _| Ok(Some(3)));
+                    store
+                        .expect_load()
+                        .times(1)
+                        .returning(move |_| Ok(Some(record.clone())));
+                } else if index == indexes[1] {
+                    store
+                        .expect_save()
+                        .with(predicate::eq(record.clone()))
+                        .times(1)
+                        .returning(|_| Ok(()));
+                } else {
+                    store.expect_increment().times(0);
+                    store.expect_save().times(0);
+                }
+                store
+            })
+            .collect();
+
+        let sharded_store = ShardedSessionStore::new(shards).with_replication(2);
+        let value = sharded_store.increment(&session_id, "visits", 1).await;
+        assert_eq!(value.unwrap(), Some(3));
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_resilient_store_timeout() {
+        #[derive(Debug)]
+        struct SlowStore;
+
+        #[async_trait]
+        impl SessionStore for SlowStore {
+            async fn save(&self, _: &Record) -> Result<()> {
+                Ok(())
+            }
+
+            async fn load(&self, _: &Id) -> Result<Option<Record>> {
+                tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+                Ok(None)
+            }
+
+            async fn delete(&self, _: &Id) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let store =
+            ResilientStore::new(SlowStore).with_timeout(std::time::Duration::from_millis(10));
+        assert!(matches!(
+            store.load(&Id::default()).await,
+            Err(Error::Timeout(_))
+        ));
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_resilient_store_circuit_breaker() {
+        let mut store = MockStore::new();
+        store
+            .expect_load()
+            .times(2)
+            .returning(|_| Err(Error::Backend("unavailable".to_string())));
+
+        let resilient_store = ResilientStore::new(store)
+            .with_circuit_breaker(2, std::time::Duration::from_secs(60))
+            .with_fallback(Fallback::Ephemeral);
+
+        let session_id = Id::default();
+        assert!(resilient_store.load(&session_id).await.is_err());
+        assert!(resilient_store.load(&session_id).await.is_err());
+
+        // The circuit is now open, so the store isn't called again.
+        assert_eq!(resilient_store.load(&session_id).await.unwrap(), None);
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_resilient_store_circuit_recovers() {
+        let mut store = MockStore::new();
+        let mut seq = mockall::Sequence::new();
+        store
+            .expect_load()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(Error::Backend("unavailable".to_string())));
+        store
+            .expect_load()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(None));
+
+        let resilient_store =
+            ResilientStore::new(store).with_circuit_breaker(1, std::time::Duration::ZERO);
+
+        let session_id = Id::default();
+        assert!(resilient_store.load(&session_id).await.is_err());
+        assert!(resilient_store.load(&session_id).await.is_ok());
+        assert!(resilient_store.load(&session_id).await.is_ok());
+    }
+
+    #[cfg(feature = "resilience")]
+    fn retry_store(store: MockStore) -> RetryStore<MockStore> {
+        RetryStore::new(store).with_backoff(
+            std::time::Duration::from_millis(1),
+            std::time::Duration::from_millis(2),
+        )
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_retry_store_retries_transient_errors() {
+        let mut store = MockStore::new();
+        let mut seq = mockall::Sequence::new();
+        store
+            .expect_load()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(|_| Err(Error::Timeout("timed out".to_string())));
+        store
+            .expect_load()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(None));
+
+        let retry_store = retry_store(store);
+        assert_eq!(retry_store.load(&Id::default()).await.unwrap(), None);
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_retry_store_gives_up() {
+        let mut store = MockStore::new();
+        store
+            .expect_save()
+            .times(4)
+            .returning(|_| Err(Error::Unavailable("unavailable".to_string())));
+
+        let retry_store = retry_store(store).with_max_attempts(4);
+        let record = Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc(),
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        };
+        assert!(matches!(
+            retry_store.save(&record).await,
+            Err(Error::Unavailable(_))
+        ));
+    }
+
+    #[cfg(feature = "resilience")]
+    #[tokio::test]
+    async fn test_retry_store_skips_permanent_errors() {
+        let mut store = MockStore::new();
+        store
+            .expect_delete()
+            .times(1)
+            .returning(|_| Err(Error::Decode("invalid".to_string())));
+
+        let retry_store = retry_store(store);
+        assert!(matches!(
+            retry_store.delete(&Id::default()).await,
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[cfg(feature = "resilience")]
+    #[test]
+    fn test_retry_store_delay() {
+        let retry_store = RetryStore::new(MockStore::new())
+            .with_backoff(
+                std::time::Duration::from_millis(10),
+                std::time::Duration::from_millis(25),
+            )
+            .with_jitter(false);
+        assert_eq!(retry_store.delay(1), std::time::Duration::from_millis(10));
+        assert_eq!(retry_store.delay(2), std::time::Duration::from_millis(20));
+        assert_eq!(retry_store.delay(3), std::time::Duration::from_millis(25));
+        assert_eq!(retry_store.delay(40), std::time::Duration::from_millis(25));
+
+        let retry_store = retry_store.with_jitter(true);
+        for _ in 0..100 {
+            let delay = retry_store.delay(2);
+            assert!(delay >= std::time::Duration::from_millis(10));
+            assert!(delay <= std::time::Duration::from_millis(20));
+        }
+    }
 }
@@ -0,0 +1,363 @@
+//! Utilities for testing applications which use sessions.
+//!
+//! The [`MockSessionStore`] behaves as an in-memory store by default, but may
+//! be scripted to inject errors and latency so that handlers can be tested
+//! against store failures deterministically. Calls are recorded, allowing
+//! tests to assert how the store was used.
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! use std::sync::Arc;
+//!
+//! use tower_sessions::{
+//!     session_store,
+//!     testing::{MockSessionStore, Operation},
+//!     Session,
+//! };
+//!
+//! let store = MockSessionStore::new();
+//! store.fail_next(
+//!     Operation::Create,
+//!     session_store::Error::Backend("unavailable".to_string()),
+//! );
+//!
+//! let session = Session::new(None, Arc::new(store.clone()), None);
+//! session.insert("foo", 42).await.unwrap();
+//! assert!(session.save().await.is_err());
+//! assert!(session.save().await.is_ok());
+//!
+//! store.assert_calls(Operation::Create, 2);
+//! # });
+//! ```
+//!
+//! The [`NoopStore`] instead persists nothing, which is useful for
+//! benchmarking handlers without store overhead. A session's record is still
+//! kept by the [`Session`](crate::Session) for the rest of the request, so
+//! handlers behave as usual within a single request. It counts the calls made
+//! to it, so tests may also assert that handlers never touch the store:
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! use std::sync::Arc;
+//!
+//! use tower_sessions::{testing::NoopStore, Session};
+//!
+//! let store = NoopStore::new();
+//! let session = Session::new(None, Arc::new(store.clone()), None);
+//! session.insert("foo", 42).await.unwrap();
+//! assert_eq!(session.get::<i32>("foo").await.unwrap(), Some(42));
+//!
+//! store.assert_no_calls();
+//! # });
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    session::{Id, Record},
+    session_store::{Error, Result},
+    SessionStore,
+};
+
+/// A store operation, used to script and inspect a [`MockSessionStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// [`SessionStore::create`].
+    Create,
+
+    /// [`SessionStore::save`].
+    Save,
+
+    /// [`SessionStore::load`].
+    Load,
+
+    /// [`SessionStore::delete`].
+    Delete,
+}
+
+/// A call made to a [`MockSessionStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call {
+    /// The operation which was called.
+    pub operation: Operation,
+
+    /// The ID of the session the operation was called with.
+    ///
+    /// For [`Operation::Create`] this is the ID after collision mitigation.
+    pub session_id: Id,
+}
+
+/// A scriptable session store for tests.
+///
+/// See [`testing`](crate::testing) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct MockSessionStore {
+    inner: Arc<parking_lot::Mutex<Inner>>,
+    latency: Option<Duration>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    records: HashMap<Id, Record>,
+    failures: HashMap<Operation, VecDeque<Error>>,
+    calls: Vec<Call>,
+}
+
+impl MockSessionStore {
+    /// Create a new, empty `MockSessionStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures a delay applied to every operation before it completes.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Queues an error to be returned by the next call of the given
+    /// operation.
+    ///
+    /// Errors queued for the same operation are returned in order. A failed
+    /// operation leaves the stored records untouched.
+    pub fn fail_next(&self, operation: Operation, error: Error) {
+        self.inner
+            .lock()
+            .failures
+            .entry(operation)
+            .or_default()
+            .push_back(error);
+    }
+
+    /// Returns the calls made to the store, in order.
+    pub fn calls(&self) -> Vec<Call> {
+        self.inner.lock().calls.clone()
+    }
+
+    /// Returns the number of times the given operation was called.
+    pub fn call_count(&self, operation: Operation) -> usize {
+        self.inner
+            .lock()
+            .calls
+            .iter()
+            .filter(|call| call.operation == operation)
+            .count()
+    }
+
+    /// Asserts the given operation was called exactly `expected` times.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the number of calls differs.
+    #[track_caller]
+    pub fn assert_calls(&self, operation: Operation, expected: usize) {
+        let actual = self.call_count(operation);
+        assert_eq!(
+            actual, expected,
+            "expected {expected} {operation:?} call(s) but got {actual}"
+        );
+    }
+
+    /// Returns the record stored for the given ID, regardless of expiry.
+    pub fn record(&self, session_id: &Id) -> Option<Record> {
+        self.inner.lock().records.get(session_id).cloned()
+    }
+
+    async fn call(&self, operation: Operation, session_id: Id) -> Result<()> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        let mut inner = self.inner.lock();
+        inner.calls.push(Call {
+            operation,
+            session_id,
+        });
+        match inner
+            .failures
+            .get_mut(&operation)
+            .and_then(VecDeque::pop_front)
+        {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MockSessionStore {
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        {
+            let inner = self.inner.lock();
+            while inner.records.contains_key(&record.id) {
+                // Session ID collision mitigation.
+                record.id = Id::default();
+            }
+        }
+        self.call(Operation::Create, record.id).await?;
+        self.inner.lock().records.insert(record.id, record.clone());
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        self.call(Operation::Save, record.id).await?;
+        self.inner.lock().records.insert(record.id, record.clone());
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        self.call(Operation::Load, *session_id).await?;
+        Ok(self
+            .inner
+            .lock()
+            .records
+            .get(session_id)
+            .filter(|record| record.is_active())
+            .cloned())
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        self.call(Operation::Delete, *session_id).await?;
+        self.inner.lock().records.remove(session_id);
+        Ok(())
+    }
+}
+
+/// A session store which persists nothing.
+///
+/// Loads find no session and writes are discarded. See
+/// [`testing`](crate::testing) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct NoopStore {
+    calls: Arc<AtomicUsize>,
+}
+
+impl NoopStore {
+    /// Create a new `NoopStore`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of calls made to the store, including those made
+    /// to clones of it.
+    pub fn call_count(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// Asserts no calls were made to the store.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the store was called.
+    #[track_caller]
+    pub fn assert_no_calls(&self) {
+        let actual = self.call_count();
+        assert_eq!(actual, 0, "expected no store calls but got {actual}");
+    }
+
+    fn call(&self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl SessionStore for NoopStore {
+    async fn create(&self, _record: &mut Record) -> Result<()> {
+        self.call();
+        Ok(())
+    }
+
+    async fn save(&self, _record: &Record) -> Result<()> {
+        self.call();
+        Ok(())
+    }
+
+    async fn load(&self, _session_id: &Id) -> Result<Option<Record>> {
+        self.call();
+        Ok(None)
+    }
+
+    async fn delete(&self, _session_id: &Id) -> Result<()> {
+        self.call();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fail_next() {
+        let store = MockSessionStore::new();
+        let mut record = Record::new(OffsetDateTime::now_utc() + time::Duration::minutes(30));
+
+        store.fail_next(Operation::Create, Error::Backend("unavailable".to_string()));
+        assert!(store.create(&mut record).await.is_err());
+        assert_eq!(store.record(&record.id), None);
+
+        store.create(&mut record).await.unwrap();
+        assert_eq!(store.load(&record.id).await.unwrap(), Some(record.clone()));
+
+        store.fail_next(Operation::Load, Error::Backend("unavailable".to_string()));
+        assert!(store.load(&record.id).await.is_err());
+
+        assert_eq!(
+            store.calls(),
+            vec![
+                Call {
+                    operation: Operation::Create,
+                    session_id: record.id
+                },
+                Call {
+                    operation: Operation::Create,
+                    session_id: record.id
+                },
+                Call {
+                    operation: Operation::Load,
+                    session_id: record.id
+                },
+                Call {
+                    operation: Operation::Load,
+                    session_id: record.id
+                },
+            ]
+        );
+        store.assert_calls(Operation::Save, 0);
+    }
+
+    #[tokio::test]
+    async fn test_latency() {
+        let store = MockSessionStore::new().with_latency(Duration::from_millis(20));
+        let started = std::time::Instant::now();
+        store.delete(&Id::default()).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        store.assert_calls(Operation::Delete, 1);
+    }
+
+    #[tokio::test]
+    async fn test_noop_store() {
+        let store = NoopStore::new();
+        let session = crate::Session::new(None, Arc::new(store.clone()), None);
+        session.insert("foo", 42).await.unwrap();
+        store.assert_no_calls();
+
+        session.save().await.unwrap();
+        let session_id = session.id().unwrap();
+        assert_eq!(session.get::<i32>("foo").await.unwrap(), Some(42));
+        assert_eq!(store.call_count(), 1);
+
+        assert_eq!(store.load(&session_id).await.unwrap(), None);
+        assert_eq!(store.call_count(), 2);
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store, ExpiredDeletion, SessionStore,
+};
+use worker::{send::SendFuture, wasm_bindgen::JsValue, D1Database};
+
+use crate::{decode, encode};
+
+/// A session store backed by a [D1](https://developers.cloudflare.com/d1/)
+/// database.
+///
+/// Unlike Workers KV, D1 is strongly consistent and inserts new sessions
+/// atomically. Expired sessions are no longer loaded but remain in the table
+/// until removed via [`ExpiredDeletion::delete_expired`], e.g. from a cron
+/// trigger.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use tower_sessions::SessionManagerLayer;
+/// use tower_sessions_workers_store::WorkersD1Store;
+///
+/// async fn session_layer(
+///     env: &worker::Env,
+/// ) -> worker::Result<SessionManagerLayer<WorkersD1Store>> {
+///     let session_store = WorkersD1Store::new(env.d1("DB")?);
+///     session_store.migrate().await?;
+///     Ok(SessionManagerLayer::new(session_store))
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WorkersD1Store {
+    db: Arc<D1Database>,
+    table_name: String,
+}
+
+impl WorkersD1Store {
+    /// Create a new `WorkersD1Store` using the given database.
+    pub fn new(db: D1Database) -> Self {
+        Self {
+            db: Arc::new(db),
+            table_name: "tower_sessions".to_string(),
+        }
+    }
+
+    /// Configures the name of the table sessions are stored in.
+    /// The default value is `"tower_sessions"`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the name isn't a non-empty ASCII identifier.
+    pub fn with_table_name(mut self, table_name: impl AsRef<str>) -> Result<Self, String> {
+        let table_name = table_name.as_ref();
+        if !is_valid_identifier(table_name) {
+            return Err(format!(
+                "Invalid table name '{table_name}'. Table names must start with a letter or \
+                 underscore (including letters with diacritical marks and non-Latin letters), \
+                 and can only contain letters, numbers and underscores."
+            ));
+        }
+        self.table_name = table_name.to_string();
+        Ok(self)
+    }
+
+    /// Create the sessions table if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the statement can't be executed.
+    pub async fn migrate(&self) -> worker::Result<()> {
+        let query = format!(
+            "create table if not exists {} (id text primary key not null, data text not null, \
+             expiry_date integer not null)",
+            self.table_name
+        );
+        SendFuture::new(self.db.prepare(query).run()).await?;
+        Ok(())
+    }
+
+    async fn insert(&self, record: &Record, upsert: bool) -> session_store::Result<bool> {
+        let conflict = if upsert {
+            "do update set data = excluded.data, expiry_date = excluded.expiry_date"
+        } else {
+            "do nothing"
+        };
+        let query = format!(
+            "insert into {} (id, data, expiry_date) values (?1, ?2, ?3) on conflict(id) {conflict}",
+            self.table_name
+        );
+        let values = [
+            JsValue::from(record.id.to_string()),
+            JsValue::from(encode(record)?),
+            JsValue::from(record.expiry_date.unix_timestamp() as f64),
+        ];
+        let statement = self
+            .db
+            .prepare(query)
+            .bind(&values)
+            .map_err(backend_error)?;
+        let result = SendFuture::new(statement.run())
+            .await
+            .map_err(backend_error)?;
+        let meta = result.meta().map_err(backend_error)?;
+        Ok(meta.and_then(|meta| meta.changes).unwrap_or_default() > 0)
+    }
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn backend_error(err: impl std::fmt::Display) -> session_store::Error {
+    session_store::Error::Backend(err.to_string()).with_backend("workers-d1")
+}
+
+#[async_trait]
+impl SessionStore for WorkersD1Store {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        while !self.insert(record, false).await? {
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        self.insert(record, true).await.map(|_| ())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let query = format!(
+            "select data from {} where id = ?1 and expiry_date > ?2",
+            self.table_name
+        );
+        let values = [
+            JsValue::from(session_id.to_string()),
+            JsValue::from(OffsetDateTime::now_utc().unix_timestamp() as f64),
+        ];
+        let statement = self
+            .db
+            .prepare(query)
+            .bind(&values)
+            .map_err(backend_error)?;
+        let data = SendFuture::new(statement.first::<String>(Some("data")))
+            .await
+            .map_err(backend_error)?;
+        data.map(|data| decode(&data))
+            .transpose()
+            .map(|record| record.filter(Record::is_active))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        let query = format!("delete from {} where id = ?1", self.table_name);
+        let statement = self
+            .db
+            .prepare(query)
+            .bind(&[JsValue::from(session_id.to_string())])
+            .map_err(backend_error)?;
+        SendFuture::new(statement.run())
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for WorkersD1Store {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let query = format!("delete from {} where expiry_date <= ?1", self.table_name);
+        let now = OffsetDateTime::now_utc().unix_timestamp() as f64;
+        let statement = self
+            .db
+            .prepare(query)
+            .bind(&[JsValue::from(now)])
+            .map_err(backend_error)?;
+        SendFuture::new(statement.run())
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_identifier() {
+        assert!(is_valid_identifier("tower_sessions"));
+        assert!(is_valid_identifier("_sessions2"));
+        assert!(!is_valid_identifier(""));
+        assert!(!is_valid_identifier("2sessions"));
+        assert!(!is_valid_identifier("sessions; drop table users"));
+    }
+}
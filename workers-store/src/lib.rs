@@ -0,0 +1,200 @@
+//! Session stores for [Cloudflare Workers](https://developers.cloudflare.com/workers/).
+//!
+//! [`WorkersKvStore`] keeps sessions in Workers KV, while [`WorkersD1Store`],
+//! behind the `d1` feature, keeps them in a D1 database. Both are intended to
+//! be built for `wasm32-unknown-unknown` with the `worker` crate.
+//!
+//! Workers run each isolate on a single thread, whereas [`SessionStore`]
+//! requires `Send` futures. The JavaScript futures awaited by these stores are
+//! wrapped in [`SendFuture`](worker::send::SendFuture) to bridge the two.
+use std::borrow::Cow;
+
+use async_trait::async_trait;
+use time::{Duration, OffsetDateTime};
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+use worker::{kv::KvStore, send::SendFuture};
+
+#[cfg(feature = "d1")]
+mod d1;
+
+#[cfg(feature = "d1")]
+#[cfg_attr(docsrs, doc(cfg(feature = "d1")))]
+pub use d1::WorkersD1Store;
+
+/// A session store backed by [Workers KV](https://developers.cloudflare.com/kv/).
+///
+/// Records are stored as JSON under their session ID, optionally prefixed,
+/// and expire through KV's own expiration so no cleanup task is required.
+/// KV is eventually consistent: a session saved in one location may take up
+/// to a minute to be visible in others, and concurrent writes to a session
+/// resolve to the last one.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use tower_sessions::{SessionManagerLayer, WorkersKvStore};
+///
+/// fn session_layer(env: &worker::Env) -> worker::Result<SessionManagerLayer<WorkersKvStore>> {
+///     let session_store = WorkersKvStore::new(env.kv("SESSIONS")?);
+///     Ok(SessionManagerLayer::new(session_store))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct WorkersKvStore {
+    kv: KvStore,
+    prefix: Cow<'static, str>,
+}
+
+impl WorkersKvStore {
+    // KV rejects expirations less than a minute in the future.
+    const MIN_TTL: Duration = Duration::seconds(60);
+
+    /// Create a new `WorkersKvStore` using the given KV namespace.
+    pub fn new(kv: KvStore) -> Self {
+        Self {
+            kv,
+            prefix: Cow::Borrowed(""),
+        }
+    }
+
+    /// Configures a prefix for the keys sessions are stored under, allowing
+    /// a KV namespace to be shared with other data.
+    /// The default value is `""`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use tower_sessions::WorkersKvStore;
+    ///
+    /// fn session_store(env: &worker::Env) -> worker::Result<WorkersKvStore> {
+    ///     Ok(WorkersKvStore::new(env.kv("CACHE")?).with_prefix("session:"))
+    /// }
+    /// ```
+    pub fn with_prefix(mut self, prefix: impl Into<Cow<'static, str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn key(&self, session_id: &Id) -> String {
+        format!("{}{}", self.prefix, session_id)
+    }
+
+    // KV expirations are whole seconds since the Unix epoch.
+    fn expiration(record: &Record, now: OffsetDateTime) -> u64 {
+        let expiration = record.expiry_date.max(now + Self::MIN_TTL);
+        expiration.unix_timestamp().unsigned_abs()
+    }
+
+    async fn get(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let value = SendFuture::new(self.kv.get(&self.key(session_id)).text())
+            .await
+            .map_err(backend_error)?;
+        value
+            .map(|value| decode(&value))
+            .transpose()
+            .map(|record| record.filter(Record::is_active))
+    }
+}
+
+impl std::fmt::Debug for WorkersKvStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkersKvStore")
+            .field("prefix", &self.prefix)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl SessionStore for WorkersKvStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        // KV can't insert conditionally, so collisions are only checked for.
+        while self.get(&record.id).await?.is_some() {
+            // Session ID collision mitigation.
+            record.id = Id::default();
+        }
+        self.save(record).await
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let value = encode(record)?;
+        let expiration = Self::expiration(record, OffsetDateTime::now_utc());
+        let put = self
+            .kv
+            .put(&self.key(&record.id), value)
+            .map_err(backend_error)?
+            .expiration(expiration);
+        SendFuture::new(put.execute()).await.map_err(backend_error)
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        self.get(session_id).await
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        SendFuture::new(self.kv.delete(&self.key(session_id)))
+            .await
+            .map_err(backend_error)
+    }
+}
+
+fn encode(record: &Record) -> session_store::Result<String> {
+    serde_json::to_string(record).map_err(|err| session_store::Error::Encode(err.to_string()))
+}
+
+fn decode(value: &str) -> session_store::Result<Record> {
+    serde_json::from_str(value).map_err(|err| session_store::Error::Decode(err.to_string()))
+}
+
+fn backend_error(err: impl std::fmt::Display) -> session_store::Error {
+    session_store::Error::Backend(err.to_string()).with_backend("workers-kv")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(expiry_date: OffsetDateTime) -> Record {
+        Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date,
+            client_fingerprint: None,
+            version: 0,
+            absolute_expiry: None,
+            validator_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_expiration() {
+        let now = OffsetDateTime::now_utc();
+
+        let later = now + Duration::hours(1);
+        let expiration = WorkersKvStore::expiration(&record(later), now);
+        assert_eq!(expiration, later.unix_timestamp() as u64);
+
+        // Expirations are pushed out to KV's minimum.
+        let soon = now + Duration::seconds(5);
+        let expiration = WorkersKvStore::expiration(&record(soon), now);
+        assert_eq!(
+            expiration,
+            (now + Duration::seconds(60)).unix_timestamp() as u64
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let mut record = record(OffsetDateTime::now_utc() + Duration::hours(1));
+        record.data.insert("foo".to_string(), 42.into());
+
+        let decoded = decode(&encode(&record).unwrap()).unwrap();
+        assert_eq!(decoded, record);
+        assert!(matches!(
+            decode("not json"),
+            Err(session_store::Error::Decode(_))
+        ));
+    }
+}